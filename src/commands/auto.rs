@@ -1,6 +1,7 @@
 //! Auto command - Autonomous mode for watching and auto-committing.
 
 use std::path::PathBuf;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone};
@@ -9,11 +10,17 @@ use dialoguer::{Input, Select};
 use rand::Rng;
 use tokio::select;
 
-use crate::config::Config;
+use crate::commands::commit::{fast_path_message, generate_spread_timestamps, recent_commits_context, WorkWindow, RECENT_COMMIT_COUNT};
+use crate::commands::merge::{self, MergePlan};
+use crate::commands::push;
+use crate::config::{Config, FastPathConfig};
+use crate::core::activity;
 use crate::core::ai::AiClient;
 use crate::core::git;
 use crate::core::lock::LockGuard;
-use crate::core::watcher::{FileWatcher, WatchEvent};
+use crate::core::prompt;
+use crate::core::report;
+use crate::core::watcher::{artifact_matcher, changed_paths, summarize_changes, FileWatcher, WatchEvent};
 
 /// Pause file name for watch mode
 const PAUSE_FILE: &str = ".bahn.pause";
@@ -25,11 +32,14 @@ fn is_paused() -> bool {
 
 /// Show pause status if paused
 fn check_pause_status() -> bool {
-    if is_paused() {
-        true
-    } else {
-        false
-    }
+    is_paused()
+}
+
+/// Stage all changes except editor artifacts and configured ignore globs
+fn stage_changes(repo: &git2::Repository, ignore_patterns: &[String]) -> Result<()> {
+    let artifacts = artifact_matcher(git::repo_root(repo)?, ignore_patterns)?;
+    git::stage_all_except(repo, &|path| artifacts.matched_path_or_any_parents(path, false).is_ignore())
+        .context("Failed to stage changes")
 }
 
 /// CLI options for auto mode
@@ -44,6 +54,14 @@ pub struct AutoModeOptions {
     pub defer: bool,
     pub spread: Option<String>,
     pub start: Option<String>,
+    /// Extra globs to skip when watching and staging
+    pub ignore: Vec<String>,
+    /// How long to wait for another instance to release the lock
+    pub lock_wait: std::time::Duration,
+    /// Auto-commit even on a protected branch
+    pub allow_protected: bool,
+    /// Always ask the AI, even for lockfile updates and version bumps
+    pub no_fast_path: bool,
 }
 
 /// Internal options for auto mode
@@ -53,10 +71,106 @@ struct AutoOptions {
     dry_run: bool,
     rewrite_history: bool,
     squash_threshold: usize,
+    /// Push after squashing
+    auto_push: bool,
+    /// Sign commits (`commit.sign`)
+    sign: bool,
     prompt: bool,
     defer: bool,
     spread: Option<String>,
     start: Option<String>,
+    /// Working hours and weekend rule for spread timestamps
+    work_window: WorkWindow,
+    /// Stop once the session has used this many tokens (`ai.max_tokens_per_session`)
+    token_budget: Option<u64>,
+    ignore_patterns: Vec<String>,
+    /// Subdirectories to watch instead of the whole repository
+    watch_paths: Vec<PathBuf>,
+    debounce_ms: u64,
+    /// Personality prompt of `commit.default_agent`
+    personality: Option<String>,
+    /// Heuristics for committing trivial changes without the AI (`commit.fast_path`)
+    fast_path: Option<FastPathConfig>,
+    /// When to hold changes back instead of committing them
+    thresholds: CommitThresholds,
+    /// Where to merge commits (`--merge`)
+    merge: Option<AutoMerge>,
+}
+
+/// Where and when auto mode merges its commits (`--merge`, `--target`)
+struct AutoMerge {
+    target: String,
+    /// Merge once when auto mode stops instead of after every commit (`auto.merge_on`)
+    on_exit: bool,
+    /// Let the AI resolve conflicts instead of refusing the merge (`auto.auto_resolve`)
+    auto_resolve: bool,
+}
+
+/// Limits below which auto mode keeps changes staged for a later commit
+/// (`auto.min_lines_changed`, `auto.min_files`, `auto.cooldown_seconds`)
+#[derive(Debug, Clone, Default)]
+struct CommitThresholds {
+    min_lines: usize,
+    min_files: usize,
+    cooldown: std::time::Duration,
+}
+
+impl CommitThresholds {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            min_lines: config.auto.min_lines_changed,
+            min_files: config.auto.min_files,
+            cooldown: std::time::Duration::from_secs(config.auto.cooldown_seconds),
+        }
+    }
+
+    /// Time left before the next commit may be made
+    fn cooldown_left(&self, last_commit: Option<Instant>) -> Option<std::time::Duration> {
+        let elapsed = last_commit?.elapsed();
+        (elapsed < self.cooldown).then(|| self.cooldown - elapsed)
+    }
+
+    /// Why `changes` shouldn't be committed yet, or `None` to commit them now
+    fn hold_back(&self, changes: &git::StagedChanges, last_commit: Option<Instant>) -> Option<String> {
+        if let Some(left) = self.cooldown_left(last_commit) {
+            return Some(format!("cooldown, {}s left", left.as_secs() + 1));
+        }
+        let lines = changes.stats.insertions + changes.stats.deletions;
+        if lines < self.min_lines {
+            return Some(format!("{} changed lines, fewer than auto.min_lines_changed ({})", lines, self.min_lines));
+        }
+        let files = changes.all_files().len();
+        if files < self.min_files {
+            return Some(format!("{} changed files, fewer than auto.min_files ({})", files, self.min_files));
+        }
+        None
+    }
+}
+
+/// Recent commit messages given to the AI as style context, read once and
+/// refreshed after each commit rather than on every poll
+struct RecentHistory {
+    messages: Vec<String>,
+}
+
+impl RecentHistory {
+    fn load(repo: &git2::Repository) -> Result<Self> {
+        Ok(Self { messages: git::get_recent_commit_messages(repo, RECENT_COMMIT_COUNT)? })
+    }
+
+    fn refresh(&mut self, repo: &git2::Repository) -> Result<()> {
+        *self = Self::load(repo)?;
+        Ok(())
+    }
+
+    /// Prompt context: the recent history, then the commits made this session
+    fn context(&self, session: Option<&str>) -> Option<String> {
+        let sections: Vec<String> = recent_commits_context(&self.messages)
+            .into_iter()
+            .chain(session.map(str::to_string))
+            .collect();
+        (!sections.is_empty()).then(|| sections.join("\n\n"))
+    }
 }
 
 /// A deferred commit waiting to be created
@@ -141,60 +255,6 @@ fn parse_start_time(s: &str) -> Result<DateTime<Local>> {
     anyhow::bail!("Invalid datetime format: {}. Use YYYY-MM-DD HH:MM", s)
 }
 
-/// Generate realistic timestamps for commits spread over a duration
-fn generate_spread_timestamps(
-    count: usize,
-    start: DateTime<Local>,
-    total_duration_secs: i64,
-) -> Vec<DateTime<Local>> {
-    if count == 0 {
-        return vec![];
-    }
-    if count == 1 {
-        return vec![start];
-    }
-
-    let mut rng = rand::thread_rng();
-    let mut timestamps = Vec::with_capacity(count);
-
-    let base_interval = total_duration_secs / (count as i64);
-
-    let mut current = start;
-    for i in 0..count {
-        timestamps.push(current);
-
-        if i < count - 1 {
-            // Add variance: 50% to 150% of base interval
-            let variance = rng.gen_range(0.5..1.5);
-            let interval = (base_interval as f64 * variance) as i64;
-
-            // Add random seconds for human-like timestamps
-            let extra_secs = rng.gen_range(0..60);
-
-            current += Duration::seconds(interval.max(60) + extra_secs);
-        }
-    }
-
-    // Scale back if overshot
-    if let Some(last) = timestamps.last() {
-        let actual_duration = (*last - start).num_seconds();
-        if actual_duration > total_duration_secs {
-            let scale = total_duration_secs as f64 / actual_duration as f64;
-            timestamps = timestamps.iter().enumerate().map(|(i, _)| {
-                if i == 0 {
-                    start
-                } else {
-                    let offset = (timestamps[i] - start).num_seconds();
-                    let scaled_offset = (offset as f64 * scale) as i64;
-                    start + Duration::seconds(scaled_offset)
-                }
-            }).collect();
-        }
-    }
-
-    timestamps
-}
-
 /// Default spread duration (2-4 hours)
 fn default_spread_duration() -> i64 {
     let mut rng = rand::thread_rng();
@@ -206,12 +266,6 @@ pub async fn run(config: &Config, cli_options: AutoModeOptions) -> Result<()> {
     println!("{}", "gitBahn - Auto Mode".bold().cyan());
     println!();
 
-    if cli_options.merge {
-        println!("{} Auto-merge to '{}' is not yet implemented. Ignoring --merge flag.",
-            "Warning:".yellow(), cli_options.target);
-        println!();
-    }
-
     // Validate flag combinations
     if cli_options.defer && !cli_options.watch {
         anyhow::bail!("--defer requires --watch mode");
@@ -221,27 +275,74 @@ pub async fn run(config: &Config, cli_options: AutoModeOptions) -> Result<()> {
         anyhow::bail!("--prompt and --defer cannot be used together. Choose one mode.");
     }
 
-    let api_key = config.anthropic_api_key()
-        .context("ANTHROPIC_API_KEY not set")?;
+    // Both modes ask questions; there's no --yes path for them
+    if cli_options.watch && (cli_options.prompt || cli_options.defer) {
+        prompt::require_terminal(prompt::is_interactive(), "Drop --prompt/--defer")?;
+    }
 
-    let ai = AiClient::new(api_key.to_string(), Some(config.ai.model.clone()));
+    let repo = git::open_repo(None)?;
+    let branch = git::current_branch(&repo)?;
+    check_protected_branch(&repo, config, cli_options.allow_protected)?;
+
+    let merge = cli_options.merge.then(|| AutoMerge {
+        target: cli_options.target.clone(),
+        on_exit: config.auto.merge_on == "exit",
+        auto_resolve: config.auto.auto_resolve,
+    });
+    if let Some(merge) = &merge {
+        check_merge_target(&repo, &branch, &merge.target)?;
+    }
+    // Squashing would rewrite commits the target branch already has
+    let merges_each = matches!(&merge, Some(m) if !m.on_exit);
+    if merges_each && config.auto.rewrite_history {
+        println!("{} Not squashing while merging each commit into '{}'; set auto.merge_on = \"exit\" to squash first.",
+            "Warning:".yellow(), cli_options.target);
+    }
+
+    let api_key = config.require_api_key()?;
+
+    // Write in the same voice as `bahn commit`
+    let agent = match config.commit.default_agent.as_deref() {
+        Some(name) => Some(config.agent_resolver(git::repo_root(&repo)?).resolve(name).await?),
+        None => None,
+    };
+    drop(repo);
+    let ai = config.ai_client(api_key);
+    let ai = match agent.as_ref().and_then(|a| a.model.clone()) {
+        Some(model) => ai.with_model(model),
+        None => ai,
+    };
+    activity::start_session();
 
     let options = AutoOptions {
         interval: cli_options.interval,
         max_commits: cli_options.max_commits,
         dry_run: cli_options.dry_run,
-        rewrite_history: config.auto.rewrite_history,
+        rewrite_history: config.auto.rewrite_history && !merges_each,
         squash_threshold: config.auto.squash_threshold,
+        auto_push: config.auto.auto_push,
+        sign: config.commit.sign,
         prompt: cli_options.prompt,
         defer: cli_options.defer,
         spread: cli_options.spread,
         start: cli_options.start,
+        work_window: config.commit.spread.work_window()?,
+        token_budget: config.ai.max_tokens_per_session,
+        ignore_patterns: config.auto.watch_ignore.iter()
+            .chain(&cli_options.ignore)
+            .cloned()
+            .collect(),
+        watch_paths: config.auto.watch_paths.iter().map(PathBuf::from).collect(),
+        debounce_ms: config.auto.debounce_ms,
+        personality: agent.map(|a| a.commit_prompt()),
+        fast_path: (!cli_options.no_fast_path).then(|| config.commit.fast_path.clone()),
+        thresholds: CommitThresholds::from_config(config),
+        merge,
     };
 
-    if cli_options.watch {
+    let result = if cli_options.watch {
         let repo = git::open_repo(None)?;
-        let repo_root = git::repo_root(&repo)?;
-        let _lock = LockGuard::acquire(repo_root)?;
+        let _lock = LockGuard::acquire_with_timeout(&repo, "auto", cli_options.lock_wait).await?;
         drop(repo);
 
         if options.defer {
@@ -252,11 +353,119 @@ pub async fn run(config: &Config, cli_options: AutoModeOptions) -> Result<()> {
             run_watch_mode(&ai, &options).await
         }
     } else {
-        run_single(&ai, options.dry_run).await
+        run_single(&ai, &options).await
+    };
+    // Deferred and batched commits only land now, so merge whatever `merge_on` says
+    let result = match (result, &options.merge) {
+        (Ok(()), Some(merge)) => merge_into_target(&ai, &options, merge).await,
+        (result, _) => result,
+    };
+
+    if config.ai.show_usage {
+        report::say_usage(&ai.usage());
+    }
+    result
+}
+
+/// Whether the session has used up `ai.max_tokens_per_session`; says so when it has
+fn budget_reached(ai: &AiClient, options: &AutoOptions) -> bool {
+    let Some(budget) = options.token_budget else {
+        return false;
+    };
+    let used = ai.usage().total_tokens();
+    if used < budget {
+        return false;
     }
+    println!("{} Token budget of {} reached ({} used). Stopping.", "→".yellow(), budget, used);
+    true
+}
+
+/// Build the file watcher from the `[auto]` watch settings
+fn build_watcher(repo: &git2::Repository, options: &AutoOptions) -> FileWatcher {
+    FileWatcher::for_repo(options.debounce_ms, repo)
+        .with_ignore_patterns(options.ignore_patterns.clone())
+        .with_watch_paths(options.watch_paths.clone())
 }
 
-async fn run_single(ai: &AiClient, dry_run: bool) -> Result<()> {
+/// Make sure `--merge` has somewhere to go: an existing branch other than the one being committed to
+fn check_merge_target(repo: &git2::Repository, branch: &str, target: &str) -> Result<()> {
+    if branch == target {
+        anyhow::bail!("Auto mode commits to '{}' itself; pass another branch to --target or drop --merge", target);
+    }
+    repo.find_branch(branch, git2::BranchType::Local)
+        .with_context(|| format!("--merge needs a branch with commits checked out, not '{}'", branch))?;
+    repo.find_branch(target, git2::BranchType::Local)
+        .with_context(|| format!("Merge target branch '{}' not found", target))?;
+    Ok(())
+}
+
+/// Merge the working branch into `--target`, or describe the merge on a dry run
+async fn merge_into_target(ai: &AiClient, options: &AutoOptions, merge: &AutoMerge) -> Result<()> {
+    let repo = git::open_repo(None)?;
+    let source = git::current_branch(&repo)?;
+    let target = &merge.target;
+    let plan = merge::plan_merge(&repo, &source, target)?;
+
+    if let MergePlan::Conflicts(files) = &plan {
+        if !merge.auto_resolve {
+            anyhow::bail!(
+                "'{}' has diverged and merging '{}' into it would conflict in {}. Merge by hand, or set auto.auto_resolve = true to let the AI resolve it.",
+                target,
+                source,
+                files.join(", ")
+            );
+        }
+    }
+
+    if options.dry_run {
+        let action = match &plan {
+            MergePlan::UpToDate => format!("'{}' is up to date with '{}'", target, source),
+            MergePlan::FastForward => format!("Would fast-forward '{}' to '{}'", target, source),
+            MergePlan::Merge => format!("Would merge '{}' into '{}'", source, target),
+            MergePlan::Conflicts(files) => format!(
+                "Would merge '{}' into '{}', resolving conflicts in {} with AI",
+                source, target, files.join(", ")
+            ),
+        };
+        println!("{} {}", "[DRY RUN]".yellow(), action);
+        return Ok(());
+    }
+
+    if matches!(plan, MergePlan::Conflicts(_)) {
+        println!("{} Merging into '{}' conflicts; resolving with AI", "→".yellow(), target);
+    }
+    match merge::apply_merge(&repo, &source, target, &plan, merge.auto_resolve.then_some(ai)).await? {
+        None => tracing::debug!(%target, "auto: merge target already up to date"),
+        Some(oid) => {
+            let how = if plan == MergePlan::FastForward { "Fast-forwarded" } else { "Merged into" };
+            println!("{} {} '{}': {}", "✓".green(), how, target, oid.to_string()[..7].cyan());
+        }
+    }
+    Ok(())
+}
+
+/// Merge into `--target` after a commit when `auto.merge_on = "each"`; failures only warn
+async fn merge_after_commit(ai: &AiClient, options: &AutoOptions) {
+    if let Some(merge) = options.merge.as_ref().filter(|m| !m.on_exit) {
+        if let Err(e) = merge_into_target(ai, options, merge).await {
+            eprintln!("{} Merge into '{}' failed: {:#}", "Warning:".yellow(), merge.target, e);
+        }
+    }
+}
+
+/// Refuse to auto-commit on a protected branch unless explicitly allowed
+fn check_protected_branch(repo: &git2::Repository, config: &Config, allow_protected: bool) -> Result<()> {
+    if !allow_protected && git::is_protected(repo, config)? {
+        anyhow::bail!(
+            "Refusing to auto-commit on protected branch '{}'. Switch to a feature branch or pass --allow-protected.",
+            git::current_branch(repo)?
+        );
+    }
+    Ok(())
+}
+
+async fn run_single(ai: &AiClient, options: &AutoOptions) -> Result<()> {
+    let dry_run = options.dry_run;
     let repo = git::open_repo(None)?;
 
     if !git::has_uncommitted_changes(&repo)? {
@@ -264,10 +473,7 @@ async fn run_single(ai: &AiClient, dry_run: bool) -> Result<()> {
         return Ok(());
     }
 
-    std::process::Command::new("git")
-        .args(["add", "-A"])
-        .output()
-        .context("Failed to stage changes")?;
+    stage_changes(&repo, &options.ignore_patterns)?;
 
     let changes = git::get_staged_changes(&repo)?;
 
@@ -282,14 +488,15 @@ async fn run_single(ai: &AiClient, dry_run: bool) -> Result<()> {
         changes.stats.deletions.to_string().red()
     );
 
-    let message = ai.generate_commit_message(&changes.diff, None, None, None).await?;
+    let context = RecentHistory::load(&repo)?.context(None);
+    let message = ai.generate_commit_message(&changes.diff, context.as_deref(), options.personality.as_deref(), None).await?;
 
     if dry_run {
         println!("{}", "[DRY RUN]".yellow().bold());
         println!("Would commit with message:");
         println!("  {}", message);
     } else {
-        let oid = git::create_commit(&repo, &message, false)?;
+        let oid = git::create_commit(&repo, &message, options.sign)?;
         println!("{} Committed: {}",
             "✓".green().bold(),
             oid.to_string()[..7].cyan()
@@ -309,27 +516,42 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
     println!("Watching for changes - you'll be prompted before each commit");
     println!("Press Ctrl+C to stop\n");
 
-    let watcher = FileWatcher::new(500);
-    let rx = watcher.watch(PathBuf::from(repo_root))?;
+    let mut watcher = build_watcher(&repo, options);
+    let mut rx = watcher.watch(PathBuf::from(repo_root))?;
+    let inhibitor = watcher.inhibitor();
 
     let mut commit_count = 0;
     let mut batch = CommitBatch::new();
     let mut session_messages: Vec<String> = Vec::new(); // Track all messages in session
+    let history = RecentHistory::load(&repo)?;
     let mut shutdown = false;
 
-    while !shutdown && commit_count < options.max_commits {
-        match rx.recv_timeout(std::time::Duration::from_millis(100)) {
-            Ok(WatchEvent::FilesChanged(paths)) => {
-                println!("\n{} {} file(s) changed",
+    while !shutdown && commit_count < options.max_commits && !budget_reached(ai, options) {
+        let event = select! {
+            biased;
+            _ = tokio::signal::ctrl_c() => {
+                println!("\n{}", "Received Ctrl+C...".yellow());
+                shutdown = true;
+                continue;
+            }
+            event = rx.recv() => event,
+        };
+
+        match event {
+            // Queued while we were committing: our own index and worktree churn
+            Some(WatchEvent::FilesChanged(changes)) if inhibitor.is_active() => {
+                tracing::debug!(paths = ?changed_paths(&changes), "auto: dropped batch inside the self-write window");
+            }
+            Some(WatchEvent::FilesChanged(changes)) => {
+                tracing::debug!(paths = ?changed_paths(&changes), "auto: watcher batch");
+                println!("\n{} {} file(s) changed ({})",
                     "→".cyan().bold(),
-                    paths.len()
+                    changes.len(),
+                    summarize_changes(&changes)
                 );
 
                 // Stage and get changes
-                std::process::Command::new("git")
-                    .args(["add", "-A"])
-                    .output()
-                    .context("Failed to stage changes")?;
+                stage_changes(&git::open_repo(None)?, &options.ignore_patterns)?;
 
                 let repo = git::open_repo(None)?;
                 let changes = git::get_staged_changes(&repo)?;
@@ -359,7 +581,8 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                 };
 
                 // Generate commit message with context
-                let message = ai.generate_commit_message(&changes.diff, session_context.as_deref(), None, None).await?;
+                let context = history.context(session_context.as_deref());
+                let message = ai.generate_commit_message(&changes.diff, context.as_deref(), options.personality.as_deref(), None).await?;
                 println!("  Suggested: {}", message.lines().next().unwrap_or("").cyan());
 
                 // Prompt user
@@ -382,7 +605,7 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                         if options.dry_run {
                             println!("{} Would commit: {}", "[DRY RUN]".yellow(), message.lines().next().unwrap_or(""));
                         } else {
-                            let oid = git::create_commit(&repo, &message, false)?;
+                            let oid = git::create_commit(&repo, &message, options.sign)?;
                             commit_count += 1;
                             session_messages.push(message.clone());
                             println!("{} Committed: {} - {}",
@@ -391,6 +614,7 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                                 message.lines().next().unwrap_or("")
                             );
                         }
+                        merge_after_commit(ai, options).await;
                     }
                     1 => {
                         // Commit with backdated time
@@ -407,7 +631,7 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                                 message.lines().next().unwrap_or("")
                             );
                         } else {
-                            let oid = git::create_commit_at(&repo, &message, false, Some(timestamp))?;
+                            let oid = git::create_commit_at(&repo, &message, options.sign, Some(timestamp))?;
                             commit_count += 1;
                             session_messages.push(message.clone());
                             println!("{} Committed at {}: {} - {}",
@@ -417,13 +641,14 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                                 message.lines().next().unwrap_or("")
                             );
                         }
+                        merge_after_commit(ai, options).await;
                     }
                     2 => {
                         // Add to batch
                         let deferred = DeferredCommit {
                             message: message.clone(),
                             diff: changes.diff.clone(),
-                            files: changes.all_files().iter().map(|s| s.to_string()).collect(),
+                            files: changes.paths_to_stage().iter().map(|s| s.to_string()).collect(),
                             timestamp: None,
                         };
                         batch.add(deferred);
@@ -442,21 +667,14 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                         git::reset_index(&repo)?;
                     }
                 }
+
+                // Ignore the churn from our own commit/reset
+                inhibitor.mark_write();
             }
-            Ok(WatchEvent::Error(e)) => {
+            Some(WatchEvent::Error(e)) => {
                 eprintln!("{} Watcher error: {}", "Warning:".yellow(), e);
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                select! {
-                    biased;
-                    _ = tokio::signal::ctrl_c() => {
-                        println!("\n{}", "Received Ctrl+C...".yellow());
-                        shutdown = true;
-                    }
-                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(1)) => {}
-                }
-            }
-            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            None => {
                 eprintln!("{}", "Watcher disconnected".red());
                 break;
             }
@@ -483,12 +701,12 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
             0 => {
                 // Commit all now
                 for deferred in &batch.commits {
-                    stage_files_for_deferred(&deferred)?;
+                    stage_files_for_deferred(deferred)?;
                     let repo = git::open_repo(None)?;
                     if options.dry_run {
                         println!("{} Would commit: {}", "[DRY RUN]".yellow(), deferred.message.lines().next().unwrap_or(""));
                     } else {
-                        let oid = git::create_commit(&repo, &deferred.message, false)?;
+                        let oid = git::create_commit(&repo, &deferred.message, options.sign)?;
                         commit_count += 1;
                         println!("{} {} - {}",
                             "✓".green(),
@@ -524,12 +742,12 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                     }
                 };
 
-                let timestamps = generate_spread_timestamps(batch.len(), start_time, spread_duration);
+                let timestamps = generate_spread_timestamps(batch.len(), start_time, spread_duration, &options.work_window);
 
                 println!("\n{}", "Creating commits with spread timestamps...".bold());
 
                 for (i, deferred) in batch.commits.iter().enumerate() {
-                    stage_files_for_deferred(&deferred)?;
+                    stage_files_for_deferred(deferred)?;
                     let repo = git::open_repo(None)?;
                     let ts = timestamps.get(i).copied();
 
@@ -540,7 +758,7 @@ async fn run_prompt_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<(
                             deferred.message.lines().next().unwrap_or("")
                         );
                     } else {
-                        let oid = git::create_commit_at(&repo, &deferred.message, false, ts)?;
+                        let oid = git::create_commit_at(&repo, &deferred.message, options.sign, ts)?;
                         commit_count += 1;
                         println!("{} {} @ {} - {}",
                             "✓".green(),
@@ -577,25 +795,40 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
     }
     println!("Press Ctrl+C to finalize\n");
 
-    let watcher = FileWatcher::new(500);
-    let rx = watcher.watch(PathBuf::from(repo_root))?;
+    let mut watcher = build_watcher(&repo, options);
+    let mut rx = watcher.watch(PathBuf::from(repo_root))?;
+    let inhibitor = watcher.inhibitor();
 
     let mut deferred_commits: Vec<DeferredCommit> = Vec::new();
     let mut shutdown = false;
+    let history = RecentHistory::load(&repo)?;
+
+    while !shutdown && deferred_commits.len() < options.max_commits && !budget_reached(ai, options) {
+        let event = select! {
+            biased;
+            _ = tokio::signal::ctrl_c() => {
+                println!("\n{}", "Finalizing session...".yellow());
+                shutdown = true;
+                continue;
+            }
+            event = rx.recv() => event,
+        };
 
-    while !shutdown && deferred_commits.len() < options.max_commits {
-        match rx.recv_timeout(std::time::Duration::from_millis(100)) {
-            Ok(WatchEvent::FilesChanged(paths)) => {
-                println!("{} {} file(s) changed",
+        match event {
+            // Queued while we were committing: our own index and worktree churn
+            Some(WatchEvent::FilesChanged(changes)) if inhibitor.is_active() => {
+                tracing::debug!(paths = ?changed_paths(&changes), "auto: dropped batch inside the self-write window");
+            }
+            Some(WatchEvent::FilesChanged(changes)) => {
+                tracing::debug!(paths = ?changed_paths(&changes), "auto: watcher batch");
+                println!("{} {} file(s) changed ({})",
                     "→".dimmed(),
-                    paths.len()
+                    changes.len(),
+                    summarize_changes(&changes)
                 );
 
                 // Stage and get changes
-                std::process::Command::new("git")
-                    .args(["add", "-A"])
-                    .output()
-                    .context("Failed to stage changes")?;
+                stage_changes(&git::open_repo(None)?, &options.ignore_patterns)?;
 
                 let repo = git::open_repo(None)?;
                 let changes = git::get_staged_changes(&repo)?;
@@ -622,17 +855,18 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
                 };
 
                 // Generate commit message with context
+                let context = history.context(session_context.as_deref());
                 let message = ai.generate_commit_message(
                     &changes.diff,
-                    session_context.as_deref(),
-                    None,
+                    context.as_deref(),
+                    options.personality.as_deref(),
                     None
                 ).await?;
 
                 let deferred = DeferredCommit {
                     message: message.clone(),
                     diff: changes.diff.clone(),
-                    files: changes.all_files().iter().map(|s| s.to_string()).collect(),
+                    files: changes.paths_to_stage().iter().map(|s| s.to_string()).collect(),
                     timestamp: None,
                 };
 
@@ -646,21 +880,12 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
 
                 // Reset staging so next changes are fresh
                 git::reset_index(&repo)?;
+                inhibitor.mark_write();
             }
-            Ok(WatchEvent::Error(e)) => {
+            Some(WatchEvent::Error(e)) => {
                 eprintln!("{} Watcher error: {}", "Warning:".yellow(), e);
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                select! {
-                    biased;
-                    _ = tokio::signal::ctrl_c() => {
-                        println!("\n{}", "Finalizing session...".yellow());
-                        shutdown = true;
-                    }
-                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(1)) => {}
-                }
-            }
-            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            None => {
                 eprintln!("{}", "Watcher disconnected".red());
                 break;
             }
@@ -689,7 +914,7 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
         Local::now() - Duration::seconds(spread_duration)
     };
 
-    let timestamps = generate_spread_timestamps(deferred_commits.len(), start_time, spread_duration);
+    let timestamps = generate_spread_timestamps(deferred_commits.len(), start_time, spread_duration, &options.work_window);
 
     println!("\nSpread: {} to {}",
         timestamps.first().map(|t| t.format("%b %d %H:%M").to_string()).unwrap_or_default().cyan(),
@@ -715,10 +940,7 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
             println!("\n{}", "Creating commits...".bold());
 
             // First, stage ALL changes that were tracked
-            std::process::Command::new("git")
-                .args(["add", "-A"])
-                .output()
-                .context("Failed to stage changes")?;
+            stage_changes(&git::open_repo(None)?, &options.ignore_patterns)?;
 
             let mut commit_count = 0;
             for (i, deferred) in deferred_commits.iter().enumerate() {
@@ -734,7 +956,7 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
                 } else {
                     // For deferred mode, we stage everything once and create commits
                     // This is simplified - in real use, we'd need smarter file tracking
-                    let oid = git::create_commit_at(&repo, &deferred.message, false, ts)?;
+                    let oid = git::create_commit_at(&repo, &deferred.message, options.sign, ts)?;
                     commit_count += 1;
                     println!("{} {} @ {} - {}",
                         "✓".green(),
@@ -762,14 +984,11 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
                 .interact_text()?;
             let new_start = parse_start_time(&input)?;
 
-            let new_timestamps = generate_spread_timestamps(deferred_commits.len(), new_start, new_duration);
+            let new_timestamps = generate_spread_timestamps(deferred_commits.len(), new_start, new_duration, &options.work_window);
 
             println!("\n{}", "Creating commits with adjusted timestamps...".bold());
 
-            std::process::Command::new("git")
-                .args(["add", "-A"])
-                .output()
-                .context("Failed to stage changes")?;
+            stage_changes(&git::open_repo(None)?, &options.ignore_patterns)?;
 
             let mut commit_count = 0;
             for (i, deferred) in deferred_commits.iter().enumerate() {
@@ -777,7 +996,7 @@ async fn run_defer_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
                 let repo = git::open_repo(None)?;
 
                 if !options.dry_run {
-                    let oid = git::create_commit_at(&repo, &deferred.message, false, ts)?;
+                    let oid = git::create_commit_at(&repo, &deferred.message, options.sign, ts)?;
                     commit_count += 1;
                     println!("{} {} @ {} - {}",
                         "✓".green(),
@@ -852,15 +1071,20 @@ async fn run_event_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
     println!("Press Ctrl+C to stop");
     println!("{} Create '{}' file to pause, delete to resume\n", "Tip:".cyan(), PAUSE_FILE);
 
-    let watcher = FileWatcher::new(500);
-    let rx = watcher.watch(PathBuf::from(repo_root))?;
+    let mut watcher = build_watcher(&repo, options);
+    let mut rx = watcher.watch(PathBuf::from(repo_root))?;
+    let inhibitor = watcher.inhibitor();
 
     let mut commit_count = 0;
     let mut commits_since_squash = 0;
     let mut shutdown = false;
     let mut was_paused = false;
+    let mut history = RecentHistory::load(&repo)?;
+    let mut last_commit = None;
+    // Changes held back by the cooldown are retried once it ends, even if nothing else changes
+    let mut retry_at: Option<Instant> = None;
 
-    while !shutdown && commit_count < options.max_commits {
+    while !shutdown && commit_count < options.max_commits && !budget_reached(ai, options) {
         // Check pause state
         if check_pause_status() {
             if !was_paused {
@@ -882,40 +1106,67 @@ async fn run_event_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<()
             was_paused = false;
         }
 
-        match rx.recv_timeout(std::time::Duration::from_millis(100)) {
-            Ok(WatchEvent::FilesChanged(paths)) => {
-                println!("{} {} file(s) changed",
-                    "→".dimmed(),
-                    paths.len()
-                );
-                if let Err(e) = check_and_commit(ai, options.dry_run, &mut commit_count).await {
-                    eprintln!("{} {}", "Error:".red(), e);
-                } else {
-                    commits_since_squash += 1;
+        let retry = async {
+            match retry_at {
+                Some(at) => tokio::time::sleep_until(at.into()).await,
+                None => std::future::pending().await,
+            }
+        };
+        let event = select! {
+            biased;
+            _ = tokio::signal::ctrl_c() => {
+                println!("\n{}", "Received Ctrl+C, shutting down gracefully...".yellow());
+                shutdown = true;
+                continue;
+            }
+            event = rx.recv() => event,
+            _ = retry => Some(WatchEvent::FilesChanged(Vec::new())),
+        };
 
-                    if options.rewrite_history && commits_since_squash >= options.squash_threshold {
-                        if let Err(e) = maybe_squash_commits(ai, options.squash_threshold, options.dry_run).await {
-                            eprintln!("{} Squash failed: {}", "Warning:".yellow(), e);
-                        } else {
-                            commits_since_squash = 0;
+        match event {
+            // Queued while we were committing: our own index and worktree churn
+            Some(WatchEvent::FilesChanged(changes)) if inhibitor.is_active() && !changes.is_empty() => {
+                tracing::debug!(paths = ?changed_paths(&changes), "auto: dropped batch inside the self-write window");
+            }
+            Some(WatchEvent::FilesChanged(changes)) => {
+                if changes.is_empty() {
+                    tracing::debug!("auto: cooldown over, retrying held-back changes");
+                } else {
+                    tracing::debug!(paths = ?changed_paths(&changes), "auto: watcher batch");
+                    println!("{} {} file(s) changed ({})",
+                        "→".dimmed(),
+                        changes.len(),
+                        summarize_changes(&changes)
+                    );
+                }
+                retry_at = None;
+                match check_and_commit(ai, options, &mut commit_count, &mut history, &mut last_commit).await {
+                    Err(e) => eprintln!("{} {}", "Error:".red(), e),
+                    Ok(false) => {
+                        retry_at = options.thresholds.cooldown_left(last_commit).map(|left| Instant::now() + left);
+                    }
+                    Ok(true) => {
+                        commits_since_squash += 1;
+
+                        if options.rewrite_history && commits_since_squash >= options.squash_threshold {
+                            if let Err(e) = maybe_squash_commits(ai, options).await {
+                                eprintln!("{} Squash failed: {}", "Warning:".yellow(), e);
+                            } else {
+                                commits_since_squash = 0;
+                                history.refresh(&repo)?;
+                            }
                         }
+                        merge_after_commit(ai, options).await;
                     }
                 }
+
+                // Ignore the churn from our own commit/squash
+                inhibitor.mark_write();
             }
-            Ok(WatchEvent::Error(e)) => {
+            Some(WatchEvent::Error(e)) => {
                 eprintln!("{} Watcher error: {}", "Warning:".yellow(), e);
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                select! {
-                    biased;
-                    _ = tokio::signal::ctrl_c() => {
-                        println!("\n{}", "Received Ctrl+C, shutting down gracefully...".yellow());
-                        shutdown = true;
-                    }
-                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(1)) => {}
-                }
-            }
-            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            None => {
                 eprintln!("{}", "Watcher disconnected".red());
                 break;
             }
@@ -945,6 +1196,8 @@ async fn run_polling_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<
     let mut commit_count = 0;
     let mut commits_since_squash = 0;
     let mut was_paused = false;
+    let mut history = RecentHistory::load(&git::open_repo(None)?)?;
+    let mut last_commit = None;
 
     loop {
         // Check pause state
@@ -969,33 +1222,30 @@ async fn run_polling_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<
             println!("{}", "Max commits reached. Stopping.".yellow());
             break;
         }
+        if budget_reached(ai, options) {
+            break;
+        }
 
-        let old_count = commit_count;
-        let should_continue = select! {
-            result = check_and_commit(ai, options.dry_run, &mut commit_count) => {
-                result?;
-                true
-            }
+        let committed = select! {
+            result = check_and_commit(ai, options, &mut commit_count, &mut history, &mut last_commit) => result?,
             _ = tokio::signal::ctrl_c() => {
                 println!("\n{}", "Received Ctrl+C, shutting down gracefully...".yellow());
-                false
+                break;
             }
         };
 
-        if !should_continue {
-            break;
-        }
-
-        if commit_count > old_count {
+        if committed {
             commits_since_squash += 1;
 
             if options.rewrite_history && commits_since_squash >= options.squash_threshold {
-                if let Err(e) = maybe_squash_commits(ai, options.squash_threshold, options.dry_run).await {
+                if let Err(e) = maybe_squash_commits(ai, options).await {
                     eprintln!("{} Squash failed: {}", "Warning:".yellow(), e);
                 } else {
                     commits_since_squash = 0;
+                    history.refresh(&git::open_repo(None)?)?;
                 }
             }
+            merge_after_commit(ai, options).await;
         }
 
         select! {
@@ -1015,20 +1265,39 @@ async fn run_polling_watch_mode(ai: &AiClient, options: &AutoOptions) -> Result<
     Ok(())
 }
 
-async fn check_and_commit(ai: &AiClient, dry_run: bool, commit_count: &mut usize) -> Result<()> {
+/// Commit pending changes; returns whether a commit was made (or would be, in a dry run)
+async fn check_and_commit(
+    ai: &AiClient,
+    options: &AutoOptions,
+    commit_count: &mut usize,
+    history: &mut RecentHistory,
+    last_commit: &mut Option<Instant>,
+) -> Result<bool> {
+    let dry_run = options.dry_run;
     let repo = git::open_repo(None)?;
+    let mut committed = false;
 
     if git::has_uncommitted_changes(&repo)? {
-        std::process::Command::new("git")
-            .args(["add", "-A"])
-            .output()
-            .context("Failed to stage changes")?;
+        stage_changes(&repo, &options.ignore_patterns)?;
 
         let repo = git::open_repo(None)?;
         let changes = git::get_staged_changes(&repo)?;
 
-        if !changes.is_empty() {
-            let message = ai.generate_commit_message(&changes.diff, None, None, None).await?;
+        let held_back = options.thresholds.hold_back(&changes, *last_commit);
+        if changes.is_empty() {
+            tracing::debug!("auto: nothing left to commit after ignore rules");
+        } else if let Some(reason) = held_back {
+            // Left staged; later changes add to it
+            tracing::info!(%reason, "auto: holding changes back");
+        } else {
+            let trivial = options.fast_path.as_ref().and_then(|fp| fast_path_message(&changes, fp));
+            let message = match trivial {
+                Some(message) => message,
+                None => {
+                    let context = history.context(None);
+                    ai.generate_commit_message(&changes.diff, context.as_deref(), options.personality.as_deref(), None).await?
+                }
+            };
 
             if dry_run {
                 println!("{} Would commit: {}",
@@ -1036,25 +1305,32 @@ async fn check_and_commit(ai: &AiClient, dry_run: bool, commit_count: &mut usize
                     message.lines().next().unwrap_or("")
                 );
             } else {
-                let oid = git::create_commit(&repo, &message, false)?;
+                let oid = git::create_commit(&repo, &message, options.sign)?;
                 println!("{} Committed: {} - {}",
                     "✓".green(),
                     oid.to_string()[..7].cyan(),
                     message.lines().next().unwrap_or("")
                 );
                 *commit_count += 1;
+                history.refresh(&repo)?;
             }
+            *last_commit = Some(Instant::now());
+            committed = true;
         }
     }
 
-    Ok(())
+    Ok(committed)
 }
 
-async fn maybe_squash_commits(ai: &AiClient, count: usize, dry_run: bool) -> Result<()> {
+/// Squash the last `squash_threshold` commits into one with an AI summary,
+/// then push if `auto.auto_push` is set. Pushed commits are never rewritten.
+async fn maybe_squash_commits(ai: &AiClient, options: &AutoOptions) -> Result<()> {
+    let count = options.squash_threshold;
     let repo = git::open_repo(None)?;
 
     let unpushed = git::count_unpushed_commits(&repo)?;
     if unpushed < count {
+        tracing::info!(unpushed, count, "auto: squash skipped, not enough unpushed commits");
         println!("{} Only {} unpushed commits, need {} to squash. Skipping.",
             "→".dimmed(),
             unpushed,
@@ -1068,16 +1344,22 @@ async fn maybe_squash_commits(ai: &AiClient, count: usize, dry_run: bool) -> Res
 
     let squash_message = ai.generate_squash_message(&commits_text).await?;
 
-    if dry_run {
+    if options.dry_run {
         println!("{} Would squash {} commits into:",
             "[DRY RUN]".yellow(),
             count
         );
-        println!("  {}", squash_message.lines().next().unwrap_or(""));
+        for line in squash_message.lines() {
+            println!("  {}", line);
+        }
+        if options.auto_push {
+            println!("{} Would push the squashed commit", "[DRY RUN]".yellow());
+        }
         return Ok(());
     }
 
     let oid = git::squash_commits(&repo, count, &squash_message)?;
+    tracing::info!(sha = %oid, count, "auto: squashed commits");
 
     println!("{} Squashed {} commits → {}",
         "⊕".cyan().bold(),
@@ -1086,5 +1368,152 @@ async fn maybe_squash_commits(ai: &AiClient, count: usize, dry_run: bool) -> Res
     );
     println!("  {}", squash_message.lines().next().unwrap_or(""));
 
+    if options.auto_push {
+        let branch = git::current_branch(&repo)?;
+        push::push_to_remote(&repo, &branch, false, true)?;
+        tracing::info!(branch = %branch, "auto: pushed squashed commit");
+        println!("{} Pushed {}", "↑".green().bold(), branch.cyan());
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher_reflects_config() {
+        let dir = std::env::temp_dir().join(format!("bahn-auto-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+
+        let config: Config = toml::from_str(
+            "[auto]\ndebounce_ms = 1200\nwatch_ignore = [\"*.bak\"]\nwatch_paths = [\"src\", \"docs\"]\n",
+        ).unwrap();
+        config.auto.validate().unwrap();
+
+        let options = AutoOptions {
+            interval: config.auto.interval,
+            max_commits: config.auto.max_commits,
+            dry_run: true,
+            rewrite_history: false,
+            squash_threshold: config.auto.squash_threshold,
+            auto_push: false,
+            sign: false,
+            prompt: false,
+            defer: false,
+            spread: None,
+            start: None,
+            work_window: WorkWindow::default(),
+            token_budget: None,
+            ignore_patterns: config.auto.watch_ignore.clone(),
+            watch_paths: config.auto.watch_paths.iter().map(PathBuf::from).collect(),
+            debounce_ms: config.auto.debounce_ms,
+            personality: None,
+            fast_path: None,
+            thresholds: CommitThresholds::default(),
+            merge: None,
+        };
+        let watcher = build_watcher(&repo, &options);
+
+        assert_eq!(watcher.debounce_duration(), std::time::Duration::from_millis(1200));
+        assert_eq!(watcher.ignore_patterns(), ["*.bak".to_string()]);
+        assert_eq!(watcher.watch_paths(), [PathBuf::from("src"), PathBuf::from("docs")]);
+    }
+
+    #[test]
+    fn test_watch_settings_are_validated() {
+        let mut config = Config::default();
+        assert_eq!(config.auto.debounce_ms, 500);
+        assert!(config.auto.validate().is_ok());
+
+        config.auto.debounce_ms = 10;
+        assert!(config.auto.validate().unwrap_err().to_string().contains("between 50 and 10000"));
+
+        config.auto.debounce_ms = 500;
+        config.auto.watch_paths = vec!["../outside".to_string()];
+        assert!(config.auto.validate().is_err());
+
+        config.auto.watch_paths = vec![];
+        config.auto.watch_ignore = vec!["src/[z-a]".to_string()];
+        assert!(config.auto.validate().is_err());
+
+        config.auto.watch_ignore = vec![];
+        config.auto.merge_on = "never".to_string();
+        assert!(config.auto.validate().unwrap_err().to_string().contains("auto.merge_on"));
+        config.auto.merge_on = "exit".to_string();
+        assert!(config.auto.validate().is_ok());
+    }
+
+    #[test]
+    fn test_prompt_has_recent_history_context() {
+        let dir = std::env::temp_dir().join(format!("bahn-auto-history-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+
+        let mut history = RecentHistory::load(&repo).unwrap();
+        assert_eq!(history.context(None), None);
+
+        let first = repo.commit(Some("HEAD"), &sig, &sig, "feat(parser): accept trailing commas", &tree, &[]).unwrap();
+        history.refresh(&repo).unwrap();
+        let context = history.context(Some("Previous commits in this session")).unwrap();
+        assert!(context.starts_with("Recent commit messages:\nfeat(parser): accept trailing commas"));
+        assert!(context.ends_with("\n\nPrevious commits in this session"));
+
+        let prompt = crate::core::ai::build_commit_user_content("+fn main() {}", Some(&context), 10_000);
+        assert!(prompt.contains("Context: Recent commit messages:\nfeat(parser): accept trailing commas"));
+
+        // The cache only changes on refresh
+        let parent = repo.find_commit(first).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "fix(lexer): handle tabs", &tree, &[&parent]).unwrap();
+        assert!(!history.context(None).unwrap().contains("fix(lexer)"));
+        history.refresh(&repo).unwrap();
+        assert!(history.context(None).unwrap().contains("fix(lexer): handle tabs\n---\nfeat(parser)"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_protected_branch_guard() {
+        let dir = std::env::temp_dir().join(format!("bahn-auto-protected-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        let check = |branch: &str, config: &Config, allow: bool| {
+            repo.set_head(&format!("refs/heads/{}", branch)).unwrap();
+            check_protected_branch(&repo, config, allow)
+        };
+        let mut config = Config::default();
+        let err = check("main", &config, false).unwrap_err();
+        assert!(err.to_string().contains("protected branch 'main'") && err.to_string().contains("--allow-protected"));
+        assert!(check("main", &config, true).is_ok());
+        assert!(check("feature/watch", &config, false).is_ok());
+
+        config.git.protected_branches = vec!["release/*".to_string()];
+        assert!(check("release/3.1", &config, false).is_err());
+        assert!(check("main", &config, false).is_ok());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_commit_thresholds() {
+        let changes = git::StagedChanges {
+            added: vec!["a.rs".to_string()],
+            modified: vec!["b.rs".to_string()],
+            stats: git::DiffStats { files_changed: 2, insertions: 3, deletions: 1 },
+            ..Default::default()
+        };
+        assert!(CommitThresholds::default().hold_back(&changes, Some(Instant::now())).is_none());
+
+        let config: Config = toml::from_str("[auto]\nmin_lines_changed = 5\nmin_files = 3\ncooldown_seconds = 60\n").unwrap();
+        let mut thresholds = CommitThresholds::from_config(&config);
+        assert!(thresholds.hold_back(&changes, Some(Instant::now())).unwrap().contains("cooldown"));
+        assert!(thresholds.hold_back(&changes, None).unwrap().contains("auto.min_lines_changed"));
+
+        thresholds.min_lines = 4;
+        assert!(thresholds.hold_back(&changes, None).unwrap().contains("auto.min_files"));
+        thresholds.min_files = 2;
+        assert!(thresholds.hold_back(&changes, None).is_none());
+        assert!(thresholds.cooldown_left(None).is_none());
+    }
+}
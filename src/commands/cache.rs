@@ -0,0 +1,16 @@
+//! Cache command - manage cached AI responses.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::core::cache;
+
+/// Delete every cached AI response
+pub fn clear() -> Result<()> {
+    let dir = cache::default_dir().context("Could not determine the cache directory")?;
+    let removed = cache::clear(&dir)?;
+
+    let noun = if removed == 1 { "response" } else { "responses" };
+    println!("{} Removed {} cached {} ({})", "✓".green().bold(), removed, noun, dir.display());
+    Ok(())
+}
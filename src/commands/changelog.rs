@@ -0,0 +1,268 @@
+//! Changelog command - Keep a Changelog sections from commit history.
+
+use std::fs;
+use std::io::ErrorKind;
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::config::Config;
+use crate::core::git::{self, LoggedCommit};
+use crate::core::message;
+use crate::core::report::{self, say, ChangelogReport};
+
+/// Changelog file, at the repository root
+const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// Order of the type groups given to the AI; every Conventional Commits type is listed
+const GROUP_ORDER: &[&str] = &[
+    "feat", "fix", "perf", "refactor", "revert", "docs", "test", "build", "ci", "style", "chore",
+];
+
+/// Group for messages that don't follow Conventional Commits
+const OTHER: &str = "Other";
+
+/// Top of a new changelog file
+const HEADER: &str = "# Changelog\n\n\
+All notable changes to this project will be documented in this file.\n\n\
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).\n";
+
+/// Options for changelog command
+pub struct ChangelogOptions {
+    /// Tag or ref to start from (default: the latest tag)
+    pub since: Option<String>,
+    /// Title the section "Unreleased" even when HEAD is tagged
+    pub unreleased: bool,
+    /// Prepend the section to CHANGELOG.md
+    pub write: bool,
+}
+
+/// Run the changelog command
+pub async fn run(config: &Config, options: ChangelogOptions) -> Result<()> {
+    let repo = git::open_repo(None)?;
+    let head = repo.head()
+        .and_then(|head| head.peel_to_commit())
+        .context("No commits yet")?;
+
+    // A tag on HEAD means a release was just cut: describe it, starting from the tag before
+    let release = if options.unreleased { None } else { git::tag_at(&repo, &head)? };
+    let since = match options.since {
+        Some(since) => Some(since),
+        None if release.is_some() => match head.parent(0) {
+            Ok(parent) => git::latest_tag(&parent)?,
+            Err(_) => None,
+        },
+        None => git::latest_tag(&head)?,
+    };
+    let heading = match &release {
+        Some(tag) => {
+            let date = git::head_commit_time(&repo)?.unwrap_or_else(Local::now);
+            format!("## [{}] - {}", tag, date.format("%Y-%m-%d"))
+        }
+        None => "## [Unreleased]".to_string(),
+    };
+
+    let commits = git::commits_since(&repo, since.as_deref())?;
+    let range = since.as_deref().unwrap_or("the first commit");
+    if commits.is_empty() {
+        say!("{} No commits since {}", "→".yellow(), range);
+        if report::is_json() {
+            report::emit(&ChangelogReport { heading, since, commits: 0, section: None, written: None })?;
+        }
+        return Ok(());
+    }
+    say!("{} {} commits since {}", "→".cyan(), commits.len(), range);
+
+    let api_key = config.require_api_key()?;
+    let ai = config.ai_client(api_key);
+
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(ProgressStyle::default_spinner()
+        .template("{spinner:.green} {msg}")
+        .unwrap());
+    pb.set_message("Writing changelog...");
+    let body = ai.generate_changelog(&format_groups(&group_commits(&commits))).await;
+    pb.finish_and_clear();
+    let section = format!("{}\n\n{}\n", heading, body?.trim());
+
+    say!();
+    say!("{}", section.trim_end());
+    say!();
+
+    let written = if options.write {
+        let path = git::repo_root(&repo)?.join(CHANGELOG_FILE);
+        let existing = match fs::read_to_string(&path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        fs::write(&path, prepend_section(&existing, &section))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        say!("{} Updated {}", "✓".green(), git::display_path(&path));
+        Some(path.display().to_string())
+    } else {
+        None
+    };
+
+    if report::is_json() {
+        report::emit(&ChangelogReport {
+            heading,
+            since,
+            commits: commits.len(),
+            section: Some(section),
+            written,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Commits bucketed by Conventional Commits type in [`GROUP_ORDER`], with
+/// non-conventional messages under [`OTHER`]; empty groups are left out
+fn group_commits(commits: &[LoggedCommit]) -> Vec<(&'static str, Vec<String>)> {
+    let mut groups: Vec<(&'static str, Vec<String>)> = GROUP_ORDER.iter()
+        .chain(std::iter::once(&OTHER))
+        .map(|group| (*group, Vec::new()))
+        .collect();
+
+    for commit in commits {
+        let (kind, entry) = match message::parse_conventional(&commit.message) {
+            Some(parsed) => {
+                let mut entry = String::new();
+                if parsed.breaking {
+                    entry.push_str("[breaking] ");
+                }
+                if let Some(scope) = parsed.scope {
+                    entry.push_str(&format!("{}: ", scope));
+                }
+                entry.push_str(parsed.description);
+                (parsed.kind, entry)
+            }
+            None => (OTHER, commit.message.lines().next().unwrap_or("").to_string()),
+        };
+        let index = groups.iter().position(|(group, _)| *group == kind).unwrap_or(groups.len() - 1);
+        groups[index].1.push(format!("{} ({})", entry, commit.id));
+    }
+
+    groups.retain(|(_, entries)| !entries.is_empty());
+    groups
+}
+
+/// Groups as prompt text: a `type:` line followed by its bullets
+fn format_groups(groups: &[(&str, Vec<String>)]) -> String {
+    groups.iter()
+        .map(|(group, entries)| {
+            let bullets: String = entries.iter().map(|entry| format!("- {}\n", entry)).collect();
+            format!("{}:\n{}", group, bullets)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `existing` with `section` above the newest release. A section with the
+/// same version is replaced instead, so running twice doesn't duplicate it.
+fn prepend_section(existing: &str, section: &str) -> String {
+    if existing.trim().is_empty() {
+        return format!("{}\n{}", HEADER, section);
+    }
+
+    let heading = section.lines().next().unwrap_or("");
+    // "## [Unreleased]" or "## [v1.2.0]", without the date
+    let key = heading.find(']').map_or(heading, |end| &heading[..=end]);
+
+    // Section headings and the link references at the bottom end a section
+    let boundaries: Vec<usize> = std::iter::once(0)
+        .chain(existing.match_indices('\n').map(|(i, _)| i + 1))
+        .filter(|&offset| {
+            let line = &existing[offset..];
+            line.starts_with("## ") || (line.starts_with('[') && line.lines().next().is_some_and(|l| l.contains("]: ")))
+        })
+        .collect();
+
+    let same = boundaries.iter().position(|&offset| existing[offset..].starts_with(key));
+    let (start, end) = match same {
+        Some(i) => (boundaries[i], boundaries.get(i + 1).copied()),
+        None => match boundaries.iter().find(|&&offset| existing[offset..].starts_with("## ")) {
+            Some(&offset) => (offset, Some(offset)),
+            None => {
+                return format!("{}\n\n{}", existing.trim_end(), section);
+            }
+        },
+    };
+
+    let mut output = String::with_capacity(existing.len() + section.len() + 1);
+    output.push_str(&existing[..start]);
+    output.push_str(section);
+    if let Some(end) = end {
+        output.push('\n');
+        output.push_str(&existing[end..]);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, message: &str) -> LoggedCommit {
+        LoggedCommit { id: id.to_string(), message: message.to_string() }
+    }
+
+    #[test]
+    fn test_group_commits_keeps_non_conventional_messages() {
+        let commits = [
+            commit("aaaaaaa", "fix(core): handle empty diff"),
+            commit("bbbbbbb", "Update README"),
+            commit("ccccccc", "feat!: new config format"),
+            commit("ddddddd", "feature: not a known type"),
+            commit("eeeeeee", "feat: add changelog"),
+        ];
+
+        let groups = group_commits(&commits);
+        let names: Vec<&str> = groups.iter().map(|(group, _)| *group).collect();
+        assert_eq!(names, ["feat", "fix", "Other"]);
+        assert_eq!(groups[0].1, ["[breaking] new config format (ccccccc)", "add changelog (eeeeeee)"]);
+        assert_eq!(groups[1].1, ["core: handle empty diff (aaaaaaa)"]);
+        assert_eq!(groups[2].1, ["Update README (bbbbbbb)", "feature: not a known type (ddddddd)"]);
+
+        assert_eq!(
+            format_groups(&groups[1..]),
+            "fix:\n- core: handle empty diff (aaaaaaa)\n\nOther:\n- Update README (bbbbbbb)\n- feature: not a known type (ddddddd)\n"
+        );
+    }
+
+    #[test]
+    fn test_prepend_section_creates_file() {
+        let section = "## [Unreleased]\n\n### Added\n- Changelog command\n";
+        let output = prepend_section("", section);
+        assert!(output.starts_with("# Changelog\n"));
+        assert!(output.ends_with(&format!("\n\n{}", section)));
+        assert_eq!(prepend_section(&output, section), output);
+    }
+
+    #[test]
+    fn test_prepend_section_is_idempotent() {
+        let existing = "# Changelog\n\n## [Unreleased]\n\n### Fixed\n- Old entry\n\n## [1.0.0] - 2026-01-02\n\n### Added\n- First release\n\n[Unreleased]: https://example.com/compare/v1.0.0...HEAD\n[1.0.0]: https://example.com/releases/v1.0.0\n";
+        let section = "## [Unreleased]\n\n### Added\n- Changelog command\n";
+
+        let once = prepend_section(existing, section);
+        assert_eq!(once.matches("## [Unreleased]").count(), 1);
+        assert!(!once.contains("Old entry"));
+        assert!(once.contains(&format!("{}\n## [1.0.0] - 2026-01-02\n", section)));
+        assert_eq!(prepend_section(&once, section), once);
+
+        // A release goes above the newest one; the link references stay at the bottom
+        let release = "## [1.1.0] - 2026-02-03\n\n### Added\n- More\n";
+        let without_unreleased = existing.replace("## [Unreleased]\n\n### Fixed\n- Old entry\n\n", "");
+        let released = prepend_section(&without_unreleased, release);
+        assert!(released.contains(&format!("# Changelog\n\n{}\n## [1.0.0]", release)));
+        assert!(released.ends_with("[1.0.0]: https://example.com/releases/v1.0.0\n"));
+        assert_eq!(prepend_section(&released, &release.replace("- More", "- More, reworded")).matches("## [1.1.0]").count(), 1);
+
+        // The last section is replaced up to the link references
+        let last = "# Changelog\n\n## [Unreleased]\n- a\n\n[Unreleased]: https://example.com\n";
+        assert_eq!(prepend_section(last, section), format!("# Changelog\n\n{}\n[Unreleased]: https://example.com\n", section));
+    }
+}
@@ -1,48 +1,90 @@
 //! Docs command - AI-powered documentation generation.
 
+use std::fmt::Write as _;
 use std::fs;
 use std::path::Path;
 
 use anyhow::{Context, Result};
 use colored::Colorize;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use once_cell::sync::Lazy;
+use regex::Regex;
 
-use crate::config::Config;
+use crate::config::{Config, DocsConfig};
 use crate::core::ai::AiClient;
+use crate::core::{batch, git};
+
+/// Extensions documented when walking a directory
+const EXTENSIONS: &[&str] = &["rs", "py", "js", "ts", "go", "rb"];
+
+/// Lines of context around each change in the dry-run diff
+const CONTEXT: usize = 2;
+
+/// Rust items that get `///` blocks, matched against the trimmed line
+static RUST_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(pub(\([^)]*\))?\s+)?((const|async|unsafe|default)\s+)*(fn|struct|enum|trait|impl|union)\b").unwrap()
+});
+
+/// Python functions and classes that get docstrings
+static PYTHON_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(async\s+)?(def|class)\s+\w+").unwrap());
+
+/// Options for docs command
+pub struct DocsOptions {
+    /// Documentation format (rust, markdown, jsdoc) for printed docs
+    pub format: String,
+    /// Insert doc comments into the files
+    pub write: bool,
+    /// With `write`, show the changes as a diff instead of writing them
+    pub dry_run: bool,
+}
 
 /// Run the docs command
-pub async fn run(config: &Config, path: &str, format: &str) -> Result<()> {
+pub async fn run(config: &Config, path: &str, options: DocsOptions) -> Result<()> {
     println!("{}", "gitBahn - Documentation Generator".bold().cyan());
     println!();
 
-    let api_key = config.anthropic_api_key()
-        .context("ANTHROPIC_API_KEY not set")?;
-
-    let ai = AiClient::new(api_key.to_string(), Some(config.ai.model.clone()));
-
     let file_path = Path::new(path);
 
     if !file_path.exists() {
         anyhow::bail!("Path does not exist: {}", path);
     }
 
-    if file_path.is_file() {
-        generate_docs_for_file(&ai, file_path, format).await?;
-    } else if file_path.is_dir() {
-        generate_docs_for_directory(&ai, file_path, format).await?;
+    let files = if file_path.is_file() {
+        vec![file_path.to_path_buf()]
+    } else {
+        source_files(file_path, &config.docs.exclude)?
+    };
+
+    let api_key = config.require_api_key()?;
+    let ai = config.ai_client(api_key);
+
+    let attempted = files.len();
+    let failures = batch::run(files, config.docs.concurrency, "Documenting", |file| git::display_path(file), |file| {
+        let (ai, options) = (&ai, &options);
+        async move {
+            if options.write {
+                write_docs_for_file(ai, &file, &config.docs, options.dry_run).await
+            } else {
+                generate_docs_for_file(ai, &file, &options.format).await
+            }
+        }
+    })
+    .await;
+
+    if options.write && options.dry_run {
+        println!("{}", "[DRY RUN] Changes not applied".yellow());
     }
 
-    Ok(())
+    batch::finish(failures, attempted)
 }
 
-async fn generate_docs_for_file(ai: &AiClient, path: &Path, format: &str) -> Result<()> {
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read file: {}", path.display()))?;
-
+fn language_for(path: &Path) -> &str {
     let extension = path.extension()
         .and_then(|e| e.to_str())
         .unwrap_or("txt");
 
-    let language = match extension {
+    match extension {
         "rs" => "rust",
         "py" => "python",
         "js" => "javascript",
@@ -50,35 +92,529 @@ async fn generate_docs_for_file(ai: &AiClient, path: &Path, format: &str) -> Res
         "go" => "go",
         "rb" => "ruby",
         _ => extension,
-    };
+    }
+}
 
-    println!("  {} {}", "Documenting".yellow(), path.display());
+/// Documentation for one file, as printed output
+async fn generate_docs_for_file(ai: &AiClient, path: &Path, format: &str) -> Result<String> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let language = language_for(path);
 
     let docs = ai.generate_docs(&content, language, format).await?;
 
-    println!("{}", "Generated documentation:".bold());
-    println!("{}", "-".repeat(50).dimmed());
-    println!("{}", docs);
-    println!("{}", "-".repeat(50).dimmed());
+    let mut output = String::new();
+    writeln!(output, "  {} {}", "Documented".green(), git::display_path(path))?;
+    writeln!(output, "{}", "Generated documentation:".bold())?;
+    writeln!(output, "{}", "-".repeat(50).dimmed())?;
+    writeln!(output, "{}", docs)?;
+    writeln!(output, "{}", "-".repeat(50).dimmed())?;
+    Ok(output)
+}
+
+/// Insert AI-written doc comments above the items of one Rust or Python file,
+/// returning what to print
+async fn write_docs_for_file(ai: &AiClient, path: &Path, config: &DocsConfig, dry_run: bool) -> Result<String> {
+    let language = language_for(path);
+    let style = match language {
+        "rust" => DocStyle::Rust,
+        "python" => DocStyle::Python,
+        _ => {
+            return Ok(format!("  {} {} (--write supports Rust and Python)\n", "Skipping".dimmed(), git::display_path(path)));
+        }
+    };
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let items: Vec<DocItem> = find_items(&lines, style)
+        .into_iter()
+        .filter(|item| item.existing == 0 || config.update_existing)
+        .collect();
+    if items.is_empty() {
+        return Ok(format!("  {} {} (nothing undocumented)\n", "Skipping".dimmed(), git::display_path(path)));
+    }
+
+    let listing: String = items.iter()
+        .enumerate()
+        .map(|(i, item)| format!("{}. line {}: {}\n", i + 1, item.line + 1, item.signature))
+        .collect();
+    let docs = ai.generate_item_docs(&content, language, &listing).await?;
+
+    let mut edits: Vec<DocEdit> = docs.iter()
+        .filter_map(|doc| {
+            let item = items.get(doc.id.checked_sub(1)?)?;
+            let text = clean_doc(&doc.doc);
+            (!text.is_empty()).then(|| DocEdit {
+                start: item.insert_at,
+                remove: item.existing,
+                insert: format_doc(&text, &item.indent, style),
+            })
+        })
+        .collect();
+    edits.sort_by_key(|edit| edit.start);
+    edits.dedup_by_key(|edit| edit.start);
+
+    let display = git::display_path(path);
+    if dry_run {
+        let mut output = String::new();
+        for line in render_diff(&display, &lines, &edits).lines() {
+            let line = match line.chars().next() {
+                Some('+') if !line.starts_with("+++") => line.green(),
+                Some('-') if !line.starts_with("---") => line.red(),
+                Some('@') => line.cyan(),
+                _ => line.normal(),
+            };
+            writeln!(output, "{}", line)?;
+        }
+        return Ok(output);
+    }
 
-    Ok(())
+    fs::write(path, apply_edits(&content, &edits))
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+    Ok(format!("  {} {} items in {}\n", "Documented".green(), edits.len(), display))
 }
 
-async fn generate_docs_for_directory(ai: &AiClient, path: &Path, format: &str) -> Result<()> {
-    let extensions = ["rs", "py", "js", "ts", "go", "rb"];
+/// Source files under `dir`, skipping gitignored files and `docs.exclude` patterns
+fn source_files(dir: &Path, exclude: &[String]) -> Result<Vec<std::path::PathBuf>> {
+    let excluded = exclude_matcher(dir, exclude)?;
+    let walker = WalkBuilder::new(dir)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !excluded.matched(entry.path(), is_dir).is_ignore()
+        })
+        .build();
 
-    for entry in fs::read_dir(path)? {
+    let mut files = Vec::new();
+    for entry in walker {
         let entry = entry?;
-        let entry_path = entry.path();
+        let path = entry.path();
+        let wanted = path.extension().and_then(|e| e.to_str()).is_some_and(|ext| EXTENSIONS.contains(&ext));
+        if wanted && entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
 
-        if entry_path.is_file() {
-            if let Some(ext) = entry_path.extension().and_then(|e| e.to_str()) {
-                if extensions.contains(&ext) {
-                    generate_docs_for_file(ai, &entry_path, format).await?;
-                }
+/// Gitignore-style matcher for `docs.exclude`, rooted at `dir`
+fn exclude_matcher(dir: &Path, exclude: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    for pattern in exclude {
+        builder.add_line(None, pattern)
+            .with_context(|| format!("Invalid docs.exclude pattern '{}'", pattern))?;
+    }
+    builder.build().context("Invalid docs.exclude patterns")
+}
+
+/// How doc comments are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocStyle {
+    /// `///` lines above the item and its attributes
+    Rust,
+    /// A docstring as the first statement of the body
+    Python,
+}
+
+/// An item that can be documented, found line by line
+#[derive(Debug, PartialEq)]
+struct DocItem {
+    /// Line of the item's signature (0-based)
+    line: usize,
+    /// Line the doc comment goes on
+    insert_at: usize,
+    /// Lines of existing docs starting at `insert_at`
+    existing: usize,
+    indent: String,
+    signature: String,
+}
+
+/// Replace `remove` lines at `start` with `insert`
+#[derive(Debug, PartialEq)]
+struct DocEdit {
+    start: usize,
+    remove: usize,
+    insert: Vec<String>,
+}
+
+fn indent_of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn find_items(lines: &[&str], style: DocStyle) -> Vec<DocItem> {
+    match style {
+        DocStyle::Rust => find_rust_items(lines),
+        DocStyle::Python => find_python_items(lines),
+    }
+}
+
+/// `fn`, `struct`, `enum`, `trait` and `impl` items. Docs go above the
+/// item's attributes; tests and items with `#[doc]` attributes are skipped.
+fn find_rust_items(lines: &[&str]) -> Vec<DocItem> {
+    let mut items = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if !RUST_ITEM.is_match(trimmed) {
+            continue;
+        }
+
+        let mut attrs_start = i;
+        while attrs_start > 0 && lines[attrs_start - 1].trim_start().starts_with("#[") {
+            attrs_start -= 1;
+        }
+        let skip = lines[attrs_start..i].iter().any(|attr| {
+            let attr = attr.trim();
+            attr == "#[test]" || attr.starts_with("#[tokio::test") || attr.starts_with("#[doc")
+        });
+        if skip {
+            continue;
+        }
+
+        let mut doc_start = attrs_start;
+        while doc_start > 0 && lines[doc_start - 1].trim_start().starts_with("///") {
+            doc_start -= 1;
+        }
+
+        items.push(DocItem {
+            line: i,
+            insert_at: doc_start,
+            existing: attrs_start - doc_start,
+            indent: indent_of(line).to_string(),
+            signature: trimmed.trim_end_matches('{').trim_end().to_string(),
+        });
+    }
+    items
+}
+
+/// `def` and `class` statements whose signature ends in `:` on its own line
+fn find_python_items(lines: &[&str]) -> Vec<DocItem> {
+    let mut items = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if !PYTHON_ITEM.is_match(trimmed) {
+            continue;
+        }
+
+        // The signature can span lines; it ends where the brackets balance
+        let mut depth = 0i32;
+        let mut end = None;
+        for (j, text) in lines.iter().enumerate().skip(i) {
+            let code = text.split('#').next().unwrap_or("");
+            depth += code.matches(['(', '[', '{']).count() as i32;
+            depth -= code.matches([')', ']', '}']).count() as i32;
+            if depth <= 0 {
+                end = Some(j);
+                break;
             }
         }
+        let Some(end) = end.filter(|&end| lines[end].split('#').next().unwrap_or("").trim_end().ends_with(':')) else {
+            continue;
+        };
+
+        let body = (end + 1..lines.len()).find(|&k| !lines[k].trim().is_empty());
+        let indent = match body {
+            Some(k) if indent_of(lines[k]).len() > indent_of(line).len() => indent_of(lines[k]).to_string(),
+            _ => format!("{}    ", indent_of(line)),
+        };
+
+        let (insert_at, existing) = match body.and_then(|k| docstring_len(&lines[k..]).map(|len| (k, len))) {
+            Some((k, len)) => (k, len),
+            None => (end + 1, 0),
+        };
+
+        items.push(DocItem {
+            line: i,
+            insert_at,
+            existing,
+            indent,
+            signature: trimmed.to_string(),
+        });
     }
+    items
+}
+
+/// Lines of the docstring starting at `lines[0]`, if there is one
+fn docstring_len(lines: &[&str]) -> Option<usize> {
+    let first = lines.first()?.trim_start();
+    let first = first.strip_prefix(['r', 'R']).unwrap_or(first);
+    let quote = ["\"\"\"", "'''"].into_iter().find(|q| first.starts_with(q))?;
+    if first[3..].contains(quote) {
+        return Some(1);
+    }
+    lines.iter().skip(1).position(|line| line.contains(quote)).map(|end| end + 2)
+}
 
-    Ok(())
+/// Strip comment markers and fences the model added despite being asked not to
+fn clean_doc(doc: &str) -> String {
+    let lines: Vec<&str> = doc.trim()
+        .trim_matches('`')
+        .trim_start_matches("\"\"\"")
+        .trim_end_matches("\"\"\"")
+        .lines()
+        .map(|line| {
+            let line = line.trim_end();
+            line.strip_prefix("/// ").or_else(|| line.strip_prefix("///")).unwrap_or(line)
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Doc comment lines for `doc`, indented to match the item
+fn format_doc(doc: &str, indent: &str, style: DocStyle) -> Vec<String> {
+    match style {
+        DocStyle::Rust => doc.lines()
+            .map(|line| match line.trim_end() {
+                "" => format!("{}///", indent),
+                line => format!("{}/// {}", indent, line),
+            })
+            .collect(),
+        DocStyle::Python => {
+            let doc = doc.replace("\"\"\"", "\\\"\\\"\\\"");
+            let lines: Vec<&str> = doc.lines().collect();
+            if lines.len() == 1 {
+                return vec![format!("{}\"\"\"{}\"\"\"", indent, lines[0])];
+            }
+            let mut out = vec![format!("{}\"\"\"{}", indent, lines[0])];
+            out.extend(lines[1..].iter().map(|line| match line.trim_end() {
+                "" => String::new(),
+                line => format!("{}{}", indent, line),
+            }));
+            out.push(format!("{}\"\"\"", indent));
+            out
+        }
+    }
+}
+
+/// `content` with `edits` (sorted, non-overlapping) applied, keeping its line endings
+fn apply_edits(content: &str, edits: &[DocEdit]) -> String {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = content.lines().collect();
+
+    let mut output: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut next = 0;
+    for edit in edits {
+        output.extend(&lines[next..edit.start]);
+        output.extend(edit.insert.iter().map(String::as_str));
+        next = edit.start + edit.remove;
+    }
+    output.extend(&lines[next..]);
+
+    let mut result = output.join(newline);
+    if content.ends_with('\n') {
+        result.push_str(newline);
+    }
+    result
+}
+
+/// Unified diff of `edits` against `lines`, for `--dry-run`
+fn render_diff(path: &str, lines: &[&str], edits: &[DocEdit]) -> String {
+    let mut output = format!("--- a/{0}\n+++ b/{0}\n", path);
+    // How far the new file's line numbers are ahead of the old file's
+    let mut shift = 0isize;
+
+    let mut i = 0;
+    while i < edits.len() {
+        // Edits whose context would overlap share a hunk
+        let mut j = i + 1;
+        while j < edits.len() && edits[j].start <= edits[j - 1].start + edits[j - 1].remove + 2 * CONTEXT {
+            j += 1;
+        }
+        let group = &edits[i..j];
+        let last = &group[group.len() - 1];
+        let start = group[0].start.saturating_sub(CONTEXT);
+        let end = (last.start + last.remove + CONTEXT).min(lines.len());
+
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut line = start;
+        for edit in group {
+            for text in &lines[line..edit.start] {
+                body.push_str(&format!(" {}\n", text));
+            }
+            for text in &lines[edit.start..edit.start + edit.remove] {
+                body.push_str(&format!("-{}\n", text));
+            }
+            for text in &edit.insert {
+                body.push_str(&format!("+{}\n", text));
+            }
+            old_count += edit.start - line + edit.remove;
+            new_count += edit.start - line + edit.insert.len();
+            line = edit.start + edit.remove;
+        }
+        for text in &lines[line..end] {
+            body.push_str(&format!(" {}\n", text));
+        }
+        old_count += end - line;
+        new_count += end - line;
+
+        let new_start = start as isize + shift;
+        output.push_str(&format!("@@ -{},{} +{},{} @@\n", start + 1, old_count, new_start + 1, new_count));
+        output.push_str(&body);
+
+        shift += group.iter().map(|edit| edit.insert.len() as isize - edit.remove as isize).sum::<isize>();
+        i = j;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = "\
+use std::fs;
+
+/// Already documented
+pub fn documented() {}
+
+#[derive(Debug)]
+pub struct Config {
+    name: String,
+}
+
+impl Config {
+    pub(crate) async fn load(path: &str) -> Self {
+        todo!()
+    }
+}
+
+#[test]
+fn test_load() {}
+";
+
+    #[test]
+    fn test_find_rust_items() {
+        let lines: Vec<&str> = RUST.lines().collect();
+        let items = find_rust_items(&lines);
+        let found: Vec<(usize, usize, usize, &str)> = items.iter()
+            .map(|item| (item.line, item.insert_at, item.existing, item.signature.as_str()))
+            .collect();
+        assert_eq!(found, vec![
+            (3, 2, 1, "pub fn documented() {}"),
+            (6, 5, 0, "pub struct Config"),
+            (10, 10, 0, "impl Config"),
+            (11, 11, 0, "pub(crate) async fn load(path: &str) -> Self"),
+        ]);
+        assert_eq!(items[3].indent, "    ");
+    }
+
+    #[test]
+    fn test_write_rust_docs() {
+        let lines: Vec<&str> = RUST.lines().collect();
+        let items = find_rust_items(&lines);
+        let edit = |item: &DocItem, doc: &str| DocEdit {
+            start: item.insert_at,
+            remove: 0,
+            insert: format_doc(doc, &item.indent, DocStyle::Rust),
+        };
+        let edits = vec![edit(&items[1], "Settings.\n\nLoaded once."), edit(&items[3], "Read `path`.")];
+
+        let output = apply_edits(RUST, &edits);
+        assert!(output.contains("\n/// Settings.\n///\n/// Loaded once.\n#[derive(Debug)]\npub struct Config {\n"));
+        assert!(output.contains("impl Config {\n    /// Read `path`.\n    pub(crate) async fn load"));
+        assert!(output.ends_with("fn test_load() {}\n"));
+
+        let diff = render_diff("src/config.rs", &lines, &edits);
+        let expected = [
+            "--- a/src/config.rs",
+            "+++ b/src/config.rs",
+            "@@ -4,4 +4,7 @@",
+            " pub fn documented() {}",
+            " ",
+            "+/// Settings.",
+            "+///",
+            "+/// Loaded once.",
+            " #[derive(Debug)]",
+            " pub struct Config {",
+            "@@ -10,4 +13,5 @@",
+            " ",
+            " impl Config {",
+            "+    /// Read `path`.",
+            "     pub(crate) async fn load(path: &str) -> Self {",
+            "         todo!()",
+        ];
+        assert_eq!(diff.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_python_docstrings() {
+        let source = "\
+class Parser:
+    \"\"\"Existing docstring.\"\"\"
+
+    def parse(self,
+              text):
+        return text
+
+    async def close(self): pass
+
+
+def main():
+
+    '''
+    Multi-line
+    '''
+    run()
+";
+        let lines: Vec<&str> = source.lines().collect();
+        let items = find_python_items(&lines);
+        let found: Vec<(usize, usize, usize, &str)> = items.iter()
+            .map(|item| (item.line, item.insert_at, item.existing, item.indent.as_str()))
+            .collect();
+        // `close` has its body on the signature line and is skipped
+        assert_eq!(found, vec![(0, 1, 1, "    "), (3, 5, 0, "        "), (10, 12, 3, "    ")]);
+
+        let edits = vec![
+            DocEdit { start: 5, remove: 0, insert: format_doc("Parse `text`.", &items[1].indent, DocStyle::Python) },
+            DocEdit { start: 12, remove: 3, insert: format_doc("Entry point.\n\nRuns the app.", &items[2].indent, DocStyle::Python) },
+        ];
+        let output = apply_edits(source, &edits);
+        assert!(output.contains("              text):\n        \"\"\"Parse `text`.\"\"\"\n        return text\n"));
+        assert!(output.contains("def main():\n\n    \"\"\"Entry point.\n\n    Runs the app.\n    \"\"\"\n    run()\n"));
+    }
+
+    #[test]
+    fn test_clean_doc_strips_markers() {
+        assert_eq!(clean_doc("/// Parse it.\n///\n/// Fast."), "Parse it.\n\nFast.");
+        assert_eq!(clean_doc("\"\"\"Parse it.\"\"\""), "Parse it.");
+        assert_eq!(clean_doc("  Plain.  "), "Plain.");
+    }
+
+    #[test]
+    fn test_source_files_honor_exclude() {
+        let dir = std::env::temp_dir().join(format!("bahn-docs-{}-{}", std::process::id(), rand::random::<u32>()));
+        for file in ["src/lib.rs", "src/gen/out.rs", "vendor/dep.py", "notes.txt", "app.py"] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let files = source_files(&dir, &["vendor".to_string(), "src/gen/".to_string()]).unwrap();
+        let relative: Vec<String> = files.iter()
+            .map(|f| f.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(relative, ["app.py", "src/lib.rs"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_docs_without_api_key() {
+        let dir = std::env::temp_dir().join(format!("bahn-docs-no-key-{}-{}", std::process::id(), rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, RUST).unwrap();
+        let mut config = Config::default();
+        config.ai.provider = "openai".to_string();
+        let config = config
+            .with_credentials(&mut crate::config::ConfigSources::default(), &crate::core::credentials::NoKeyring, |_| None);
+        let options = || DocsOptions { format: "rust".to_string(), write: true, dry_run: true };
+
+        let err = run(&config, file.to_str().unwrap(), options()).await.unwrap_err();
+        assert!(err.to_string().contains("OPENAI_API_KEY"), "{}", err);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), RUST);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,409 @@
+//! Doctor command - check the environment and repository for common problems.
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+use colored::Colorize;
+use git2::Repository;
+
+use crate::config::{Config, ConfigIssue};
+use crate::core::git;
+use crate::core::lock::{self, LockState};
+use crate::core::report::{self, say, CheckStatus, DoctorCheck, DoctorReport};
+
+/// Run every check and print the results.
+///
+/// `config` is the result of loading configuration, so a broken config file
+/// is reported as a failed check instead of stopping the command.
+pub async fn run(config: Result<Config>, config_path: Option<&str>, offline: bool) -> Result<()> {
+    let mut checks = vec![git_binary(git_version().as_deref(), git2::Version::get().libgit2_version())];
+
+    let repo = git::open_repo(None);
+    checks.push(repository(&repo));
+
+    let git_config = match &repo {
+        Ok(repo) => repo.config().ok(),
+        Err(_) => git2::Config::open_default().ok(),
+    };
+    let get = |key: &str| git_config.as_ref().and_then(|c| c.get_string(key).ok()).filter(|v| !v.is_empty());
+    checks.push(identity(get("user.name"), get("user.email")));
+
+    match &repo {
+        Ok(repo) => {
+            checks.push(head(repo));
+            checks.push(upstream(repo));
+            let path = lock::lock_path(repo);
+            checks.push(lock_file(lock::lock_state(&path), &path));
+        }
+        Err(_) => {
+            for name in ["head", "upstream", "lock"] {
+                checks.push(DoctorCheck::skip(name, "Skipped (not in a repository)"));
+            }
+        }
+    }
+
+    checks.push(config_files(Config::validate_layers(&Config::layer_paths(config_path))));
+
+    match &config {
+        Ok(config) => {
+            checks.push(api_key(config));
+            checks.push(match (offline, config.api_key()) {
+                (true, _) => DoctorCheck::skip("api", "Skipped (--offline)"),
+                (false, None) => DoctorCheck::skip("api", "Skipped (no API key)"),
+                (false, Some(key)) => {
+                    let ai = config.ai_client(key);
+                    api_ping(ai.ping().await, &config.ai.model)
+                }
+            });
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::fail("config", format!("Failed to load: {:#}", e), "Fix the file, then run `bahn config validate`"));
+        }
+    }
+
+    let report = DoctorReport::new(checks);
+
+    if report::is_json() {
+        report::emit(&report)?;
+        if report.failures > 0 {
+            // The report is the whole output; an error document would follow it
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    print_report(&report);
+
+    if report.failures > 0 {
+        anyhow::bail!("{} check(s) failed", report.failures);
+    }
+    Ok(())
+}
+
+fn print_report(report: &DoctorReport) {
+    say!("{}", "gitBahn - Doctor".bold().cyan());
+    say!();
+
+    for check in &report.checks {
+        let icon = match check.status {
+            CheckStatus::Pass => "✓".green().bold(),
+            CheckStatus::Warn => "!".yellow().bold(),
+            CheckStatus::Fail => "✗".red().bold(),
+            CheckStatus::Skip => "-".dimmed(),
+        };
+        say!("  {} {:<10} {}", icon, check.name, check.message);
+        if let Some(hint) = &check.hint {
+            say!("    {} {}", "→".dimmed(), hint.dimmed());
+        }
+    }
+
+    let passed = report.checks.iter().filter(|c| c.status == CheckStatus::Pass).count();
+    say!();
+    say!("{} passed, {} warning(s), {} failure(s)", passed, report.warnings, report.failures);
+}
+
+/// Output of `git --version`, if git runs at all
+fn git_version() -> Option<String> {
+    let output = Command::new("git").arg("--version").output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The git binary is on PATH (push shells out to it)
+fn git_binary(version: Option<&str>, libgit2: (u32, u32, u32)) -> DoctorCheck {
+    let libgit2 = format!("libgit2 {}.{}.{}", libgit2.0, libgit2.1, libgit2.2);
+    match version {
+        Some(version) => DoctorCheck::pass("git", format!("{}, {}", version, libgit2)),
+        None => DoctorCheck::fail(
+            "git",
+            format!("git binary not found ({} is built in)", libgit2),
+            "Install git and make sure it is on PATH; `bahn push` runs it",
+        ),
+    }
+}
+
+/// The current directory is inside a git repository
+fn repository(repo: &Result<Repository>) -> DoctorCheck {
+    match repo {
+        Ok(repo) => {
+            let root = repo.workdir().unwrap_or_else(|| repo.path());
+            DoctorCheck::pass("repository", root.display().to_string())
+        }
+        Err(e) => DoctorCheck::fail("repository", e.to_string(), "Run bahn inside a git repository, or `git init` one"),
+    }
+}
+
+/// user.name and user.email are set so commits can be created
+fn identity(name: Option<String>, email: Option<String>) -> DoctorCheck {
+    match (name, email) {
+        (Some(name), Some(email)) => DoctorCheck::pass("identity", format!("{} <{}>", name, email)),
+        (name, email) => {
+            let missing: Vec<&str> = [("user.name", name.is_none()), ("user.email", email.is_none())]
+                .iter()
+                .filter(|(_, missing)| *missing)
+                .map(|(key, _)| *key)
+                .collect();
+            let hints: Vec<String> = missing.iter()
+                .map(|key| format!("git config --global {} \"...\"", key))
+                .collect();
+            DoctorCheck::fail("identity", format!("{} not set", missing.join(" and ")), hints.join("; "))
+        }
+    }
+}
+
+/// HEAD points at a branch
+fn head(repo: &Repository) -> DoctorCheck {
+    if repo.head_detached().unwrap_or(false) {
+        return DoctorCheck::warn("head", "HEAD is detached", "Check out a branch before committing: git switch <branch>");
+    }
+    match repo.head() {
+        Ok(head) => DoctorCheck::pass("head", format!("On branch {}", head.shorthand().unwrap_or("?"))),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => DoctorCheck::pass("head", "On a new branch with no commits yet"),
+        Err(e) => DoctorCheck::fail("head", e.message().to_string(), "Check the repository with `git status`"),
+    }
+}
+
+/// An origin remote exists and the current branch tracks something
+fn upstream(repo: &Repository) -> DoctorCheck {
+    if repo.find_remote("origin").is_err() {
+        return DoctorCheck::warn("upstream", "No 'origin' remote", "git remote add origin <url> (needed for `bahn push`)");
+    }
+
+    let branch = match repo.head() {
+        Ok(head) if head.is_branch() => head,
+        _ => return DoctorCheck::skip("upstream", "Skipped (not on a branch)"),
+    };
+    let name = branch.shorthand().unwrap_or("HEAD").to_string();
+
+    match branch.name().map(|refname| repo.branch_upstream_name(refname)) {
+        Some(Ok(upstream)) => DoctorCheck::pass(
+            "upstream",
+            format!("{} tracks {}", name, upstream.as_str().unwrap_or("?").trim_start_matches("refs/remotes/")),
+        ),
+        _ => DoctorCheck::warn(
+            "upstream",
+            format!("{} has no upstream", name),
+            format!("`bahn push` sets it, or run git push -u origin {}", name),
+        ),
+    }
+}
+
+/// Nothing holds the repository lock, or its holder is alive
+fn lock_file(state: LockState, path: &Path) -> DoctorCheck {
+    match state {
+        LockState::Free => DoctorCheck::pass("lock", "Not held"),
+        LockState::Held(info) => DoctorCheck::warn(
+            "lock",
+            match info {
+                Some(info) => format!("Held by `bahn {}` (PID {} on {})", info.command, info.pid, info.hostname),
+                None => "Held by another bahn instance".to_string(),
+            },
+            "Another bahn instance is running; see `bahn lock status`",
+        ),
+        LockState::Stale(_) => DoctorCheck::warn(
+            "lock",
+            format!("Stale lock at {}", path.display()),
+            "Run `bahn lock release`",
+        ),
+    }
+}
+
+/// Config files parse and contain only known keys
+fn config_files(issues: Result<Vec<(String, ConfigIssue)>>) -> DoctorCheck {
+    let issues = match issues {
+        Ok(issues) => issues,
+        Err(e) => return DoctorCheck::fail("config", format!("{:#}", e), "Fix the file, then run `bahn config validate`"),
+    };
+
+    let describe = |issues: &[&(String, ConfigIssue)]| issues.iter()
+        .map(|(file, issue)| format!("{}: {}", file, issue))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    let (unknown, invalid): (Vec<_>, Vec<_>) = issues.iter()
+        .partition(|(_, issue)| matches!(issue, ConfigIssue::UnknownKey { .. }));
+
+    if !invalid.is_empty() {
+        DoctorCheck::fail("config", describe(&invalid), "Fix the values, then run `bahn config validate`")
+    } else if !unknown.is_empty() {
+        DoctorCheck::warn("config", describe(&unknown), "Unknown keys are ignored; check for typos")
+    } else {
+        DoctorCheck::pass("config", "No problems found")
+    }
+}
+
+/// An API key is configured for the active provider
+fn api_key(config: &Config) -> DoctorCheck {
+    let hint = match config.ai.provider.as_str() {
+        "openai" => "Set OPENAI_API_KEY, run `bahn config set-key openai`, or set ai.base_url for a server without keys",
+        _ => "Set ANTHROPIC_API_KEY or run `bahn config set-key anthropic`",
+    };
+    match config.api_key() {
+        Some(_) => DoctorCheck::pass("api key", format!("Set for {}", config.ai.provider)),
+        None => DoctorCheck::fail("api key", "No API key configured", hint),
+    }
+}
+
+/// The API accepts the key and knows the configured model
+fn api_ping(result: Result<()>, model: &str) -> DoctorCheck {
+    let error = match result {
+        Ok(()) => return DoctorCheck::pass("api", format!("Reachable, model {} available", model)),
+        Err(e) => e.to_string(),
+    };
+
+    if error.contains("401") || error.contains("authentication_error") {
+        DoctorCheck::fail("api", "API key was rejected", "Check the key, or store a new one with `bahn config set-key anthropic`")
+    } else if error.contains("404") || error.contains("not_found_error") {
+        DoctorCheck::fail("api", format!("Model '{}' not found", model), "Set ai.model to a valid model name, or pass --model")
+    } else {
+        DoctorCheck::fail("api", error, "Check your network connection, or run with --offline")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::lock::LockInfo;
+
+    fn temp_repo() -> (std::path::PathBuf, Repository) {
+        let dir = std::env::temp_dir().join(format!("bahn-doctor-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = Repository::init(&dir).unwrap();
+        (dir, repo)
+    }
+
+    fn commit(repo: &Repository) -> git2::Oid {
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial", &tree, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_git_binary() {
+        let check = git_binary(Some("git version 2.43.0"), (1, 7, 1));
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.message, "git version 2.43.0, libgit2 1.7.1");
+
+        let check = git_binary(None, (1, 7, 1));
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hint.unwrap().contains("PATH"));
+    }
+
+    #[test]
+    fn test_repository() {
+        let (dir, repo) = temp_repo();
+        assert_eq!(repository(&Ok(repo)).status, CheckStatus::Pass);
+        assert_eq!(repository(&Err(anyhow::anyhow!("Not a git repository: ."))).status, CheckStatus::Fail);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_identity() {
+        let check = identity(Some("Ada".to_string()), Some("ada@example.com".to_string()));
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.message, "Ada <ada@example.com>");
+
+        let check = identity(Some("Ada".to_string()), None);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(check.message, "user.email not set");
+        assert_eq!(check.hint.as_deref(), Some("git config --global user.email \"...\""));
+
+        assert_eq!(identity(None, None).message, "user.name and user.email not set");
+    }
+
+    #[test]
+    fn test_head() {
+        let (dir, repo) = temp_repo();
+        assert_eq!(head(&repo).message, "On a new branch with no commits yet");
+
+        let oid = commit(&repo);
+        assert_eq!(head(&repo).status, CheckStatus::Pass);
+
+        repo.set_head_detached(oid).unwrap();
+        let check = head(&repo);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(check.message, "HEAD is detached");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_upstream() {
+        let (dir, repo) = temp_repo();
+        commit(&repo);
+        assert_eq!(upstream(&repo).message, "No 'origin' remote");
+
+        repo.remote("origin", "https://example.com/repo.git").unwrap();
+        let check = upstream(&repo);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.message.ends_with("has no upstream"));
+
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        let mut config = repo.config().unwrap();
+        config.set_str(&format!("branch.{}.remote", branch), "origin").unwrap();
+        config.set_str(&format!("branch.{}.merge", branch), &format!("refs/heads/{}", branch)).unwrap();
+        let check = upstream(&repo);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.message, format!("{} tracks origin/{}", branch, branch));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_lock_file() {
+        let path = Path::new("/repo/.git/gitbahn.lock");
+        assert_eq!(lock_file(LockState::Free, path).status, CheckStatus::Pass);
+
+        let info = LockInfo::current("auto");
+        let check = lock_file(LockState::Held(Some(info)), path);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.message.starts_with("Held by `bahn auto`"));
+
+        let check = lock_file(LockState::Stale(None), path);
+        assert_eq!(check.hint.as_deref(), Some("Run `bahn lock release`"));
+    }
+
+    #[test]
+    fn test_config_files() {
+        assert_eq!(config_files(Ok(vec![])).status, CheckStatus::Pass);
+
+        let unknown = ConfigIssue::UnknownKey { key: "commit.atomc".to_string(), suggestion: Some("commit.atomic".to_string()) };
+        let check = config_files(Ok(vec![(".bahn.toml".to_string(), unknown)]));
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.message.contains("did you mean 'commit.atomic'"));
+
+        let invalid = ConfigIssue::InvalidValue { key: "review.strictness".to_string(), message: "must be one of ...".to_string() };
+        assert_eq!(config_files(Ok(vec![(".bahn.toml".to_string(), invalid)])).status, CheckStatus::Fail);
+        assert_eq!(config_files(Err(anyhow::anyhow!("Failed to read config file"))).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_api_key() {
+        let mut config = Config::default();
+        config.ai.anthropic_api_key = None;
+        assert_eq!(api_key(&config).status, CheckStatus::Fail);
+
+        config.ai.anthropic_api_key = Some("sk-ant-test".to_string());
+        assert_eq!(api_key(&config).status, CheckStatus::Pass);
+
+        // The openai provider needs its own key, unless it points at a local server
+        config.ai.provider = "openai".to_string();
+        let check = api_key(&config);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hint.unwrap().contains("OPENAI_API_KEY"));
+        config.ai.base_url = Some("http://localhost:8000/v1".to_string());
+        assert_eq!(api_key(&config).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_api_ping() {
+        assert_eq!(api_ping(Ok(()), "claude-x").status, CheckStatus::Pass);
+
+        let rejected = api_ping(Err(anyhow::anyhow!("Claude API error (401 Unauthorized): authentication_error")), "claude-x");
+        assert_eq!(rejected.message, "API key was rejected");
+
+        let missing = api_ping(Err(anyhow::anyhow!("Claude API error (404 Not Found): not_found_error")), "claude-x");
+        assert_eq!(missing.message, "Model 'claude-x' not found");
+
+        let offline = api_ping(Err(anyhow::anyhow!("Claude API request failed after 4 attempts")), "claude-x");
+        assert_eq!(offline.status, CheckStatus::Fail);
+        assert!(offline.hint.unwrap().contains("--offline"));
+    }
+}
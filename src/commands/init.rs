@@ -68,9 +68,27 @@ pub fn run(path: Option<&str>) -> Result<()> {
     println!("{}", "gitBahn initialized!".green().bold());
     println!();
     println!("Next steps:");
-    println!("  1. Set ANTHROPIC_API_KEY environment variable");
+    println!("  1. Run {} or set ANTHROPIC_API_KEY", "bahn config set-key anthropic".cyan());
     println!("  2. Run {} to generate AI commit messages", "bahn commit".cyan());
     println!("  3. Run {} for autonomous mode", "bahn auto --watch".cyan());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_without_api_key() {
+        let dir = std::env::temp_dir().join(format!("bahn-init-no-key-{}-{}", std::process::id(), rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+
+        run(Some(dir.to_str().unwrap())).unwrap();
+        assert!(dir.join(".git").exists());
+        assert_eq!(fs::read_to_string(dir.join(".bahn.toml")).unwrap(), DEFAULT_CONFIG);
+        assert_eq!(fs::read_to_string(dir.join(".gitignore")).unwrap(), ".bahn.toml\n");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
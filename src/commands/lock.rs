@@ -0,0 +1,52 @@
+//! Lock command - inspect and release the repository lock.
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::core::git;
+use crate::core::lock::{self, LockState};
+
+/// Show who holds the repository lock
+pub fn status() -> Result<()> {
+    let repo = git::open_repo(None)?;
+    let path = lock::lock_path(&repo);
+
+    match lock::lock_state(&path) {
+        LockState::Free => {
+            println!("{} No bahn instance holds the lock", "✓".green().bold());
+        }
+        LockState::Held(info) => {
+            println!("{} Lock held ({})", "●".yellow().bold(), path.display());
+            match info {
+                Some(info) => print!("{}", info),
+                None => println!("  {}", "Holder is still writing its metadata".dimmed()),
+            }
+        }
+        LockState::Stale(info) => {
+            println!("{} Stale lock ({})", "!".red().bold(), path.display());
+            if let Some(info) = info {
+                print!("{}", info);
+            }
+            println!();
+            println!("{}", "Run `bahn lock release` to remove it.".dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the repository lock
+pub fn release(force: bool) -> Result<()> {
+    let repo = git::open_repo(None)?;
+
+    match lock::release(&repo, force)? {
+        LockState::Free => println!("{} No lock to release", "✓".green().bold()),
+        LockState::Stale(_) => println!("{} Removed stale lock", "✓".green().bold()),
+        LockState::Held(info) => {
+            let holder = info.map(|i| format!(" held by PID {}", i.pid)).unwrap_or_default();
+            println!("{} Force-released lock{}", "!".yellow().bold(), holder);
+        }
+    }
+
+    Ok(())
+}
@@ -2,7 +2,8 @@
 
 use anyhow::{Context, Result};
 use colored::Colorize;
-use git2::MergeOptions;
+use git2::build::CheckoutBuilder;
+use git2::{MergeOptions, Oid, Repository};
 
 use crate::config::Config;
 use crate::core::ai::AiClient;
@@ -66,7 +67,7 @@ pub async fn run(config: &Config, branch: &str, auto_resolve: bool) -> Result<()
         let tree_id = index.write_tree()?;
         let tree = repo.find_tree(tree_id)?;
 
-        let msg = format!("Merge branch '{}' into {}", branch, current);
+        let msg = merge_message(branch, &current);
         repo.commit(
             Some("HEAD"),
             &sig,
@@ -84,10 +85,31 @@ pub async fn run(config: &Config, branch: &str, auto_resolve: bool) -> Result<()
 }
 
 async fn resolve_conflicts_with_ai(config: &Config, repo: &git2::Repository) -> Result<()> {
-    let api_key = config.anthropic_api_key()
-        .context("ANTHROPIC_API_KEY not set")?;
+    let api_key = config.require_api_key()?;
 
-    let ai = AiClient::new(api_key.to_string(), Some(config.ai.model.clone()));
+    let ai = config.ai_client(api_key);
+    resolve_conflicts(&ai, repo).await?;
+
+    // Create merge commit
+    let mut index = repo.index()?;
+    let sig = repo.signature()?;
+    let head = repo.head()?.peel_to_commit()?;
+    let tree_id = index.write_tree()?;
+    let tree = repo.find_tree(tree_id)?;
+
+    let msg = "Merge with AI-resolved conflicts";
+    repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &[&head])?;
+
+    repo.cleanup_state()?;
+    println!("{} All conflicts resolved with AI", "".green());
+
+    Ok(())
+}
+
+/// Resolve the index's conflicts with the AI, writing and staging each file.
+/// Fails if any conflict is left, e.g. a file deleted on one side.
+pub(crate) async fn resolve_conflicts(ai: &AiClient, repo: &git2::Repository) -> Result<()> {
+    let workdir = repo.workdir().context("Cannot resolve conflicts in a bare repository")?;
     let mut index = repo.index()?;
 
     let conflicts: Vec<_> = index.conflicts()?.collect();
@@ -106,7 +128,7 @@ async fn resolve_conflicts_with_ai(config: &Config, repo: &git2::Repository) ->
             let resolved = ai.resolve_conflict(&ancestor_content, &ours_content, &theirs_content).await?;
 
             // Write resolved content
-            std::fs::write(&path, &resolved)?;
+            std::fs::write(workdir.join(&path), &resolved)?;
 
             // Stage the resolved file
             index.add_path(std::path::Path::new(&path))?;
@@ -117,24 +139,230 @@ async fn resolve_conflicts_with_ai(config: &Config, repo: &git2::Repository) ->
 
     index.write()?;
 
-    // Create merge commit
-    let sig = repo.signature()?;
-    let head = repo.head()?.peel_to_commit()?;
-    let tree_id = index.write_tree()?;
-    let tree = repo.find_tree(tree_id)?;
+    if index.has_conflicts() {
+        anyhow::bail!("Some conflicts need resolving by hand (a file deleted on one side?). Check `git status`.");
+    }
+    Ok(())
+}
 
-    let msg = "Merge with AI-resolved conflicts";
-    repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &[&head])?;
+/// How merging one branch into another would go
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MergePlan {
+    /// The target already contains the source
+    UpToDate,
+    /// The target can move forward to the source
+    FastForward,
+    /// The branches diverged but merge cleanly
+    Merge,
+    /// The branches diverged and these files conflict
+    Conflicts(Vec<String>),
+}
+
+/// Work out how `source` would merge into `target` without touching the working tree
+pub(crate) fn plan_merge(repo: &Repository, source: &str, target: &str) -> Result<MergePlan> {
+    let ours = branch_commit(repo, target)?;
+    let theirs = branch_commit(repo, source)?;
+
+    if ours.id() == theirs.id() || repo.graph_descendant_of(ours.id(), theirs.id())? {
+        return Ok(MergePlan::UpToDate);
+    }
+    if repo.graph_descendant_of(theirs.id(), ours.id())? {
+        return Ok(MergePlan::FastForward);
+    }
+
+    let index = repo.merge_commits(&ours, &theirs, None)?;
+    if !index.has_conflicts() {
+        return Ok(MergePlan::Merge);
+    }
+    let mut files: Vec<String> = index.conflicts()?
+        .filter_map(|conflict| conflict.ok())
+        .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+        .collect();
+    files.dedup();
+    Ok(MergePlan::Conflicts(files))
+}
+
+/// Merge `source` (the checked-out branch) into `target` as `plan` says, returning
+/// the new tip of `target`, or `None` when it was already up to date.
+///
+/// Fast-forwards and clean merges only move `target`. Conflicts are resolved with
+/// `ai` on a checkout of `target`, then `source` is checked out again; without `ai`
+/// they're refused.
+pub(crate) async fn apply_merge(
+    repo: &Repository,
+    source: &str,
+    target: &str,
+    plan: &MergePlan,
+    ai: Option<&AiClient>,
+) -> Result<Option<Oid>> {
+    let ours = branch_commit(repo, target)?;
+    let theirs = branch_commit(repo, source)?;
+    let refname = format!("refs/heads/{}", target);
+
+    match plan {
+        MergePlan::UpToDate => Ok(None),
+        MergePlan::FastForward => {
+            let log = format!("merge {}: Fast-forward", source);
+            repo.find_reference(&refname)?.set_target(theirs.id(), &log)?;
+            Ok(Some(theirs.id()))
+        }
+        MergePlan::Merge => {
+            let mut index = repo.merge_commits(&ours, &theirs, None)?;
+            let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+            let sig = repo.signature()?;
+            let oid = repo.commit(Some(&refname), &sig, &sig, &merge_message(source, target), &tree, &[&ours, &theirs])?;
+            Ok(Some(oid))
+        }
+        MergePlan::Conflicts(files) => {
+            let Some(ai) = ai else {
+                anyhow::bail!(
+                    "Merging '{}' into '{}' would conflict in {}",
+                    source, target, files.join(", ")
+                );
+            };
+            let mut opts = git2::StatusOptions::new();
+            opts.include_untracked(false);
+            if !repo.statuses(Some(&mut opts))?.is_empty() {
+                anyhow::bail!("Commit or stash your changes before merging into '{}'", target);
+            }
+
+            checkout_branch(repo, target)?;
+            let merged = merge_resolving_conflicts(repo, ai, source, target, &theirs).await;
+            if merged.is_err() {
+                // Drop the half-done merge so the source branch can be checked out again
+                repo.cleanup_state()?;
+                repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+            }
+            checkout_branch(repo, source)?;
+            merged.map(Some)
+        }
+    }
+}
+
+/// Merge `source` into the checked-out `target`, resolving conflicts with the AI
+async fn merge_resolving_conflicts(
+    repo: &Repository,
+    ai: &AiClient,
+    source: &str,
+    target: &str,
+    theirs: &git2::Commit<'_>,
+) -> Result<Oid> {
+    let annotated = repo.find_annotated_commit(theirs.id())?;
+    repo.merge(&[&annotated], Some(&mut MergeOptions::new()), None)?;
+    resolve_conflicts(ai, repo).await?;
 
+    let head = repo.head()?.peel_to_commit()?;
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let sig = repo.signature()?;
+    let oid = repo.commit(Some("HEAD"), &sig, &sig, &merge_message(source, target), &tree, &[&head, theirs])?;
     repo.cleanup_state()?;
-    println!("{} All conflicts resolved with AI", "".green());
+    Ok(oid)
+}
 
+/// Check out a local branch, keeping uncommitted changes that don't clash
+fn checkout_branch(repo: &Repository, name: &str) -> Result<()> {
+    let refname = format!("refs/heads/{}", name);
+    let tree = repo.find_reference(&refname)?.peel_to_tree()?;
+    repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+        .with_context(|| format!("Failed to check out '{}'", name))?;
+    repo.set_head(&refname)?;
     Ok(())
 }
 
+fn branch_commit<'r>(repo: &'r Repository, name: &str) -> Result<git2::Commit<'r>> {
+    let branch = repo.find_branch(name, git2::BranchType::Local)
+        .with_context(|| format!("Branch not found: {}", name))?;
+    Ok(branch.get().peel_to_commit()?)
+}
+
+fn merge_message(branch: &str, into: &str) -> String {
+    format!("Merge branch '{}' into {}", branch, into)
+}
+
 fn get_blob_content(repo: &git2::Repository, oid: git2::Oid) -> Result<String> {
     let blob = repo.find_blob(oid)?;
     let content = std::str::from_utf8(blob.content())
         .context("Invalid UTF-8 in blob")?;
     Ok(content.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn temp_repo(label: &str) -> (Repository, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("bahn-merge-{}-{}-{}", label, std::process::id(), rand::random::<u32>()));
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        (repo, dir)
+    }
+
+    fn commit_file(repo: &Repository, dir: &Path, file: &str, content: &str, message: &str) -> Oid {
+        std::fs::write(dir.join(file), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+        git::create_commit(repo, message, false).unwrap()
+    }
+
+    /// A repo on branch `work`, created from `trunk` after one commit
+    fn repo_with_branches(label: &str) -> (Repository, PathBuf) {
+        let (repo, dir) = temp_repo(label);
+        commit_file(&repo, &dir, "app.txt", "one\n", "Add app");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("trunk", &head, true).unwrap();
+        repo.branch("work", &head, true).unwrap();
+        drop(head);
+        checkout_branch(&repo, "work").unwrap();
+        (repo, dir)
+    }
+
+    #[tokio::test]
+    async fn test_fast_forward_and_clean_merges_leave_the_checkout_alone() {
+        let (repo, dir) = repo_with_branches("clean");
+        assert_eq!(plan_merge(&repo, "work", "trunk").unwrap(), MergePlan::UpToDate);
+
+        let tip = commit_file(&repo, &dir, "notes.txt", "notes\n", "Add notes");
+        let plan = plan_merge(&repo, "work", "trunk").unwrap();
+        assert_eq!(plan, MergePlan::FastForward);
+        assert_eq!(apply_merge(&repo, "work", "trunk", &plan, None).await.unwrap(), Some(tip));
+        assert_eq!(branch_commit(&repo, "trunk").unwrap().id(), tip);
+
+        // trunk moves on by itself; the branches diverge without conflicting
+        checkout_branch(&repo, "trunk").unwrap();
+        commit_file(&repo, &dir, "other.txt", "other\n", "Add other");
+        checkout_branch(&repo, "work").unwrap();
+        commit_file(&repo, &dir, "app.txt", "two\n", "Change app");
+
+        let plan = plan_merge(&repo, "work", "trunk").unwrap();
+        assert_eq!(plan, MergePlan::Merge);
+        let merged = apply_merge(&repo, "work", "trunk", &plan, None).await.unwrap().unwrap();
+        let merged = repo.find_commit(merged).unwrap();
+        assert_eq!(merged.parent_count(), 2);
+        assert_eq!(merged.message(), Some("Merge branch 'work' into trunk"));
+        assert!(merged.tree().unwrap().get_name("other.txt").is_some());
+        assert_eq!(git::current_branch(&repo).unwrap(), "work");
+        assert!(!dir.join("other.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_merge_is_refused_without_ai() {
+        let (repo, dir) = repo_with_branches("conflict");
+        checkout_branch(&repo, "trunk").unwrap();
+        commit_file(&repo, &dir, "app.txt", "trunk\n", "Change app on trunk");
+        checkout_branch(&repo, "work").unwrap();
+        commit_file(&repo, &dir, "app.txt", "work\n", "Change app on work");
+
+        let plan = plan_merge(&repo, "work", "trunk").unwrap();
+        assert_eq!(plan, MergePlan::Conflicts(vec!["app.txt".to_string()]));
+        let before = branch_commit(&repo, "trunk").unwrap().id();
+        let err = apply_merge(&repo, "work", "trunk", &plan, None).await.unwrap_err();
+        assert!(err.to_string().contains("app.txt"));
+        assert_eq!(branch_commit(&repo, "trunk").unwrap().id(), before);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+}
@@ -1,10 +1,19 @@
 pub mod auto;
+pub mod cache;
+pub mod changelog;
 pub mod commit;
 pub mod docs;
+pub mod doctor;
+pub mod hook;
 pub mod init;
+pub mod lock;
 pub mod merge;
 pub mod push;
 pub mod review;
+pub mod revert;
 pub mod rewrite;
+pub mod secrets;
+pub mod squash;
+pub mod stats;
 pub mod status;
 pub mod undo;
@@ -4,10 +4,11 @@ use std::process::Command;
 
 use anyhow::{Context, Result};
 use colored::Colorize;
-use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 use crate::core::git;
+use crate::core::github::{GitHub, NewPullRequest};
+use crate::core::report::{self, say, PushReport};
 
 /// Options for push command
 pub struct PushOptions {
@@ -41,51 +42,53 @@ impl Default for PushOptions {
     }
 }
 
-/// GitHub PR creation request
-#[derive(Debug, Serialize)]
-struct CreatePrRequest {
-    title: String,
-    body: String,
-    head: String,
-    base: String,
-    draft: bool,
-}
-
-/// GitHub PR response
-#[derive(Debug, Deserialize)]
-struct PrResponse {
-    #[allow(dead_code)]
-    number: u64,
-    html_url: String,
-}
-
 /// Run the push command
 pub async fn run(config: &Config, options: PushOptions) -> Result<()> {
-    let repo = git::open_repo(None)?;
+    push_repo(git::open_repo(None)?, config, options).await
+}
+
+/// Push the current branch of `repo`, opening a PR if `options` ask for one
+async fn push_repo(repo: git2::Repository, config: &Config, options: PushOptions) -> Result<()> {
     let branch = git::current_branch(&repo)?;
 
     // Check if on protected branch
-    if is_protected_branch(&branch) && !options.force {
-        println!(
+    if git::is_protected(&repo, config)? && !options.force {
+        say!(
             "{} You're on '{}'. Consider using a feature branch.",
             "Warning:".yellow(),
             branch
         );
     }
 
+    // Fail before pushing if a PR can't be created
+    let token = if options.create_pr {
+        Some(config.github_token()
+            .context("GitHub token required for PR creation. Set GITHUB_TOKEN env var or add to .bahn.toml")?)
+    } else {
+        None
+    };
+
     // Push to remote
-    println!("{} Pushing to remote...", "→".cyan());
-    push_to_remote(&branch, options.force, options.set_upstream)?;
-    println!("{} Pushed successfully", "✓".green());
+    say!("{} Pushing to remote...", "→".cyan());
+    push_to_remote(&repo, &branch, options.force, options.set_upstream)?;
+    say!("{} Pushed successfully", "✓".green());
 
     // Create PR if requested
-    if options.create_pr {
-        let token = config.github_token()
-            .context("GitHub token required for PR creation. Set GITHUB_TOKEN env var or add to .bahn.toml")?;
+    let mut pr_url = None;
+    if let Some(token) = token {
+        if commits_ahead(&repo, &options.base)? == Some(0) {
+            say!(
+                "{} '{}' has no commits ahead of '{}'; not opening an empty pull request.",
+                "Warning:".yellow(),
+                branch,
+                options.base
+            );
+            return emit_report(branch, options.force, None);
+        }
 
-        println!("{} Creating pull request...", "→".cyan());
+        say!("{} Creating pull request...", "→".cyan());
 
-        let pr_url = create_pull_request(
+        let url = create_pull_request(
             token,
             &branch,
             &options.base,
@@ -95,21 +98,36 @@ pub async fn run(config: &Config, options: PushOptions) -> Result<()> {
             &repo,
         ).await?;
 
-        println!("{} Pull request created: {}", "✓".green(), pr_url.cyan());
+        say!("{} Pull request created: {}", "✓".green(), url.cyan());
+        pr_url = Some(url);
+    }
+
+    emit_report(branch, options.force, pr_url)
+}
+
+fn emit_report(branch: String, forced: bool, pr_url: Option<String>) -> Result<()> {
+    if report::is_json() {
+        report::emit(&PushReport {
+            remote: "origin".to_string(),
+            pushed_refs: vec![format!("refs/heads/{}", branch)],
+            branch,
+            forced,
+            pr_url,
+        })?;
     }
 
     Ok(())
 }
 
-/// Push to remote
-fn push_to_remote(branch: &str, force: bool, set_upstream: bool) -> Result<()> {
+/// Push `branch` of `repo` to origin
+pub(crate) fn push_to_remote(repo: &git2::Repository, branch: &str, force: bool, set_upstream: bool) -> Result<()> {
     let mut args = vec!["push"];
 
     if set_upstream {
         args.push("-u");
-        args.push("origin");
     }
 
+    args.push("origin");
     args.push(branch);
 
     if force {
@@ -118,6 +136,7 @@ fn push_to_remote(branch: &str, force: bool, set_upstream: bool) -> Result<()> {
 
     let output = Command::new("git")
         .args(&args)
+        .current_dir(git::repo_root(repo)?)
         .output()
         .context("Failed to execute git push")?;
 
@@ -140,7 +159,7 @@ async fn create_pull_request(
     repo: &git2::Repository,
 ) -> Result<String> {
     // Get repository info from remote URL
-    let (owner, repo_name) = get_repo_info(repo)?;
+    let github = GitHub::for_repo(token, repo)?;
 
     // Generate title from branch name or commits if not provided
     let title = title.unwrap_or_else(|| generate_pr_title(head));
@@ -148,7 +167,7 @@ async fn create_pull_request(
     // Generate body from commits if not provided
     let body = body.unwrap_or_else(|| generate_pr_body(repo, base).unwrap_or_default());
 
-    let request = CreatePrRequest {
+    let request = NewPullRequest {
         title,
         body,
         head: head.to_string(),
@@ -156,70 +175,10 @@ async fn create_pull_request(
         draft,
     };
 
-    let client = reqwest::Client::new();
-    let url = format!("https://api.github.com/repos/{}/{}/pulls", owner, repo_name);
-
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "gitBahn")
-        .header("X-GitHub-Api-Version", "2022-11-28")
-        .json(&request)
-        .send()
-        .await
-        .context("Failed to send PR request")?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        anyhow::bail!("GitHub API error ({}): {}", status, error_text);
-    }
-
-    let pr: PrResponse = response.json().await
-        .context("Failed to parse PR response")?;
-
+    let pr = github.create_pull_request(&request).await?;
     Ok(pr.html_url)
 }
 
-/// Get owner and repo name from git remote
-fn get_repo_info(repo: &git2::Repository) -> Result<(String, String)> {
-    let remote = repo.find_remote("origin")
-        .context("No 'origin' remote found")?;
-
-    let url = remote.url()
-        .context("Could not get remote URL")?;
-
-    parse_github_url(url)
-}
-
-/// Parse GitHub URL to extract owner and repo
-fn parse_github_url(url: &str) -> Result<(String, String)> {
-    // Handle SSH format: git@github.com:owner/repo.git
-    if url.starts_with("git@github.com:") {
-        let path = url.trim_start_matches("git@github.com:");
-        let path = path.trim_end_matches(".git");
-        let parts: Vec<&str> = path.split('/').collect();
-        if parts.len() >= 2 {
-            return Ok((parts[0].to_string(), parts[1].to_string()));
-        }
-    }
-
-    // Handle HTTPS format: https://github.com/owner/repo.git
-    if url.contains("github.com") {
-        let path = url
-            .trim_start_matches("https://github.com/")
-            .trim_start_matches("http://github.com/")
-            .trim_end_matches(".git");
-        let parts: Vec<&str> = path.split('/').collect();
-        if parts.len() >= 2 {
-            return Ok((parts[0].to_string(), parts[1].to_string()));
-        }
-    }
-
-    anyhow::bail!("Could not parse GitHub repository from URL: {}", url)
-}
-
 /// Generate PR title from branch name
 fn generate_pr_title(branch: &str) -> String {
     // Convert branch name to title
@@ -235,8 +194,7 @@ fn generate_pr_title(branch: &str) -> String {
         .replace("chore/", "Chore: ")
         .replace("docs/", "Docs: ")
         .replace("refactor/", "Refactor: ")
-        .replace('-', " ")
-        .replace('_', " ");
+        .replace(['-', '_'], " ");
 
     // Capitalize first letter
     let mut chars = title.chars();
@@ -272,23 +230,35 @@ fn generate_pr_body(repo: &git2::Repository, base: &str) -> Result<String> {
     Ok(body)
 }
 
+/// The base branch's commit, preferring `origin/<base>` over the local branch
+pub fn find_base<'r>(repo: &'r git2::Repository, base: &str) -> Result<Option<git2::Commit<'r>>> {
+    for spec in [format!("origin/{}", base), base.to_string()] {
+        if let Ok(obj) = repo.revparse_single(&spec) {
+            return Ok(Some(obj.peel_to_commit()?));
+        }
+    }
+    Ok(None)
+}
+
+/// Commits on HEAD that the base branch doesn't have; `None` if the base can't be found
+fn commits_ahead(repo: &git2::Repository, base: &str) -> Result<Option<usize>> {
+    let Some(base_commit) = find_base(repo, base)? else {
+        return Ok(None);
+    };
+    let head = repo.head()?.peel_to_commit()?;
+    let (ahead, _) = repo.graph_ahead_behind(head.id(), base_commit.id())?;
+    Ok(Some(ahead))
+}
+
 /// Get commit messages since diverging from base branch
-fn get_commits_since_base(repo: &git2::Repository, base: &str) -> Result<Vec<String>> {
+pub fn get_commits_since_base(repo: &git2::Repository, base: &str) -> Result<Vec<String>> {
     let mut messages = Vec::new();
 
     // Try to find merge base
     let head = repo.head()?.peel_to_commit()?;
 
-    let base_ref = format!("origin/{}", base);
-    let base_commit = match repo.revparse_single(&base_ref) {
-        Ok(obj) => obj.peel_to_commit()?,
-        Err(_) => {
-            // Try without origin/
-            match repo.revparse_single(base) {
-                Ok(obj) => obj.peel_to_commit()?,
-                Err(_) => return Ok(messages),
-            }
-        }
+    let Some(base_commit) = find_base(repo, base)? else {
+        return Ok(messages);
     };
 
     let merge_base = repo.merge_base(head.id(), base_commit.id())?;
@@ -308,29 +278,10 @@ fn get_commits_since_base(repo: &git2::Repository, base: &str) -> Result<Vec<Str
     Ok(messages)
 }
 
-/// Check if branch is protected (main, master, etc.)
-fn is_protected_branch(branch: &str) -> bool {
-    matches!(branch, "main" | "master" | "develop" | "production" | "staging")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_github_url_ssh() {
-        let (owner, repo) = parse_github_url("git@github.com:user/project.git").unwrap();
-        assert_eq!(owner, "user");
-        assert_eq!(repo, "project");
-    }
-
-    #[test]
-    fn test_parse_github_url_https() {
-        let (owner, repo) = parse_github_url("https://github.com/user/project.git").unwrap();
-        assert_eq!(owner, "user");
-        assert_eq!(repo, "project");
-    }
-
     #[test]
     fn test_generate_pr_title() {
         assert_eq!(generate_pr_title("feat/add-user-auth"), "Add user auth");
@@ -340,8 +291,65 @@ mod tests {
 
     #[test]
     fn test_is_protected_branch() {
-        assert!(is_protected_branch("main"));
-        assert!(is_protected_branch("master"));
-        assert!(!is_protected_branch("feature/my-feature"));
+        let mut config = Config::default();
+        assert!(config.is_protected("main"));
+        assert!(config.is_protected("master"));
+        assert!(!config.is_protected("feature/my-feature"));
+
+        config.git.protected_branches = vec!["trunk".to_string(), "release/*".to_string()];
+        assert!(config.is_protected("release/2.0"));
+        assert!(!config.is_protected("main"));
+    }
+
+    #[test]
+    fn test_commits_ahead_of_base() {
+        let dir = std::env::temp_dir().join(format!("bahn-push-ahead-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+
+        let first = repo.commit(Some("HEAD"), &sig, &sig, "First", &tree, &[]).unwrap();
+        let first = repo.find_commit(first).unwrap();
+        repo.branch("main-base", &first, false).unwrap();
+        assert_eq!(commits_ahead(&repo, "main-base").unwrap(), Some(0));
+        assert_eq!(commits_ahead(&repo, "missing").unwrap(), None);
+
+        repo.commit(Some("HEAD"), &sig, &sig, "Second", &tree, &[&first]).unwrap();
+        assert_eq!(commits_ahead(&repo, "main-base").unwrap(), Some(1));
+        assert_eq!(get_commits_since_base(&repo, "main-base").unwrap(), vec!["Second"]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_push_without_api_key() {
+        let dir = std::env::temp_dir().join(format!("bahn-push-no-key-{}-{}", std::process::id(), rand::random::<u32>()));
+        let remote = dir.with_extension("remote");
+        git2::Repository::init_bare(&remote).unwrap();
+        let repo = git2::Repository::init(&dir).unwrap();
+        repo.remote("origin", remote.to_str().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let first = repo.commit(Some("HEAD"), &sig, &sig, "First", &tree, &[]).unwrap();
+        let branch = git::current_branch(&repo).unwrap();
+        let config = Config::default()
+            .with_credentials(&mut crate::config::ConfigSources::default(), &crate::core::credentials::NoKeyring, |_| None);
+        let pushed = || git2::Repository::open_bare(&remote).unwrap().refname_to_id(&format!("refs/heads/{}", branch)).ok();
+
+        // A plain push needs no AI key or GitHub token
+        let options = PushOptions::default();
+        push_repo(git::open_repo(Some(&dir)).unwrap(), &config, options).await.unwrap();
+        assert_eq!(pushed(), Some(first));
+
+        // --pr needs the token, and fails before pushing
+        let first = repo.find_commit(first).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Second", &tree, &[&first]).unwrap();
+        let options = PushOptions { create_pr: true, ..PushOptions::default() };
+        let err = push_repo(git::open_repo(Some(&dir)).unwrap(), &config, options).await.unwrap_err();
+        assert!(err.to_string().contains("GitHub token required"), "{}", err);
+        assert_eq!(pushed(), Some(first.id()));
+
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::remove_dir_all(&remote).ok();
     }
 }
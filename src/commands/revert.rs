@@ -0,0 +1,342 @@
+//! Revert command - undo commits with new commits, safe for pushed history.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dialoguer::Confirm;
+use git2::build::CheckoutBuilder;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::commands::merge;
+use crate::config::Config;
+use crate::core::ai::AiClient;
+use crate::core::git;
+use crate::core::prompt::{self, Interaction};
+use crate::core::report::say;
+
+/// Most commits a single revert will undo
+const MAX_REVERT_COMMITS: usize = 50;
+
+/// Options for the revert command
+pub struct RevertOptions {
+    /// Commit (`abc123`) or range (`abc123..def456`) to revert
+    pub target: String,
+    /// Why the commits are being reverted, for the messages
+    pub reason: Option<String>,
+    /// Use git's standard message instead of an AI-written one
+    pub no_ai: bool,
+    /// Resolve conflicts with the AI instead of stopping
+    pub auto_resolve: bool,
+    /// Skip confirmation prompt
+    pub yes: bool,
+    /// Sign the revert commits (`commit.sign`)
+    pub sign: bool,
+}
+
+/// Run the revert command
+pub async fn run(config: &Config, options: RevertOptions) -> Result<()> {
+    let repo = git::open_repo(None)?;
+
+    let mut status = git2::StatusOptions::new();
+    status.include_untracked(false);
+    if !repo.statuses(Some(&mut status))?.is_empty() {
+        anyhow::bail!("You have uncommitted changes. Commit or stash them before reverting.");
+    }
+
+    let commits = commits_to_revert(&repo, &options.target)?;
+
+    say!("{} Commits to revert, newest first:", "→".cyan());
+    for commit in &commits {
+        say!("  {} {}", short_id(commit).yellow(), commit.summary().unwrap_or(""));
+    }
+    say!();
+
+    let interaction = prompt::decide(prompt::is_interactive(), options.yes, prompt::assume_yes_env())?;
+    if interaction == Interaction::Prompt {
+        let count = commits.len();
+        let confirm = Confirm::new()
+            .with_prompt(format!("Create {} revert commit{}?", count, if count == 1 { "" } else { "s" }))
+            .default(true)
+            .interact()?;
+
+        if !confirm {
+            say!("{} Aborted", "→".yellow());
+            return Ok(());
+        }
+    }
+
+    let ai = if options.no_ai {
+        None
+    } else if options.auto_resolve {
+        Some(config.ai_client(config.require_api_key()?))
+    } else {
+        match config.api_key() {
+            Some(api_key) => Some(config.ai_client(api_key)),
+            None => {
+                say!("{} {} Using git's standard message.", "Warning:".yellow(), config.missing_api_key_message());
+                None
+            }
+        }
+    };
+
+    for (i, commit) in commits.iter().enumerate() {
+        let message = revert_message(&repo, ai.as_ref(), commit, options.reason.as_deref()).await?;
+
+        let oid = match revert_onto_head(&repo, commit, &message, options.sign)? {
+            Some(oid) => oid,
+            None if options.auto_resolve => {
+                say!("{} Reverting {} conflicts; resolving with AI", "→".yellow(), short_id(commit));
+                let ai = ai.as_ref().context("Resolving conflicts needs the AI; drop --no-ai")?;
+                merge::resolve_conflicts(ai, &repo).await?;
+                let oid = git::create_commit(&repo, &message, options.sign)?;
+                repo.cleanup_state()?;
+                oid
+            }
+            None => {
+                say!("{} Reverting {} conflicts with later changes.", "✗".red(), short_id(commit));
+                say!("Conflict markers are in the working tree and the message is prepared. To finish:");
+                say!("  resolve the files, `git add` them, then run `git commit`");
+                say!("  or give up with `git revert --abort`");
+                say!("Or run again with --auto-resolve to let the AI resolve them.");
+                if i + 1 < commits.len() {
+                    say!("Not reverted yet:");
+                    for rest in &commits[i + 1..] {
+                        say!("  {} {}", short_id(rest), rest.summary().unwrap_or(""));
+                    }
+                }
+                anyhow::bail!("Revert of {} stopped on conflicts", short_id(commit));
+            }
+        };
+
+        say!(
+            "{} Reverted {} → {} {}",
+            "✓".green(),
+            short_id(commit).yellow(),
+            oid.to_string()[..7].cyan(),
+            message.lines().next().unwrap_or("")
+        );
+    }
+
+    Ok(())
+}
+
+/// Commits named by `spec`, newest first: one commit, or every commit in `A..B`
+fn commits_to_revert<'r>(repo: &'r git2::Repository, spec: &str) -> Result<Vec<git2::Commit<'r>>> {
+    let revspec = repo.revparse(spec)
+        .with_context(|| format!("Unknown commit or range '{}'", spec))?;
+    let from = revspec.from()
+        .with_context(|| format!("Range '{}' has no start", spec))?
+        .peel_to_commit()?;
+
+    let commits = if revspec.mode().contains(git2::RevparseMode::SINGLE) {
+        vec![from]
+    } else {
+        if revspec.mode().contains(git2::RevparseMode::MERGE_BASE) {
+            anyhow::bail!("Give a range like A..B; '{}' is a symmetric difference", spec);
+        }
+        let to = match revspec.to() {
+            Some(to) => to.peel_to_commit()?,
+            None => repo.head()?.peel_to_commit()?,
+        };
+        let mut walk = repo.revwalk()?;
+        walk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+        walk.push(to.id())?;
+        walk.hide(from.id())?;
+        walk.map(|oid| Ok(repo.find_commit(oid?)?))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    if commits.is_empty() {
+        anyhow::bail!("No commits to revert in '{}'", spec);
+    }
+    if commits.len() > MAX_REVERT_COMMITS {
+        anyhow::bail!("'{}' has {} commits; revert at most {} at a time", spec, commits.len(), MAX_REVERT_COMMITS);
+    }
+
+    let head = repo.head()?.peel_to_commit()?.id();
+    for commit in &commits {
+        if commit.parent_count() > 1 {
+            anyhow::bail!(
+                "{} is a merge commit; revert it with `git revert -m 1 {}`",
+                short_id(commit), short_id(commit)
+            );
+        }
+        if commit.parent_count() == 0 {
+            anyhow::bail!("{} is the root commit and can't be reverted", short_id(commit));
+        }
+        if commit.id() != head && !repo.graph_descendant_of(head, commit.id())? {
+            anyhow::bail!("{} is not on the current branch", short_id(commit));
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Revert `commit` on top of HEAD and commit the result. On conflicts nothing
+/// is committed: the markers are written to the working tree, the repository
+/// is left mid-revert and `None` is returned.
+fn revert_onto_head(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    message: &str,
+    sign: bool,
+) -> Result<Option<git2::Oid>> {
+    let head = repo.head()?.peel_to_commit()?;
+    let mut index = repo.revert_commit(commit, &head, 0, None)?;
+
+    if index.has_conflicts() {
+        repo.revert(commit, None)?;
+        // `git commit` picks this up once the conflicts are resolved
+        std::fs::write(repo.path().join("MERGE_MSG"), format!("{}\n", message))?;
+        return Ok(None);
+    }
+
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+    repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))?;
+    Ok(Some(git::create_commit(repo, message, sign)?))
+}
+
+/// Message for reverting `commit`, written by the AI when there is one
+async fn revert_message(
+    repo: &git2::Repository,
+    ai: Option<&AiClient>,
+    commit: &git2::Commit<'_>,
+    reason: Option<&str>,
+) -> Result<String> {
+    let message = match ai {
+        Some(ai) => {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .unwrap());
+            pb.set_message(format!("Writing revert message for {}...", short_id(commit)));
+            let diff = git::get_commit_diff_text(repo, commit, None)?;
+            let message = ai.generate_revert_message(commit.message().unwrap_or("").trim(), &diff, reason).await;
+            pb.finish_and_clear();
+            message?
+        }
+        None => standard_message(commit.summary().unwrap_or(""), reason),
+    };
+    Ok(with_trailer(&message, commit.id()))
+}
+
+/// git's own revert subject, with the reason as the body
+fn standard_message(subject: &str, reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("Revert \"{}\"\n\n{}", subject, reason.trim()),
+        None => format!("Revert \"{}\"", subject),
+    }
+}
+
+/// Add git's `This reverts commit <sha>.` line unless the message has it
+fn with_trailer(message: &str, reverted: git2::Oid) -> String {
+    let trailer = format!("This reverts commit {}.", reverted);
+    if message.contains(&trailer) {
+        return message.trim().to_string();
+    }
+    format!("{}\n\n{}", message.trim(), trailer)
+}
+
+fn short_id(commit: &git2::Commit) -> String {
+    commit.id().to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn temp_repo(label: &str) -> (git2::Repository, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("bahn-revert-{}-{}-{}", label, std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        (repo, dir)
+    }
+
+    fn commit_file(repo: &git2::Repository, dir: &Path, file: &str, content: &str, message: &str) -> git2::Oid {
+        std::fs::write(dir.join(file), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+        git::create_commit(repo, message, false).unwrap()
+    }
+
+    fn head_message(repo: &git2::Repository) -> String {
+        repo.head().unwrap().peel_to_commit().unwrap().message().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_revert_restores_file_contents() {
+        let (repo, dir) = temp_repo("restore");
+        commit_file(&repo, &dir, "app.txt", "one\n", "Add app");
+        let change = commit_file(&repo, &dir, "app.txt", "two\n", "Change app");
+        commit_file(&repo, &dir, "notes.txt", "later\n", "Add notes");
+
+        let commits = commits_to_revert(&repo, &change.to_string()).unwrap();
+        let message = with_trailer(&standard_message("Change app", Some("Broke the build")), change);
+        let oid = revert_onto_head(&repo, &commits[0], &message, false).unwrap().unwrap();
+
+        assert_eq!(std::fs::read_to_string(dir.join("app.txt")).unwrap(), "one\n");
+        assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "later\n");
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+        assert_eq!(
+            head_message(&repo),
+            format!("Revert \"Change app\"\n\nBroke the build\n\nThis reverts commit {}.", change)
+        );
+        // Index and working tree match the new commit
+        assert!(repo.statuses(None).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_revert_range_newest_first() {
+        let (repo, dir) = temp_repo("range");
+        let base = commit_file(&repo, &dir, "app.txt", "one\n", "Add app");
+        commit_file(&repo, &dir, "app.txt", "two\n", "Second");
+        commit_file(&repo, &dir, "app.txt", "three\n", "Third");
+
+        let commits = commits_to_revert(&repo, &format!("{}..HEAD", base)).unwrap();
+        let subjects: Vec<&str> = commits.iter().map(|c| c.summary().unwrap()).collect();
+        assert_eq!(subjects, ["Third", "Second"]);
+
+        for commit in &commits {
+            let message = standard_message(commit.summary().unwrap(), None);
+            revert_onto_head(&repo, commit, &message, false).unwrap().unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(dir.join("app.txt")).unwrap(), "one\n");
+
+        assert!(commits_to_revert(&repo, "HEAD..HEAD").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_conflicting_revert_leaves_markers() {
+        let (repo, dir) = temp_repo("conflict");
+        commit_file(&repo, &dir, "app.txt", "one\n", "Add app");
+        let change = commit_file(&repo, &dir, "app.txt", "two\n", "Change app");
+        commit_file(&repo, &dir, "app.txt", "three\n", "Change app again");
+        let head = repo.head().unwrap().target();
+
+        let commit = repo.find_commit(change).unwrap();
+        let message = with_trailer("revert: drop the app change", change);
+        assert!(revert_onto_head(&repo, &commit, &message, false).unwrap().is_none());
+
+        assert_eq!(repo.head().unwrap().target(), head);
+        assert_eq!(repo.state(), git2::RepositoryState::Revert);
+        assert!(repo.index().unwrap().has_conflicts());
+        assert!(std::fs::read_to_string(dir.join("app.txt")).unwrap().contains("<<<<<<<"));
+        assert!(std::fs::read_to_string(repo.path().join("MERGE_MSG")).unwrap().starts_with("revert: drop the app change"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_with_trailer_is_added_once() {
+        let oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        let message = with_trailer("revert: drop retries\n", oid);
+        assert_eq!(message, format!("revert: drop retries\n\nThis reverts commit {}.", oid));
+        assert_eq!(with_trailer(&message, oid), message);
+    }
+}
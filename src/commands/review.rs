@@ -2,28 +2,117 @@
 
 use anyhow::{Context, Result};
 use colored::Colorize;
+use futures::stream::{self, StreamExt};
 
+use crate::commands::push;
 use crate::config::Config;
-use crate::core::ai::AiClient;
+use crate::core::ai::{AiClient, CodeReview, ReviewIssue, REVIEW_DIFF_BUDGET};
+use crate::core::annotations;
+use crate::core::diff;
 use crate::core::git;
+use crate::core::github::{ApiError, GitHub, NewReview, PullRequest, ReviewComment};
+use crate::core::report::{self, say};
+use crate::core::review_report::{self, FailOn};
+use crate::core::secrets_report::ReportFormat;
+
+/// Most commit subjects listed as context for a range review
+const MAX_RANGE_COMMITS: usize = 50;
+
+/// Batches of one review sent to the AI at the same time
+const REVIEW_CONCURRENCY: usize = 4;
+
+/// Issue added for each file reviewed from its stats alone
+const TOO_LARGE_MESSAGE: &str = "file too large for detailed review";
+
+/// Options for the review command
+pub struct ReviewOptions {
+    /// Review staged changes
+    pub staged: bool,
+    /// Review one commit
+    pub commit: Option<String>,
+    /// Review a commit range, e.g. `main..feature` or `v1.0...HEAD`
+    pub range: Option<String>,
+    /// Review the current branch since it diverged from this one
+    pub branch: Option<String>,
+    pub strictness: String,
+    pub agent: Option<String>,
+    pub format: ReportFormat,
+    /// Exit non-zero when issues at or above this severity are found
+    pub fail_on: FailOn,
+    /// Post the review to GitHub
+    pub post: bool,
+}
+
+/// Changes of a range or branch review, with the commits they come from
+struct RangeDiff {
+    /// What was reviewed, e.g. `main..feature`
+    label: String,
+    diff: String,
+    commits: Vec<String>,
+}
+
+/// Pull request a review gets posted to
+struct PostTarget {
+    github: GitHub,
+    pr: PullRequest,
+    /// Commit the inline comments' line numbers refer to
+    commit_id: String,
+}
 
 /// Run the review command
-pub async fn run(config: &Config, staged: bool, commit: Option<&str>, strictness: &str) -> Result<()> {
-    println!("{}", "gitBahn - Code Review".bold().cyan());
-    println!();
+pub async fn run(config: &Config, options: ReviewOptions) -> Result<()> {
+    say!("{}", "gitBahn - Code Review".bold().cyan());
+    say!();
 
-    let api_key = config.anthropic_api_key()
-        .context("ANTHROPIC_API_KEY not set")?;
+    review_repo(git::open_repo(None)?, config, options).await
+}
 
-    let ai = AiClient::new(api_key.to_string(), Some(config.ai.model.clone()));
-    let repo = git::open_repo(None)?;
+/// Review the changes `options` select in `repo`
+async fn review_repo(repo: git2::Repository, config: &Config, options: ReviewOptions) -> Result<()> {
+    let (staged, commit, post) = (options.staged, options.commit.as_deref(), options.post);
+    let (strictness, format) = (options.strictness.as_str(), options.format);
 
-    let diff = if let Some(commit_sha) = commit {
+    let range = match (&options.range, &options.branch) {
+        (Some(spec), _) => Some(range_diff(&repo, spec)?),
+        (None, Some(base)) => Some(branch_diff(&repo, base)?),
+        (None, None) => None,
+    };
+
+    // Find the pull request before spending tokens on a review that can't be posted
+    let auto_post = config.review.auto_post && commit.is_some();
+    if post && staged && commit.is_none() {
+        anyhow::bail!(
+            "Staged changes aren't on GitHub yet. Use --post with --commit, or alone to review the current branch's pull request"
+        );
+    }
+    let target = if post || auto_post {
+        find_post_target(config, &repo, commit, post).await?
+    } else {
+        None
+    };
+
+    let diff = if let Some(range) = &range {
+        if range.diff.is_empty() {
+            if report::is_json() {
+                anyhow::bail!("No changes to review in {}", range.label);
+            }
+            say!("{}", format!("No changes to review in {}.", range.label).yellow());
+            return Ok(());
+        }
+        say!("{} Reviewing {} ({} commits)", "→".cyan(), range.label, range.commits.len());
+        range.diff.clone()
+    } else if let Some(commit_sha) = commit {
         get_commit_diff(&repo, commit_sha)?
+    } else if let Some(target) = &target {
+        say!("{} Reviewing pull request #{} ({} → {})", "→".cyan(), target.pr.number, target.pr.head.name, target.pr.base.name);
+        get_pull_request_diff(&repo, &target.pr)?
     } else if staged {
         let changes = git::get_staged_changes(&repo)?;
         if changes.is_empty() {
-            println!("{}", "No staged changes to review.".yellow());
+            if report::is_json() {
+                anyhow::bail!("No staged changes to review");
+            }
+            say!("{}", "No staged changes to review.".yellow());
             return Ok(());
         }
         changes.diff
@@ -31,59 +120,290 @@ pub async fn run(config: &Config, staged: bool, commit: Option<&str>, strictness
         // Default to staged changes
         let changes = git::get_staged_changes(&repo)?;
         if changes.is_empty() {
-            println!("{}", "No staged changes to review.".yellow());
-            println!("Stage changes with: git add <files>");
+            if report::is_json() {
+                anyhow::bail!("No staged changes to review");
+            }
+            say!("{}", "No staged changes to review.".yellow());
+            say!("Stage changes with: git add <files>");
             return Ok(());
         }
         changes.diff
     };
 
-    println!("{}", "Analyzing code...".dimmed());
+    let api_key = config.require_api_key()?;
+    let ai = config.ai_client(api_key);
+
+    say!("{}", "Analyzing code...".dimmed());
+
+    let agent_name = options.agent.as_deref().or(config.review.default_agent.as_deref());
+    let agent = match agent_name {
+        Some(name) => Some(config.agent_resolver(git::repo_root(&repo)?).resolve(name).await?),
+        None => None,
+    };
+    let ai = match agent.as_ref().and_then(|a| a.model.clone()) {
+        Some(model) => ai.with_model(model),
+        None => ai,
+    };
+    let personality = agent.map(|a| a.review_prompt());
+
+    let context = range.as_ref().map(range_context);
+    let budget = config.ai.diff_budget.unwrap_or(REVIEW_DIFF_BUDGET);
+    let review = review_in_batches(&ai, &diff, context.as_deref(), budget, personality.as_deref(), strictness).await?;
+    let show_usage = || {
+        if config.ai.show_usage {
+            report::say_usage(&ai.usage());
+        }
+    };
+
+    if let Some(target) = &target {
+        let url = post_review(target, &review, &diff).await?;
+        say!("{} Posted review on pull request #{}: {}", "✓".green(), target.pr.number, url.cyan());
+    }
+
+    match format {
+        ReportFormat::Json | ReportFormat::Sarif => {
+            show_usage();
+            let output = match format {
+                ReportFormat::Sarif => review_report::render_sarif(&review)?,
+                _ => review_report::render_json(&review)?,
+            };
+            println!("{}", output);
+            let failing = options.fail_on.failing(&review);
+            if !failing.is_empty() {
+                say!("{} {} issue(s) at or above {}", "✗".red(), failing.len(), options.fail_on.name());
+                // The report is the whole output; an error document would follow it
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        ReportFormat::Github | ReportFormat::Text => {}
+    }
 
-    let review = ai.review_code(&diff, None, None, strictness).await?;
+    if format == ReportFormat::Github {
+        for line in annotations::review_annotations(&review) {
+            println!("{}", line);
+        }
+    }
 
     // Display review results
-    println!();
-    println!("{} {}", "Verdict:".bold(), format_verdict(&review.verdict));
-    println!("{} {}/10", "Score:".bold(), review.overall_score);
-    println!();
+    say!();
+    say!("{} {}", "Verdict:".bold(), format_verdict(&review.verdict));
+    say!("{} {}/10", "Score:".bold(), review.overall_score);
+    say!();
 
-    println!("{}", "Summary:".bold());
-    println!("  {}", review.summary);
-    println!();
+    say!("{}", "Summary:".bold());
+    say!("  {}", review.summary);
+    say!();
 
     if !review.issues.is_empty() {
-        println!("{}", "Issues:".bold().red());
+        say!("{}", "Issues:".bold().red());
         for issue in &review.issues {
             let severity_color = match issue.severity.as_str() {
                 "critical" => "".red().bold(),
                 "warning" => "".yellow(),
                 _ => "".dimmed(),
             };
-            println!("  {} [{}] {}:{}",
+            say!("  {} [{}] {}:{}",
                 severity_color,
                 issue.severity.to_uppercase(),
                 issue.file,
                 issue.line.map(|l| l.to_string()).unwrap_or_default()
             );
-            println!("    {}", issue.message);
+            say!("    {}", issue.message);
             if let Some(suggestion) = &issue.suggestion {
-                println!("    {} {}", "Suggestion:".dimmed(), suggestion);
+                say!("    {} {}", "Suggestion:".dimmed(), suggestion);
             }
         }
-        println!();
+        say!();
     }
 
     if !review.positives.is_empty() {
-        println!("{}", "Positives:".bold().green());
+        say!("{}", "Positives:".bold().green());
         for positive in &review.positives {
-            println!("  {} {}", "".green(), positive);
+            say!("  {} {}", "".green(), positive);
         }
     }
 
+    show_usage();
+
+    let failing = options.fail_on.failing(&review);
+    if !failing.is_empty() {
+        anyhow::bail!("{} issue(s) at or above {} (--fail-on)", failing.len(), options.fail_on.name());
+    }
     Ok(())
 }
 
+/// Commit subjects of a range, as context for its review
+fn range_context(range: &RangeDiff) -> String {
+    let listed: Vec<String> = range.commits.iter()
+        .take(MAX_RANGE_COMMITS)
+        .map(|subject| format!("- {}", subject))
+        .collect();
+    format!("Reviewing {}, commits:\n{}", range.label, listed.join("\n"))
+}
+
+/// Review `diff` in batches of whole files that fit `budget`, a few at a time,
+/// then merge the reviews. A file bigger than `budget` on its own is reviewed
+/// from its stats only.
+async fn review_in_batches(
+    ai: &AiClient,
+    diff: &str,
+    context: Option<&str>,
+    budget: usize,
+    personality: Option<&str>,
+    strictness: &str,
+) -> Result<CodeReview> {
+    let batches = diff::chunk_by_file(diff, budget);
+    if batches.len() > 1 {
+        say!("{} Reviewing in {} batches", "→".cyan(), batches.len());
+    }
+
+    let reviews: Vec<Result<(Vec<String>, CodeReview)>> = stream::iter(batches)
+        .map(|batch| review_batch(ai, batch, context, budget, personality, strictness))
+        .buffered(REVIEW_CONCURRENCY)
+        .collect()
+        .await;
+
+    let reviews = reviews.into_iter().collect::<Result<Vec<_>>>()?;
+    merge_reviews(reviews).context("Nothing to review")
+}
+
+/// Review one batch, falling back to its stats when it's over `budget`
+async fn review_batch(
+    ai: &AiClient,
+    batch: diff::DiffChunk,
+    context: Option<&str>,
+    budget: usize,
+    personality: Option<&str>,
+    strictness: &str,
+) -> Result<(Vec<String>, CodeReview)> {
+    if batch.diff.len() <= budget {
+        let review = ai.review_code(&batch.diff, context, personality, strictness).await?;
+        return Ok((batch.files, review));
+    }
+
+    say!("{} {} is too large for detailed review; reviewing its stats only",
+        "Warning:".yellow(), batch.files.join(", "));
+    let stats: Vec<String> = diff::file_stats(&batch.diff).iter()
+        .map(|stat| format!("# {} (diff too large, stats only)", stat.line()))
+        .collect();
+    let review = ai.review_code(&stats.join("\n"), context, personality, strictness).await?;
+    let review = stats_only_review(review, &batch.files);
+    Ok((batch.files, review))
+}
+
+/// Mark a review that only saw change stats: it can't approve, and each file
+/// gets a warning that it wasn't reviewed in detail
+fn stats_only_review(mut review: CodeReview, files: &[String]) -> CodeReview {
+    if review.verdict == "approve" {
+        review.verdict = "comment".to_string();
+    }
+    for file in files {
+        review.issues.push(ReviewIssue {
+            severity: "warning".to_string(),
+            file: file.clone(),
+            line: None,
+            message: TOO_LARGE_MESSAGE.to_string(),
+            suggestion: Some("Review this file by hand or split the change".to_string()),
+        });
+    }
+    review
+}
+
+/// Combine per-batch reviews: the worst verdict wins (anything unknown counts
+/// as a comment), scores are averaged, and issues and positives are
+/// concatenated without duplicates. The summary says how many batches there
+/// were and which files each one covered.
+fn merge_reviews(mut reviews: Vec<(Vec<String>, CodeReview)>) -> Option<CodeReview> {
+    if reviews.len() <= 1 {
+        return reviews.pop().map(|(_, review)| review);
+    }
+
+    const VERDICTS: [&str; 3] = ["approve", "comment", "request_changes"];
+    let worst = reviews.iter()
+        .map(|(_, review)| VERDICTS.iter().position(|v| *v == review.verdict).unwrap_or(1))
+        .max()
+        .unwrap_or(1);
+    let verdict = VERDICTS[worst].to_string();
+    let total: usize = reviews.iter().map(|(_, review)| review.overall_score as usize).sum();
+    let overall_score = ((total + reviews.len() / 2) / reviews.len()) as u8;
+    let batches = reviews.iter()
+        .enumerate()
+        .map(|(i, (files, review))| format!("Batch {} ({}): {}", i + 1, files.join(", "), review.summary))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let summary = format!("Reviewed in {} batches.\n{}", reviews.len(), batches);
+
+    let mut issues: Vec<ReviewIssue> = Vec::new();
+    let mut positives: Vec<String> = Vec::new();
+    for (_, review) in reviews {
+        for issue in review.issues {
+            let duplicate = issues.iter()
+                .any(|seen| seen.file == issue.file && seen.line == issue.line && seen.message == issue.message);
+            if !duplicate {
+                issues.push(issue);
+            }
+        }
+        for positive in review.positives {
+            if !positives.contains(&positive) {
+                positives.push(positive);
+            }
+        }
+    }
+
+    Some(CodeReview { verdict, summary, issues, positives, overall_score })
+}
+
+/// Diff and commits of a `A..B` or `A...B` range
+fn range_diff(repo: &git2::Repository, spec: &str) -> Result<RangeDiff> {
+    let revspec = repo.revparse(spec)
+        .with_context(|| format!("Invalid range '{}'", spec))?;
+    if revspec.mode().contains(git2::RevparseMode::SINGLE) {
+        anyhow::bail!("'{}' is a single revision; give a range like main..feature, or use --commit", spec);
+    }
+    let from = revspec.from()
+        .with_context(|| format!("Range '{}' has no start", spec))?
+        .peel_to_commit()?;
+    let to = match revspec.to() {
+        Some(to) => to.peel_to_commit()?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+
+    // `A...B` is what B changed since it diverged from A
+    let base = if revspec.mode().contains(git2::RevparseMode::MERGE_BASE) {
+        repo.find_commit(repo.merge_base(from.id(), to.id())?)?
+    } else {
+        from
+    };
+    let diff = repo.diff_tree_to_tree(Some(&base.tree()?), Some(&to.tree()?), None)?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(to.id())?;
+    revwalk.hide(base.id())?;
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        commits.push(commit.summary().unwrap_or("").to_string());
+    }
+
+    Ok(RangeDiff { label: spec.to_string(), diff: diff_text(&diff)?, commits })
+}
+
+/// Diff and commits of the current branch since it diverged from `base`
+fn branch_diff(repo: &git2::Repository, base: &str) -> Result<RangeDiff> {
+    let base_commit = push::find_base(repo, base)?
+        .with_context(|| format!("Branch '{}' not found", base))?;
+    let head = repo.head()?.peel_to_commit()?;
+    let merge_base = repo.find_commit(repo.merge_base(head.id(), base_commit.id())?)?;
+    let diff = repo.diff_tree_to_tree(Some(&merge_base.tree()?), Some(&head.tree()?), None)?;
+
+    Ok(RangeDiff {
+        label: format!("{}..{}", base, git::current_branch(repo)?),
+        diff: diff_text(&diff)?,
+        commits: push::get_commits_since_base(repo, base)?,
+    })
+}
+
 fn format_verdict(verdict: &str) -> colored::ColoredString {
     match verdict {
         "approve" => "APPROVED".green().bold(),
@@ -92,11 +412,163 @@ fn format_verdict(verdict: &str) -> colored::ColoredString {
     }
 }
 
-fn get_commit_diff(repo: &git2::Repository, commit_sha: &str) -> Result<String> {
+/// The open pull request to post to: the one containing `commit`, or the
+/// current branch's. With `required` (an explicit `--post`) a missing pull
+/// request is an error; otherwise it's a warning and nothing is posted.
+async fn find_post_target(
+    config: &Config,
+    repo: &git2::Repository,
+    commit: Option<&str>,
+    required: bool,
+) -> Result<Option<PostTarget>> {
+    let token = config.github_token()
+        .context("GitHub token required to post reviews. Set GITHUB_TOKEN env var or add to .bahn.toml")?;
+    let github = GitHub::for_repo(token, repo)?;
+
+    say!("{}", "Looking up the pull request...".dimmed());
+    let (pr, commit_id, missing) = match commit {
+        Some(sha) => {
+            let commit_id = find_commit(repo, sha)?.id().to_string();
+            let pr = github.open_pull_request_for_commit(&commit_id).await?;
+            let missing = format!(
+                "No open pull request on {} contains commit {}. Push it and open one with `bahn push --pr`.",
+                github.full_name(),
+                &commit_id[..7]
+            );
+            (pr, Some(commit_id), missing)
+        }
+        None => {
+            let branch = git::current_branch(repo)?;
+            let pr = github.open_pull_request_for_branch(&branch).await?;
+            let missing = format!(
+                "No open pull request for branch '{}' on {}. Open one with `bahn push --pr`.",
+                branch,
+                github.full_name()
+            );
+            (pr, None, missing)
+        }
+    };
+
+    let Some(pr) = pr else {
+        if required {
+            anyhow::bail!(missing);
+        }
+        say!("{} {} Not posting the review.", "Warning:".yellow(), missing);
+        return Ok(None);
+    };
+    let commit_id = commit_id.unwrap_or_else(|| pr.head.sha.clone());
+    Ok(Some(PostTarget { github, pr, commit_id }))
+}
+
+/// Post `review` as a pull request review. If GitHub rejects it (an inline
+/// line it doesn't consider part of the diff, or approving your own pull
+/// request), it's posted again as a plain comment with every issue in the body.
+async fn post_review(target: &PostTarget, review: &CodeReview, diff: &str) -> Result<String> {
+    let request = github_review(review, diff, &target.commit_id, true);
+    match target.github.create_review(target.pr.number, &request).await {
+        Ok(posted) => Ok(posted.html_url),
+        Err(e) if ApiError::is_unprocessable(&e) => {
+            say!("{} {}", "Warning:".yellow(), e);
+            say!("{}", "  Posting the review as a comment with every issue in its body instead.".dimmed());
+            let mut request = github_review(review, diff, &target.commit_id, false);
+            request.event = "COMMENT";
+            Ok(target.github.create_review(target.pr.number, &request).await?.html_url)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Review event for a verdict
+fn review_event(verdict: &str) -> &'static str {
+    match verdict {
+        "approve" => "APPROVE",
+        "request_changes" => "REQUEST_CHANGES",
+        _ => "COMMENT",
+    }
+}
+
+/// GitHub review for `review`. With `inline`, issues on lines `diff` shows
+/// become comments on those lines; the others are listed in the body.
+fn github_review(review: &CodeReview, diff: &str, commit_id: &str, inline: bool) -> NewReview {
+    let lines = if inline { diff::new_side_lines(diff) } else { Default::default() };
+
+    let mut comments = Vec::new();
+    let mut unplaced = Vec::new();
+    for issue in &review.issues {
+        let line = issue.line.map(|line| line as usize)
+            .filter(|line| lines.get(&issue.file).is_some_and(|shown| shown.contains(line)));
+        match line {
+            Some(line) => comments.push(ReviewComment {
+                path: issue.file.clone(),
+                line,
+                side: "RIGHT",
+                body: issue_comment(issue),
+            }),
+            None => unplaced.push(issue),
+        }
+    }
+
+    let mut body = format!("{}
+
+**Score:** {}/10
+", review.summary, review.overall_score);
+    if !unplaced.is_empty() {
+        body.push_str("
+### Issues
+
+");
+        for issue in unplaced {
+            let location = match issue.line {
+                Some(line) => format!("{}:{}", issue.file, line),
+                None => issue.file.clone(),
+            };
+            body.push_str(&format!("- **{}** `{}`: {}
+", issue.severity.to_uppercase(), location, issue.message));
+            if let Some(suggestion) = &issue.suggestion {
+                body.push_str(&format!("  Suggestion: {}
+", suggestion));
+            }
+        }
+    }
+    if !review.positives.is_empty() {
+        body.push_str("
+### Positives
+
+");
+        for positive in &review.positives {
+            body.push_str(&format!("- {}
+", positive));
+        }
+    }
+    body.push_str("
+---
+*Reviewed with [gitBahn](https://github.com/gitBahn)*");
+
+    NewReview {
+        commit_id: commit_id.to_string(),
+        body,
+        event: review_event(&review.verdict),
+        comments,
+    }
+}
+
+/// Inline comment text for one issue
+fn issue_comment(issue: &ReviewIssue) -> String {
+    let mut body = format!("**{}**: {}", issue.severity.to_uppercase(), issue.message);
+    if let Some(suggestion) = &issue.suggestion {
+        body.push_str(&format!("\n\n**Suggestion:** {}", suggestion));
+    }
+    body
+}
+
+fn find_commit<'r>(repo: &'r git2::Repository, commit_sha: &str) -> Result<git2::Commit<'r>> {
     let oid = git2::Oid::from_str(commit_sha)
         .with_context(|| format!("Invalid commit SHA: {}", commit_sha))?;
+    Ok(repo.find_commit(oid)?)
+}
 
-    let commit = repo.find_commit(oid)?;
+fn get_commit_diff(repo: &git2::Repository, commit_sha: &str) -> Result<String> {
+    let commit = find_commit(repo, commit_sha)?;
     let tree = commit.tree()?;
 
     let parent_tree = if commit.parent_count() > 0 {
@@ -106,7 +578,30 @@ fn get_commit_diff(repo: &git2::Repository, commit_sha: &str) -> Result<String>
     };
 
     let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    diff_text(&diff)
+}
 
+/// Diff of the current branch against the pull request's base, from where they diverged
+fn get_pull_request_diff(repo: &git2::Repository, pr: &PullRequest) -> Result<String> {
+    let base = repo.revparse_single(&format!("origin/{}", pr.base.name))
+        .or_else(|_| repo.revparse_single(&pr.base.sha))
+        .and_then(|base| base.peel_to_commit())
+        .with_context(|| format!("Base branch '{}' not found locally. Run `git fetch origin {}` first.", pr.base.name, pr.base.name))?;
+    let head = repo.head()?.peel_to_commit()?;
+    if head.id().to_string() != pr.head.sha {
+        say!(
+            "{} Local HEAD isn't the pull request's head; push first so inline comments land on the right lines.",
+            "Warning:".yellow()
+        );
+    }
+
+    let merge_base = repo.find_commit(repo.merge_base(head.id(), base.id())?)?;
+    let diff = repo.diff_tree_to_tree(Some(&merge_base.tree()?), Some(&head.tree()?), None)?;
+    diff_text(&diff)
+}
+
+/// Patch text of `diff`
+fn diff_text(diff: &git2::Diff) -> Result<String> {
     let mut diff_text = String::new();
     diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
         let prefix = match line.origin() {
@@ -126,3 +621,172 @@ fn get_commit_diff(repo: &git2::Repository, commit_sha: &str) -> Result<String>
 
     Ok(diff_text)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(file: &str, line: Option<u32>, message: &str) -> ReviewIssue {
+        ReviewIssue {
+            severity: "warning".to_string(),
+            file: file.to_string(),
+            line,
+            message: message.to_string(),
+            suggestion: None,
+        }
+    }
+
+    #[test]
+    fn test_github_review_places_issues_on_diff_lines() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -10,2 +10,3 @@ fn a() {\n ctx\n+added\n ctx\n";
+        let mut on_line = issue("src/lib.rs", Some(11), "Unwrap can panic");
+        on_line.suggestion = Some("Use ?".to_string());
+        let review = CodeReview {
+            verdict: "request_changes".to_string(),
+            summary: "Mostly fine.".to_string(),
+            issues: vec![
+                on_line,
+                issue("src/lib.rs", Some(40), "Outside the diff"),
+                issue("src/other.rs", None, "No line"),
+            ],
+            positives: vec!["Small change".to_string()],
+            overall_score: 6,
+        };
+
+        let request = github_review(&review, diff, "abc123", true);
+        assert_eq!(request.event, "REQUEST_CHANGES");
+        assert_eq!(request.commit_id, "abc123");
+        assert_eq!(request.comments, vec![ReviewComment {
+            path: "src/lib.rs".to_string(),
+            line: 11,
+            side: "RIGHT",
+            body: "**WARNING**: Unwrap can panic\n\n**Suggestion:** Use ?".to_string(),
+        }]);
+        assert!(request.body.starts_with("Mostly fine.\n\n**Score:** 6/10\n"));
+        assert!(request.body.contains("- **WARNING** `src/lib.rs:40`: Outside the diff\n"));
+        assert!(request.body.contains("- **WARNING** `src/other.rs`: No line\n"));
+        assert!(!request.body.contains("Unwrap can panic"));
+        assert!(request.body.contains("### Positives\n\n- Small change\n"));
+
+        // Without inline comments every issue goes into the body
+        let request = github_review(&review, diff, "abc123", false);
+        assert!(request.comments.is_empty());
+        assert!(request.body.contains("- **WARNING** `src/lib.rs:11`: Unwrap can panic\n  Suggestion: Use ?\n"));
+    }
+
+    #[test]
+    fn test_review_event_for_verdict() {
+        assert_eq!(review_event("approve"), "APPROVE");
+        assert_eq!(review_event("request_changes"), "REQUEST_CHANGES");
+        assert_eq!(review_event("comment"), "COMMENT");
+        assert_eq!(review_event("unsure"), "COMMENT");
+    }
+
+    #[test]
+    fn test_merge_reviews_keeps_worst_verdict() {
+        let review = |verdict: &str, score: u8, issues: Vec<ReviewIssue>| CodeReview {
+            verdict: verdict.to_string(),
+            summary: format!("{} summary", verdict),
+            issues,
+            positives: vec!["Tests added".to_string()],
+            overall_score: score,
+        };
+        let merged = merge_reviews(vec![
+            (vec!["a.rs".to_string()], review("approve", 9, vec![issue("a.rs", Some(1), "Typo")])),
+            (vec!["b.rs".to_string(), "c.rs".to_string()], review("request_changes", 4, vec![
+                issue("a.rs", Some(1), "Typo"),
+                issue("b.rs", None, "Missing check"),
+            ])),
+            (vec!["d.rs".to_string()], review("comment", 6, Vec::new())),
+        ])
+        .unwrap();
+
+        assert_eq!(merged.verdict, "request_changes");
+        assert_eq!(merged.overall_score, 6);
+        assert_eq!(merged.issues.len(), 2);
+        assert_eq!(merged.positives, ["Tests added"]);
+        assert_eq!(merged.summary.lines().next(), Some("Reviewed in 3 batches."));
+        assert_eq!(merged.summary.lines().nth(2), Some("Batch 2 (b.rs, c.rs): request_changes summary"));
+
+        let single = merge_reviews(vec![(vec!["a.rs".to_string()], review("approve", 9, Vec::new()))]).unwrap();
+        assert_eq!(single.summary, "approve summary");
+        assert!(merge_reviews(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_merge_reviews_verdict_and_score_edges() {
+        let review = |verdict: &str, score: u8| CodeReview {
+            verdict: verdict.to_string(),
+            summary: String::new(),
+            issues: Vec::new(),
+            positives: Vec::new(),
+            overall_score: score,
+        };
+        let merge = |reviews: Vec<CodeReview>| {
+            merge_reviews(reviews.into_iter().map(|r| (vec!["x.rs".to_string()], r)).collect()).unwrap()
+        };
+
+        // Every batch has to approve for the whole review to approve
+        let merged = merge(vec![review("approve", 7), review("approve", 8)]);
+        assert_eq!(merged.verdict, "approve");
+        // Halves round up
+        assert_eq!(merged.overall_score, 8);
+
+        // An unrecognized verdict counts as a comment and is reported as one
+        assert_eq!(merge(vec![review("approve", 9), review("unsure", 9)]).verdict, "comment");
+        assert_eq!(merge(vec![review("unsure", 9), review("request_changes", 3)]).verdict, "request_changes");
+
+        assert_eq!(merge(vec![review("comment", 0), review("comment", 10)]).overall_score, 5);
+        assert_eq!(merge(vec![review("comment", 10), review("comment", 10), review("comment", 9)]).overall_score, 10);
+    }
+
+    #[test]
+    fn test_stats_only_review_never_approves() {
+        let review = CodeReview {
+            verdict: "approve".to_string(),
+            summary: "Looks fine".to_string(),
+            issues: Vec::new(),
+            positives: Vec::new(),
+            overall_score: 9,
+        };
+        let files = vec!["src/huge.rs".to_string()];
+        let marked = stats_only_review(review, &files);
+
+        assert_eq!(marked.verdict, "comment");
+        assert_eq!(marked.issues.len(), 1);
+        assert_eq!(marked.issues[0].severity, "warning");
+        assert_eq!(marked.issues[0].file, "src/huge.rs");
+        assert_eq!(marked.issues[0].message, TOO_LARGE_MESSAGE);
+        assert_eq!(FailOn::Warning.failing(&marked).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_review_without_api_key() {
+        let dir = std::env::temp_dir().join(format!("bahn-review-no-key-{}-{}", std::process::id(), rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let repo = git2::Repository::init(&dir).unwrap();
+        let config = Config::default()
+            .with_credentials(&mut crate::config::ConfigSources::default(), &crate::core::credentials::NoKeyring, |_| None);
+        let options = || ReviewOptions {
+            staged: true,
+            commit: None,
+            range: None,
+            branch: None,
+            strictness: "normal".to_string(),
+            agent: None,
+            format: ReportFormat::Text,
+            fail_on: FailOn::Never,
+            post: false,
+        };
+
+        // Nothing to review needs no key
+        review_repo(git::open_repo(Some(&dir)).unwrap(), &config, options()).await.unwrap();
+
+        std::fs::write(dir.join("lib.rs"), "pub fn f() {}\n").unwrap();
+        git::stage_files(&repo, &["lib.rs"]).unwrap();
+        let err = review_repo(git::open_repo(Some(&dir)).unwrap(), &config, options()).await.unwrap_err();
+        assert!(err.to_string().contains("ANTHROPIC_API_KEY"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
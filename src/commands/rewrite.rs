@@ -1,41 +1,110 @@
 //! Rewrite command - AI-powered code transformation.
+//!
+//! Only files git tracks and that have no uncommitted edits are rewritten, and
+//! every run keeps the originals under `.bahn/rewrite-backup/` so
+//! `bahn rewrite --undo` can put them back.
 
+use std::fmt::Write as _;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use anyhow::{Context, Result};
+use chrono::Local;
 use colored::Colorize;
+use ignore::WalkBuilder;
 
 use crate::config::Config;
 use crate::core::ai::AiClient;
+use crate::core::{batch, git};
+
+/// Backups of each run, one timestamped directory per run, relative to the repo root
+const BACKUP_DIR: &str = ".bahn/rewrite-backup";
+
+/// Extensions rewritten when walking a directory
+const EXTENSIONS: &[&str] = &["rs", "py", "js", "ts", "go", "rb"];
+
+/// Options for rewrite command
+pub struct RewriteOptions {
+    /// Rewrite instructions
+    pub instructions: Option<String>,
+    /// Show a diff instead of writing
+    pub dry_run: bool,
+    /// Also rewrite files with uncommitted changes
+    pub allow_dirty: bool,
+}
 
 /// Run the rewrite command
-pub async fn run(config: &Config, path: &str, instructions: Option<&str>, dry_run: bool) -> Result<()> {
+pub async fn run(config: &Config, path: &str, options: RewriteOptions) -> Result<()> {
     println!("{}", "gitBahn - Code Rewrite".bold().cyan());
     println!();
 
-    let api_key = config.anthropic_api_key()
-        .context("ANTHROPIC_API_KEY not set")?;
-
-    let ai = AiClient::new(api_key.to_string(), Some(config.ai.model.clone()));
-
     let file_path = Path::new(path);
 
     if !file_path.exists() {
         anyhow::bail!("Path does not exist: {}", path);
     }
 
-    if file_path.is_file() {
-        rewrite_file(&ai, file_path, instructions, dry_run).await?;
-    } else if file_path.is_dir() {
-        rewrite_directory(&ai, file_path, instructions, dry_run).await?;
+    let repo = git::open_repo(None)?;
+    let root = fs::canonicalize(git::repo_root(&repo)?)?;
+
+    let candidates = if file_path.is_file() {
+        vec![file_path.to_path_buf()]
+    } else {
+        source_files(file_path)?
+    };
+
+    let mut files = Vec::new();
+    for file in candidates {
+        let relative = relative_path(&root, &file)?;
+        match skip_reason(&repo, &relative, options.allow_dirty)? {
+            Some(reason) => println!("  {} {} ({})", "Skipping".dimmed(), relative, reason),
+            None => files.push(relative),
+        }
+    }
+    if files.is_empty() {
+        println!("{}", "Nothing to rewrite.".yellow());
+        return Ok(());
+    }
+
+    let api_key = config.require_api_key()?;
+    let ai = config.ai_client(api_key);
+    let instructions = options.instructions.as_deref().unwrap_or("Improve code quality, fix bugs, and optimize");
+
+    let backup = Mutex::new(None);
+    let attempted = files.len();
+    let failures = batch::run(files, config.docs.concurrency, "Rewriting", String::clone, |relative| {
+        let (ai, root, backup) = (&ai, &root, &backup);
+        async move { rewrite_file(ai, root, &relative, instructions, options.dry_run, backup).await }
+    })
+    .await;
+
+    if options.dry_run {
+        println!("{}", "[DRY RUN] Changes not applied".yellow());
+    } else if let Some(backup) = backup.into_inner().unwrap() {
+        println!();
+        println!(
+            "{} Originals saved in {}; restore them with `bahn rewrite --undo`",
+            "→".cyan(),
+            git::display_path(&backup.dir)
+        );
     }
 
-    Ok(())
+    batch::finish(failures, attempted)
 }
 
-async fn rewrite_file(ai: &AiClient, path: &Path, instructions: Option<&str>, dry_run: bool) -> Result<()> {
-    let content = fs::read_to_string(path)
+/// Rewrite one file, backing it up first, and return what to print. The
+/// backup for the run is created by the first file actually rewritten.
+async fn rewrite_file(
+    ai: &AiClient,
+    root: &Path,
+    relative: &str,
+    instructions: &str,
+    dry_run: bool,
+    backup: &Mutex<Option<Backup>>,
+) -> Result<String> {
+    let path = root.join(relative);
+    let content = fs::read_to_string(&path)
         .with_context(|| format!("Failed to read file: {}", path.display()))?;
 
     let extension = path.extension()
@@ -52,47 +121,301 @@ async fn rewrite_file(ai: &AiClient, path: &Path, instructions: Option<&str>, dr
         _ => extension,
     };
 
-    println!("  {} {}", "Rewriting".yellow(), path.display());
-
-    let instructions = instructions.unwrap_or("Improve code quality, fix bugs, and optimize");
-
-    let rewritten = ai.rewrite_code(&content, language, instructions).await?;
+    let mut rewritten = ai.rewrite_code(&content, language, instructions).await?;
+    if content.ends_with('\n') && !rewritten.ends_with('\n') {
+        rewritten.push('\n');
+    }
+    if rewritten == content {
+        return Ok(format!("  {} {} (no changes)\n", "Unchanged".dimmed(), relative));
+    }
 
     if dry_run {
-        println!("{}", "--- Original ---".dimmed());
-        println!("{}", &content[..content.len().min(500)]);
-        println!("{}", "--- Rewritten ---".dimmed());
-        println!("{}", &rewritten[..rewritten.len().min(500)]);
-        println!("{}", "[DRY RUN] Changes not applied".yellow());
-    } else {
-        fs::write(path, &rewritten)
-            .with_context(|| format!("Failed to write file: {}", path.display()))?;
-        println!("  {} {}", "Rewrote".green(), path.display());
+        let mut output = String::new();
+        for line in unified_diff(relative, &content, &rewritten)?.lines() {
+            let line = match line.chars().next() {
+                Some('+') if !line.starts_with("+++") => line.green(),
+                Some('-') if !line.starts_with("---") => line.red(),
+                Some('@') => line.cyan(),
+                _ => line.normal(),
+            };
+            writeln!(output, "{}", line)?;
+        }
+        return Ok(output);
     }
 
-    Ok(())
+    {
+        let mut backup = backup.lock().unwrap();
+        if backup.is_none() {
+            *backup = Some(Backup::create(root)?);
+        }
+        if let Some(backup) = backup.as_ref() {
+            backup.save(relative, &content, &rewritten)?;
+        }
+    }
+    fs::write(&path, &rewritten)
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+    Ok(format!("  {} {}\n", "Rewrote".green(), relative))
 }
 
-async fn rewrite_directory(ai: &AiClient, path: &Path, instructions: Option<&str>, dry_run: bool) -> Result<()> {
-    let extensions = ["rs", "py", "js", "ts", "go", "rb"];
+/// Source files under `dir`, leaving out gitignored files and hidden,
+/// `target` and `node_modules` directories
+fn source_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let walker = WalkBuilder::new(dir)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            name != "target" && name != "node_modules"
+        })
+        .build();
 
-    for entry in fs::read_dir(path)? {
+    let mut files = Vec::new();
+    for entry in walker {
         let entry = entry?;
-        let entry_path = entry.path();
+        let path = entry.path();
+        let wanted = path.extension().and_then(|e| e.to_str()).is_some_and(|ext| EXTENSIONS.contains(&ext));
+        if wanted && entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
 
-        if entry_path.is_file() {
-            if let Some(ext) = entry_path.extension().and_then(|e| e.to_str()) {
-                if extensions.contains(&ext) {
-                    rewrite_file(ai, &entry_path, instructions, dry_run).await?;
-                }
-            }
-        } else if entry_path.is_dir() {
-            let dir_name = entry_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if !dir_name.starts_with('.') && dir_name != "target" && dir_name != "node_modules" {
-                Box::pin(rewrite_directory(ai, &entry_path, instructions, dry_run)).await?;
+/// `path` relative to the repository root, with `/` separators
+fn relative_path(root: &Path, path: &Path) -> Result<String> {
+    let absolute = fs::canonicalize(path)
+        .with_context(|| format!("Failed to resolve {}", path.display()))?;
+    git::to_repo_relative(root, &absolute)
+        .with_context(|| format!("{} is outside the repository", path.display()))
+}
+
+/// Why a file can't be rewritten, if it can't: it must be tracked, not
+/// ignored or minified, and without unstaged edits unless `allow_dirty`
+fn skip_reason(repo: &git2::Repository, relative: &str, allow_dirty: bool) -> Result<Option<&'static str>> {
+    let path = Path::new(relative);
+    if path.file_name().is_some_and(|name| name.to_string_lossy().contains(".min.")) {
+        return Ok(Some("minified"));
+    }
+    if repo.is_path_ignored(path)? {
+        return Ok(Some("ignored by .gitignore"));
+    }
+
+    let status = match repo.status_file(path) {
+        Ok(status) => status,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(Some("not tracked by git")),
+        Err(e) => return Err(e).with_context(|| format!("Failed to get the status of {}", relative)),
+    };
+    if status.is_wt_new() || status.is_ignored() {
+        return Ok(Some("not tracked by git"));
+    }
+    let dirty = status.is_wt_modified() || status.is_wt_typechange() || status.is_wt_renamed();
+    if dirty && !allow_dirty {
+        return Ok(Some("uncommitted changes; commit or stash them, or pass --allow-dirty"));
+    }
+    Ok(None)
+}
+
+/// Unified diff between the original and rewritten contents
+fn unified_diff(relative: &str, old: &str, new: &str) -> Result<String> {
+    let mut patch = git2::Patch::from_buffers(old.as_bytes(), Some(Path::new(relative)), new.as_bytes(), Some(Path::new(relative)), None)?;
+    let buf = patch.to_buf()?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Originals of the files rewritten in one run. Each file is kept as it was
+/// (`before/`) and as the rewrite left it (`after/`), so undo can tell when a
+/// file was edited again since.
+struct Backup {
+    dir: PathBuf,
+}
+
+impl Backup {
+    /// New, empty backup for a run starting now
+    fn create(root: &Path) -> Result<Self> {
+        let backups = root.join(BACKUP_DIR);
+        fs::create_dir_all(&backups)
+            .with_context(|| format!("Failed to create {}", backups.display()))?;
+        // Keep backups out of `git status` and `git add -A`
+        let ignore = backups.join(".gitignore");
+        if !ignore.exists() {
+            fs::write(&ignore, "*\n")?;
+        }
+
+        let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut dir = backups.join(&stamp);
+        let mut suffix = 1;
+        while dir.exists() {
+            suffix += 1;
+            dir = backups.join(format!("{}-{}", stamp, suffix));
+        }
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn save(&self, relative: &str, before: &str, after: &str) -> Result<()> {
+        for (side, content) in [("before", before), ("after", after)] {
+            let path = self.dir.join(side).join(relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
             }
+            fs::write(&path, content)
+                .with_context(|| format!("Failed to back up {}", relative))?;
+        }
+        Ok(())
+    }
+}
+
+/// Restore the files from the last rewrite run
+pub fn undo() -> Result<()> {
+    let repo = git::open_repo(None)?;
+    let root = git::repo_root(&repo)?;
+    undo_in(root)
+}
+
+/// Restore the files from the newest backup under `root`. Files edited since
+/// the rewrite are left alone and their backup is kept.
+fn undo_in(root: &Path) -> Result<()> {
+    let backups = root.join(BACKUP_DIR);
+    let latest = match fs::read_dir(&backups) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .max(),
+        Err(_) => None,
+    };
+    let Some(latest) = latest else {
+        println!("{}", "No rewrite to undo.".yellow());
+        return Ok(());
+    };
+
+    let before = latest.join("before");
+    let mut kept = 0;
+    for relative in files_under(&before)? {
+        let target = root.join(&relative);
+        let rewritten = fs::read(latest.join("after").join(&relative)).ok();
+        let current = fs::read(&target).ok();
+        let shown = relative.display();
+        if current.is_some() && current != rewritten {
+            println!("  {} {} (changed since the rewrite)", "Kept".yellow(), shown);
+            kept += 1;
+            continue;
         }
+        fs::copy(before.join(&relative), &target)
+            .with_context(|| format!("Failed to restore {}", shown))?;
+        // A later undo only has to deal with the files kept this time
+        fs::remove_file(before.join(&relative)).ok();
+        fs::remove_file(latest.join("after").join(&relative)).ok();
+        println!("  {} {}", "Restored".green(), shown);
     }
 
+    if kept == 0 {
+        fs::remove_dir_all(&latest)
+            .with_context(|| format!("Failed to remove {}", latest.display()))?;
+    } else {
+        println!("{} Originals of the kept files are in {}", "→".cyan(), latest.join("before").display());
+    }
     Ok(())
 }
+
+/// Every file under `dir`, relative to it
+fn files_under(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let entries = match fs::read_dir(dir.join(&relative)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo() -> (PathBuf, git2::Repository) {
+        let dir = std::env::temp_dir().join(format!("bahn-rewrite-{}-{}", std::process::id(), rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+        let repo = git2::Repository::init(&dir).unwrap();
+        fs::write(dir.join(".gitignore"), "gen/\n").unwrap();
+        fs::write(dir.join("clean.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.join("dirty.rs"), "fn b() {}\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        for file in [".gitignore", "clean.rs", "dirty.rs"] {
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial", &tree, &[]).unwrap();
+        drop(tree);
+
+        fs::write(dir.join("dirty.rs"), "fn b() { todo!() }\n").unwrap();
+        fs::write(dir.join("new.rs"), "fn c() {}\n").unwrap();
+        fs::create_dir_all(dir.join("gen")).unwrap();
+        fs::write(dir.join("gen/out.rs"), "fn d() {}\n").unwrap();
+        fs::write(dir.join("app.min.js"), "x()\n").unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_only_clean_tracked_files_are_rewritten() {
+        let (dir, repo) = temp_repo();
+
+        assert_eq!(skip_reason(&repo, "clean.rs", false).unwrap(), None);
+        assert!(skip_reason(&repo, "dirty.rs", false).unwrap().unwrap().contains("--allow-dirty"));
+        assert_eq!(skip_reason(&repo, "dirty.rs", true).unwrap(), None);
+        assert_eq!(skip_reason(&repo, "new.rs", true).unwrap(), Some("not tracked by git"));
+        assert_eq!(skip_reason(&repo, "gen/out.rs", true).unwrap(), Some("ignored by .gitignore"));
+        assert_eq!(skip_reason(&repo, "app.min.js", true).unwrap(), Some("minified"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_undo_restores_the_last_run() {
+        let (dir, _repo) = temp_repo();
+
+        let backup = Backup::create(&dir).unwrap();
+        for (file, before, after) in [("clean.rs", "fn a() {}\n", "fn a() -> () {}\n"), ("dirty.rs", "fn b() { todo!() }\n", "fn b() {}\n")] {
+            backup.save(file, before, after).unwrap();
+            fs::write(dir.join(file), after).unwrap();
+        }
+        // Edited again after the rewrite: undo must not clobber it
+        fs::write(dir.join("dirty.rs"), "fn b() { 1 }\n").unwrap();
+        assert_eq!(fs::read_to_string(dir.join(BACKUP_DIR).join(".gitignore")).unwrap(), "*\n");
+
+        undo_in(&dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("clean.rs")).unwrap(), "fn a() {}\n");
+        assert_eq!(fs::read_to_string(dir.join("dirty.rs")).unwrap(), "fn b() { 1 }\n");
+        assert!(backup.dir.exists(), "backup kept for the file that wasn't restored");
+
+        // Once everything is restored the backup goes away
+        fs::write(dir.join("dirty.rs"), "fn b() {}\n").unwrap();
+        undo_in(&dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("dirty.rs")).unwrap(), "fn b() { todo!() }\n");
+        assert!(!backup.dir.exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("src/lib.rs", "a\nb\nc\n", "a\nB\nc\n").unwrap();
+        assert!(diff.contains("--- a/src/lib.rs\n+++ b/src/lib.rs\n"), "{}", diff);
+        assert!(diff.contains("@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"), "{}", diff);
+    }
+}
@@ -0,0 +1,125 @@
+//! Squash command for combining recent commits into one.
+
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::config::Config;
+use crate::core::git;
+use crate::core::lock::LockGuard;
+use crate::core::prompt::{self, Interaction};
+use crate::core::report::{self, say, SquashReport};
+
+/// Options for squash command
+pub struct SquashOptions {
+    /// Number of commits to squash, counting back from HEAD
+    pub count: usize,
+    /// Message for the squashed commit; generated by AI when absent
+    pub message: Option<String>,
+    /// Skip confirmation prompt
+    pub yes: bool,
+    /// Squash even if some of the commits are pushed
+    pub force: bool,
+    /// How long to wait for another instance to release the lock
+    pub lock_wait: std::time::Duration,
+}
+
+/// Run the squash command
+pub async fn run(config: &Config, options: SquashOptions) -> Result<()> {
+    let repo = git::open_repo(None)?;
+    let _lock = LockGuard::acquire_with_timeout(&repo, "squash", options.lock_wait).await?;
+
+    if options.count < 2 {
+        anyhow::bail!("Need at least 2 commits to squash");
+    }
+    // Fail on short history or merges before spending an AI request
+    git::squash_base(&repo, options.count)?;
+
+    let unpushed = git::count_unpushed_commits(&repo)?;
+    if unpushed < options.count && !options.force {
+        if report::is_json() {
+            anyhow::bail!(
+                "Only {} commits are unpushed, but {} were requested. Pass --force to squash pushed commits.",
+                unpushed, options.count
+            );
+        }
+        say!(
+            "{} Some commits have already been pushed to remote.",
+            "Warning:".yellow()
+        );
+        say!("Only {} commits are unpushed, but you requested {}.", unpushed, options.count);
+        say!("Use --force to squash anyway (will require force push).");
+        return Ok(());
+    }
+
+    let messages = git::get_commit_messages_for_squash(&repo, options.count)?;
+
+    say!("{} Commits to squash:", "→".cyan());
+    for (i, msg) in messages.iter().enumerate() {
+        say!("  {}. {}", i + 1, msg.lines().next().unwrap_or(""));
+    }
+    say!();
+
+    let message = match options.message {
+        Some(message) => message,
+        None => {
+            let api_key = config.require_api_key()?;
+            let ai = config.ai_client(api_key);
+
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .unwrap());
+            pb.set_message("Generating squash message...");
+            let message = ai.generate_squash_message(&messages.join("\n---\n")).await;
+            pb.finish_and_clear();
+            message?
+        }
+    };
+
+    say!("{}", "Squashed commit message:".bold());
+    say!("{}", "─".repeat(50).dimmed());
+    say!("{}", message.trim());
+    say!("{}", "─".repeat(50).dimmed());
+    say!();
+
+    if report::is_json() && !options.yes {
+        return Err(report::confirmation_required("Squash", "--yes"));
+    }
+
+    let interaction = prompt::decide(prompt::is_interactive(), options.yes, prompt::assume_yes_env())?;
+    if interaction == Interaction::Prompt {
+        let confirm = Confirm::new()
+            .with_prompt(format!("Squash {} commits into one?", options.count))
+            .default(false)
+            .interact()?;
+
+        if !confirm {
+            say!("{} Aborted", "→".yellow());
+            return Ok(());
+        }
+    }
+
+    let oid = git::squash_commits(&repo, options.count, message.trim())?;
+
+    say!(
+        "{} Squashed {} commits → {}",
+        "✓".green(),
+        options.count,
+        oid.to_string()[..7].cyan()
+    );
+    if unpushed < options.count {
+        say!("{} The branch has diverged from its upstream; push with --force.", "Tip:".cyan());
+    }
+
+    if report::is_json() {
+        report::emit(&SquashReport {
+            squashed: messages,
+            message: message.trim().to_string(),
+            head: oid.to_string(),
+        })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,309 @@
+//! Stats command - summarize what bahn has been doing in a repository.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use colored::Colorize;
+use git2::{Repository, Sort};
+
+use crate::core::activity::{self, Event, Record};
+use crate::core::git;
+use crate::core::report::{self, say, CommitStats, SessionStats, StatsReport};
+
+/// Size of one commit in history
+#[derive(Debug, Clone)]
+struct CommitSize {
+    sha: String,
+    files: usize,
+    lines: usize,
+}
+
+/// Run the stats command
+pub fn run(since: Option<&str>) -> Result<()> {
+    let repo = git::open_repo(None)?;
+    let cutoff = since
+        .map(parse_window)
+        .transpose()?
+        .map(|window| Utc::now() - window);
+
+    let log = activity::log_path(&repo);
+    let records = activity::load(&log)?;
+    let history = commit_sizes(&repo, cutoff.map(|c| c.timestamp()))?;
+    let stats = build(&history, &records, cutoff, log.exists());
+
+    if report::is_json() {
+        return report::emit(&stats);
+    }
+
+    render(&stats, since);
+    Ok(())
+}
+
+/// Parse a window such as `12h`, `30d` or `2w`
+fn parse_window(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let unit_start = value.char_indices().last().map_or(0, |(i, _)| i);
+    let (number, unit) = value.split_at(unit_start);
+    let count: i64 = number.parse()
+        .with_context(|| format!("Invalid --since '{}'. Use e.g. 12h, 30d or 2w", value))?;
+
+    match unit {
+        "h" => Ok(Duration::hours(count)),
+        "d" => Ok(Duration::days(count)),
+        "w" => Ok(Duration::weeks(count)),
+        _ => anyhow::bail!("Invalid --since '{}'. Use e.g. 12h, 30d or 2w", value),
+    }
+}
+
+/// Files and lines touched by each commit on HEAD, newer than `cutoff`
+fn commit_sizes(repo: &Repository, cutoff: Option<i64>) -> Result<Vec<CommitSize>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    if walk.push_head().is_err() {
+        return Ok(Vec::new()); // No commits yet
+    }
+
+    let mut sizes = Vec::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let time = commit.time().seconds();
+        if cutoff.is_some_and(|cutoff| time < cutoff) {
+            continue;
+        }
+
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let stats = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?.stats()?;
+        sizes.push(CommitSize {
+            sha: commit.id().to_string(),
+            files: stats.files_changed(),
+            lines: stats.insertions() + stats.deletions(),
+        });
+    }
+    Ok(sizes)
+}
+
+/// Combine history with the activity log
+fn build(history: &[CommitSize], records: &[Record], cutoff: Option<DateTime<Utc>>, activity_recorded: bool) -> StatsReport {
+    let by_bahn: HashSet<&str> = records.iter()
+        .filter_map(|r| match &r.event {
+            Event::Commit { sha, .. } | Event::Squash { sha, .. } => Some(sha.as_str()),
+            Event::SecretsBlocked { .. } => None,
+        })
+        .collect();
+
+    let total = history.len();
+    let bahn = history.iter().filter(|c| by_bahn.contains(c.sha.as_str())).count();
+    let average = |f: fn(&CommitSize) -> usize| {
+        if total == 0 { 0.0 } else { history.iter().map(f).sum::<usize>() as f64 / total as f64 }
+    };
+    let commits = CommitStats {
+        total,
+        by_bahn: bahn,
+        manual: total - bahn,
+        avg_files: average(|c| c.files),
+        avg_lines: average(|c| c.lines),
+    };
+
+    let recent: Vec<&Record> = records.iter()
+        .filter(|r| cutoff.is_none_or(|cutoff| r.at >= cutoff.timestamp()))
+        .collect();
+
+    // Sessions are the auto runs that produced commits: (first, last, count)
+    let mut sessions: BTreeMap<&str, (i64, i64, usize)> = BTreeMap::new();
+    for record in &recent {
+        if let Event::Commit { session: Some(id), .. } = &record.event {
+            let entry = sessions.entry(id.as_str()).or_insert((record.at, record.at, 0));
+            entry.0 = entry.0.min(record.at);
+            entry.1 = entry.1.max(record.at);
+            entry.2 += 1;
+        }
+    }
+    let mut sessions: Vec<SessionStats> = sessions.into_iter()
+        .map(|(id, (started, ended, commits))| SessionStats {
+            id: id.to_string(),
+            started: timestamp(started),
+            ended: timestamp(ended),
+            commits,
+        })
+        .collect();
+    sessions.sort_by(|a, b| a.started.cmp(&b.started));
+
+    let squashes: Vec<usize> = recent.iter()
+        .filter_map(|r| match r.event {
+            Event::Squash { count, .. } => Some(count),
+            _ => None,
+        })
+        .collect();
+    let secrets_blocked = recent.iter()
+        .filter(|r| matches!(r.event, Event::SecretsBlocked { .. }))
+        .count();
+
+    StatsReport {
+        since: cutoff.map(|c| c.to_rfc3339()),
+        commits,
+        sessions,
+        squashes: squashes.len(),
+        squashed_commits: squashes.iter().sum(),
+        secrets_blocked,
+        activity_recorded,
+    }
+}
+
+fn timestamp(seconds: i64) -> String {
+    Utc.timestamp_opt(seconds, 0).single().unwrap_or_default().to_rfc3339()
+}
+
+fn render(stats: &StatsReport, since: Option<&str>) {
+    let window = since.map(|s| format!(" (last {})", s)).unwrap_or_default();
+    say!("{}{}", "gitBahn - Stats".bold().cyan(), window);
+    say!();
+
+    let c = &stats.commits;
+    say!("  {:<16} {} ({} by bahn, {} manual)", "Commits", c.total.to_string().bold(), c.by_bahn, c.manual);
+    say!("  {:<16} {:.1} files, {:.0} lines", "Average size", c.avg_files, c.avg_lines);
+    say!("  {:<16} {} ({} commits folded)", "Squashes", stats.squashes, stats.squashed_commits);
+    say!("  {:<16} {}", "Secrets blocked", stats.secrets_blocked);
+    say!("  {:<16} {}", "Auto sessions", stats.sessions.len());
+
+    for session in &stats.sessions {
+        say!("    {} → {}  {} commit(s)",
+            short_time(&session.started),
+            short_time(&session.ended),
+            session.commits
+        );
+    }
+
+    if !stats.activity_recorded {
+        say!();
+        say!("{}", "No bahn activity recorded in this repository yet; all commits count as manual.".dimmed());
+    }
+}
+
+/// `2026-10-01T09:12:00+00:00` as local `2026-10-01 09:12`
+fn short_time(rfc3339: &str) -> String {
+    DateTime::parse_from_rfc3339(rfc3339)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| rfc3339.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo(label: &str) -> (Repository, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "bahn-stats-{}-{}-{}",
+            label,
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let repo = Repository::init(&dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        (repo, dir)
+    }
+
+    fn size(sha: &str, files: usize, lines: usize) -> CommitSize {
+        CommitSize { sha: sha.to_string(), files, lines }
+    }
+
+    fn record(at: i64, event: Event) -> Record {
+        Record { at, event }
+    }
+
+    fn commit(sha: &str, session: Option<&str>) -> Event {
+        Event::Commit { sha: sha.to_string(), session: session.map(String::from) }
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_window("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_window("2w").unwrap(), Duration::weeks(2));
+        assert!(parse_window("30").is_err());
+        assert!(parse_window("d").is_err());
+        assert!(parse_window("3m").is_err());
+        assert!(parse_window("3é").is_err());
+    }
+
+    #[test]
+    fn test_build_from_synthetic_history_and_log() {
+        let history = vec![size("a", 1, 10), size("b", 2, 20), size("c", 3, 30), size("d", 2, 0)];
+        let records = vec![
+            record(100, commit("a", Some("s1"))),
+            record(160, commit("b", Some("s1"))),
+            record(500, commit("c", Some("s2"))),
+            record(600, Event::Squash { sha: "d".to_string(), count: 3 }),
+            record(700, Event::SecretsBlocked { findings: 2 }),
+            record(800, commit("gone", None)),
+        ];
+
+        let stats = build(&history, &records, None, true);
+        assert_eq!(stats.commits.total, 4);
+        assert_eq!(stats.commits.by_bahn, 4);
+        assert_eq!(stats.commits.manual, 0);
+        assert_eq!(stats.commits.avg_files, 2.0);
+        assert_eq!(stats.commits.avg_lines, 15.0);
+        assert_eq!((stats.squashes, stats.squashed_commits, stats.secrets_blocked), (1, 3, 1));
+
+        let sessions: Vec<(&str, usize)> = stats.sessions.iter().map(|s| (s.id.as_str(), s.commits)).collect();
+        assert_eq!(sessions, vec![("s1", 2), ("s2", 1)]);
+        assert_eq!(stats.sessions[0].started, timestamp(100));
+        assert_eq!(stats.sessions[0].ended, timestamp(160));
+
+        // Only log events inside the window count
+        let cutoff = Utc.timestamp_opt(550, 0).unwrap();
+        let recent = build(&history[..1], &records, Some(cutoff), true);
+        assert!(recent.sessions.is_empty());
+        assert_eq!((recent.squashes, recent.secrets_blocked), (1, 1));
+        assert_eq!(recent.commits.by_bahn, 1);
+        assert_eq!(recent.since.as_deref(), Some(timestamp(550).as_str()));
+    }
+
+    #[test]
+    fn test_repo_without_artifacts() {
+        let (repo, dir) = temp_repo("empty");
+
+        assert!(commit_sizes(&repo, None).unwrap().is_empty());
+        let records = activity::load(&activity::log_path(&repo)).unwrap();
+        let stats = build(&[], &records, None, activity::log_path(&repo).exists());
+        assert_eq!(stats.commits.total, 0);
+        assert_eq!(stats.commits.avg_files, 0.0);
+        assert!(stats.sessions.is_empty());
+        assert!(!stats.activity_recorded);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_bahn_and_manual_commits_are_told_apart() {
+        let (repo, dir) = temp_repo("mixed");
+
+        std::fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+        git::stage_files(&repo, &["a.txt"]).unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        let manual = repo.commit(Some("HEAD"), &sig, &sig, "Manual commit", &tree, &[]).unwrap();
+
+        std::fs::write(dir.join("b.txt"), "three\n").unwrap();
+        git::stage_files(&repo, &["b.txt"]).unwrap();
+        let by_bahn = git::create_commit(&repo, "Add b", false).unwrap();
+
+        let history = commit_sizes(&repo, None).unwrap();
+        let shas: Vec<&str> = history.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(shas, vec![by_bahn.to_string(), manual.to_string()]);
+        assert_eq!((history[1].files, history[1].lines), (1, 2));
+
+        let records = activity::load(&activity::log_path(&repo)).unwrap();
+        let stats = build(&history, &records, None, true);
+        assert_eq!((stats.commits.by_bahn, stats.commits.manual), (1, 1));
+        assert_eq!(stats.commits.avg_lines, 1.5);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
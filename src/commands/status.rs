@@ -4,82 +4,165 @@ use anyhow::Result;
 use colored::Colorize;
 
 use crate::core::git;
+use crate::core::report::{self, say, StagedReport, StatusReport, UpstreamReport};
 
 /// Run the status command
 pub fn run() -> Result<()> {
-    println!("{}", "gitBahn - Status".bold().cyan());
-    println!();
+    say!("{}", "gitBahn - Status".bold().cyan());
+    say!();
 
-    let repo = git::open_repo(None)?;
+    status_repo(git::open_repo(None)?)
+}
+
+/// Show the branch, upstream and staged changes of `repo`
+fn status_repo(repo: git2::Repository) -> Result<()> {
     let branch = git::current_branch(&repo)?;
     let root = git::repo_root(&repo)?;
 
-    println!("{} {}", "Repository:".bold(), root.display());
-    println!("{} {}", "Branch:".bold(), branch.green());
-    println!();
+    say!("{} {}", "Repository:".bold(), root.display());
+    say!("{} {}", "Branch:".bold(), branch.green());
+
+    let upstream = git::upstream_status(&repo)?;
+    match &upstream {
+        Some(upstream) if upstream.ahead == 0 && upstream.behind == 0 => {
+            say!("{} up to date with {}", "Upstream:".bold(), upstream.name.cyan());
+        }
+        Some(upstream) => {
+            say!("{} ahead {}, behind {} of {}",
+                "Upstream:".bold(),
+                upstream.ahead.to_string().green(),
+                upstream.behind.to_string().red(),
+                upstream.name.cyan()
+            );
+        }
+        None => {
+            say!("{} {} (run {} to publish the branch)",
+                "Upstream:".bold(), "none".yellow(), "bahn push".cyan());
+        }
+    }
+
+    let stashes = git::stash_count(&repo);
+    if stashes > 0 {
+        say!("{} {}", "Stashes:".bold(), stashes);
+    }
+    say!();
 
     // Check for staged changes
     let staged = git::get_staged_changes(&repo)?;
 
     if staged.is_empty() {
-        println!("{}", "No staged changes.".dimmed());
+        say!("{}", "No staged changes.".dimmed());
     } else {
-        println!("{}", "Staged changes:".bold());
-        println!("  {} (+{}, -{})",
+        say!("{}", "Staged changes:".bold());
+        say!("  {} (+{}, -{})",
             staged.summary(),
             staged.stats.insertions.to_string().green(),
             staged.stats.deletions.to_string().red()
         );
-        println!();
+        say!();
 
         if !staged.added.is_empty() {
-            println!("  {}", "Added:".green());
+            say!("  {}", "Added:".green());
             for file in &staged.added {
-                println!("    + {}", file);
+                say!("    + {}", file);
             }
         }
 
         if !staged.modified.is_empty() {
-            println!("  {}", "Modified:".yellow());
+            say!("  {}", "Modified:".yellow());
             for file in &staged.modified {
-                println!("    M {}", file);
+                say!("    M {}", file);
             }
         }
 
         if !staged.deleted.is_empty() {
-            println!("  {}", "Deleted:".red());
+            say!("  {}", "Deleted:".red());
             for file in &staged.deleted {
-                println!("    - {}", file);
+                say!("    - {}", file);
             }
         }
 
         if !staged.renamed.is_empty() {
-            println!("  {}", "Renamed:".blue());
+            say!("  {}", "Renamed:".blue());
             for (old, new) in &staged.renamed {
-                println!("    {} → {}", old, new);
+                say!("    {} → {}", old, new);
             }
         }
     }
 
-    println!();
+    say!();
+
+    // Changes that aren't staged yet
+    let working = git::get_working_tree_changes(&repo)?;
+
+    if !working.unstaged.is_empty() {
+        say!("{}", "Unstaged changes:".bold());
+        for file in &working.unstaged {
+            say!("    M {}", file);
+        }
+        say!();
+    }
+
+    if !working.untracked.is_empty() {
+        say!("{}", "Untracked files:".bold());
+        for file in &working.untracked {
+            say!("    ? {}", file);
+        }
+        say!();
+    }
 
-    // Check for uncommitted changes
-    if git::has_uncommitted_changes(&repo)? {
-        println!("{}", "You have uncommitted changes.".yellow());
-        println!("Run {} to generate a commit message.", "bahn commit".cyan());
+    let uncommitted = !staged.is_empty() || !working.is_empty();
+    if uncommitted {
+        say!("{}", "You have uncommitted changes.".yellow());
+        say!("Run {} to generate a commit message.", "bahn commit".cyan());
     } else {
-        println!("{}", "Working tree clean.".green());
+        say!("{}", "Working tree clean.".green());
     }
 
     // Show recent commits
     let recent = git::get_recent_commits(&repo, 5)?;
+    if report::is_json() {
+        return report::emit(&StatusReport {
+            repository: root.display().to_string(),
+            branch,
+            upstream: upstream.as_ref().map(UpstreamReport::from),
+            staged: StagedReport::from(&staged),
+            unstaged: working.unstaged,
+            untracked: working.untracked,
+            uncommitted_changes: uncommitted,
+            stashes,
+            recent_commits: recent,
+        });
+    }
+
     if !recent.is_empty() {
-        println!();
-        println!("{}", "Recent commits:".bold());
+        say!();
+        say!("{}", "Recent commits:".bold());
         for msg in recent {
-            println!("  {} {}", "".dimmed(), msg);
+            say!("  {} {}", "".dimmed(), msg);
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_without_api_key() {
+        // Status never loads an AI client, so a repo alone is enough
+        let dir = std::env::temp_dir().join(format!("bahn-status-no-key-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[]).unwrap();
+        std::fs::write(dir.join("lib.rs"), "pub fn f() {}\n").unwrap();
+        git::stage_files(&repo, &["lib.rs"]).unwrap();
+
+        status_repo(git::open_repo(Some(&dir)).unwrap()).unwrap();
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -5,6 +5,9 @@ use colored::Colorize;
 use dialoguer::Confirm;
 
 use crate::core::git;
+use crate::core::lock::LockGuard;
+use crate::core::prompt::{self, Interaction};
+use crate::core::report::{self, say, UndoPreviewReport, UndoReport};
 
 /// Options for undo command
 pub struct UndoOptions {
@@ -16,6 +19,8 @@ pub struct UndoOptions {
     pub yes: bool,
     /// Force undo even if commits are pushed
     pub force: bool,
+    /// How long to wait for another instance to release the lock
+    pub lock_wait: std::time::Duration,
 }
 
 impl Default for UndoOptions {
@@ -25,62 +30,82 @@ impl Default for UndoOptions {
             hard: false,
             yes: false,
             force: false,
+            lock_wait: std::time::Duration::ZERO,
         }
     }
 }
 
 /// Run the undo command
-pub fn run(options: UndoOptions) -> Result<()> {
-    let repo = git::open_repo(None)?;
+pub async fn run(options: UndoOptions) -> Result<()> {
+    undo_repo(git::open_repo(None)?, options).await
+}
+
+/// Undo the last `options.count` commits of `repo`
+async fn undo_repo(repo: git2::Repository, options: UndoOptions) -> Result<()> {
+    let _lock = LockGuard::acquire_with_timeout(&repo, "undo", options.lock_wait).await?;
 
     // Check if there are commits to undo
     let recent = git::get_recent_commits(&repo, options.count)?;
     if recent.is_empty() {
-        println!("{} No commits to undo", "Info:".cyan());
+        say!("{} No commits to undo", "Info:".cyan());
+        if report::is_json() {
+            report::emit(&UndoReport { undone: vec![], hard: options.hard, head: None })?;
+        }
         return Ok(());
     }
 
     // Check if commits have been pushed
     let unpushed = git::count_unpushed_commits(&repo)?;
     if unpushed < options.count && !options.force {
-        println!(
+        if report::is_json() {
+            anyhow::bail!(
+                "Only {} commits are unpushed, but {} were requested. Pass --force to undo pushed commits.",
+                unpushed, options.count
+            );
+        }
+        say!(
             "{} Some commits have already been pushed to remote.",
             "Warning:".yellow()
         );
-        println!("Only {} commits are unpushed, but you requested {}.", unpushed, options.count);
-        println!("Use --force to undo anyway (will require force push).");
+        say!("Only {} commits are unpushed, but you requested {}.", unpushed, options.count);
+        say!("Use --force to undo anyway (will require force push).");
         return Ok(());
     }
 
     // Show what will be undone
-    println!("{} Commits to undo:", "→".cyan());
+    say!("{} Commits to undo:", "→".cyan());
     for (i, msg) in recent.iter().enumerate() {
-        println!("  {}. {}", i + 1, msg);
+        say!("  {}. {}", i + 1, msg);
     }
-    println!();
+    say!();
 
     if options.hard {
-        println!(
+        say!(
             "{} This will {} all changes in these commits!",
             "Warning:".yellow().bold(),
             "PERMANENTLY DELETE".red().bold()
         );
     } else {
-        println!(
+        say!(
             "{} Changes will be unstaged but preserved in working directory.",
             "Note:".cyan()
         );
     }
 
-    // Confirm unless --yes flag is set
-    if !options.yes {
+    if report::is_json() && !options.yes {
+        return Err(report::confirmation_required("Undo", "--yes"));
+    }
+
+    // Confirm unless --yes flag is set (or assumed without a terminal)
+    let interaction = prompt::decide(prompt::is_interactive(), options.yes, prompt::assume_yes_env())?;
+    if interaction == Interaction::Prompt {
         let confirm = Confirm::new()
             .with_prompt("Proceed with undo?")
             .default(false)
             .interact()?;
 
         if !confirm {
-            println!("{} Aborted", "→".yellow());
+            say!("{} Aborted", "→".yellow());
             return Ok(());
         }
     }
@@ -88,7 +113,7 @@ pub fn run(options: UndoOptions) -> Result<()> {
     // Perform the undo
     undo_commits(&repo, options.count, options.hard)?;
 
-    println!(
+    say!(
         "{} Successfully undid {} commit{}",
         "✓".green(),
         options.count,
@@ -96,7 +121,12 @@ pub fn run(options: UndoOptions) -> Result<()> {
     );
 
     if !options.hard {
-        println!("{} Your changes are preserved in the working directory.", "Tip:".cyan());
+        say!("{} Your changes are preserved in the working directory.", "Tip:".cyan());
+    }
+
+    if report::is_json() {
+        let head = repo.head().ok().and_then(|h| h.target()).map(|oid| oid.to_string());
+        report::emit(&UndoReport { undone: recent, hard: options.hard, head })?;
     }
 
     Ok(())
@@ -132,18 +162,25 @@ pub fn preview(count: usize) -> Result<()> {
     let recent = git::get_recent_commits(&repo, count)?;
 
     if recent.is_empty() {
-        println!("{} No commits in history", "Info:".cyan());
+        say!("{} No commits in history", "Info:".cyan());
+        if report::is_json() {
+            report::emit(&UndoPreviewReport { commits: vec![], unpushed: 0 })?;
+        }
         return Ok(());
     }
 
-    println!("{} Last {} commit{}:", "→".cyan(), count, if count == 1 { "" } else { "s" });
+    say!("{} Last {} commit{}:", "→".cyan(), count, if count == 1 { "" } else { "s" });
     for (i, msg) in recent.iter().enumerate() {
-        println!("  {}. {}", i + 1, msg);
+        say!("  {}. {}", i + 1, msg);
     }
 
     let unpushed = git::count_unpushed_commits(&repo)?;
-    println!();
-    println!(
+    if report::is_json() {
+        return report::emit(&UndoPreviewReport { commits: recent, unpushed });
+    }
+
+    say!();
+    say!(
         "{} {} commit{} can be safely undone (not pushed)",
         "Info:".cyan(),
         unpushed,
@@ -165,4 +202,83 @@ mod tests {
         assert!(!opts.yes);
         assert!(!opts.force);
     }
+
+    fn repo_with_commits(label: &str, count: usize) -> (git2::Repository, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("bahn-undo-{}-{}-{}", label, std::process::id(), rand::random::<u32>()));
+        let repo = git2::Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+
+        for i in 0..count {
+            std::fs::write(dir.join(format!("file{}.txt", i)), format!("content {}\n", i)).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(std::path::Path::new(&format!("file{}.txt", i))).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents: Vec<git2::Commit> = repo.head().ok()
+                .and_then(|h| h.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, &format!("Commit {}", i), &tree, &parents).unwrap();
+        }
+        (repo, dir)
+    }
+
+    fn head_message(repo: &git2::Repository) -> String {
+        repo.head().unwrap().peel_to_commit().unwrap().summary().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_soft_undo_keeps_changes_in_working_tree() {
+        let (repo, dir) = repo_with_commits("soft", 3);
+
+        undo_commits(&repo, 2, false).unwrap();
+        assert_eq!(head_message(&repo), "Commit 0");
+        assert!(dir.join("file1.txt").exists());
+        assert!(dir.join("file2.txt").exists());
+
+        // Undone changes are left unstaged
+        let statuses = repo.statuses(None).unwrap();
+        let untracked: Vec<String> = statuses.iter()
+            .filter(|s| s.status().contains(git2::Status::WT_NEW))
+            .filter_map(|s| s.path().map(str::to_string))
+            .collect();
+        assert_eq!(untracked, vec!["file1.txt", "file2.txt"]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_hard_undo_discards_changes() {
+        let (repo, dir) = repo_with_commits("hard", 2);
+
+        undo_commits(&repo, 1, true).unwrap();
+        assert_eq!(head_message(&repo), "Commit 0");
+        assert!(dir.join("file0.txt").exists());
+        assert!(!dir.join("file1.txt").exists());
+        assert!(repo.statuses(None).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_undo_past_the_root_fails_without_changes() {
+        let (repo, dir) = repo_with_commits("root", 2);
+
+        assert!(undo_commits(&repo, 2, true).is_err());
+        assert_eq!(head_message(&repo), "Commit 1");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_undo_without_api_key() {
+        let (repo, dir) = repo_with_commits("no-key", 2);
+
+        let options = UndoOptions { yes: true, ..UndoOptions::default() };
+        undo_repo(git::open_repo(Some(&dir)).unwrap(), options).await.unwrap();
+        assert_eq!(head_message(&repo), "Commit 0");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
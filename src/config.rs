@@ -1,12 +1,25 @@
 //! Configuration management for gitBahn.
 
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use chrono::NaiveTime;
+use globset::GlobBuilder;
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
+use crate::commands::commit::WorkWindow;
+use crate::core::agents::{Agent, AgentResolver};
+use crate::core::ai::{AiClient, Provider};
+use crate::core::cache::ResponseCache;
+use crate::core::credentials::{self, KeyStore, CREDENTIALS};
+use crate::core::prompt;
+use crate::core::secrets::{ScanOptions, SecretPolicy};
+
 /// Default configuration file name
 const CONFIG_FILE: &str = ".bahn.toml";
 
@@ -40,9 +53,29 @@ pub struct Config {
     #[serde(default)]
     pub review: ReviewConfig,
 
+    /// Git settings
+    #[serde(default)]
+    pub git: GitConfig,
+
     /// GitHub settings
     #[serde(default)]
     pub github: GitHubConfig,
+
+    /// Secret detection settings
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+
+    /// Named personality agents, e.g. `[agents.terse]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub agents: BTreeMap<String, AgentConfig>,
+
+    /// Profile to apply (a project file can pin one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Named partial overrides of this config, e.g. `[profiles.work.ai]`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, toml::Table>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,10 +84,24 @@ pub struct AiConfig {
     #[serde(default)]
     pub anthropic_api_key: Option<String>,
 
-    /// OpenAI API key for embeddings (can also use OPENAI_API_KEY env var)
+    /// OpenAI API key, for embeddings and the "openai" provider (can also use OPENAI_API_KEY env var)
     #[serde(default)]
     pub openai_api_key: Option<String>,
 
+    /// AI provider: "anthropic", "openai" for any OpenAI-compatible API, or
+    /// "ollama" for local models without an API key
+    #[serde(default = "default_provider")]
+    pub provider: String,
+
+    /// API base URL, e.g. http://localhost:8000/v1 for a local vLLM server
+    /// (default for ollama: http://localhost:11434)
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    /// Sampling temperature (0.0 to 2.0); unset uses the provider's default
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
     /// Default model to use
     #[serde(default = "default_model")]
     pub model: String,
@@ -62,6 +109,34 @@ pub struct AiConfig {
     /// Elite Coder API URL (for personality agents)
     #[serde(default)]
     pub elite_coder_url: Option<String>,
+
+    /// Bytes of diff sent per prompt; larger diffs keep small files whole and
+    /// shrink the rest to per-file stats (default: 10000, 15000 for reviews)
+    #[serde(default)]
+    pub diff_budget: Option<usize>,
+
+    /// Print the requests and tokens a command used when it finishes
+    #[serde(default = "default_true")]
+    pub show_usage: bool,
+
+    /// Stop auto mode once its session has used this many tokens
+    #[serde(default)]
+    pub max_tokens_per_session: Option<u64>,
+
+    /// Hours to reuse the response to an identical request (0 = no cache)
+    #[serde(default = "default_cache_ttl_hours")]
+    pub cache_ttl_hours: u64,
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    24
+}
+
+/// Providers `ai.provider` and `--provider` accept
+pub const SUPPORTED_PROVIDERS: &[&str] = &["anthropic", "openai", "ollama"];
+
+fn default_provider() -> String {
+    "anthropic".to_string()
 }
 
 fn default_model() -> String {
@@ -73,8 +148,15 @@ impl Default for AiConfig {
         Self {
             anthropic_api_key: None,
             openai_api_key: None,
+            provider: default_provider(),
+            base_url: None,
+            temperature: None,
             model: default_model(),
             elite_coder_url: None,
+            diff_budget: None,
+            show_usage: true,
+            max_tokens_per_session: None,
+            cache_ttl_hours: default_cache_ttl_hours(),
         }
     }
 }
@@ -89,7 +171,7 @@ pub struct CommitConfig {
     #[serde(default)]
     pub atomic: bool,
 
-    /// Sign commits with GPG
+    /// Sign commits with gpg, gpgsm or ssh-keygen, following git's gpg.format and user.signingkey
     #[serde(default)]
     pub sign: bool,
 
@@ -100,12 +182,163 @@ pub struct CommitConfig {
     /// Commit message template
     #[serde(default)]
     pub template: Option<String>,
+
+    /// Number of commit message candidates to offer
+    #[serde(default = "default_candidates")]
+    pub candidates: usize,
+
+    /// Markdown file (relative to the repo root) describing commit conventions
+    #[serde(default)]
+    pub style_guide: Option<String>,
+
+    /// Default timestamp spreading for atomic commits
+    #[serde(default)]
+    pub spread: SpreadConfig,
+
+    /// Without a terminal, behave as if `--yes` was passed instead of failing
+    #[serde(default)]
+    pub assume_yes: bool,
+
+    /// Deterministic messages for trivial changes, skipping the AI
+    #[serde(default)]
+    pub fast_path: FastPathConfig,
+}
+
+/// Which staged changes are simple enough to commit without asking the AI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FastPathConfig {
+    /// Use the fast path at all
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// File names treated as lockfiles ("chore: update Cargo.lock")
+    #[serde(default = "default_lockfiles")]
+    pub lockfiles: Vec<String>,
+
+    /// Recognize manifest changes that only touch a version field ("chore: bump version to 1.2.3")
+    #[serde(default = "default_true")]
+    pub version_bumps: bool,
+
+    /// Also skip the AI for a single file with at most this many changed lines (0 = off)
+    #[serde(default)]
+    pub max_lines: usize,
+}
+
+fn default_lockfiles() -> Vec<String> {
+    [
+        "Cargo.lock", "package-lock.json", "yarn.lock", "pnpm-lock.yaml", "poetry.lock",
+        "Pipfile.lock", "uv.lock", "Gemfile.lock", "composer.lock", "go.sum",
+    ].iter().map(|s| s.to_string()).collect()
+}
+
+impl Default for FastPathConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lockfiles: default_lockfiles(),
+            version_bumps: true,
+            max_lines: 0,
+        }
+    }
+}
+
+/// Defaults for `--spread` / `--start` when neither flag is given
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpreadConfig {
+    /// Spread atomic commits even without the CLI flags
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Total time to spread commits over (e.g. "6h", "90m")
+    #[serde(default)]
+    pub duration: Option<String>,
+
+    /// Time of day for the first commit, on the session date (e.g. "09:30")
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// Working window (e.g. "09:00-17:30" or "9-18"); supplies start and duration
+    /// when those are unset, and keeps spread timestamps inside it
+    #[serde(default)]
+    pub work_hours: Option<String>,
+
+    /// Move spread timestamps that land on Saturday or Sunday to Monday
+    #[serde(default)]
+    pub skip_weekends: bool,
+}
+
+impl SpreadConfig {
+    /// The configured duration in seconds
+    pub fn duration_secs(&self) -> Result<Option<i64>> {
+        self.duration.as_deref()
+            .map(|d| crate::commands::commit::parse_duration(d)
+                .with_context(|| format!("Invalid commit.spread.duration '{}'", d)))
+            .transpose()
+    }
+
+    /// The configured start time of day
+    pub fn start_time(&self) -> Result<Option<NaiveTime>> {
+        self.start.as_deref()
+            .map(|t| parse_time_of_day(t).with_context(|| format!("Invalid commit.spread.start '{}'", t)))
+            .transpose()
+    }
+
+    /// The configured working window as (start, end)
+    pub fn work_hours(&self) -> Result<Option<(NaiveTime, NaiveTime)>> {
+        self.work_hours.as_deref()
+            .map(|hours| parse_work_hours(hours)
+                .with_context(|| format!("Invalid commit.spread.work_hours '{}'. Use HH:MM-HH:MM", hours)))
+            .transpose()
+    }
+
+    /// The working hours and weekend rule spread timestamps must stay within
+    pub fn work_window(&self) -> Result<WorkWindow> {
+        Ok(WorkWindow {
+            hours: self.work_hours()?,
+            skip_weekends: self.skip_weekends,
+        })
+    }
+
+    /// Check every field parses
+    pub fn validate(&self) -> Result<()> {
+        self.duration_secs()?;
+        self.start_time()?;
+        self.work_hours()?;
+        Ok(())
+    }
+}
+
+/// Parse a time of day like "09:30"
+fn parse_time_of_day(s: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .with_context(|| format!("Invalid time of day: {}. Use HH:MM", s.trim()))
+}
+
+/// Parse a working window like "09:00-17:30" or "9-18" into (start, end)
+pub fn parse_work_hours(s: &str) -> Result<(NaiveTime, NaiveTime)> {
+    let hour_or_time = |t: &str| match t.trim().parse::<u32>() {
+        Ok(hour) => NaiveTime::from_hms_opt(hour, 0, 0)
+            .with_context(|| format!("Invalid hour: {}", hour)),
+        Err(_) => parse_time_of_day(t),
+    };
+
+    let (from, to) = s.split_once('-').context("Expected a range like 9-18")?;
+    let from = hour_or_time(from)?;
+    let to = hour_or_time(to)?;
+    if to <= from {
+        anyhow::bail!("end must be after start");
+    }
+    Ok((from, to))
 }
 
 fn default_true() -> bool {
     true
 }
 
+fn default_candidates() -> usize {
+    1
+}
+
 impl Default for CommitConfig {
     fn default() -> Self {
         Self {
@@ -114,6 +347,11 @@ impl Default for CommitConfig {
             sign: false,
             default_agent: None,
             template: None,
+            candidates: default_candidates(),
+            style_guide: None,
+            spread: SpreadConfig::default(),
+            assume_yes: false,
+            fast_path: FastPathConfig::default(),
         }
     }
 }
@@ -139,6 +377,85 @@ pub struct AutoConfig {
     /// Auto-push after squash
     #[serde(default)]
     pub auto_push: bool,
+
+    /// Extra gitignore-style globs the watcher and auto-staging skip
+    #[serde(default)]
+    pub watch_ignore: Vec<String>,
+
+    /// Directories (relative to the repo root) to watch; empty watches the whole repo
+    #[serde(default)]
+    pub watch_paths: Vec<String>,
+
+    /// Milliseconds to batch file events before acting on them
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+
+    /// Hold changes back until they add up to this many inserted plus deleted lines
+    #[serde(default)]
+    pub min_lines_changed: usize,
+
+    /// Hold changes back until they touch this many files
+    #[serde(default)]
+    pub min_files: usize,
+
+    /// Seconds to wait after a commit before the next one, accumulating changes meanwhile
+    #[serde(default)]
+    pub cooldown_seconds: u64,
+
+    /// When `--merge` merges into the target branch: "each" commit or on "exit"
+    #[serde(default = "default_merge_on")]
+    pub merge_on: String,
+
+    /// Let the AI resolve `--merge` conflicts instead of refusing the merge
+    #[serde(default)]
+    pub auto_resolve: bool,
+}
+
+/// Allowed range for `auto.debounce_ms`
+pub const DEBOUNCE_RANGE_MS: std::ops::RangeInclusive<u64> = 50..=10_000;
+
+fn default_debounce_ms() -> u64 {
+    500
+}
+
+fn default_merge_on() -> String {
+    "each".to_string()
+}
+
+impl AutoConfig {
+    /// Check watcher settings: globs compile, paths stay inside the repo, debounce in range, merge_on known
+    pub fn validate(&self) -> Result<()> {
+        let mut builder = ignore::gitignore::GitignoreBuilder::new("/");
+        for pattern in &self.watch_ignore {
+            builder.add_line(None, pattern)
+                .with_context(|| format!("Invalid auto.watch_ignore pattern '{}'", pattern))?;
+        }
+        builder.build().context("Invalid auto.watch_ignore patterns")?;
+
+        for path in &self.watch_paths {
+            let escapes = Path::new(path).components().any(|c| {
+                !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir)
+            });
+            if escapes {
+                anyhow::bail!("Invalid auto.watch_paths entry '{}': use a path inside the repository", path);
+            }
+        }
+
+        if !DEBOUNCE_RANGE_MS.contains(&self.debounce_ms) {
+            anyhow::bail!(
+                "Invalid auto.debounce_ms {}: must be between {} and {}",
+                self.debounce_ms,
+                DEBOUNCE_RANGE_MS.start(),
+                DEBOUNCE_RANGE_MS.end()
+            );
+        }
+
+        if !matches!(self.merge_on.as_str(), "each" | "exit") {
+            anyhow::bail!("Invalid auto.merge_on '{}': use \"each\" or \"exit\"", self.merge_on);
+        }
+
+        Ok(())
+    }
 }
 
 fn default_interval() -> u64 {
@@ -161,6 +478,14 @@ impl Default for AutoConfig {
             rewrite_history: false,
             squash_threshold: default_squash_threshold(),
             auto_push: false,
+            watch_ignore: Vec::new(),
+            watch_paths: Vec::new(),
+            debounce_ms: default_debounce_ms(),
+            min_lines_changed: 0,
+            min_files: 0,
+            cooldown_seconds: 0,
+            merge_on: default_merge_on(),
+            auto_resolve: false,
         }
     }
 }
@@ -178,12 +503,20 @@ pub struct DocsConfig {
     /// Update existing docs or only add new
     #[serde(default)]
     pub update_existing: bool,
+
+    /// Files sent to the AI at once by `bahn docs` and `bahn rewrite`
+    #[serde(default = "default_docs_concurrency")]
+    pub concurrency: usize,
 }
 
 fn default_doc_format() -> String {
     "auto".to_string()
 }
 
+fn default_docs_concurrency() -> usize {
+    4
+}
+
 impl Default for DocsConfig {
     fn default() -> Self {
         Self {
@@ -195,6 +528,7 @@ impl Default for DocsConfig {
                 "vendor".to_string(),
             ],
             update_existing: false,
+            concurrency: default_docs_concurrency(),
         }
     }
 }
@@ -228,6 +562,28 @@ impl Default for ReviewConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Branches (globs like "release/*") that warn or refuse direct commits and pushes
+    #[serde(default = "default_protected_branches")]
+    pub protected_branches: Vec<String>,
+}
+
+fn default_protected_branches() -> Vec<String> {
+    ["main", "master", "develop", "development", "production", "staging", "release"]
+        .iter()
+        .map(|b| b.to_string())
+        .collect()
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            protected_branches: default_protected_branches(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GitHubConfig {
     /// GitHub token (can also use GITHUB_TOKEN env var)
@@ -239,62 +595,442 @@ pub struct GitHubConfig {
     pub default_repo: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    /// Flag high-entropy strings that match no known token format
+    #[serde(default = "default_true")]
+    pub entropy_enabled: bool,
+
+    /// Minimum Shannon entropy (bits per char) for flagging unknown tokens
+    #[serde(default = "default_entropy_threshold")]
+    pub entropy_threshold: f64,
+
+    /// Findings at or above this confidence abort the commit
+    #[serde(default = "default_block_threshold")]
+    pub block_threshold: f64,
+
+    /// Findings at or above this confidence are reported but allowed
+    #[serde(default = "default_warn_threshold")]
+    pub warn_threshold: f64,
+
+    /// Per-pattern actions (block, warn, ignore) keyed by secret type
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
+
+    /// Lines longer than this are skipped (minified bundles, generated data)
+    #[serde(default = "default_max_line_length")]
+    pub max_line_length: usize,
+
+    /// Globs for test fixtures and docs, where findings get lower confidence
+    #[serde(default = "default_relaxed_paths")]
+    pub relaxed_paths: Vec<String>,
+}
+
+fn default_entropy_threshold() -> f64 {
+    4.5
+}
+
+fn default_block_threshold() -> f64 {
+    0.9
+}
+
+fn default_warn_threshold() -> f64 {
+    0.7
+}
+
+fn default_max_line_length() -> usize {
+    4096
+}
+
+fn default_relaxed_paths() -> Vec<String> {
+    crate::core::secrets::DEFAULT_RELAXED_PATHS.iter().map(|p| p.to_string()).collect()
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            entropy_enabled: true,
+            entropy_threshold: default_entropy_threshold(),
+            block_threshold: default_block_threshold(),
+            warn_threshold: default_warn_threshold(),
+            overrides: HashMap::new(),
+            max_line_length: default_max_line_length(),
+            relaxed_paths: default_relaxed_paths(),
+        }
+    }
+}
+
+impl SecretsConfig {
+    /// Scan options for the repository at `repo_root`: its allowlist and baseline, then these settings
+    pub fn scan_options(&self, repo_root: &Path) -> Result<ScanOptions> {
+        ScanOptions::load(repo_root)?
+            .with_entropy_scan(self.entropy_enabled)
+            .with_entropy_threshold(self.entropy_threshold)
+            .with_max_line_length(self.max_line_length)
+            .with_relaxed_paths(&self.relaxed_paths)
+    }
+
+    /// Which findings block and which only warn
+    pub fn policy(&self) -> Result<SecretPolicy> {
+        SecretPolicy::new(self.block_threshold, self.warn_threshold).with_overrides(&self.overrides)
+    }
+}
+
+/// A personality agent defined in config; project definitions override global ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// System-prompt fragment describing the personality
+    #[serde(default)]
+    pub prompt: String,
+
+    /// Model to use for this agent instead of `ai.model`
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigSource {
+    Default,
+    Global,
+    Project,
+    Profile,
+    Keyring,
+    Env,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Default => "default",
+            Self::Global => "global",
+            Self::Project => "project",
+            Self::Profile => "profile",
+            Self::Keyring => "keyring",
+            Self::Env => "env",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The layer that last set each value, keyed by dotted path (e.g. "ai.model")
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources(BTreeMap<String, ConfigSource>);
+
+impl ConfigSources {
+    /// Source of a value; anything no layer set comes from the defaults
+    pub fn get(&self, key: &str) -> ConfigSource {
+        self.0.get(key).copied().unwrap_or(ConfigSource::Default)
+    }
+
+    fn record(&mut self, prefix: &str, value: &toml::Value, source: ConfigSource) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    self.record(&join_key(prefix, key), value, source);
+                }
+            }
+            _ => {
+                self.0.insert(prefix.to_string(), source);
+            }
+        }
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Overlay `layer` onto `base`, merging tables key by key.
+/// Arrays and scalars are replaced wholesale.
+fn merge_values(base: &mut toml::Value, layer: toml::Value) {
+    match (base, layer) {
+        (toml::Value::Table(base), toml::Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Look up a profile's overrides, listing the defined profiles if it's missing
+fn profile_table(merged: &toml::Value, name: &str) -> Result<toml::Table> {
+    let profiles = merged.get("profiles").and_then(|p| p.as_table());
+    if let Some(toml::Value::Table(table)) = profiles.and_then(|p| p.get(name)) {
+        return Ok(table.clone());
+    }
+
+    let mut available: Vec<&str> = profiles
+        .map(|p| p.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+    available.sort_unstable();
+    if available.is_empty() {
+        anyhow::bail!("Unknown profile '{}'. No profiles are defined in [profiles]", name);
+    }
+    anyhow::bail!("Unknown profile '{}'. Available profiles: {}", name, available.join(", "))
+}
+
+/// Path of the global configuration file
+pub fn global_config_path() -> PathBuf {
+    global_config_dir().join("config.toml")
+}
+
 impl Config {
     /// Load configuration from file(s)
-    pub fn load(path: Option<&str>) -> Result<Self> {
-        // Priority: explicit path > project config > global config > defaults
-        let config = if let Some(path) = path {
-            let content = fs::read_to_string(path)
-                .with_context(|| format!("Failed to read config file: {}", path))?;
-            toml::from_str(&content)?
-        } else {
-            // Try project-local config first
-            let local_path = PathBuf::from(CONFIG_FILE);
-            if local_path.exists() {
-                let content = fs::read_to_string(&local_path)?;
-                toml::from_str(&content)?
-            } else {
-                // Try global config
-                let global_path = global_config_dir().join("config.toml");
-                if global_path.exists() {
-                    let content = fs::read_to_string(&global_path)?;
-                    toml::from_str(&content)?
-                } else {
-                    Config::default()
-                }
+    pub fn load(path: Option<&str>, profile: Option<&str>, store: &dyn KeyStore) -> Result<Self> {
+        Self::load_with_sources(path, profile, store).map(|(config, _)| config)
+    }
+
+    /// Load configuration, recording which layer set each value.
+    ///
+    /// Layers are applied field by field: defaults, then the global config,
+    /// then the project config (or an explicit path), then the selected
+    /// profile. API keys then resolve env var first, keyring second, config
+    /// file last.
+    pub fn load_with_sources(
+        path: Option<&str>,
+        profile: Option<&str>,
+        store: &dyn KeyStore,
+    ) -> Result<(Self, ConfigSources)> {
+        // An explicitly requested file must exist rather than falling back to defaults
+        if let Some(path) = path.filter(|p| !Path::new(p).exists()) {
+            anyhow::bail!("Config file not found: {}", path);
+        }
+
+        let layers = Self::layer_paths(path);
+        for (file, issue) in Self::validate_layers(&layers)? {
+            // Type errors surface as a hard error when merging below
+            if !matches!(&issue, ConfigIssue::InvalidValue { key, .. } if key.is_empty()) {
+                eprintln!("{} {}: {}", "Warning:".yellow(), file, issue);
             }
-        };
+        }
+        let (config, mut sources) = Self::from_layers_with_profile(&layers, profile)?;
+        config.commit.spread.validate()?;
+        config.auto.validate()?;
 
-        // Override with environment variables
-        Ok(config.with_env_overrides())
+        let config = config.with_credentials(&mut sources, store, |var| std::env::var(var).ok());
+        Ok((config, sources))
     }
 
-    /// Apply environment variable overrides
-    fn with_env_overrides(mut self) -> Self {
-        if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
-            self.ai.anthropic_api_key = Some(key);
+    /// Settings for the repository at `root`, without API keys: the global
+    /// config, then `path` (or `root`'s `.bahn.toml`), then the profile.
+    /// For callers whose working directory isn't the repository, like gitbahn-mcp.
+    pub fn load_for_repo(root: &Path, path: Option<&str>, profile: Option<&str>) -> Result<Self> {
+        if let Some(path) = path.filter(|p| !Path::new(p).exists()) {
+            anyhow::bail!("Config file not found: {}", path);
         }
 
-        if let Ok(key) = std::env::var("OPENAI_API_KEY") {
-            self.ai.openai_api_key = Some(key);
+        let project_path = path.map(PathBuf::from).unwrap_or_else(|| root.join(CONFIG_FILE));
+        let layers = [
+            (ConfigSource::Global, global_config_path()),
+            (ConfigSource::Project, project_path),
+        ];
+        Self::from_layers_with_profile(&layers, profile).map(|(config, _)| config)
+    }
+
+    /// Config file layers in merge order
+    pub fn layer_paths(path: Option<&str>) -> Vec<(ConfigSource, PathBuf)> {
+        let project_path = path.map(PathBuf::from).unwrap_or_else(|| PathBuf::from(CONFIG_FILE));
+        vec![
+            (ConfigSource::Global, global_config_path()),
+            (ConfigSource::Project, project_path),
+        ]
+    }
+
+    /// Merge the given config files over the defaults, skipping missing ones
+    pub fn from_layers(layers: &[(ConfigSource, PathBuf)]) -> Result<(Self, ConfigSources)> {
+        Self::from_layers_with_profile(layers, None)
+    }
+
+    /// Merge config files, then the named profile (or the one the files select)
+    pub fn from_layers_with_profile(
+        layers: &[(ConfigSource, PathBuf)],
+        profile: Option<&str>,
+    ) -> Result<(Self, ConfigSources)> {
+        let mut merged = toml::Value::try_from(Config::default())?;
+        let mut sources = ConfigSources::default();
+
+        for (source, path) in layers {
+            if !path.exists() {
+                continue;
+            }
+            let layer = read_layer(path)?;
+            sources.record("", &layer, *source);
+            merge_values(&mut merged, layer);
+        }
+
+        let selected = profile
+            .map(str::to_string)
+            .or_else(|| merged.get("profile").and_then(|p| p.as_str()).map(str::to_string));
+        if let Some(name) = selected {
+            let mut overlay = profile_table(&merged, &name)?;
+            overlay.remove("profile");
+            overlay.remove("profiles");
+
+            let overlay = toml::Value::Table(overlay);
+            sources.record("", &overlay, ConfigSource::Profile);
+            merge_values(&mut merged, overlay);
+            if let Some(table) = merged.as_table_mut() {
+                table.insert("profile".to_string(), toml::Value::String(name));
+            }
         }
 
-        if let Ok(token) = std::env::var("GITHUB_TOKEN") {
-            self.github.token = Some(token);
+        let config: Config = merged.try_into()
+            .context("Invalid configuration")?;
+        Ok((config, sources))
+    }
+
+    /// Resolve API keys from env vars and the keyring, then apply other env overrides
+    pub fn with_credentials(
+        mut self,
+        sources: &mut ConfigSources,
+        store: &dyn KeyStore,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        for (provider, key, var) in CREDENTIALS {
+            let (value, source) = match env(var) {
+                Some(value) => (value, ConfigSource::Env),
+                None => match store.get(provider) {
+                    Some(value) => (value, ConfigSource::Keyring),
+                    None => continue,
+                },
+            };
+            if let Some(field) = self.credential_mut(key) {
+                *field = Some(value);
+                sources.0.insert(key.to_string(), source);
+            }
         }
 
-        if let Ok(url) = std::env::var("ELITE_CODER_URL") {
+        if let Some(url) = env("ELITE_CODER_URL") {
             self.ai.elite_coder_url = Some(url);
+            sources.0.insert("ai.elite_coder_url".to_string(), ConfigSource::Env);
         }
 
         self
     }
 
+    /// The config field holding a credential, by dotted key
+    fn credential_mut(&mut self, key: &str) -> Option<&mut Option<String>> {
+        match key {
+            "ai.anthropic_api_key" => Some(&mut self.ai.anthropic_api_key),
+            "ai.openai_api_key" => Some(&mut self.ai.openai_api_key),
+            "github.token" => Some(&mut self.github.token),
+            _ => None,
+        }
+    }
+
+    /// Apply `--model` / `--provider` from the command line, the last override layer
+    pub fn apply_cli_overrides(&mut self, model: Option<String>, provider: Option<String>) -> Result<()> {
+        if let Some(provider) = provider {
+            if !SUPPORTED_PROVIDERS.contains(&provider.as_str()) {
+                anyhow::bail!(
+                    "Unknown provider '{}'. Supported: {}",
+                    provider,
+                    SUPPORTED_PROVIDERS.join(", ")
+                );
+            }
+            self.ai.provider = provider;
+        }
+
+        if let Some(model) = model {
+            self.ai.model = model;
+        }
+
+        Ok(())
+    }
+
+    /// Whether a branch matches `git.protected_branches` (case-insensitive)
+    pub fn is_protected(&self, branch: &str) -> bool {
+        self.git.protected_branches.iter().any(|pattern| {
+            GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .literal_separator(true)
+                .build()
+                .map(|glob| glob.compile_matcher().is_match(branch))
+                .unwrap_or_else(|_| pattern.eq_ignore_ascii_case(branch))
+        })
+    }
+
     /// Get the Anthropic API key
     pub fn anthropic_api_key(&self) -> Option<&str> {
         self.ai.anthropic_api_key.as_deref()
     }
 
+    /// API key for the active provider. A self-hosted OpenAI-compatible server
+    /// (`ai.base_url` set) may not need one, which gives an empty key.
+    pub fn api_key(&self) -> Option<&str> {
+        match self.ai.provider.as_str() {
+            "openai" => self.ai.openai_api_key.as_deref()
+                .or(self.ai.base_url.as_ref().map(|_| "")),
+            // A local server; no key involved
+            "ollama" => Some(""),
+            _ => self.anthropic_api_key(),
+        }
+    }
+
+    /// AI client for the configured provider, model and base URL
+    pub fn ai_client(&self, api_key: &str) -> AiClient {
+        AiClient::new(api_key.to_string(), Some(self.ai.model.clone()))
+            .with_provider(Provider::from_name(&self.ai.provider), self.ai.base_url.as_deref())
+            .with_diff_budget(self.ai.diff_budget)
+            .with_temperature(self.ai.temperature)
+            .with_cache(self.response_cache())
+    }
+
+    /// The AI response cache, unless `ai.cache_ttl_hours` turns it off
+    pub fn response_cache(&self) -> Option<ResponseCache> {
+        if self.ai.cache_ttl_hours == 0 {
+            return None;
+        }
+        ResponseCache::in_default_dir(Duration::from_secs(self.ai.cache_ttl_hours.saturating_mul(3600)))
+    }
+
+    /// Agent resolver for `repo_root` that knows the `[agents]` table
+    pub fn agent_resolver(&self, repo_root: &Path) -> AgentResolver {
+        let configured = self.agents.iter()
+            .map(|(name, agent)| (name.clone(), Agent {
+                name: name.clone(),
+                description: agent.prompt.clone(),
+                model: agent.model.clone(),
+                ..Default::default()
+            }))
+            .collect();
+        AgentResolver::new(repo_root, self.ai.elite_coder_url.as_deref()).with_configured(configured)
+    }
+
+    /// Get the active provider's API key, failing with setup instructions if missing
+    pub fn require_api_key(&self) -> Result<&str> {
+        self.api_key()
+            .with_context(|| self.missing_api_key_message())
+    }
+
+    /// Explain how to configure credentials for the active AI provider
+    pub fn missing_api_key_message(&self) -> String {
+        match self.ai.provider.as_str() {
+            "openai" => format!(
+                "No API key configured for the OpenAI provider (model {}). \
+                Set OPENAI_API_KEY, run `bahn config set-key openai`, set ai.openai_api_key in {}, \
+                or point ai.base_url at a server that needs no key.",
+                self.ai.model, CONFIG_FILE
+            ),
+            _ => format!(
+                "No API key configured for the Anthropic provider (model {}). \
+                Set ANTHROPIC_API_KEY, run `bahn config set-key anthropic`, or set ai.anthropic_api_key in {}.",
+                self.ai.model, CONFIG_FILE
+            ),
+        }
+    }
+
     /// Get the GitHub token
     #[allow(dead_code)] // Will be used when GitHub integration is implemented
     pub fn github_token(&self) -> Option<&str> {
@@ -302,6 +1038,244 @@ impl Config {
     }
 }
 
+/// Keys that are valid but absent from the serialized defaults because they're unset
+const OPTIONAL_KEYS: &[&str] = &[
+    "profile",
+    "profiles",
+    "agents",
+    "ai.anthropic_api_key",
+    "ai.openai_api_key",
+    "ai.elite_coder_url",
+    "ai.base_url",
+    "ai.temperature",
+    "ai.diff_budget",
+    "ai.max_tokens_per_session",
+    "commit.default_agent",
+    "commit.template",
+    "commit.style_guide",
+    "commit.spread.duration",
+    "commit.spread.start",
+    "commit.spread.work_hours",
+    "review.default_agent",
+    "github.token",
+    "github.default_repo",
+];
+
+const STRICTNESS_LEVELS: &[&str] = &["relaxed", "normal", "strict"];
+
+/// A problem found while validating configuration
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigIssue {
+    /// A key no setting reads, with the closest known key if there is one
+    UnknownKey { key: String, suggestion: Option<String> },
+    /// A key whose value has the wrong type or is out of range
+    InvalidValue { key: String, message: String },
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKey { key, suggestion: Some(suggestion) } => {
+                write!(f, "unknown key '{}' (did you mean '{}'?)", key, suggestion)
+            }
+            Self::UnknownKey { key, suggestion: None } => write!(f, "unknown key '{}'", key),
+            Self::InvalidValue { key, message } if key.is_empty() => f.write_str(message),
+            Self::InvalidValue { key, message } => write!(f, "invalid value for '{}': {}", key, message),
+        }
+    }
+}
+
+/// Every dotted key the config understands, including section names
+fn known_keys() -> Vec<String> {
+    fn walk(prefix: &str, table: &toml::value::Table, out: &mut Vec<String>) {
+        for (key, value) in table {
+            let key = join_key(prefix, key);
+            if let toml::Value::Table(nested) = value {
+                walk(&key, nested, out);
+            }
+            out.push(key);
+        }
+    }
+
+    let mut keys = Vec::new();
+    if let Ok(toml::Value::Table(table)) = toml::Value::try_from(Config::default()) {
+        walk("", &table, &mut keys);
+    }
+    keys.extend(OPTIONAL_KEYS.iter().map(|k| k.to_string()));
+    keys
+}
+
+/// Edit distance between two strings
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            let next = (prev + cost).min(row[j] + 1).min(row[j + 1] + 1);
+            prev = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Closest known key in the same section as `key`, if any is similar enough
+pub fn suggest_key(key: &str, known: &[String]) -> Option<String> {
+    let (parent, name) = key.rsplit_once('.').unwrap_or(("", key));
+
+    known.iter()
+        .filter_map(|candidate| {
+            let (candidate_parent, candidate_name) = candidate.rsplit_once('.').unwrap_or(("", candidate));
+            if candidate_parent != parent {
+                return None;
+            }
+            let distance = levenshtein(name, candidate_name);
+            let close = distance <= (name.len() / 3).max(2)
+                || name.starts_with(candidate_name)
+                || candidate_name.starts_with(name);
+            close.then_some((distance, candidate))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Find unknown keys and type mismatches in one config file's content
+pub fn check_layer(content: &str) -> Vec<ConfigIssue> {
+    let known = known_keys();
+    let mut unknown = Vec::new();
+
+    let deserializer = toml::Deserializer::new(content);
+    let parsed: std::result::Result<Config, _> = serde_ignored::deserialize(deserializer, |path| {
+        unknown.push(path.to_string());
+    });
+
+    let mut issues: Vec<ConfigIssue> = unknown.into_iter()
+        .map(|key| {
+            let suggestion = suggest_key(&key, &known);
+            ConfigIssue::UnknownKey { key, suggestion }
+        })
+        .collect();
+
+    if let Err(e) = parsed {
+        issues.push(ConfigIssue::InvalidValue {
+            key: String::new(),
+            message: e.to_string().trim().to_string(),
+        });
+    }
+
+    issues
+}
+
+impl Config {
+    /// Check values that parse but make no sense
+    pub fn check_values(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut invalid = |key: &str, message: String| {
+            issues.push(ConfigIssue::InvalidValue { key: key.to_string(), message });
+        };
+
+        if !STRICTNESS_LEVELS.contains(&self.review.strictness.as_str()) {
+            invalid("review.strictness", format!(
+                "'{}' is not one of {}", self.review.strictness, STRICTNESS_LEVELS.join(", ")
+            ));
+        }
+
+        if !SUPPORTED_PROVIDERS.contains(&self.ai.provider.as_str()) {
+            invalid("ai.provider", format!(
+                "'{}' is not one of {}", self.ai.provider, SUPPORTED_PROVIDERS.join(", ")
+            ));
+        }
+
+        for (key, value) in [
+            ("secrets.block_threshold", self.secrets.block_threshold),
+            ("secrets.warn_threshold", self.secrets.warn_threshold),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                invalid(key, format!("{} is outside 0.0 to 1.0", value));
+            }
+        }
+
+        if let Some(temperature) = self.ai.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            invalid("ai.temperature", format!("{} is outside 0.0 to 2.0", temperature));
+        }
+        if self.ai.diff_budget == Some(0) {
+            invalid("ai.diff_budget", "must be greater than 0".to_string());
+        }
+        if self.ai.max_tokens_per_session == Some(0) {
+            invalid("ai.max_tokens_per_session", "must be greater than 0".to_string());
+        }
+
+        for pattern in &self.git.protected_branches {
+            if let Err(e) = GlobBuilder::new(pattern).build() {
+                invalid("git.protected_branches", e.to_string());
+            }
+        }
+
+        if let Err(e) = self.auto.validate() {
+            invalid("auto", format!("{:#}", e));
+        }
+
+        if let Err(e) = self.commit.spread.validate() {
+            invalid("commit.spread", format!("{:#}", e));
+        }
+
+        if self.commit.candidates == 0 {
+            invalid("commit.candidates", "must be at least 1".to_string());
+        }
+
+        for (name, agent) in &self.agents {
+            if agent.prompt.trim().is_empty() {
+                invalid(&format!("agents.{}.prompt", name), "must not be empty".to_string());
+            }
+        }
+
+        for (name, action) in &self.secrets.overrides {
+            if let Err(e) = action.parse::<crate::core::secrets::SecretAction>() {
+                invalid(&format!("secrets.overrides.{}", name), e.to_string());
+            }
+        }
+
+        issues
+    }
+
+    /// Validate every config layer and the merged values
+    pub fn validate_layers(layers: &[(ConfigSource, PathBuf)]) -> Result<Vec<(String, ConfigIssue)>> {
+        let mut issues = Vec::new();
+
+        for (_, path) in layers {
+            if !path.exists() {
+                continue;
+            }
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+            for issue in check_layer(&content) {
+                issues.push((path.display().to_string(), issue));
+            }
+        }
+
+        // Merged values can only be checked once every file parses
+        if issues.iter().all(|(_, issue)| matches!(issue, ConfigIssue::UnknownKey { .. })) {
+            let (config, _) = Self::from_layers(layers)?;
+            issues.extend(config.check_values().into_iter().map(|issue| ("config".to_string(), issue)));
+        }
+
+        Ok(issues)
+    }
+}
+
+/// Read one config file as a TOML table
+fn read_layer(path: &Path) -> Result<toml::Value> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Invalid config file: {}", path.display()))
+}
+
 /// Initialize configuration file
 #[allow(dead_code)] // Available for future CLI subcommand
 pub fn init_config(force: bool) -> Result<()> {
@@ -330,67 +1304,291 @@ pub fn init_config(force: bool) -> Result<()> {
     Ok(())
 }
 
-/// Show current configuration
-#[allow(dead_code)] // Available for future CLI subcommand
-pub fn show_config(config: &Config) -> Result<()> {
-    println!("{}", "Current Configuration:".bold());
+/// Check all config files, failing if anything is unknown or invalid
+pub fn validate_config(path: Option<&str>) -> Result<()> {
+    let layers = Config::layer_paths(path);
+    for (_, path) in &layers {
+        let status = if path.exists() { "checked".green() } else { "not found".dimmed() };
+        println!("  {} ({})", path.display(), status);
+    }
+    println!();
+
+    let issues = Config::validate_layers(&layers)?;
+    if issues.is_empty() {
+        println!("{} Configuration is valid", "✓".green().bold());
+        return Ok(());
+    }
+
+    for (file, issue) in &issues {
+        println!("  {} {}: {}", "✗".red(), file, issue);
+    }
     println!();
+    anyhow::bail!("{} configuration problem(s) found", issues.len())
+}
+
+/// Prompt for an API key and store it in the OS keyring
+pub fn set_key(provider: &str, store: &dyn KeyStore, path: Option<&str>) -> Result<()> {
+    let (_, key, var) = credentials::credential(provider)?;
+    prompt::require_terminal(prompt::is_interactive(), &format!("Set {}", var))?;
+
+    let secret: String = dialoguer::Password::new()
+        .with_prompt(format!("{} API key", provider))
+        .interact()?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        anyhow::bail!("No key entered");
+    }
+
+    store.set(provider, secret)?;
+    println!("{} Stored {} key in the OS keyring (service '{}')", "✓".green().bold(), provider, credentials::SERVICE);
+
+    let (_, sources) = Config::from_layers(&Config::layer_paths(path))?;
+    if matches!(sources.get(key), ConfigSource::Global | ConfigSource::Project) {
+        println!("{}", format!("  {} is also set in a config file; you can remove it there.", key).dimmed());
+    }
+    if std::env::var(var).is_ok() {
+        println!("{}", format!("  {} is set and takes precedence over the keyring.", var).dimmed());
+    }
+
+    Ok(())
+}
+
+/// Whether a config key holds a credential that shouldn't be printed
+fn is_secret_key(key: &str) -> bool {
+    key.ends_with("api_key") || key.ends_with("token")
+}
+
+/// Show the effective configuration and which layer set each value
+pub fn show_config(config: &Config, sources: &ConfigSources, path: Option<&str>) -> Result<()> {
+    println!("{}", "Current Configuration:".bold());
+    for (source, file) in Config::layer_paths(path) {
+        let status = if file.exists() { "loaded" } else { "not found" };
+        println!("{}", format!("  {:<8} {} ({})", format!("{}:", source), file.display(), status).dimmed());
+    }
+    if let Some(profile) = &config.profile {
+        println!("{}", format!("  profile: {}", profile).dimmed());
+    }
+
+    let value = toml::Value::try_from(config)?;
+    let Some(sections) = value.as_table() else {
+        return Ok(());
+    };
+
+    for (section, table) in sections {
+        let Some(table) = table.as_table().filter(|_| section != "profiles") else {
+            continue;
+        };
+        println!("\n[{}]", section.cyan());
+
+        let mut leaves = Vec::new();
+        collect_leaves(section, table, &mut leaves);
+        for (key, value) in leaves {
+            let display = if is_secret_key(&key) && sources.get(&key) == ConfigSource::Keyring {
+                "✓ stored in keyring".green().to_string()
+            } else if is_secret_key(&key) {
+                "✓ Set".green().to_string()
+            } else {
+                value.to_string()
+            };
+            let name = key.strip_prefix(&format!("{}.", section)).unwrap_or(&key);
+            println!("  {} = {}  {}", name, display, format!("({})", sources.get(&key)).dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+/// Print every effective value as `key = value  (source)`, one per line
+pub fn list_config(config: &Config, sources: &ConfigSources) -> Result<()> {
+    let value = toml::Value::try_from(config)?;
+    let Some(table) = value.as_table() else {
+        return Ok(());
+    };
+
+    let mut leaves = Vec::new();
+    collect_leaves("", table, &mut leaves);
+    for (key, value) in leaves.iter().filter(|(key, _)| !key.starts_with("profiles.")) {
+        let display = if is_secret_key(key) { "(set)".to_string() } else { value.to_string() };
+        println!("{} = {}  {}", key, display, format!("({})", sources.get(key)).dimmed());
+    }
+    Ok(())
+}
 
-    // AI settings
-    println!("{}:", "AI Settings".cyan());
-    println!(
-        "  Model: {}",
-        config.ai.model
-    );
-    println!(
-        "  Anthropic API Key: {}",
-        if config.ai.anthropic_api_key.is_some() {
-            "✓ Set".green().to_string()
-        } else {
-            "✗ Not set".red().to_string()
-        }
-    );
-    println!(
-        "  OpenAI API Key: {}",
-        if config.ai.openai_api_key.is_some() {
-            "✓ Set".green().to_string()
-        } else {
-            "✗ Not set".red().to_string()
-        }
-    );
-
-    // Commit settings
-    println!("\n{}:", "Commit Settings".cyan());
-    println!("  Conventional: {}", config.commit.conventional);
-    println!("  Atomic: {}", config.commit.atomic);
-    println!("  Sign: {}", config.commit.sign);
-    if let Some(agent) = &config.commit.default_agent {
-        println!("  Default Agent: {}", agent);
-    }
-
-    // Docs settings
-    println!("\n{}:", "Docs Settings".cyan());
-    println!("  Format: {}", config.docs.format);
-    println!("  Update Existing: {}", config.docs.update_existing);
-
-    // Review settings
-    println!("\n{}:", "Review Settings".cyan());
-    println!("  Strictness: {}", config.review.strictness);
-    println!("  Auto Post: {}", config.review.auto_post);
-
-    // GitHub settings
-    println!("\n{}:", "GitHub Settings".cyan());
-    println!(
-        "  Token: {}",
-        if config.github.token.is_some() {
-            "✓ Set".green().to_string()
-        } else {
-            "✗ Not set".red().to_string()
-        }
-    );
-    if let Some(repo) = &config.github.default_repo {
-        println!("  Default Repo: {}", repo);
+/// Print the effective value of a dotted key: strings bare, tables as TOML
+pub fn get_config_value(config: &Config, key: &str) -> Result<()> {
+    let value = toml::Value::try_from(config)?;
+    let found = key.split('.').try_fold(&value, |value, part| value.get(part));
+
+    match found {
+        Some(_) if is_secret_key(key) => println!("(set)"),
+        Some(toml::Value::String(s)) => println!("{}", s),
+        Some(toml::Value::Table(table)) => print!("{}", toml::to_string_pretty(table)?),
+        Some(other) => println!("{}", other),
+        None if known_keys().iter().any(|known| known == key) => println!("{}", "(not set)".dimmed()),
+        None => anyhow::bail!("{}", ConfigIssue::UnknownKey { key: key.to_string(), suggestion: suggest_key(key, &known_keys()) }),
     }
+    Ok(())
+}
 
+/// Set a dotted key in the project config (or the global one) and say where
+pub fn set_config_value(path: Option<&str>, global: bool, key: &str, raw: &str) -> Result<()> {
+    let file = config_file_for(path, global);
+    let value = write_setting(&file, key, raw)?;
+    let shown = if is_secret_key(key) { "(set)".to_string() } else { value.to_string() };
+    println!("{} {} = {} in {}", "✓".green().bold(), key, shown.trim(), file.display());
+    if is_secret_key(key) {
+        println!("{}", "  Consider `bahn config set-key` to keep keys out of config files.".dimmed());
+    }
     Ok(())
 }
+
+/// Edit the project config (or the global one) in $EDITOR, checking it before saving
+pub fn edit_config(path: Option<&str>, global: bool) -> Result<()> {
+    let file = config_file_for(path, global);
+    prompt::require_terminal(prompt::is_interactive(), &format!("Edit {}", file.display()))?;
+
+    let content = match fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read config file: {}", file.display())),
+    };
+    let Some(edited) = dialoguer::Editor::new().extension(".toml").edit(&content)? else {
+        println!("{}", "Editor closed without saving; nothing changed.".dimmed());
+        return Ok(());
+    };
+    if edited == content {
+        println!("{}", "No changes.".dimmed());
+        return Ok(());
+    }
+
+    let issues = check_layer(&edited);
+    if !issues.is_empty() {
+        for issue in &issues {
+            println!("  {} {}", "✗".red(), issue);
+        }
+        let save = dialoguer::Confirm::new()
+            .with_prompt("Save anyway?")
+            .default(false)
+            .interact()?;
+        if !save {
+            anyhow::bail!("{} not saved", file.display());
+        }
+    }
+
+    write_config_file(&file, &edited)?;
+    println!("{} Saved {}", "✓".green().bold(), file.display());
+    Ok(())
+}
+
+/// The file `set` and `edit` change: the global config, or the project one
+/// (`--config` if given)
+fn config_file_for(path: Option<&str>, global: bool) -> PathBuf {
+    if global {
+        global_config_path()
+    } else {
+        path.map(PathBuf::from).unwrap_or_else(|| PathBuf::from(CONFIG_FILE))
+    }
+}
+
+fn write_config_file(file: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(file, content).with_context(|| format!("Failed to write config file: {}", file.display()))
+}
+
+/// Set a dotted key in one config file, keeping its other keys and comments.
+/// The value is checked against the config model before anything is written.
+pub fn write_setting(file: &Path, key: &str, raw: &str) -> Result<toml_edit::Value> {
+    let content = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read config file: {}", file.display())),
+    };
+    let doc: toml_edit::DocumentMut = content.parse()
+        .with_context(|| format!("Invalid config file: {}", file.display()))?;
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.trim().is_empty()) {
+        anyhow::bail!("Invalid key '{}'. Use a dotted key like ai.model", key);
+    }
+
+    // The first reading of `raw` the config model accepts wins
+    let before = check_layer(&content);
+    let mut first_problem = None;
+    for value in setting_candidates(key, raw) {
+        let updated = with_setting(doc.clone(), &parts, value.clone())?;
+        match setting_problem(&before, &updated, key) {
+            None => {
+                write_config_file(file, &updated)?;
+                return Ok(value);
+            }
+            Some(problem) => {
+                first_problem.get_or_insert(problem);
+            }
+        }
+    }
+    anyhow::bail!("Not saved: {}", first_problem.context("No value to set")?)
+}
+
+/// `doc` with `value` stored under the dotted key `parts`, creating sections as needed
+fn with_setting(mut doc: toml_edit::DocumentMut, parts: &[&str], mut value: toml_edit::Value) -> Result<String> {
+    let (name, parents) = parts.split_last().context("Empty key")?;
+
+    let mut table = doc.as_table_mut() as &mut dyn toml_edit::TableLike;
+    for (depth, part) in parents.iter().enumerate() {
+        if table.get(part).is_none() {
+            let mut section = toml_edit::Table::new();
+            section.set_implicit(true);
+            table.insert(part, toml_edit::Item::Table(section));
+        }
+        table = table.get_mut(part).and_then(|item| item.as_table_like_mut())
+            .with_context(|| format!("'{}' is a value, not a section", parts[..=depth].join(".")))?;
+    }
+    // Keep a trailing comment on the line being replaced
+    if let Some(existing) = table.get(name).and_then(|item| item.as_value()) {
+        *value.decor_mut() = existing.decor().clone();
+    }
+    table.insert(name, toml_edit::Item::Value(value));
+    Ok(doc.to_string())
+}
+
+/// Ways to read `raw`: as a string where the setting is one, otherwise as a
+/// TOML literal (true, 42, 0.2, ["a", "b"]) and then as a plain string
+fn setting_candidates(key: &str, raw: &str) -> Vec<toml_edit::Value> {
+    let defaults = toml::Value::try_from(Config::default()).ok();
+    let default = defaults.as_ref().and_then(|d| key.split('.').try_fold(d, |value, part| value.get(part)));
+    let mut candidates = Vec::new();
+    if !matches!(default, Some(toml::Value::String(_))) {
+        if let Ok(doc) = format!("value = {}", raw).parse::<toml_edit::DocumentMut>() {
+            if let Some(mut value) = doc.get("value").and_then(|item| item.as_value()).cloned() {
+                value.decor_mut().clear();
+                candidates.push(value);
+            }
+        }
+    }
+    if !candidates.iter().any(|value| value.is_str()) {
+        candidates.push(raw.into());
+    }
+    candidates
+}
+
+/// A problem `updated` has that `before` didn't, or a bad value for `key`
+fn setting_problem(before: &[ConfigIssue], updated: &str, key: &str) -> Option<ConfigIssue> {
+    if let Some(issue) = check_layer(updated).into_iter().find(|issue| !before.contains(issue)) {
+        return Some(issue);
+    }
+    let config = toml::from_str::<Config>(updated).ok()?;
+    config.check_values().into_iter()
+        .find(|issue| matches!(issue, ConfigIssue::InvalidValue { key: k, .. } if k == key))
+}
+
+fn collect_leaves(prefix: &str, table: &toml::value::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = join_key(prefix, key);
+        match value {
+            toml::Value::Table(nested) if !nested.is_empty() => collect_leaves(&key, nested, out),
+            _ => out.push((key, value.clone())),
+        }
+    }
+}
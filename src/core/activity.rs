@@ -0,0 +1,135 @@
+//! Log of what bahn did in a repository, read by `bahn stats`.
+//!
+//! Events are JSON lines in the common gitdir, next to the lock, so worktrees
+//! share one log and it never ends up in a commit. Recording is best effort:
+//! a failed write is logged and never fails the command.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use git2::Repository;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use crate::core::git;
+
+const ACTIVITY_FILE: &str = "bahn-activity.jsonl";
+
+static SESSION: OnceCell<String> = OnceCell::new();
+
+/// Something bahn did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A commit created by bahn, with the auto session it belongs to
+    Commit {
+        sha: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session: Option<String>,
+    },
+    /// `count` commits folded into `sha`
+    Squash { sha: String, count: usize },
+    /// A commit refused because of detected secrets
+    SecretsBlocked { findings: usize },
+}
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Unix timestamp in seconds
+    pub at: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Path of the activity log for a repository
+pub fn log_path(repo: &Repository) -> PathBuf {
+    git::common_dir(repo).join(ACTIVITY_FILE)
+}
+
+/// Attribute commits made by this process to a new auto session
+pub fn start_session() {
+    SESSION.get_or_init(|| format!("{}-{}", Utc::now().format("%Y%m%dT%H%M%S"), std::process::id()));
+}
+
+/// The current auto session, if one was started
+pub fn session() -> Option<String> {
+    SESSION.get().cloned()
+}
+
+/// Append an event to the repository's log
+pub fn record(repo: &Repository, event: Event) {
+    let record = Record { at: Utc::now().timestamp(), event };
+    if let Err(e) = append(&log_path(repo), &record) {
+        tracing::warn!(error = %e, "failed to record activity");
+    }
+}
+
+fn append(path: &Path, record: &Record) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Read the log. A missing file is an empty log; malformed lines are skipped
+pub fn load(path: &Path) -> Result<Vec<Record>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                tracing::debug!(error = %e, "skipping malformed activity line");
+                None
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("bahn-activity-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = Repository::init(&dir).unwrap();
+        assert!(load(&log_path(&repo)).unwrap().is_empty());
+
+        record(&repo, Event::Commit { sha: "abc".to_string(), session: None });
+        record(&repo, Event::SecretsBlocked { findings: 2 });
+        std::fs::OpenOptions::new().append(true).open(log_path(&repo)).unwrap()
+            .write_all(b"not json\n").unwrap();
+
+        let records = load(&log_path(&repo)).unwrap();
+        let events: Vec<&Event> = records.iter().map(|r| &r.event).collect();
+        assert_eq!(events, vec![
+            &Event::Commit { sha: "abc".to_string(), session: None },
+            &Event::SecretsBlocked { findings: 2 },
+        ]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_record_format() {
+        let record = Record { at: 10, event: Event::Squash { sha: "abc".to_string(), count: 3 } };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"at":10,"event":"squash","sha":"abc","count":3}"#
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Personality agents loaded from local files or an Elite Coder server.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Directory (relative to the repo root) holding agent definitions
+const AGENTS_DIR: &str = ".bahn/agents";
+
+/// How long a fetched agent definition stays fresh in the cache
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Timeout for fetching agents from the remote server
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A personality agent definition
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Agent {
+    /// Agent name (filled in from the lookup name if missing)
+    #[serde(default)]
+    pub name: String,
+
+    /// Short description of the agent's personality
+    #[serde(default)]
+    pub description: String,
+
+    /// Guidance for writing commit messages
+    #[serde(default)]
+    pub commit_style: Option<String>,
+
+    /// Guidance for reviewing code
+    #[serde(default)]
+    pub review_style: Option<String>,
+
+    /// Example outputs in the agent's voice
+    #[serde(default)]
+    pub examples: Vec<String>,
+
+    /// Model to use for this agent instead of `ai.model`
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl Agent {
+    /// Build an agent from a raw personality string (legacy `--agent "..."` usage)
+    pub fn inline(text: &str) -> Self {
+        Self {
+            name: text.to_string(),
+            description: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse an agent definition from TOML (or JSON, as served by some endpoints)
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let mut agent: Agent = match toml::from_str(content) {
+            Ok(agent) => agent,
+            Err(e) => serde_json::from_str(content)
+                .map_err(|_| e)
+                .with_context(|| format!("Invalid agent definition for '{}'", name))?,
+        };
+
+        if agent.name.is_empty() {
+            agent.name = name.to_string();
+        }
+
+        Ok(agent)
+    }
+
+    /// Personality text for commit message generation
+    pub fn commit_prompt(&self) -> String {
+        self.prompt_with(self.commit_style.as_deref())
+    }
+
+    /// Personality text for code reviews
+    pub fn review_prompt(&self) -> String {
+        self.prompt_with(self.review_style.as_deref())
+    }
+
+    fn prompt_with(&self, style: Option<&str>) -> String {
+        let mut text = self.description.clone();
+
+        if let Some(style) = style {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(style);
+        }
+
+        if !self.examples.is_empty() {
+            text.push_str("\n\nExamples:\n");
+            for example in &self.examples {
+                text.push_str(&format!("- {}\n", example));
+            }
+        }
+
+        text.trim_end().to_string()
+    }
+}
+
+/// Resolves agents by name from the remote server, cache, and local files
+pub struct AgentResolver {
+    agents_dir: PathBuf,
+    remote_url: Option<String>,
+    cache_dir: Option<PathBuf>,
+    /// Agents from the `[agents]` config table
+    configured: BTreeMap<String, Agent>,
+}
+
+impl AgentResolver {
+    /// Create a resolver for the given repository root
+    pub fn new(repo_root: &Path, remote_url: Option<&str>) -> Self {
+        Self {
+            agents_dir: repo_root.join(AGENTS_DIR),
+            remote_url: remote_url.map(|u| u.trim_end_matches('/').to_string()),
+            cache_dir: dirs::cache_dir().map(|d| d.join("gitBahn").join("agents")),
+            configured: BTreeMap::new(),
+        }
+    }
+
+    /// Agents defined in config, which take precedence over every other source
+    pub fn with_configured(mut self, agents: BTreeMap<String, Agent>) -> Self {
+        self.configured = agents;
+        self
+    }
+
+    /// Override the cache directory
+    #[allow(dead_code)]
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache_dir = Some(dir);
+        self
+    }
+
+    /// Resolve an agent by name.
+    ///
+    /// Order: config > fresh cache > remote server > local file > stale cache.
+    /// An agent name that matches no definition is an error; text that can't be a
+    /// name (e.g. "be very formal") is used as inline personality text.
+    pub async fn resolve(&self, name: &str) -> Result<Agent> {
+        if let Some(agent) = self.configured.get(name) {
+            return Ok(agent.clone());
+        }
+
+        if self.remote_url.is_some() && is_valid_name(name) {
+            if let Some(agent) = self.read_cache(name, true) {
+                return Ok(agent);
+            }
+
+            match self.fetch_remote(name).await {
+                Ok(content) => {
+                    let agent = Agent::parse(name, &content)?;
+                    self.write_cache(name, &content);
+                    return Ok(agent);
+                }
+                Err(e) => {
+                    eprintln!("Warning: failed to fetch agent '{}': {}", name, e);
+                }
+            }
+        }
+
+        if let Some(agent) = self.read_local(name)? {
+            return Ok(agent);
+        }
+
+        if let Some(agent) = self.read_cache(name, false) {
+            return Ok(agent);
+        }
+
+        if is_valid_name(name) {
+            let available = self.available();
+            if available.is_empty() {
+                anyhow::bail!(
+                    "Unknown agent '{}'. No agents are defined in [agents] or {}",
+                    name, AGENTS_DIR
+                );
+            }
+            anyhow::bail!("Unknown agent '{}'. Available agents: {}", name, available.join(", "));
+        }
+
+        Ok(Agent::inline(name))
+    }
+
+    /// Names of the configured and local agents, sorted
+    fn available(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.configured.keys().cloned().collect();
+        if let Ok(entries) = std::fs::read_dir(&self.agents_dir) {
+            names.extend(entries.flatten().filter_map(|entry| {
+                let path = entry.path();
+                let stem = path.file_stem()?.to_str()?;
+                (path.extension()? == "toml" && is_valid_name(stem)).then(|| stem.to_string())
+            }));
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn read_local(&self, name: &str) -> Result<Option<Agent>> {
+        if !is_valid_name(name) {
+            return Ok(None);
+        }
+
+        let path = self.agents_dir.join(format!("{}.toml", name));
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read agent file: {}", path.display()))?;
+
+        Agent::parse(name, &content).map(Some)
+    }
+
+    async fn fetch_remote(&self, name: &str) -> Result<String> {
+        let base = self.remote_url.as_deref().context("No agent server configured")?;
+        let url = format!("{}/agents/{}", base, name);
+
+        let response = reqwest::Client::new()
+            .get(&url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Agent server returned {}", response.status());
+        }
+
+        response.text().await.context("Failed to read agent response")
+    }
+
+    fn cache_path(&self, name: &str) -> Option<PathBuf> {
+        if !is_valid_name(name) {
+            return None;
+        }
+        self.cache_dir.as_ref().map(|d| d.join(format!("{}.toml", name)))
+    }
+
+    fn read_cache(&self, name: &str, fresh_only: bool) -> Option<Agent> {
+        let path = self.cache_path(name)?;
+        let metadata = std::fs::metadata(&path).ok()?;
+
+        if fresh_only {
+            let age = metadata.modified().ok()
+                .and_then(|m| SystemTime::now().duration_since(m).ok())?;
+            if age > CACHE_TTL {
+                return None;
+            }
+        }
+
+        let content = std::fs::read_to_string(&path).ok()?;
+        Agent::parse(name, &content).ok()
+    }
+
+    fn write_cache(&self, name: &str, content: &str) {
+        if let Some(path) = self.cache_path(name) {
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = std::fs::write(path, content);
+        }
+    }
+}
+
+/// Agent names map to file names, so keep them to a safe character set
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bahn-agents-{}-{}-{}",
+            label,
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_agent(root: &Path, name: &str, content: &str) {
+        let dir = root.join(AGENTS_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("{}.toml", name)), content).unwrap();
+    }
+
+    /// Serve a single HTTP response on a local port and return the base URL
+    fn mock_server(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_load_agent_from_file() {
+        let root = temp_dir("file");
+        write_agent(&root, "linus", r#"
+description = "Blunt kernel maintainer"
+commit_style = "Terse imperative subjects"
+review_style = "No patience for sloppy code"
+examples = ["mm: fix page refcount leak"]
+"#);
+
+        let resolver = AgentResolver::new(&root, None).with_cache_dir(root.join("cache"));
+        let agent = resolver.resolve("linus").await.unwrap();
+
+        assert_eq!(agent.name, "linus");
+        assert!(agent.commit_prompt().contains("Terse imperative subjects"));
+        assert!(agent.commit_prompt().contains("mm: fix page refcount leak"));
+        assert!(agent.review_prompt().contains("No patience"));
+        assert!(!agent.review_prompt().contains("Terse"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_agent_is_inline_text() {
+        let root = temp_dir("inline");
+        let resolver = AgentResolver::new(&root, None).with_cache_dir(root.join("cache"));
+        let agent = resolver.resolve("be very formal").await.unwrap();
+        assert_eq!(agent.commit_prompt(), "be very formal");
+    }
+
+    #[tokio::test]
+    async fn test_configured_agent_wins_and_carries_model() {
+        let root = temp_dir("configured");
+        write_agent(&root, "terse", "description = \"Local\"\n");
+        let configured = BTreeMap::from([("terse".to_string(), Agent {
+            name: "terse".to_string(),
+            description: "One line, no fluff".to_string(),
+            model: Some("claude-3-5-haiku-20241022".to_string()),
+            ..Default::default()
+        })]);
+
+        let resolver = AgentResolver::new(&root, None)
+            .with_cache_dir(root.join("cache"))
+            .with_configured(configured);
+        let agent = resolver.resolve("terse").await.unwrap();
+        assert_eq!(agent.commit_prompt(), "One line, no fluff");
+        assert_eq!(agent.model.as_deref(), Some("claude-3-5-haiku-20241022"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_agent_name_lists_available() {
+        let root = temp_dir("unknown");
+        let resolver = AgentResolver::new(&root, None).with_cache_dir(root.join("cache"));
+        let err = resolver.resolve("terse").await.unwrap_err().to_string();
+        assert!(err.contains("No agents are defined"), "{}", err);
+
+        write_agent(&root, "linus", "description = \"Local\"\n");
+        let configured = BTreeMap::from([("oss".to_string(), Agent::default())]);
+        let resolver = resolver.with_configured(configured);
+        let err = resolver.resolve("terse").await.unwrap_err().to_string();
+        assert_eq!(err, "Unknown agent 'terse'. Available agents: linus, oss");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_agent_from_remote_and_cache() {
+        let root = temp_dir("remote");
+        let url = mock_server("200 OK", "description = \"Remote reviewer\"\ncommit_style = \"From the server\"\n");
+
+        let resolver = AgentResolver::new(&root, Some(&url)).with_cache_dir(root.join("cache"));
+        let agent = resolver.resolve("remote").await.unwrap();
+        assert!(agent.commit_prompt().contains("From the server"));
+
+        // Server only answers once; the second lookup must come from the cache
+        let agent = resolver.resolve("remote").await.unwrap();
+        assert!(agent.commit_prompt().contains("From the server"));
+    }
+
+    #[tokio::test]
+    async fn test_remote_takes_precedence_over_local() {
+        let root = temp_dir("precedence");
+        write_agent(&root, "linus", "description = \"Local\"\n");
+        let url = mock_server("200 OK", "description = \"Remote\"\n");
+
+        let resolver = AgentResolver::new(&root, Some(&url)).with_cache_dir(root.join("cache"));
+        let agent = resolver.resolve("linus").await.unwrap();
+        assert_eq!(agent.description, "Remote");
+    }
+
+    #[tokio::test]
+    async fn test_remote_failure_falls_back_to_local() {
+        let root = temp_dir("fallback");
+        write_agent(&root, "linus", "description = \"Local\"\n");
+        let url = mock_server("404 Not Found", "");
+
+        let resolver = AgentResolver::new(&root, Some(&url)).with_cache_dir(root.join("cache"));
+        let agent = resolver.resolve("linus").await.unwrap();
+        assert_eq!(agent.description, "Local");
+    }
+}
@@ -1,15 +1,52 @@
 //! AI integration for commit message generation and code review.
 
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::core::cache::{self, ResponseCache};
+use crate::core::diff;
 
 /// Retry configuration for API calls
 const MAX_RETRIES: u32 = 3;
 const BASE_DELAY_MS: u64 = 1000;
 const MAX_DELAY_MS: u64 = 30000;
 
+/// Default base URLs; `ai.base_url` replaces them
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// Placeholder sent instead of a redacted line
+const REDACTED_LINE: &str = "[line redacted: possible secret]";
+
+/// Bytes of diff sent with commit message and atomic split prompts, unless `ai.diff_budget` is set
+pub const COMMIT_DIFF_BUDGET: usize = 10000;
+/// Bytes of diff sent with review prompts, unless `ai.diff_budget` is set
+pub const REVIEW_DIFF_BUDGET: usize = 15000;
+
+/// A non-retryable error response from the provider's API
+#[derive(Debug, thiserror::Error)]
+#[error("{api} error ({status}): {message}")]
+pub struct ApiError {
+    pub api: &'static str,
+    pub status: reqwest::StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    /// The API rejected the request itself, e.g. a provider or model without tool-use
+    fn is_bad_request(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<ApiError>().is_some_and(|e| {
+            e.status == reqwest::StatusCode::BAD_REQUEST || e.status == reqwest::StatusCode::UNPROCESSABLE_ENTITY
+        })
+    }
+}
+
 /// Message for the Claude API
 #[derive(Debug, Serialize)]
 pub struct Message {
@@ -25,17 +62,472 @@ pub struct ClaudeRequest {
     pub messages: Vec<Message>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+/// Tool definition for structured output
+#[derive(Debug, Clone, Serialize)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: serde_json::Value,
+}
+
+/// Forces the model to call a specific tool
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolChoice {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+}
+
+impl ToolChoice {
+    /// Require the model to call the named tool
+    pub fn tool(name: &str) -> Self {
+        Self {
+            kind: "tool".to_string(),
+            name: name.to_string(),
+        }
+    }
 }
 
 /// Response from Claude API
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClaudeResponse {
     pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+/// Token counts reported with a response
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Tokens spent by one [`AiClient`] across all its requests
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageStats {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl UsageStats {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// "3 requests, 41.2k in / 1.8k out tokens"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} request{}, {} in / {} out tokens",
+            self.requests,
+            if self.requests == 1 { "" } else { "s" },
+            format_tokens(self.input_tokens),
+            format_tokens(self.output_tokens)
+        )
+    }
+
+    fn record(&mut self, usage: Option<&Usage>) {
+        self.requests += 1;
+        if let Some(usage) = usage {
+            self.input_tokens += usage.input_tokens;
+            self.output_tokens += usage.output_tokens;
+        }
+    }
+}
+
+/// 950, 41.2k, 1.3M
+fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+impl ClaudeResponse {
+    /// Concatenated text of all text blocks
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// Parse the input of the first call to `tool` into `T`
+    pub fn tool_input<T: DeserializeOwned>(&self, tool: &str) -> Option<T> {
+        self.content.iter().find_map(|block| match block {
+            ContentBlock::ToolUse { name, input, .. } if name == tool => {
+                serde_json::from_value(input.clone()).ok()
+            }
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        name: String,
+        input: serde_json::Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Chat completions request for OpenAI-compatible APIs
+#[derive(Debug, Serialize)]
+pub struct ChatRequest {
+    pub model: String,
+    pub max_tokens: u32,
+    pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+impl From<&ClaudeRequest> for ChatRequest {
+    fn from(request: &ClaudeRequest) -> Self {
+        let system = request.system.iter().map(|system| Message {
+            role: "system".to_string(),
+            content: system.clone(),
+        });
+        let messages = request.messages.iter().map(|m| Message {
+            role: m.role.clone(),
+            content: m.content.clone(),
+        });
+
+        Self {
+            model: request.model.clone(),
+            max_tokens: request.max_tokens,
+            messages: system.chain(messages).collect(),
+            tools: request.tools.as_ref().map(|tools| tools.iter()
+                .map(|tool| json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    }
+                }))
+                .collect()),
+            tool_choice: request.tool_choice.as_ref()
+                .map(|choice| json!({ "type": "function", "function": { "name": choice.name } })),
+            temperature: request.temperature,
+        }
+    }
+}
+
+/// Chat request for Ollama's `/api/chat`
+#[derive(Debug, Serialize)]
+pub struct OllamaRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    pub stream: bool,
+    pub options: OllamaOptions,
+}
+
+/// Sampling options of an Ollama request
+#[derive(Debug, Serialize)]
+pub struct OllamaOptions {
+    /// Most tokens to generate
+    pub num_predict: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+impl From<&ClaudeRequest> for OllamaRequest {
+    fn from(request: &ClaudeRequest) -> Self {
+        // Same message list as chat completions; tools aren't sent (see `with_provider`)
+        let chat = ChatRequest::from(request);
+        Self {
+            model: chat.model,
+            messages: chat.messages,
+            stream: false,
+            options: OllamaOptions {
+                num_predict: request.max_tokens,
+                temperature: request.temperature,
+            },
+        }
+    }
+}
+
+/// Non-streaming response from Ollama's `/api/chat`
+#[derive(Debug, Deserialize)]
+pub struct OllamaResponse {
+    pub message: OllamaMessage,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OllamaMessage {
+    #[serde(default)]
+    pub content: String,
+}
+
+impl From<OllamaResponse> for ClaudeResponse {
+    fn from(response: OllamaResponse) -> Self {
+        let mut content = Vec::new();
+        if !response.message.content.is_empty() {
+            content.push(ContentBlock::Text { text: response.message.content });
+        }
+        let usage = match (response.prompt_eval_count, response.eval_count) {
+            (None, None) => None,
+            (input, output) => Some(Usage {
+                input_tokens: input.unwrap_or(0),
+                output_tokens: output.unwrap_or(0),
+            }),
+        };
+        Self { content, usage }
+    }
+}
+
+/// Response from an OpenAI-compatible chat completions endpoint
+#[derive(Debug, Deserialize)]
+pub struct ChatResponse {
+    pub choices: Vec<ChatChoice>,
+    #[serde(default)]
+    pub usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatChoice {
+    pub message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatMessage {
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ChatToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatToolCall {
+    pub function: ChatFunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatFunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments
+    pub arguments: String,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct ContentBlock {
-    pub text: String,
+pub struct ChatUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl From<ChatResponse> for ClaudeResponse {
+    /// Map the first choice onto content blocks, so callers see one response shape
+    fn from(response: ChatResponse) -> Self {
+        let mut content = Vec::new();
+        if let Some(choice) = response.choices.into_iter().next() {
+            if let Some(text) = choice.message.content.filter(|t| !t.is_empty()) {
+                content.push(ContentBlock::Text { text });
+            }
+            for call in choice.message.tool_calls {
+                // Unparseable arguments are dropped, like a missing tool call
+                if let Ok(input) = serde_json::from_str(&call.function.arguments) {
+                    content.push(ContentBlock::ToolUse { name: call.function.name, input });
+                }
+            }
+        }
+
+        Self {
+            content,
+            usage: response.usage.map(|u| Usage {
+                input_tokens: u.prompt_tokens,
+                output_tokens: u.completion_tokens,
+            }),
+        }
+    }
+}
+
+/// Which API shape requests and responses use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    /// Claude messages API
+    Anthropic,
+    /// Chat completions API of OpenAI and compatible servers (vLLM, llama.cpp, ...)
+    OpenAi,
+    /// Ollama's native chat API, for local models
+    Ollama,
+}
+
+impl Provider {
+    /// Parse an `ai.provider` value; unknown names fall back to Anthropic
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "openai" => Provider::OpenAi,
+            "ollama" => Provider::Ollama,
+            _ => Provider::Anthropic,
+        }
+    }
+
+    /// Endpoint under a base URL such as `https://api.openai.com/v1`
+    pub fn endpoint(self, base_url: Option<&str>) -> String {
+        let (default, path) = match self {
+            Provider::Anthropic => (ANTHROPIC_BASE_URL, "messages"),
+            Provider::OpenAi => (OPENAI_BASE_URL, "chat/completions"),
+            Provider::Ollama => (OLLAMA_BASE_URL, "api/chat"),
+        };
+        format!("{}/{}", base_url.unwrap_or(default).trim_end_matches('/'), path)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Provider::Anthropic => "Claude API",
+            Provider::OpenAi => "OpenAI-compatible API",
+            Provider::Ollama => "Ollama API",
+        }
+    }
+}
+
+/// One decoded event from a streaming response
+#[derive(Debug, PartialEq)]
+pub(crate) enum StreamEvent {
+    /// A piece of the response text
+    Text(String),
+    /// The server aborted the stream; retryable errors fall back to a buffered request
+    Error { retryable: bool, message: String },
+    /// Token counts so far; output counts are cumulative
+    Usage(Usage),
+    /// End of the response
+    Done,
+    /// Bookkeeping events (message_start, pings, ...)
+    Other,
+}
+
+/// Splits a server-sent event stream into the `data:` payload of each event.
+/// Bytes are held until an event is complete, so multibyte characters split
+/// across network chunks decode correctly.
+#[derive(Default)]
+pub(crate) struct SseBuffer {
+    pending: Vec<u8>,
+}
+
+impl SseBuffer {
+    /// Add a chunk and return the payloads of every event it completed
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+
+        while let Some((end, separator)) = find_event_end(&self.pending) {
+            let event: Vec<u8> = self.pending.drain(..end + separator).collect();
+            let event = String::from_utf8_lossy(&event[..end]);
+            let data: Vec<&str> = event.lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+            if !data.is_empty() {
+                payloads.push(data.join("\n"));
+            }
+        }
+
+        payloads
+    }
+
+    /// Payload of a trailing event the server didn't terminate with a blank line
+    pub(crate) fn finish(&mut self) -> Vec<String> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        self.push(b"\n\n")
+    }
+}
+
+/// Position and length of the blank line that ends the first event
+fn find_event_end(bytes: &[u8]) -> Option<(usize, usize)> {
+    (0..bytes.len()).find_map(|i| {
+        if bytes[i..].starts_with(b"\r\n\r\n") {
+            Some((i, 4))
+        } else if bytes[i..].starts_with(b"\n\n") {
+            Some((i, 2))
+        } else {
+            None
+        }
+    })
+}
+
+/// Decode one `data:` payload from `provider`'s streaming API
+pub(crate) fn parse_stream_event(provider: Provider, data: &str) -> StreamEvent {
+    if data.trim() == "[DONE]" {
+        return StreamEvent::Done;
+    }
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(data) else {
+        return StreamEvent::Other;
+    };
+
+    if let Some(error) = value.get("error") {
+        let kind = error["type"].as_str().unwrap_or("error");
+        let message = error["message"].as_str().unwrap_or("");
+        return StreamEvent::Error {
+            retryable: matches!(kind, "overloaded_error" | "rate_limit_error" | "api_error" | "server_error"),
+            message: format!("{}: {}", kind, message),
+        };
+    }
+
+    let count = |usage: &serde_json::Value, key: &str| usage[key].as_u64().unwrap_or(0);
+    let text = match provider {
+        Provider::Anthropic => match value["type"].as_str() {
+            Some("message_stop") => return StreamEvent::Done,
+            Some("content_block_delta") => value["delta"]["text"].as_str(),
+            Some("message_start") | Some("message_delta") => {
+                let usage = value.get("usage").or(value["message"].get("usage"));
+                return match usage {
+                    Some(usage) => StreamEvent::Usage(Usage {
+                        input_tokens: count(usage, "input_tokens"),
+                        output_tokens: count(usage, "output_tokens"),
+                    }),
+                    None => StreamEvent::Other,
+                };
+            }
+            _ => None,
+        },
+        // Ollama streams newline-delimited JSON, which `try_stream` doesn't request
+        Provider::Ollama => value["message"]["content"].as_str(),
+        Provider::OpenAi => match value["choices"][0]["delta"]["content"].as_str() {
+            // Sent in a final chunk when `stream_options.include_usage` is set
+            None if value["usage"].is_object() => {
+                return StreamEvent::Usage(Usage {
+                    input_tokens: count(&value["usage"], "prompt_tokens"),
+                    output_tokens: count(&value["usage"], "completion_tokens"),
+                });
+            }
+            text => text,
+        },
+    };
+
+    match text {
+        Some(text) if !text.is_empty() => StreamEvent::Text(text.to_string()),
+        _ => StreamEvent::Other,
+    }
 }
 
 /// AI client for interacting with Claude
@@ -43,6 +535,19 @@ pub struct AiClient {
     client: reqwest::Client,
     api_key: String,
     model: String,
+    use_tools: bool,
+    api_url: String,
+    provider: Provider,
+    /// Lines that must never leave the machine, e.g. detected secrets
+    redactions: Vec<String>,
+    /// Bytes of diff per prompt; `None` uses the per-prompt defaults
+    diff_budget: Option<usize>,
+    /// Sampling temperature; `None` leaves the provider's default
+    temperature: Option<f32>,
+    /// Tokens spent so far
+    usage: Mutex<UsageStats>,
+    /// Responses to identical earlier requests (`ai.cache_ttl_hours`)
+    cache: Option<ResponseCache>,
 }
 
 impl AiClient {
@@ -52,7 +557,131 @@ impl AiClient {
             client: reqwest::Client::new(),
             api_key,
             model: model.unwrap_or_else(|| "claude-sonnet-4-20250514".to_string()),
+            use_tools: true,
+            api_url: Provider::Anthropic.endpoint(None),
+            provider: Provider::Anthropic,
+            redactions: Vec::new(),
+            diff_budget: None,
+            temperature: None,
+            usage: Mutex::new(UsageStats::default()),
+            cache: None,
+        }
+    }
+
+    /// Talk to `provider` at `base_url` (or the provider's public API)
+    pub fn with_provider(mut self, provider: Provider, base_url: Option<&str>) -> Self {
+        self.provider = provider;
+        self.api_url = provider.endpoint(base_url);
+        // Tool calling varies between local models; the JSON prompt path works with all of them
+        if provider == Provider::Ollama {
+            self.use_tools = false;
         }
+        self
+    }
+
+    /// Sample at `temperature` instead of the provider's default
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Answer repeated requests from `cache` instead of the network
+    pub fn with_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Use a different model, e.g. one pinned by a personality agent
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Send requests to a different messages endpoint (proxies, tests)
+    #[allow(dead_code)]
+    pub fn with_api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = url.into();
+        self
+    }
+
+    /// Replace these lines with a placeholder wherever they appear in a request
+    pub fn with_redactions(mut self, lines: Vec<String>) -> Self {
+        self.redactions = lines;
+        self
+    }
+
+    /// Lines kept out of requests, for other clients sent the same diff
+    pub fn redactions(&self) -> &[String] {
+        &self.redactions
+    }
+
+    /// Fit diffs into `budget` bytes per prompt instead of the defaults
+    pub fn with_diff_budget(mut self, budget: Option<usize>) -> Self {
+        self.diff_budget = budget;
+        self
+    }
+
+    /// Tokens spent by this client so far
+    pub fn usage(&self) -> UsageStats {
+        *self.usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record_usage(&self, usage: Option<&Usage>) {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).record(usage);
+    }
+
+    fn commit_diff_budget(&self) -> usize {
+        self.diff_budget.unwrap_or(COMMIT_DIFF_BUDGET)
+    }
+
+    /// `request` in the provider's wire format, redactions applied
+    fn provider_body(&self, request: &ClaudeRequest) -> Result<String> {
+        match self.provider {
+            Provider::OpenAi => self.request_body(&ChatRequest::from(request)),
+            Provider::Anthropic => self.request_body(request),
+            Provider::Ollama => self.request_body(&OllamaRequest::from(request)),
+        }
+    }
+
+    /// A fresh cached response to this exact request body, if caching is on
+    fn cached_response(&self, body: &str) -> Option<ClaudeResponse> {
+        let cache = self.cache.as_ref()?;
+        let response = serde_json::from_str(&cache.get(&cache::key(&[&self.api_url, body]))?).ok()?;
+        tracing::info!("AI response served from cache");
+        Some(response)
+    }
+
+    /// Keep `response` for identical requests; failing to is only logged
+    fn store_response(&self, body: &str, response: &ClaudeResponse) {
+        let Some(cache) = &self.cache else { return };
+        let stored = serde_json::to_string(response)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| cache.put(&cache::key(&[&self.api_url, body]), &json));
+        if let Err(e) = stored {
+            tracing::warn!(error = %e, "could not cache AI response");
+        }
+    }
+
+    /// Request body as JSON, with redacted lines replaced
+    fn request_body<T: Serialize>(&self, request: &T) -> Result<String> {
+        let mut body = serde_json::to_string(request).context("Failed to serialize request")?;
+        for line in &self.redactions {
+            // Match the line as it appears inside a JSON string
+            let escaped = serde_json::to_string(line)?;
+            let escaped = &escaped[1..escaped.len() - 1];
+            if !escaped.is_empty() {
+                body = body.replace(escaped, REDACTED_LINE);
+            }
+        }
+        Ok(body)
+    }
+
+    /// Enable or disable tool-use for structured outputs.
+    /// When disabled, structured calls use the prompt-based JSON path.
+    #[allow(dead_code)]
+    pub fn with_tools(mut self, enabled: bool) -> Self {
+        self.use_tools = enabled;
+        self
     }
 
     /// Generate a commit message from a diff
@@ -64,28 +693,104 @@ impl AiClient {
         template: Option<&str>,
     ) -> Result<String> {
         let system_prompt = self.build_commit_system_prompt(personality, template);
+        let user_content = build_commit_user_content(diff, context, self.commit_diff_budget());
 
-        let mut user_content = String::new();
-        user_content.push_str("Generate a commit message for the following changes:\n\n");
+        let response = self.send_message(&system_prompt, &user_content).await?;
 
-        if let Some(ctx) = context {
-            user_content.push_str(&format!("Context: {}\n\n", ctx));
+        Ok(response.trim().to_string())
+    }
+
+    /// Generate a commit message, calling `on_text` with the partial message as it streams in
+    pub async fn stream_commit_message(
+        &self,
+        diff: &str,
+        context: Option<&str>,
+        personality: Option<&str>,
+        template: Option<&str>,
+        on_text: impl FnMut(&str),
+    ) -> Result<String> {
+        let system_prompt = self.build_commit_system_prompt(personality, template);
+        let user_content = build_commit_user_content(diff, context, self.commit_diff_budget());
+
+        let response = self.send_message_streaming(&system_prompt, &user_content, on_text).await?;
+
+        Ok(response.trim().to_string())
+    }
+
+    /// Ask for the parts of a commit message to fill a `commit.template` with
+    pub async fn generate_commit_fields(
+        &self,
+        diff: &str,
+        context: Option<&str>,
+        personality: Option<&str>,
+        template: &str,
+    ) -> Result<CommitFields> {
+        let mut system_prompt = format!(
+            r#"You are an expert at writing clear, concise git commit messages.
+
+The message will be assembled from this template:
+{}
+
+Provide its parts:
+- type: commit type (feat, fix, docs, style, refactor, test, chore, perf, ci, build)
+- scope: affected component/module, or empty if none fits
+- subject: brief description in imperative mood ("add" not "added"), under 60 characters, no trailing period
+- body: optional longer explanation of WHY, wrapped at 72 columns, or empty
+
+Respond in JSON format:
+{{"type": "feat", "scope": "auth", "subject": "add login validation", "body": ""}}"#,
+            template
+        );
+        if let Some(p) = personality {
+            system_prompt = format!("{}\n\nPersonality: {}", system_prompt, p);
         }
+        let user_content = build_commit_user_content(diff, context, self.commit_diff_budget());
 
-        user_content.push_str("```diff\n");
-        // Truncate diff if too long
-        let max_diff_len = 10000;
-        if diff.len() > max_diff_len {
-            user_content.push_str(&diff[..max_diff_len]);
-            user_content.push_str("\n... (truncated)\n");
-        } else {
-            user_content.push_str(diff);
+        if self.use_tools {
+            let tool = commit_fields_tool();
+            if let Some(fields) = self.send_tool_message(&system_prompt, &user_content, &tool).await? {
+                return Ok(fields);
+            }
         }
-        user_content.push_str("\n```");
+
+        self.send_json_message(&system_prompt, &user_content, "commit fields").await
+    }
+
+    /// Generate several distinct commit message candidates in one request.
+    /// Falls back to a single message when `count` is 1 or the response can't be parsed.
+    pub async fn generate_commit_candidates(
+        &self,
+        diff: &str,
+        context: Option<&str>,
+        personality: Option<&str>,
+        template: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<String>> {
+        if count <= 1 {
+            let message = self.generate_commit_message(diff, context, personality, template).await?;
+            return Ok(vec![message]);
+        }
+
+        let system_prompt = format!(
+            r#"{}
+
+IMPORTANT: Instead of a single message, write {} DISTINCT commit messages for the same changes.
+Vary the framing (scope, emphasis, level of detail) so each is a genuine alternative.
+Respond with ONLY a JSON array of strings, e.g. ["feat(auth): add login", "fix(auth): validate tokens"]."#,
+            self.build_commit_system_prompt(personality, template),
+            count
+        );
+        let user_content = build_commit_user_content(diff, context, self.commit_diff_budget());
 
         let response = self.send_message(&system_prompt, &user_content).await?;
 
-        Ok(response.trim().to_string())
+        match parse_commit_candidates(&response, count) {
+            Some(candidates) => Ok(candidates),
+            None => {
+                let message = self.generate_commit_message(diff, context, personality, template).await?;
+                Ok(vec![message])
+            }
+        }
     }
 
     /// Generate multiple atomic commit suggestions
@@ -95,6 +800,7 @@ impl AiClient {
         diff: &str,
         files: &[&str],
         target_count: Option<usize>,
+        hint: Option<&str>,
     ) -> Result<Vec<AtomicCommitSuggestion>> {
         let target_instruction = if let Some(count) = target_count {
             format!(
@@ -132,26 +838,16 @@ If the changes should be a single commit, return just one item in the array."#,
             target_instruction
         );
 
-        let mut user_content = String::new();
-        user_content.push_str(&format!("Files changed: {}\n\n", files.join(", ")));
-        user_content.push_str("```diff\n");
+        let user_content = build_atomic_user_content(diff, files, hint, self.commit_diff_budget());
 
-        let max_diff_len = 10000;
-        if diff.len() > max_diff_len {
-            user_content.push_str(&diff[..max_diff_len]);
-            user_content.push_str("\n... (truncated)\n");
-        } else {
-            user_content.push_str(diff);
+        if self.use_tools {
+            let tool = atomic_commits_tool();
+            if let Some(parsed) = self.send_tool_message::<AtomicCommitsResponse>(&system_prompt, &user_content, &tool).await? {
+                return Ok(parsed.commits);
+            }
         }
-        user_content.push_str("\n```");
-
-        let response = self.send_message(&system_prompt, &user_content).await?;
-
-        // Parse JSON response - extract JSON if wrapped in text/markdown
-        let json_str = extract_json(&response);
-        let parsed: AtomicCommitsResponse = serde_json::from_str(json_str)
-            .with_context(|| format!("Failed to parse AI response as JSON: {}", &response[..200.min(response.len())]))?;
 
+        let parsed: AtomicCommitsResponse = self.send_json_message(&system_prompt, &user_content, "AI response").await?;
         Ok(parsed.commits)
     }
 
@@ -221,12 +917,9 @@ The hunk_ids are the IDs provided in the hunk list. Each hunk should appear in e
             ));
         }
 
-        let response = self.send_message(&system_prompt, &user_content).await?;
-
-        let json_str = extract_json(&response);
-        let parsed: GranularCommitsResponse = serde_json::from_str(json_str)
-            .with_context(|| format!("Failed to parse granular commits response: {}", &response[..200.min(response.len())]))?;
-
+        let parsed: GranularCommitsResponse = self
+            .send_json_message(&system_prompt, &user_content, "granular commits response")
+            .await?;
         Ok(parsed.commits)
     }
 
@@ -323,12 +1016,9 @@ Rules:
             ));
         }
 
-        let response = self.send_message(&system_prompt, &user_content).await?;
-
-        let json_str = extract_json(&response);
-        let parsed: RealisticCommitsResponse = serde_json::from_str(json_str)
-            .with_context(|| format!("Failed to parse realistic commits response: {}", &response[..300.min(response.len())]))?;
-
+        let parsed: RealisticCommitsResponse = self
+            .send_json_message(&system_prompt, &user_content, "realistic commits response")
+            .await?;
         Ok(parsed.commits)
     }
 
@@ -376,15 +1066,16 @@ Only output the documentation, ready to be inserted into the code."#,
         }
 
         user_content.push_str("```diff\n");
-        let max_diff_len = 15000;
-        if diff.len() > max_diff_len {
-            user_content.push_str(&diff[..max_diff_len]);
-            user_content.push_str("\n... (truncated)\n");
-        } else {
-            user_content.push_str(diff);
-        }
+        user_content.push_str(&diff::summarize(diff, self.diff_budget.unwrap_or(REVIEW_DIFF_BUDGET)));
         user_content.push_str("\n```");
 
+        if self.use_tools {
+            let tool = code_review_tool();
+            if let Some(review) = self.send_tool_message(&system_prompt, &user_content, &tool).await? {
+                return Ok(review);
+            }
+        }
+
         user_content.push_str("\n\nProvide your review in JSON format with the following structure:\n");
         user_content.push_str(r#"{
   "verdict": "approve" | "request_changes" | "comment",
@@ -402,18 +1093,84 @@ Only output the documentation, ready to be inserted into the code."#,
   "overall_score": 1-10
 }"#);
 
-        let response = self.send_message(&system_prompt, &user_content).await?;
-
-        let json_str = extract_json(&response);
-        let review: CodeReview = serde_json::from_str(json_str)
-            .with_context(|| format!("Failed to parse review response as JSON: {}", &response[..200.min(response.len())]))?;
+        self.send_json_message(&system_prompt, &user_content, "review response").await
+    }
 
-        Ok(review)
+    /// Send the smallest possible request to check the key and model
+    pub async fn ping(&self) -> Result<()> {
+        let mut request = self.build_request("Reply with one word.", "ping");
+        request.max_tokens = 1;
+        self.send_request(&request).await.map(|_| ())
     }
 
-    /// Send a message to Claude API with retry logic
+    /// Send a message to Claude API and return the text response
     async fn send_message(&self, system: &str, user: &str) -> Result<String> {
-        let request = ClaudeRequest {
+        let request = self.build_request(system, user);
+        let response = self.send_request(&request).await?;
+        Ok(response.text())
+    }
+
+    /// Send a message that forces a call to `tool` and parse its input.
+    /// Returns `Ok(None)`, so the caller can use the JSON prompt instead, if the
+    /// response contained no usable tool call or the API rejected the tool request.
+    /// Transport and server errors are returned as-is: they already went through
+    /// the retry loop, and the JSON prompt would only repeat it.
+    async fn send_tool_message<T: DeserializeOwned>(
+        &self,
+        system: &str,
+        user: &str,
+        tool: &Tool,
+    ) -> Result<Option<T>> {
+        let mut request = self.build_request(system, user);
+        request.tools = Some(vec![tool.clone()]);
+        request.tool_choice = Some(ToolChoice::tool(&tool.name));
+
+        let response = match self.send_request(&request).await {
+            Ok(response) => response,
+            Err(e) if ApiError::is_bad_request(&e) => {
+                tracing::warn!(error = %e, "tool-use request rejected, falling back to JSON prompt");
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let parsed = response.tool_input(&tool.name);
+        if parsed.is_none() {
+            tracing::warn!("no structured tool output, falling back to JSON prompt");
+        }
+        Ok(parsed)
+    }
+
+    /// Send a message whose reply should be a JSON object and parse it into `T`.
+    /// A reply that doesn't parse gets one follow-up asking for raw JSON only.
+    async fn send_json_message<T: DeserializeOwned>(&self, system: &str, user: &str, what: &str) -> Result<T> {
+        let mut request = self.build_request(system, user);
+        let response = self.send_request(&request).await?.text();
+        let error = match extract_json(&response) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => e,
+        };
+
+        tracing::warn!(error = %error, what, "reply was not valid JSON, asking again");
+        request.messages.push(Message {
+            role: "assistant".to_string(),
+            content: if response.trim().is_empty() { "(empty reply)".to_string() } else { response.trim_end().to_string() },
+        });
+        request.messages.push(Message {
+            role: "user".to_string(),
+            content: format!(
+                "That reply could not be parsed as JSON ({}). Reply again with only the raw JSON object: \
+                no code fences and no text before or after it.",
+                error
+            ),
+        });
+
+        let retry = self.send_request(&request).await?.text();
+        extract_json(&retry)
+            .with_context(|| format!("Failed to parse {} as JSON: {}", what, preview(&retry, 200)))
+    }
+
+    fn build_request(&self, system: &str, user: &str) -> ClaudeRequest {
+        ClaudeRequest {
             model: self.model.clone(),
             max_tokens: 4096,
             messages: vec![Message {
@@ -421,50 +1178,216 @@ Only output the documentation, ready to be inserted into the code."#,
                 content: user.to_string(),
             }],
             system: Some(system.to_string()),
+            tools: None,
+            tool_choice: None,
+            temperature: self.temperature,
+        }
+    }
+
+    /// POST `body` to the provider's endpoint with its auth headers
+    fn post(&self, body: String) -> reqwest::RequestBuilder {
+        let builder = self.client
+            .post(&self.api_url)
+            .header("Content-Type", "application/json");
+        let builder = match self.provider {
+            // Self-hosted servers often run without a key
+            Provider::OpenAi | Provider::Ollama if self.api_key.is_empty() => builder,
+            Provider::Ollama => builder.bearer_auth(&self.api_key),
+            Provider::OpenAi => builder.bearer_auth(&self.api_key),
+            Provider::Anthropic => builder
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01"),
         };
+        builder.body(body)
+    }
+
+    /// Send a message with streaming enabled and return the full text.
+    /// `on_text` is called with the text received so far after every delta.
+    /// Rate limits and overloads, whether they arrive up front or mid-stream,
+    /// fall back to a buffered request with the usual retry loop.
+    pub async fn send_message_streaming(
+        &self,
+        system: &str,
+        user: &str,
+        mut on_text: impl FnMut(&str),
+    ) -> Result<String> {
+        let request = self.build_request(system, user);
+        let body = self.provider_body(&request)?;
+        if let Some(cached) = self.cached_response(&body) {
+            let text = cached.text();
+            on_text(&text);
+            return Ok(text);
+        }
+        if let Some(text) = self.try_stream(&request, &mut on_text).await? {
+            let streamed = ClaudeResponse { content: vec![ContentBlock::Text { text: text.clone() }], usage: None };
+            self.store_response(&body, &streamed);
+            return Ok(text);
+        }
+
+        let text = self.send_request(&request).await?.text();
+        on_text(&text);
+        Ok(text)
+    }
+
+    /// Stream a single attempt. `Ok(None)` means it hit a retryable error.
+    #[tracing::instrument(
+        name = "ai_stream",
+        skip_all,
+        fields(model = %request.model, api_key = %redact_key(&self.api_key))
+    )]
+    async fn try_stream(
+        &self,
+        request: &ClaudeRequest,
+        on_text: &mut impl FnMut(&str),
+    ) -> Result<Option<String>> {
+        let api = self.provider.label();
+        let mut body = match self.provider {
+            Provider::OpenAi => serde_json::to_value(ChatRequest::from(request))?,
+            Provider::Anthropic => serde_json::to_value(request)?,
+            // Local generation is the slow part, not the round trip; take the buffered path
+            Provider::Ollama => return Ok(None),
+        };
+        body["stream"] = json!(true);
+        if self.provider == Provider::OpenAi {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        let body = self.request_body(&body)?;
+
+        let started = Instant::now();
+        let mut response = match self.post(body).send().await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "streaming request failed to send");
+                return Ok(None);
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            if status.as_u16() == 429 || status.as_u16() >= 500 {
+                tracing::debug!(status = status.as_u16(), "streaming request returned a retryable error");
+                return Ok(None);
+            }
+            anyhow::bail!("{} error ({}): {}", api, status, error_text);
+        }
+
+        let mut events = SseBuffer::default();
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        let mut finished = false;
+        while !finished {
+            let payloads = match response.chunk().await {
+                Ok(Some(chunk)) => events.push(&chunk),
+                Ok(None) => {
+                    finished = true;
+                    events.finish()
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "stream interrupted, retrying without streaming");
+                    return Ok(None);
+                }
+            };
+
+            for data in payloads {
+                match parse_stream_event(self.provider, &data) {
+                    StreamEvent::Text(delta) => {
+                        text.push_str(&delta);
+                        on_text(&text);
+                    }
+                    StreamEvent::Error { retryable: true, message } => {
+                        tracing::warn!(error = %message, "stream interrupted, retrying without streaming");
+                        return Ok(None);
+                    }
+                    StreamEvent::Error { retryable: false, message } => {
+                        anyhow::bail!("{} error: {}", api, message);
+                    }
+                    StreamEvent::Usage(counts) => {
+                        usage.input_tokens = usage.input_tokens.max(counts.input_tokens);
+                        usage.output_tokens = usage.output_tokens.max(counts.output_tokens);
+                    }
+                    StreamEvent::Done => finished = true,
+                    StreamEvent::Other => {}
+                }
+            }
+        }
+        self.record_usage(Some(&usage));
+
+        tracing::info!(
+            status = status.as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            "API stream completed"
+        );
+        Ok(Some(text))
+    }
 
+    /// Send a request to the provider's API with retry logic
+    #[tracing::instrument(
+        name = "ai_request",
+        skip_all,
+        fields(model = %request.model, api_key = %redact_key(&self.api_key), tools = request.tools.is_some())
+    )]
+    async fn send_request(&self, request: &ClaudeRequest) -> Result<ClaudeResponse> {
         let mut last_error = None;
         let mut delay_ms = BASE_DELAY_MS;
+        let api = self.provider.label();
+        let body = self.provider_body(request)?;
+        if let Some(cached) = self.cached_response(&body) {
+            return Ok(cached);
+        }
 
         for attempt in 0..=MAX_RETRIES {
             if attempt > 0 {
-                eprintln!("Retrying API request (attempt {}/{})", attempt + 1, MAX_RETRIES + 1);
+                tracing::warn!(
+                    attempt = attempt + 1,
+                    max_attempts = MAX_RETRIES + 1,
+                    delay_ms,
+                    error = last_error.as_deref().unwrap_or(""),
+                    "retrying API request"
+                );
                 tokio::time::sleep(Duration::from_millis(delay_ms)).await;
                 delay_ms = (delay_ms * 2).min(MAX_DELAY_MS);
             }
 
-            let response = match self.client
-                .post("https://api.anthropic.com/v1/messages")
-                .header("Content-Type", "application/json")
-                .header("x-api-key", &self.api_key)
-                .header("anthropic-version", "2023-06-01")
-                .json(&request)
-                .send()
-                .await
-            {
+            let started = Instant::now();
+            let response = match self.post(body.clone()).send().await {
                 Ok(resp) => resp,
                 Err(e) => {
                     // Network errors are retryable
+                    tracing::debug!(attempt = attempt + 1, error = %e, "API request failed to send");
                     last_error = Some(format!("Network error: {}", e));
                     continue;
                 }
             };
 
             let status = response.status();
+            let latency_ms = started.elapsed().as_millis() as u64;
 
             // Success - return the response
             if status.is_success() {
-                let claude_response: ClaudeResponse = response.json().await
-                    .context("Failed to parse Claude API response")?;
-
-                return Ok(claude_response.content
-                    .first()
-                    .map(|c| c.text.clone())
-                    .unwrap_or_default());
+                let parsed: ClaudeResponse = match self.provider {
+                    Provider::Anthropic => response.json().await,
+                    Provider::OpenAi => response.json::<ChatResponse>().await.map(ClaudeResponse::from),
+                    Provider::Ollama => response.json::<OllamaResponse>().await.map(ClaudeResponse::from),
+                }
+                .with_context(|| format!("Failed to parse {} response", api))?;
+                let usage = parsed.usage.as_ref();
+                self.record_usage(usage);
+                tracing::info!(
+                    attempt = attempt + 1,
+                    status = status.as_u16(),
+                    latency_ms,
+                    input_tokens = usage.map(|u| u.input_tokens),
+                    output_tokens = usage.map(|u| u.output_tokens),
+                    "API request completed"
+                );
+                self.store_response(&body, &parsed);
+                return Ok(parsed);
             }
 
             // Check if error is retryable
             let error_text = response.text().await.unwrap_or_default();
+            tracing::debug!(attempt = attempt + 1, status = status.as_u16(), latency_ms, "API request returned an error");
 
             if status.as_u16() == 429 || status.as_u16() >= 500 {
                 // Rate limit (429) or server errors (5xx) are retryable
@@ -473,11 +1396,12 @@ Only output the documentation, ready to be inserted into the code."#,
             }
 
             // Non-retryable errors (400, 401, 403, etc.) - fail immediately
-            anyhow::bail!("Claude API error ({}): {}", status, error_text);
+            return Err(ApiError { api, status, message: error_text }.into());
         }
 
         // All retries exhausted
-        anyhow::bail!("Claude API request failed after {} attempts. Last error: {}",
+        anyhow::bail!("{} request failed after {} attempts. Last error: {}",
+            api,
             MAX_RETRIES + 1,
             last_error.unwrap_or_else(|| "Unknown error".to_string())
         )
@@ -620,10 +1544,89 @@ Output ONLY the commit message, nothing else."#;
         let response = self.send_message(system_prompt, &user_content).await?;
         Ok(response.trim().to_string())
     }
+
+    /// Write the message for a commit that reverts `original`, explaining what is undone
+    pub async fn generate_revert_message(&self, original: &str, diff: &str, reason: Option<&str>) -> Result<String> {
+        let system_prompt = r#"You are an expert at writing clear, concise git commit messages.
+
+Write the message for a commit that reverts an earlier commit:
+1. Follows Conventional Commits format: revert: <what is being undone>
+2. Keeps the first line under 72 characters
+3. Uses imperative mood
+4. The body says in plain words which behavior goes away and, when a reason is given, why
+5. Do not include the commit hash; it is added separately
+
+Output ONLY the commit message, nothing else."#;
+
+        let mut user_content = format!("Commit being reverted:\n\n{}\n\n", original);
+        if let Some(reason) = reason {
+            user_content.push_str(&format!("Reason for reverting: {}\n\n", reason));
+        }
+        user_content.push_str("Changes it made (the revert undoes these):\n```diff\n");
+        user_content.push_str(&diff::summarize(diff, self.diff_budget.unwrap_or(COMMIT_DIFF_BUDGET)));
+        user_content.push_str("\n```");
+
+        let response = self.send_message(system_prompt, &user_content).await?;
+        Ok(response.trim().to_string())
+    }
+
+    /// Write the body of a Keep a Changelog section from commits grouped by type
+    pub async fn generate_changelog(&self, grouped_commits: &str) -> Result<String> {
+        let system_prompt = r#"You are an expert at writing release notes in the Keep a Changelog format.
+
+Given commits grouped by Conventional Commits type, write the entries for one release:
+1. Use only these subsections, in this order, and leave out empty ones:
+   ### Added, ### Changed, ### Deprecated, ### Removed, ### Fixed, ### Security, ### Other
+2. One "- " bullet per change, written for users of the project, in past or imperative tense consistently
+3. Every commit must be covered; merge near-duplicates, but never drop a change
+4. Put entries from the "Other" group under ### Other unless they clearly belong elsewhere
+5. Start bullets for breaking changes with **BREAKING:**
+
+Output ONLY the subsections, without a version heading or any commentary."#;
+
+        let user_content = format!("Commits since the last release:\n\n{}", grouped_commits);
+
+        let response = self.send_message(system_prompt, &user_content).await?;
+        Ok(response.trim().to_string())
+    }
+
+    /// Write documentation for the numbered `items` of `code`, one entry per item
+    pub async fn generate_item_docs(&self, code: &str, language: &str, items: &str) -> Result<Vec<ItemDoc>> {
+        let system_prompt = format!(
+            r#"You are an expert at writing clear, concise documentation for {} code.
+
+For each numbered item, write its documentation comment:
+1. Start with a one-sentence summary of what it does
+2. Add a short paragraph on parameters, return values or errors only when they aren't obvious
+3. Keep each under 8 lines and match the tone of any existing docs in the file
+4. Plain text only: no comment markers (///, #, quotes) and no code fences
+
+Respond with ONLY a JSON object, no other text:
+{{"docs": [{{"id": 1, "doc": "Parse the config file at `path`."}}]}}"#,
+            language
+        );
+
+        let user_content = format!("Items to document:\n{}\n```{}\n{}\n```", items, language, code);
+
+        let parsed: ItemDocsResponse = self.send_json_message(&system_prompt, &user_content, "docs response").await?;
+        Ok(parsed.docs)
+    }
 }
 
-/// Suggestion for an atomic commit
+/// Documentation for one item from [`AiClient::generate_item_docs`]
 #[derive(Debug, Deserialize)]
+pub struct ItemDoc {
+    pub id: usize,
+    pub doc: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemDocsResponse {
+    docs: Vec<ItemDoc>,
+}
+
+/// Suggestion for an atomic commit
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct AtomicCommitSuggestion {
     pub message: String,
     pub files: Vec<String>,
@@ -631,8 +1634,59 @@ pub struct AtomicCommitSuggestion {
 }
 
 #[derive(Debug, Deserialize)]
-struct AtomicCommitsResponse {
-    commits: Vec<AtomicCommitSuggestion>,
+pub struct AtomicCommitsResponse {
+    pub commits: Vec<AtomicCommitSuggestion>,
+}
+
+/// Ways atomic commit suggestions disagree with the staged files
+#[derive(Debug, Default, PartialEq)]
+pub struct SuggestionProblems {
+    /// Suggested paths that aren't staged
+    pub unknown: Vec<String>,
+    /// Staged paths no suggestion includes
+    pub missing: Vec<String>,
+}
+
+impl SuggestionProblems {
+    pub fn is_empty(&self) -> bool {
+        self.unknown.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Check that suggestions only name staged files and cover all of them
+pub fn check_atomic_suggestions(suggestions: &[AtomicCommitSuggestion], files: &[&str]) -> SuggestionProblems {
+    let mut problems = SuggestionProblems::default();
+    for file in suggestions.iter().flat_map(|s| &s.files) {
+        if !files.contains(&file.as_str()) && !problems.unknown.contains(file) {
+            problems.unknown.push(file.clone());
+        }
+    }
+    problems.missing = files.iter()
+        .filter(|file| !suggestions.iter().any(|s| s.files.iter().any(|f| f == *file)))
+        .map(|file| file.to_string())
+        .collect();
+    problems
+}
+
+/// Hint for re-asking after `problems`: the exact paths allowed and the coverage rule
+pub fn atomic_retry_hint(problems: &SuggestionProblems, files: &[&str], hint: Option<&str>) -> String {
+    let mut out = String::new();
+    if let Some(hint) = hint {
+        out.push_str(hint);
+        out.push_str("\n\n");
+    }
+    out.push_str("IMPORTANT: A previous answer was invalid.\n");
+    if !problems.unknown.is_empty() {
+        out.push_str(&format!("- These paths are not staged and must not be used: {}\n", problems.unknown.join(", ")));
+    }
+    if !problems.missing.is_empty() {
+        out.push_str(&format!("- These staged paths were left out: {}\n", problems.missing.join(", ")));
+    }
+    out.push_str("Use only these exact paths, and put every one of them in exactly one commit:\n");
+    for file in files {
+        out.push_str(&format!("- {}\n", file));
+    }
+    out
 }
 
 /// Simplified hunk info for AI analysis
@@ -688,6 +1742,18 @@ struct RealisticCommitsResponse {
     commits: Vec<RealisticCommitPlan>,
 }
 
+/// Parts of a commit message for filling a `commit.template`
+#[derive(Debug, Default, Deserialize)]
+pub struct CommitFields {
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+    pub subject: String,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
 /// Code review result
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CodeReview {
@@ -707,45 +1773,245 @@ pub struct ReviewIssue {
     pub suggestion: Option<String>,
 }
 
-/// Extract JSON from a response that might be wrapped in markdown or text
-fn extract_json(response: &str) -> &str {
-    let response = response.trim();
+/// Tool schema matching `AtomicCommitsResponse`
+pub fn atomic_commits_tool() -> Tool {
+    Tool {
+        name: "suggest_atomic_commits".to_string(),
+        description: "Report how the changes should be split into atomic commits".to_string(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "commits": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string", "description": "Conventional commit message" },
+                            "files": { "type": "array", "items": { "type": "string" } },
+                            "description": { "type": "string" }
+                        },
+                        "required": ["message", "files", "description"]
+                    }
+                }
+            },
+            "required": ["commits"]
+        }),
+    }
+}
+
+/// Tool schema matching `CommitFields`
+pub fn commit_fields_tool() -> Tool {
+    Tool {
+        name: "submit_commit_fields".to_string(),
+        description: "Submit the parts of the commit message".to_string(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "type": { "type": "string", "enum": ["feat", "fix", "docs", "style", "refactor", "test", "chore", "perf", "ci", "build"] },
+                "scope": { "type": ["string", "null"] },
+                "subject": { "type": "string" },
+                "body": { "type": ["string", "null"] }
+            },
+            "required": ["type", "subject"]
+        }),
+    }
+}
 
-    // If it starts with {, assume it's already JSON
-    if response.starts_with('{') {
-        return response;
+/// Tool schema matching `CodeReview`
+pub fn code_review_tool() -> Tool {
+    Tool {
+        name: "submit_review".to_string(),
+        description: "Submit the structured code review".to_string(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "verdict": { "type": "string", "enum": ["approve", "request_changes", "comment"] },
+                "summary": { "type": "string" },
+                "issues": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "severity": { "type": "string", "enum": ["critical", "warning", "suggestion"] },
+                            "file": { "type": "string" },
+                            "line": { "type": ["integer", "null"] },
+                            "message": { "type": "string" },
+                            "suggestion": { "type": ["string", "null"] }
+                        },
+                        "required": ["severity", "file", "message"]
+                    }
+                },
+                "positives": { "type": "array", "items": { "type": "string" } },
+                "overall_score": { "type": "integer", "minimum": 1, "maximum": 10 }
+            },
+            "required": ["verdict", "summary", "issues", "positives", "overall_score"]
+        }),
     }
+}
 
-    // Try to find JSON in markdown code blocks
-    if let Some(start) = response.find("```json") {
-        let json_start = start + 7; // Skip ```json
-        if let Some(end) = response[json_start..].find("```") {
-            return response[json_start..json_start + end].trim();
+/// Build the user message for atomic commit suggestions.
+/// Every file is listed with its change counts, even when its diff doesn't fit in `budget`.
+pub fn build_atomic_user_content(diff: &str, files: &[&str], hint: Option<&str>, budget: usize) -> String {
+    let stats = diff::file_stats(diff);
+    let mut user_content = String::from("Files changed:\n");
+    for file in files {
+        match stats.iter().find(|stat| stat.path == *file) {
+            Some(stat) => user_content.push_str(&format!("- {}\n", stat.line())),
+            None => user_content.push_str(&format!("- {}\n", file)),
         }
     }
+    user_content.push('\n');
 
-    // Try plain code blocks
-    if let Some(start) = response.find("```") {
-        let block_start = start + 3;
-        // Skip optional language identifier
-        let content_start = response[block_start..]
-            .find('\n')
-            .map(|i| block_start + i + 1)
-            .unwrap_or(block_start);
-        if let Some(end) = response[content_start..].find("```") {
-            return response[content_start..content_start + end].trim();
+    if let Some(hint) = hint {
+        user_content.push_str(hint);
+        user_content.push('\n');
+    }
+
+    user_content.push_str("```diff\n");
+    user_content.push_str(&diff::summarize(diff, budget));
+    user_content.push_str("\n```");
+
+    user_content
+}
+
+/// Build the user message for commit message generation, fitting the diff into `budget` bytes
+pub fn build_commit_user_content(diff: &str, context: Option<&str>, budget: usize) -> String {
+    let mut user_content = String::new();
+    user_content.push_str("Generate a commit message for the following changes:\n\n");
+
+    if let Some(ctx) = context {
+        user_content.push_str(&format!("Context: {}\n\n", ctx));
+    }
+
+    user_content.push_str("```diff\n");
+    user_content.push_str(&diff::summarize(diff, budget));
+    user_content.push_str("\n```");
+
+    user_content
+}
+
+/// At most `max` bytes from the start of `text`, for error messages
+fn preview(text: &str, max: usize) -> &str {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Parse a JSON array of commit message candidates.
+/// Returns `None` if no usable candidates could be extracted.
+pub fn parse_commit_candidates(response: &str, max: usize) -> Option<Vec<String>> {
+    let response = response.trim();
+    let start = response.find('[')?;
+    let end = response.rfind(']')?;
+    if end <= start {
+        return None;
+    }
+
+    let parsed: Vec<String> = serde_json::from_str(&response[start..=end]).ok()?;
+
+    let mut candidates: Vec<String> = Vec::new();
+    for message in parsed {
+        let message = message.trim().to_string();
+        if !message.is_empty() && !candidates.contains(&message) {
+            candidates.push(message);
         }
     }
+    candidates.truncate(max);
 
-    // Try to find { and } and extract
-    if let Some(start) = response.find('{') {
-        if let Some(end) = response.rfind('}') {
-            if end > start {
-                return &response[start..=end];
-            }
+    if candidates.is_empty() {
+        None
+    } else {
+        Some(candidates)
+    }
+}
+
+/// Shorten an API key for logs: the prefix and last four characters only
+pub fn redact_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 16 {
+        return "[redacted]".to_string();
+    }
+    let prefix: String = chars[..7].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", prefix, suffix)
+}
+
+/// Parse the JSON object in a model reply. Tries the reply as-is, then the
+/// inside of its first code fence, then each balanced `{...}` block in turn,
+/// so fences, a leading sentence or trailing commentary don't break parsing.
+pub(crate) fn extract_json<T: DeserializeOwned>(response: &str) -> serde_json::Result<T> {
+    let response = response.trim();
+    let mut last_error = match serde_json::from_str(response) {
+        Ok(parsed) => return Ok(parsed),
+        Err(e) => e,
+    };
+
+    let unfenced = strip_code_fence(response);
+    let candidates = std::iter::once(unfenced)
+        .chain(balanced_objects(unfenced))
+        .chain(balanced_objects(response));
+    for candidate in candidates {
+        match serde_json::from_str(candidate) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => last_error = e,
         }
     }
+    Err(last_error)
+}
+
+/// Contents of the first ``` fence in `text` (without its language tag), or `text`
+fn strip_code_fence(text: &str) -> &str {
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    let after = &text[start + 3..];
+    let Some(newline) = after.find('\n') else {
+        return text;
+    };
+    let body = &after[newline + 1..];
+    match body.find("```") {
+        Some(end) => body[..end].trim(),
+        None => body.trim(),
+    }
+}
 
-    // Return as-is and let the parser fail with a better error
-    response
+/// Every top-level `{...}` block in `text`, in order. Braces inside JSON
+/// strings are skipped, so `"{"` in a value doesn't end a block early.
+fn balanced_objects(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' if depth > 0 => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    blocks.push(&text[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
 }
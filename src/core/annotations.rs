@@ -0,0 +1,233 @@
+//! GitHub Actions workflow commands for review and secret findings.
+//!
+//! Lines like `::error file=src/main.rs,line=3::message` printed on stdout
+//! show up as file-anchored annotations in the PR checks UI.
+
+use anyhow::Result;
+
+use crate::core::ai::CodeReview;
+use crate::core::secrets::{SecretAction, SecretMatch, SecretPolicy};
+
+/// Set to `true` by GitHub Actions runners
+const GITHUB_ACTIONS_ENV: &str = "GITHUB_ACTIONS";
+
+/// Whether we're running inside GitHub Actions
+pub fn in_github_actions() -> bool {
+    std::env::var(GITHUB_ACTIONS_ENV).is_ok_and(|v| v == "true")
+}
+
+/// Human output, optionally with annotations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    /// Text plus GitHub Actions annotations
+    Github,
+}
+
+impl Format {
+    /// Parse `--format`, defaulting to annotations under GitHub Actions
+    pub fn resolve(format: Option<&str>, github_actions: bool) -> Result<Self> {
+        match format.map(|f| f.to_lowercase()).as_deref() {
+            Some("text") => Ok(Self::Text),
+            Some("github") => Ok(Self::Github),
+            Some(other) => anyhow::bail!("Unknown format '{}'. Use text or github (or --json)", other),
+            None if github_actions => Ok(Self::Github),
+            None => Ok(Self::Text),
+        }
+    }
+}
+
+/// Annotation severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl Level {
+    fn command(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Notice => "notice",
+        }
+    }
+}
+
+/// Escape a message per the workflow command rules
+fn escape_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escape a property value, which additionally can't contain `:` or `,`
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Build one workflow command line
+pub fn annotation(level: Level, file: Option<&str>, line: Option<usize>, title: &str, message: &str) -> String {
+    let mut properties = Vec::new();
+    if let Some(file) = file.filter(|f| !f.is_empty()) {
+        properties.push(format!("file={}", escape_property(file)));
+        if let Some(line) = line.filter(|l| *l > 0) {
+            properties.push(format!("line={}", line));
+        }
+    }
+    properties.push(format!("title={}", escape_property(title)));
+
+    format!("::{} {}::{}", level.command(), properties.join(","), escape_data(message))
+}
+
+/// One annotation per review issue, then a summary notice with verdict and score
+pub fn review_annotations(review: &CodeReview) -> Vec<String> {
+    let mut lines: Vec<String> = review.issues.iter()
+        .map(|issue| {
+            let level = match issue.severity.as_str() {
+                "critical" => Level::Error,
+                "warning" => Level::Warning,
+                _ => Level::Notice,
+            };
+            let message = match &issue.suggestion {
+                Some(suggestion) => format!("{}\nSuggestion: {}", issue.message, suggestion),
+                None => issue.message.clone(),
+            };
+            let title = format!("bahn review: {}", issue.severity);
+            annotation(level, Some(&issue.file), issue.line.map(|l| l as usize), &title, &message)
+        })
+        .collect();
+
+    lines.push(annotation(
+        Level::Notice,
+        None,
+        None,
+        "bahn review",
+        &format!("Verdict: {} (score {}/10)\n{}", review.verdict, review.overall_score, review.summary),
+    ));
+    lines
+}
+
+/// One annotation per reported secret (errors for blocking ones), then a summary notice
+pub fn secret_annotations(secrets: &[SecretMatch], policy: &SecretPolicy) -> Vec<String> {
+    let mut blocking = 0;
+    let mut lines = Vec::new();
+
+    for secret in secrets {
+        let level = match policy.action_for(secret) {
+            SecretAction::Block => {
+                blocking += 1;
+                Level::Error
+            }
+            SecretAction::Warn => Level::Warning,
+            SecretAction::Ignore => continue,
+        };
+        let message = format!(
+            "Potential {} ({}), confidence {:.0}%",
+            secret.secret_type, secret.masked_value, secret.confidence * 100.0
+        );
+        lines.push(annotation(level, Some(&secret.file_path), Some(secret.line), "bahn secrets", &message));
+    }
+
+    let summary = if lines.is_empty() {
+        "No secrets found".to_string()
+    } else {
+        format!("{} potential secret(s), {} blocking", lines.len(), blocking)
+    };
+    lines.push(annotation(Level::Notice, None, None, "bahn secrets", &summary));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ai::ReviewIssue;
+    use crate::core::secrets::fingerprint;
+
+    fn secret(secret_type: &str, confidence: f64, file: &str, line: usize) -> SecretMatch {
+        SecretMatch {
+            secret_type: secret_type.to_string(),
+            line,
+            masked_value: "ghp_********cD5".to_string(),
+            confidence,
+            file_path: file.to_string(),
+            fingerprint: fingerprint(file),
+        }
+    }
+
+    #[test]
+    fn test_format_resolution() {
+        assert_eq!(Format::resolve(None, false).unwrap(), Format::Text);
+        assert_eq!(Format::resolve(None, true).unwrap(), Format::Github);
+        assert_eq!(Format::resolve(Some("text"), true).unwrap(), Format::Text);
+        assert_eq!(Format::resolve(Some("GitHub"), false).unwrap(), Format::Github);
+        assert!(Format::resolve(Some("sarif"), false).is_err());
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape_data("50% done\r\nnext"), "50%25 done%0D%0Anext");
+        assert_eq!(escape_property("a:b,c%\n"), "a%3Ab%2Cc%25%0A");
+        assert_eq!(
+            annotation(Level::Warning, Some("dir,x/a:b.rs"), Some(0), "t", "m"),
+            "::warning file=dir%2Cx/a%3Ab.rs,title=t::m"
+        );
+    }
+
+    #[test]
+    fn test_review_snapshot() {
+        let review = CodeReview {
+            verdict: "request_changes".to_string(),
+            summary: "Two problems".to_string(),
+            issues: vec![
+                ReviewIssue {
+                    severity: "critical".to_string(),
+                    file: "src/main.rs".to_string(),
+                    line: Some(42),
+                    message: "Unchecked unwrap".to_string(),
+                    suggestion: Some("Use `?`,\nor handle the error".to_string()),
+                },
+                ReviewIssue {
+                    severity: "warning".to_string(),
+                    file: "src/lib.rs".to_string(),
+                    line: None,
+                    message: "Unused import".to_string(),
+                    suggestion: None,
+                },
+                ReviewIssue {
+                    severity: "suggestion".to_string(),
+                    file: "README.md".to_string(),
+                    line: Some(3),
+                    message: "Typo".to_string(),
+                    suggestion: None,
+                },
+            ],
+            positives: vec![],
+            overall_score: 5,
+        };
+
+        assert_eq!(review_annotations(&review), vec![
+            "::error file=src/main.rs,line=42,title=bahn review%3A critical::Unchecked unwrap%0ASuggestion: Use `?`,%0Aor handle the error",
+            "::warning file=src/lib.rs,title=bahn review%3A warning::Unused import",
+            "::notice file=README.md,line=3,title=bahn review%3A suggestion::Typo",
+            "::notice title=bahn review::Verdict: request_changes (score 5/10)%0ATwo problems",
+        ]);
+    }
+
+    #[test]
+    fn test_secret_snapshot() {
+        let mut policy = SecretPolicy::default();
+        policy.overrides.insert("Ignored Type".to_string(), SecretAction::Ignore);
+        let secrets = vec![
+            secret("GitHub Personal Access Token", 0.95, "src/config.rs", 12),
+            secret("High Entropy String", 0.75, ".env", 3),
+            secret("Ignored Type", 0.95, "x.rs", 1),
+        ];
+
+        assert_eq!(secret_annotations(&secrets, &policy), vec![
+            "::error file=src/config.rs,line=12,title=bahn secrets::Potential GitHub Personal Access Token (ghp_********cD5), confidence 95%25",
+            "::warning file=.env,line=3,title=bahn secrets::Potential High Entropy String (ghp_********cD5), confidence 75%25",
+            "::notice title=bahn secrets::2 potential secret(s), 1 blocking",
+        ]);
+        assert_eq!(secret_annotations(&[], &policy), vec!["::notice title=bahn secrets::No secrets found"]);
+    }
+}
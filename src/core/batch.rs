@@ -0,0 +1,121 @@
+//! Run an AI job over many files at once, with a spinner per file in flight.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// A file the job failed on
+#[derive(Debug)]
+pub struct Failure {
+    pub name: String,
+    pub error: anyhow::Error,
+}
+
+/// Run `job` on each item, at most `concurrency` at a time. While a job runs
+/// its spinner reads `verb name`; whatever it returns is printed when it
+/// finishes. A failing item doesn't stop the others; failures are returned.
+pub async fn run<T, F, Fut>(
+    items: Vec<T>,
+    concurrency: usize,
+    verb: &str,
+    name: impl Fn(&T) -> String,
+    job: F,
+) -> Vec<Failure>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let multi = MultiProgress::new();
+    let total = multi.add(ProgressBar::new(items.len() as u64));
+    total.set_style(ProgressStyle::default_bar()
+        .template("{bar:30.green} {pos}/{len} files")
+        .unwrap());
+    let spinner = ProgressStyle::default_spinner()
+        .template("{spinner:.green} {msg}")
+        .unwrap();
+
+    let mut results = stream::iter(items)
+        .map(|item| {
+            let name = name(&item);
+            let pb = multi.insert_before(&total, ProgressBar::new_spinner());
+            pb.set_style(spinner.clone());
+            pb.set_message(format!("{} {}", verb, name));
+            pb.enable_steady_tick(Duration::from_millis(100));
+            let work = job(item);
+            async move {
+                let result = work.await;
+                pb.finish_and_clear();
+                (name, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut failures = Vec::new();
+    while let Some((name, result)) = results.next().await {
+        total.inc(1);
+        // Printed through `suspend` so output survives a hidden (non-tty) progress display
+        match result {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => multi.suspend(|| print!("{}", output)),
+            Err(error) => {
+                multi.suspend(|| println!("  {} {}: {:#}", "Failed".red(), name, error));
+                failures.push(Failure { name, error });
+            }
+        }
+    }
+    total.finish_and_clear();
+    failures
+}
+
+/// Summarize the failures of a run, as an error if there were any
+pub fn finish(failures: Vec<Failure>, attempted: usize) -> Result<()> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    println!();
+    println!("{}", "Failed files:".red().bold());
+    for failure in &failures {
+        println!("  {} {:#}", failure.name, failure.error);
+    }
+    anyhow::bail!("{} of {} files failed", failures.len(), attempted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_bounded_concurrency_collects_failures() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let failures = run((0..8).collect(), 3, "Testing", |i: &u32| format!("file{}", i), |i| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                if i % 4 == 1 {
+                    anyhow::bail!("boom");
+                }
+                Ok(String::new())
+            }
+        })
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        let mut failed: Vec<&str> = failures.iter().map(|f| f.name.as_str()).collect();
+        failed.sort();
+        assert_eq!(failed, ["file1", "file5"]);
+
+        let err = finish(failures, 8).unwrap_err();
+        assert_eq!(err.to_string(), "2 of 8 files failed");
+        assert!(finish(Vec::new(), 8).is_ok());
+    }
+}
@@ -0,0 +1,151 @@
+//! On-disk cache of AI responses.
+//!
+//! Re-running a command on the same diff (after cancelling a commit, or
+//! switching between single and `--atomic` mode) sends byte-identical
+//! requests. Their responses are kept under the user's cache directory,
+//! one file per request hash, until they are older than the TTL.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Extension of cached responses
+const ENTRY_EXTENSION: &str = "json";
+
+/// Cached responses in one directory, valid for `ttl` after they were written
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// The cache in the platform's cache directory (`~/.cache/gitBahn` on Linux)
+    pub fn in_default_dir(ttl: Duration) -> Option<Self> {
+        default_dir().map(|dir| Self::new(dir, ttl))
+    }
+
+    /// The response stored under `key`, unless it's missing or expired
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.entry_path(key);
+        let modified = fs::metadata(&path).ok()?.modified().ok()?;
+        // A timestamp in the future counts as fresh
+        if modified.elapsed().unwrap_or_default() > self.ttl {
+            fs::remove_file(&path).ok();
+            return None;
+        }
+        fs::read_to_string(&path).ok()
+    }
+
+    /// Store `value` under `key`. The file is written to a temporary name and
+    /// renamed into place, so concurrent readers never see half an entry.
+    pub fn put(&self, key: &str, value: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache directory {}", self.dir.display()))?;
+        let temp = self.dir.join(format!(".{}.{}-{}.tmp", key, std::process::id(), rand::random::<u32>()));
+        fs::write(&temp, value)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        if let Err(e) = fs::rename(&temp, self.entry_path(key)) {
+            fs::remove_file(&temp).ok();
+            return Err(e).context("Failed to store cached response");
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ENTRY_EXTENSION))
+    }
+}
+
+/// Where responses are cached by default
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("gitBahn"))
+}
+
+/// Hex SHA-256 of `parts`, kept apart so ("ab", "c") and ("a", "bc") differ
+pub fn key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Delete every cached response (and leftover temporary file) in `dir`,
+/// returning how many responses there were
+pub fn clear(dir: &Path) -> Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        let ext = path.extension().and_then(|e| e.to_str());
+        if ext == Some(ENTRY_EXTENSION) || ext == Some("tmp") {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            if ext == Some(ENTRY_EXTENSION) {
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bahn-cache-{}-{}-{}", label, std::process::id(), rand::random::<u32>()))
+    }
+
+    #[test]
+    fn test_put_get_and_clear() {
+        let dir = temp_dir("roundtrip");
+        let cache = ResponseCache::new(dir.clone(), Duration::from_secs(3600));
+        let key = key(&["system", "user", "model"]);
+
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, "{\"content\":[]}").unwrap();
+        assert_eq!(cache.get(&key).as_deref(), Some("{\"content\":[]}"));
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert_eq!(clear(&dir).unwrap(), 1);
+        assert!(cache.get(&key).is_none());
+        assert_eq!(clear(&dir.join("missing")).unwrap(), 0);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let dir = temp_dir("ttl");
+        let cache = ResponseCache::new(dir.clone(), Duration::ZERO);
+        cache.put("abc", "old").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(cache.get("abc").is_none());
+        assert!(!dir.join("abc.json").exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_key_separates_parts() {
+        assert_eq!(key(&["a", "b"]), key(&["a", "b"]));
+        assert_ne!(key(&["ab", "c"]), key(&["a", "bc"]));
+        assert_eq!(key(&["x"]).len(), 64);
+    }
+}
@@ -0,0 +1,138 @@
+//! API key storage in the OS keyring.
+//!
+//! The keyring backend is behind the default `keyring` feature; minimal
+//! builds without it resolve keys from env vars and config files only.
+
+#[cfg(feature = "keyring")]
+use std::cell::Cell;
+
+use anyhow::{Context, Result};
+#[cfg(feature = "keyring")]
+use colored::Colorize;
+
+/// Keyring service name all gitBahn entries are stored under
+pub const SERVICE: &str = "gitBahn";
+
+/// Providers whose keys can be stored: (provider, config key, env var)
+pub const CREDENTIALS: &[(&str, &str, &str)] = &[
+    ("anthropic", "ai.anthropic_api_key", "ANTHROPIC_API_KEY"),
+    ("openai", "ai.openai_api_key", "OPENAI_API_KEY"),
+    ("github", "github.token", "GITHUB_TOKEN"),
+];
+
+/// Look up the config key and env var for a provider name
+pub fn credential(provider: &str) -> Result<(&'static str, &'static str, &'static str)> {
+    CREDENTIALS.iter()
+        .find(|(name, _, _)| *name == provider)
+        .copied()
+        .with_context(|| {
+            let names: Vec<&str> = CREDENTIALS.iter().map(|(name, _, _)| *name).collect();
+            format!("Unknown provider '{}'. Use one of: {}", provider, names.join(", "))
+        })
+}
+
+/// Somewhere API keys can be read from and written to
+pub trait KeyStore {
+    /// Fetch the key for a provider; `None` if nothing is stored
+    fn get(&self, provider: &str) -> Option<String>;
+
+    /// Store the key for a provider
+    fn set(&self, provider: &str, secret: &str) -> Result<()>;
+}
+
+/// The platform keyring (Keychain, Credential Manager, Secret Service)
+#[cfg(feature = "keyring")]
+#[derive(Default)]
+pub struct OsKeyring {
+    /// Set after the first backend failure so we warn once and stop asking
+    unavailable: Cell<bool>,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyStore for OsKeyring {
+    fn get(&self, provider: &str) -> Option<String> {
+        if self.unavailable.get() {
+            return None;
+        }
+
+        let result = keyring::Entry::new(SERVICE, provider).and_then(|entry| entry.get_password());
+        match result {
+            Ok(secret) => Some(secret),
+            Err(keyring::Error::NoEntry) => None,
+            Err(e) => {
+                self.unavailable.set(true);
+                eprintln!(
+                    "{} OS keyring unavailable ({}); using keys from env and config only. Pass --no-keyring to skip it.",
+                    "Warning:".yellow(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn set(&self, provider: &str, secret: &str) -> Result<()> {
+        keyring::Entry::new(SERVICE, provider)
+            .and_then(|entry| entry.set_password(secret))
+            .with_context(|| format!(
+                "Failed to store the {} key in the OS keyring. Set {} instead.",
+                provider,
+                credential(provider).map(|(_, _, var)| var).unwrap_or("the environment variable")
+            ))
+    }
+}
+
+/// Used with `--no-keyring`, or in builds without the `keyring` feature:
+/// never finds or stores anything
+pub struct NoKeyring;
+
+impl KeyStore for NoKeyring {
+    fn get(&self, _provider: &str) -> Option<String> {
+        None
+    }
+
+    fn set(&self, _provider: &str, _secret: &str) -> Result<()> {
+        if cfg!(feature = "keyring") {
+            anyhow::bail!("The keyring is disabled (--no-keyring)")
+        }
+        anyhow::bail!("This build of bahn has no keyring support (built without the `keyring` feature)")
+    }
+}
+
+/// The key store to use for this run
+#[cfg(feature = "keyring")]
+pub fn store(no_keyring: bool) -> Box<dyn KeyStore> {
+    if no_keyring {
+        Box::new(NoKeyring)
+    } else {
+        Box::new(OsKeyring::default())
+    }
+}
+
+/// The key store to use for this run; without the `keyring` feature there is none
+#[cfg(not(feature = "keyring"))]
+pub fn store(_no_keyring: bool) -> Box<dyn KeyStore> {
+    Box::new(NoKeyring)
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    /// In-memory key store for tests
+    #[derive(Default)]
+    pub struct MemoryStore(pub RefCell<HashMap<String, String>>);
+
+    impl KeyStore for MemoryStore {
+        fn get(&self, provider: &str) -> Option<String> {
+            self.0.borrow().get(provider).cloned()
+        }
+
+        fn set(&self, provider: &str, secret: &str) -> Result<()> {
+            self.0.borrow_mut().insert(provider.to_string(), secret.to_string());
+            Ok(())
+        }
+    }
+}
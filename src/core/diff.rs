@@ -0,0 +1,459 @@
+//! Fitting unified diffs into AI prompt budgets.
+//!
+//! Cutting a diff at a byte offset drops whole files from the prompt, so the
+//! model never learns they changed. [`summarize`] works file by file instead:
+//! small files keep every hunk, and files that don't fit shrink to their
+//! leading hunks or to a one-line stat.
+
+use std::collections::{HashMap, HashSet};
+
+/// Most function names listed in a stat line
+const MAX_FUNCTIONS: usize = 5;
+
+/// Change counts for one file in a diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStat {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+    /// Function headings from the hunk headers, in order of appearance
+    pub functions: Vec<String>,
+}
+
+impl FileStat {
+    /// `path: +120/-40, functions touched: parse, render`
+    pub fn line(&self) -> String {
+        let mut line = format!("{}: +{}/-{}", self.path, self.added, self.removed);
+        if !self.functions.is_empty() {
+            line.push_str(&format!(", functions touched: {}", self.functions.join(", ")));
+        }
+        line
+    }
+}
+
+/// One file's part of a unified diff
+struct FileSection<'a> {
+    /// Everything before the first hunk (`diff --git`, index and `---`/`+++` lines)
+    header: &'a str,
+    hunks: Vec<&'a str>,
+    stat: FileStat,
+}
+
+impl FileSection<'_> {
+    fn len(&self) -> usize {
+        self.header.len() + self.hunks.iter().map(|h| h.len()).sum::<usize>()
+    }
+
+    /// Stat line standing in for the hunks that were left out
+    fn omitted_line(&self, kept_hunks: usize) -> String {
+        let note = if kept_hunks == 0 {
+            "diff omitted".to_string()
+        } else {
+            format!("{} of {} hunks omitted", self.hunks.len() - kept_hunks, self.hunks.len())
+        };
+        format!("# {} ({})\n", self.stat.line(), note)
+    }
+}
+
+/// Whole files of a diff that are sent to the AI together
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffChunk {
+    pub files: Vec<String>,
+    pub diff: String,
+}
+
+/// Split `diff` into chunks of whole files of at most `budget` bytes. A file
+/// bigger than `budget` gets a chunk of its own (and is summarized later).
+pub fn chunk_by_file(diff: &str, budget: usize) -> Vec<DiffChunk> {
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+    for section in split_files(diff) {
+        let fits = chunks.last().is_some_and(|chunk| chunk.diff.len() + section.len() <= budget);
+        if !fits {
+            chunks.push(DiffChunk { files: Vec::new(), diff: String::new() });
+        }
+        let chunk = chunks.last_mut().expect("a chunk was just pushed");
+        chunk.diff.push_str(section.header);
+        chunk.diff.extend(section.hunks.iter().copied());
+        chunk.files.push(section.stat.path);
+    }
+    chunks
+}
+
+/// Per-file stats for every file in `diff`
+pub fn file_stats(diff: &str) -> Vec<FileStat> {
+    split_files(diff).into_iter().map(|section| section.stat).collect()
+}
+
+/// Line numbers in the new version of each file that the diff shows, as added
+/// or context lines; the lines a review comment can be anchored to
+pub fn new_side_lines(diff: &str) -> HashMap<String, HashSet<usize>> {
+    let mut files: HashMap<String, HashSet<usize>> = HashMap::new();
+    for section in split_files(diff) {
+        let lines = files.entry(section.stat.path).or_default();
+        for hunk in &section.hunks {
+            let mut hunk_lines = hunk.lines();
+            let Some(mut line) = hunk_lines.next().and_then(hunk_new_start) else {
+                continue;
+            };
+            for text in hunk_lines {
+                if text.starts_with('+') || text.starts_with(' ') || text.is_empty() {
+                    lines.insert(line);
+                    line += 1;
+                }
+            }
+        }
+    }
+    files.retain(|_, lines| !lines.is_empty());
+    files
+}
+
+/// Fit `diff` into about `budget` bytes without dropping any file.
+///
+/// Files are kept whole smallest first. Files that don't fit keep as many
+/// leading hunks as the remaining room allows and end with a stat line, so
+/// every file is still named with its change counts. The result is only cut
+/// further when the stat lines alone exceed the budget.
+pub fn summarize(diff: &str, budget: usize) -> String {
+    if diff.len() <= budget {
+        return diff.to_string();
+    }
+
+    let files = split_files(diff);
+    let stat_lines: Vec<String> = files.iter().map(|f| f.omitted_line(0)).collect();
+    // Room still needed for the stat lines of files not yet placed
+    let mut reserve: usize = stat_lines.iter().map(String::len).sum();
+    let mut used = 0;
+    let mut kept = vec![0; files.len()];
+    let mut whole = vec![false; files.len()];
+
+    let mut by_size: Vec<usize> = (0..files.len()).collect();
+    by_size.sort_by_key(|&i| files[i].len());
+    for i in by_size {
+        let len = files[i].len();
+        if used + len + reserve - stat_lines[i].len() <= budget {
+            whole[i] = true;
+            used += len;
+            reserve -= stat_lines[i].len();
+        }
+    }
+
+    for (i, file) in files.iter().enumerate() {
+        if whole[i] {
+            continue;
+        }
+        let mut size = file.header.len();
+        for (count, hunk) in file.hunks.iter().enumerate() {
+            let note = file.omitted_line(count + 1).len();
+            if used + size + hunk.len() + note + reserve - stat_lines[i].len() > budget {
+                break;
+            }
+            size += hunk.len();
+            kept[i] = count + 1;
+        }
+        if kept[i] == file.hunks.len() {
+            whole[i] = true;
+            used += size;
+            reserve -= stat_lines[i].len();
+        } else if kept[i] > 0 {
+            used += size;
+            reserve += file.omitted_line(kept[i]).len() - stat_lines[i].len();
+        }
+    }
+
+    let mut output = String::with_capacity(budget);
+    for (i, file) in files.iter().enumerate() {
+        if whole[i] {
+            output.push_str(file.header);
+            file.hunks.iter().for_each(|hunk| output.push_str(hunk));
+        } else if kept[i] > 0 {
+            output.push_str(file.header);
+            file.hunks[..kept[i]].iter().for_each(|hunk| output.push_str(hunk));
+            output.push_str(&file.omitted_line(kept[i]));
+        } else {
+            output.push_str(&stat_lines[i]);
+        }
+    }
+
+    if output.len() > budget {
+        let omitted = "# ... (more files omitted)\n";
+        let room = budget.saturating_sub(omitted.len());
+        let mut end = room.min(output.len());
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        let cut = output[..end].rfind('\n').map_or(0, |i| i + 1);
+        output.truncate(cut);
+        output.push_str(omitted);
+    }
+
+    output
+}
+
+/// Each file's path with its added and removed lines, `+`/`-` prefix included
+pub fn changed_lines(diff: &str) -> Vec<(String, Vec<&str>)> {
+    split_files(diff).into_iter()
+        .map(|FileSection { hunks, stat, .. }| {
+            let lines = hunks.into_iter()
+                .flat_map(|hunk| hunk.lines().skip(1))
+                .filter(|line| line.starts_with('+') || line.starts_with('-'))
+                .collect();
+            (stat.path, lines)
+        })
+        .collect()
+}
+
+/// Split `diff` at its `diff --git` lines; text before the first one is its own section
+fn split_files(diff: &str) -> Vec<FileSection<'_>> {
+    let mut starts: Vec<usize> = line_offsets(diff)
+        .filter(|&offset| diff[offset..].starts_with("diff --git "))
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(diff.len());
+
+    starts.windows(2)
+        .map(|w| &diff[w[0]..w[1]])
+        .filter(|text| !text.is_empty())
+        .map(parse_section)
+        .collect()
+}
+
+/// Byte offset of every line start in `text`
+fn line_offsets(text: &str) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1).filter(move |&i| i < text.len()))
+}
+
+fn parse_section(text: &str) -> FileSection<'_> {
+    let hunk_starts: Vec<usize> = line_offsets(text)
+        .filter(|&offset| text[offset..].starts_with("@@"))
+        .collect();
+    let header_end = hunk_starts.first().copied().unwrap_or(text.len());
+    let header = &text[..header_end];
+
+    let mut bounds = hunk_starts.clone();
+    bounds.push(text.len());
+    let hunks: Vec<&str> = bounds.windows(2).map(|w| &text[w[0]..w[1]]).collect();
+
+    let mut stat = FileStat {
+        path: section_path(header),
+        added: 0,
+        removed: 0,
+        functions: Vec::new(),
+    };
+    for hunk in &hunks {
+        let mut lines = hunk.lines();
+        if let Some(function) = lines.next().and_then(hunk_function) {
+            if !stat.functions.contains(&function) && stat.functions.len() < MAX_FUNCTIONS {
+                stat.functions.push(function);
+            }
+        }
+        for line in lines {
+            if line.starts_with('+') {
+                stat.added += 1;
+            } else if line.starts_with('-') {
+                stat.removed += 1;
+            }
+        }
+    }
+
+    FileSection { header, hunks, stat }
+}
+
+/// Path a file section refers to, preferring the new path.
+///
+/// `header` is the part of the section before its first hunk.
+pub fn section_path(header: &str) -> String {
+    let mut old = None;
+    for line in header.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            if path != "/dev/null" {
+                let path = unquote_path(path);
+                return path.strip_prefix("b/").map(str::to_string).unwrap_or(path);
+            }
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            if path != "/dev/null" {
+                let path = unquote_path(path);
+                old = Some(path.strip_prefix("a/").map(str::to_string).unwrap_or(path));
+            }
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            return unquote_path(path);
+        }
+    }
+    if let Some(old) = old {
+        return old;
+    }
+
+    // Binary files and mode changes only have the `diff --git` line
+    header.lines().next()
+        .and_then(|line| line.strip_prefix("diff --git "))
+        .and_then(git_header_path)
+        .unwrap_or_else(|| "(unknown file)".to_string())
+}
+
+/// New-side path from the `a/… b/…` pair of a `diff --git` line, quoted or not
+pub fn git_header_path(paths: &str) -> Option<String> {
+    if paths.ends_with('"') {
+        // A quote inside a quoted path is escaped, so ` "` only starts the last path
+        let start = paths.rfind(" \"")?;
+        unquote_path(&paths[start + 1..]).strip_prefix("b/").map(str::to_string)
+    } else {
+        paths.rsplit_once(" b/").map(|(_, path)| path.to_string())
+    }
+}
+
+/// Undo git's C-style quoting of paths with special characters,
+/// e.g. `"b/caf\303\251 \"menu\".txt"`
+pub fn unquote_path(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some(d @ '0'..='7') => {
+                let octal: String = std::iter::once(d).chain(chars.clone().take(2)).collect();
+                chars.nth(1);
+                bytes.push(u8::from_str_radix(&octal, 8).unwrap_or(b'?'));
+            }
+            Some(other) => bytes.push(other as u8),
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// First new-side line of a hunk: 12 from `@@ -10,4 +12,6 @@`
+fn hunk_new_start(header: &str) -> Option<usize> {
+    let range = header.strip_prefix("@@ ")?.split(' ').nth(1)?.strip_prefix('+')?;
+    range.split(',').next()?.parse().ok()
+}
+
+/// Function name from the heading git puts after a hunk header, e.g.
+/// `@@ -10,4 +10,6 @@ pub fn parse(input: &str) {` gives `pub fn parse`
+fn hunk_function(header: &str) -> Option<String> {
+    let heading = header.splitn(3, "@@").nth(2)?.trim();
+    let name = heading.split(['(', '{']).next()?.trim().trim_end_matches(':').trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_diff(path: &str, hunks: &[(&str, &str)]) -> String {
+        let mut diff = format!("diff --git a/{0} b/{0}\nindex 1111111..2222222 100644\n--- a/{0}\n+++ b/{0}\n", path);
+        for (i, (function, body)) in hunks.iter().enumerate() {
+            diff.push_str(&format!("@@ -{0},3 +{0},4 @@ {1}\n{2}", i * 10 + 1, function, body));
+        }
+        diff
+    }
+
+    #[test]
+    fn test_small_diff_is_unchanged() {
+        let diff = file_diff("src/lib.rs", &[("fn main() {", " a\n-b\n+c\n")]);
+        assert_eq!(summarize(&diff, 10_000), diff);
+    }
+
+    #[test]
+    fn test_chunk_by_file_keeps_files_whole() {
+        let a = file_diff("src/a.rs", &[("fn a() {", " x\n+y\n")]);
+        let b = file_diff("src/b.rs", &[("fn b() {", " x\n+y\n")]);
+        let big = file_diff("src/big.rs", &[("fn big() {", &"+line\n".repeat(50))]);
+        let diff = format!("{}{}{}", a, b, big);
+
+        let chunks = chunk_by_file(&diff, a.len() + b.len());
+        let files: Vec<Vec<&str>> = chunks.iter().map(|c| c.files.iter().map(String::as_str).collect()).collect();
+        assert_eq!(files, [vec!["src/a.rs", "src/b.rs"], vec!["src/big.rs"]]);
+        assert_eq!(chunks[0].diff, format!("{}{}", a, b));
+        assert_eq!(chunks[1].diff, big);
+
+        assert_eq!(chunk_by_file(&diff, 10).len(), 3);
+        assert!(chunk_by_file("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_file_stats() {
+        let mut diff = file_diff("src/lib.rs", &[
+            ("pub fn parse(input: &str) {", "-old\n+new\n+more\n"),
+            ("impl Parser {", "+x\n"),
+            ("pub fn parse(input: &str) {", "-gone\n"),
+        ]);
+        diff.push_str("diff --git a/old.txt b/old.txt\ndeleted file mode 100644\n--- a/old.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-bye\n");
+        diff.push_str("diff --git a/logo.png b/logo.png\nnew file mode 100644\nBinary files /dev/null and b/logo.png differ\n");
+
+        let stats = file_stats(&diff);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].line(), "src/lib.rs: +3/-2, functions touched: pub fn parse, impl Parser");
+        assert_eq!(stats[1].line(), "old.txt: +0/-1");
+        assert_eq!(stats[2].path, "logo.png");
+    }
+
+    #[test]
+    fn test_large_files_become_stat_lines_and_every_file_is_named() {
+        let big_body: String = (0..2000).map(|i| format!("+line {}\n", i)).collect();
+        let small = file_diff("src/small.rs", &[("fn small() {", "-a\n+b\n")]);
+        let big = file_diff("vendor/big.js", &[("function big() {", &big_body)]);
+        let diff = format!("{}{}", big, small);
+
+        let summary = summarize(&diff, 1000);
+        assert!(summary.len() <= 1000, "summary is {} bytes", summary.len());
+        assert!(summary.starts_with("# vendor/big.js: +2000/-0, functions touched: function big (diff omitted)\n"));
+        assert!(summary.ends_with(&small));
+    }
+
+    #[test]
+    fn test_oversized_file_keeps_leading_hunks() {
+        let body: String = (0..20).map(|i| format!("+line {}\n", i)).collect();
+        let hunks: Vec<(&str, &str)> = (0..10).map(|_| ("fn f() {", body.as_str())).collect();
+        let diff = file_diff("src/big.rs", &hunks);
+
+        let summary = summarize(&diff, diff.len() / 2);
+        assert!(summary.len() <= diff.len() / 2);
+        assert!(summary.starts_with("diff --git a/src/big.rs"));
+        assert!(summary.contains("@@ -1,3 +1,4 @@"));
+        assert!(summary.trim_end().ends_with("hunks omitted)"));
+    }
+
+    #[test]
+    fn test_multibyte_characters_at_the_cut_point() {
+        // 'é' is two bytes; put one across every plausible cut offset
+        let body: String = (0..400).map(|_| "+é\n").collect();
+        let diff = file_diff("src/é.rs", &[("fn é() {", &body)]);
+        for budget in [100, 101, 102, 103, 500, 501, diff.len() - 1] {
+            let summary = summarize(&diff, budget);
+            assert!(summary.len() <= budget, "budget {} gave {} bytes", budget, summary.len());
+        }
+
+        // Too many files for even their stat lines: cut on a line boundary
+        let many: String = (0..200).map(|i| file_diff(&format!("src/é{}.rs", i), &[("", "+é\n")])).collect();
+        let summary = summarize(&many, 501);
+        assert!(summary.len() <= 501);
+        assert!(summary.ends_with("# ... (more files omitted)\n"));
+        assert!(summary.starts_with("# src/é0.rs: +1/-0 (diff omitted)\n"));
+    }
+
+    #[test]
+    fn test_new_side_lines() {
+        let diff = format!(
+            "{}{}",
+            file_diff("src/lib.rs", &[("fn a() {", " a\n-b\n+c\n+d\n e\n")]),
+            "diff --git a/gone.rs b/gone.rs\ndeleted file mode 100644\n--- a/gone.rs\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-x\n-y\n",
+        );
+        let lines = new_side_lines(&diff);
+        // The hunk starts at new line 1: context a, added c and d, context e
+        assert_eq!(lines["src/lib.rs"], HashSet::from([1, 2, 3, 4]));
+        assert!(!lines.contains_key("gone.rs"));
+        assert_eq!(hunk_new_start("@@ -10,4 +12,6 @@ fn main() {"), Some(12));
+        assert_eq!(hunk_new_start("@@ -1 +1 @@"), Some(1));
+    }
+}
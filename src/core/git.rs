@@ -1,13 +1,16 @@
 //! Git operations using libgit2.
 
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::io::Write;
 
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, TimeZone};
 use git2::{DiffOptions, IndexAddOption, Repository, Signature, StatusOptions, Time};
 
+use crate::config::Config;
+use crate::core::activity::{self, Event};
+
 /// A single hunk (chunk) of changes within a file
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -173,81 +176,113 @@ fn extract_hunk_context(header: &str, content: &str) -> String {
     }
 }
 
-/// Build a patch for specific hunks and apply it to the index
-pub fn stage_hunks(repo_path: &Path, hunks: &[&DiffHunk]) -> Result<()> {
-    if hunks.is_empty() {
-        return Ok(());
-    }
+/// One hunk of a staged file, by its position among that file's hunks
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HunkRef {
+    pub file_path: String,
+    pub index: usize,
+}
 
-    // Group hunks by file
-    let mut files_hunks: std::collections::HashMap<&str, Vec<&DiffHunk>> = std::collections::HashMap::new();
-    for hunk in hunks {
-        files_hunks.entry(&hunk.file_path).or_default().push(hunk);
+impl HunkRef {
+    /// Reference a hunk from `parse_diff_into_hunks` output
+    pub fn from_parsed(hunks: &[DiffHunk], hunk: &DiffHunk) -> Self {
+        let index = hunks.iter()
+            .filter(|h| h.file_path == hunk.file_path && h.id < hunk.id)
+            .count();
+        Self { file_path: hunk.file_path.clone(), index }
     }
+}
 
-    for (file_path, file_hunks) in files_hunks {
-        // Check if this is a new file (all hunks are from new file)
-        let is_new_file = file_hunks.iter().all(|h| h.is_new_file);
+/// The staged changes at one point in time, so their hunks can be staged again
+/// after the index was reset
+#[derive(Debug, Clone, Copy)]
+pub struct StagedSnapshot {
+    base: Option<git2::Oid>,
+    staged: git2::Oid,
+}
 
-        if is_new_file {
-            // For new files, just stage the whole file
-            Command::new("git")
-                .args(["add", file_path])
-                .current_dir(repo_path)
-                .output()
-                .context("Failed to stage new file")?;
-        } else {
-            // Build a patch for this file's hunks
-            let patch = build_patch_for_hunks(file_path, &file_hunks);
-
-            // Apply patch to index using git apply --cached
-            let mut child = Command::new("git")
-                .args(["apply", "--cached", "--unidiff-zero", "-"])
-                .current_dir(repo_path)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .context("Failed to spawn git apply")?;
-
-            if let Some(mut stdin) = child.stdin.take() {
-                stdin.write_all(patch.as_bytes())
-                    .context("Failed to write patch to git apply")?;
-            }
+/// Record HEAD's tree and the index's tree
+pub fn snapshot_staged(repo: &Repository) -> Result<StagedSnapshot> {
+    let base = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?.id()),
+        Err(_) => None, // No commits yet
+    };
+    let staged = repo.index()?.write_tree().context("Failed to write the index as a tree")?;
+    Ok(StagedSnapshot { base, staged })
+}
 
-            let output = child.wait_with_output()
-                .context("Failed to wait for git apply")?;
-
-            if !output.status.success() {
-                // If patch apply fails, fall back to staging the whole file
-                // This can happen with complex changes
-                Command::new("git")
-                    .args(["add", file_path])
-                    .current_dir(repo_path)
-                    .output()
-                    .context("Failed to stage file")?;
-            }
-        }
+/// Diff of a snapshot, limited to `paths` when given
+fn snapshot_diff<'r>(repo: &'r Repository, snapshot: &StagedSnapshot, paths: Option<&[&str]>) -> Result<git2::Diff<'r>> {
+    let base = snapshot.base.map(|oid| repo.find_tree(oid)).transpose()?;
+    let staged = repo.find_tree(snapshot.staged)?;
+
+    // Binary contents are needed to apply binary deltas to the index
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.show_binary(true);
+    if let Some(paths) = paths {
+        diff_opts.disable_pathspec_match(true);
+        paths.iter().for_each(|path| { diff_opts.pathspec(path); });
     }
 
-    Ok(())
+    Ok(repo.diff_tree_to_tree(base.as_ref(), Some(&staged), Some(&mut diff_opts))?)
+}
+
+/// Number of hunks per changed file in a snapshot
+pub fn snapshot_hunk_counts(repo: &Repository, snapshot: &StagedSnapshot) -> Result<std::collections::HashMap<String, usize>> {
+    let diff = snapshot_diff(repo, snapshot, None)?;
+    let mut counts = std::collections::HashMap::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(patch) = git2::Patch::from_diff(&diff, idx)? else { continue };
+        let delta = patch.delta();
+        let Some(path) = delta.new_file().path().or(delta.old_file().path()).and_then(to_git_path) else { continue };
+        counts.insert(path, patch.num_hunks());
+    }
+    Ok(counts)
 }
 
-/// Build a git patch for specific hunks of a file
-fn build_patch_for_hunks(file_path: &str, hunks: &[&DiffHunk]) -> String {
-    let mut patch = String::new();
+/// Apply selected hunks of a snapshot to the index, leaving the working tree alone.
+///
+/// Hunks apply on top of whatever is staged, so one file can be split across
+/// several commits. Each hunk should be staged once; repeats within a call are
+/// ignored, and staging an already applied hunk again fails.
+pub fn stage_hunks(repo: &Repository, snapshot: &StagedSnapshot, hunks: &[HunkRef]) -> Result<()> {
+    use std::cell::{Cell, RefCell};
+    use std::collections::{HashMap, HashSet};
 
-    // Patch header
-    patch.push_str(&format!("diff --git a/{} b/{}\n", file_path, file_path));
-    patch.push_str(&format!("--- a/{}\n", file_path));
-    patch.push_str(&format!("+++ b/{}\n", file_path));
+    if hunks.is_empty() {
+        return Ok(());
+    }
 
-    // Add each hunk
+    let mut wanted: HashMap<&str, HashSet<usize>> = HashMap::new();
     for hunk in hunks {
-        patch.push_str(&hunk.content);
+        wanted.entry(hunk.file_path.as_str()).or_default().insert(hunk.index);
     }
 
-    patch
+    // libgit2 drops deleted files from the index even when their delta is
+    // skipped, so only the wanted files may be in the diff
+    let paths: Vec<&str> = wanted.keys().copied().collect();
+    let diff = snapshot_diff(repo, snapshot, Some(&paths))?;
+    let current: RefCell<Option<&HashSet<usize>>> = RefCell::new(None);
+    let position = Cell::new(0);
+
+    let mut options = git2::ApplyOptions::new();
+    options.delta_callback(|delta| {
+        let selected = delta.and_then(|d| d.new_file().path().or(d.old_file().path()).and_then(to_git_path))
+            .and_then(|path| wanted.get(path.as_str()));
+        *current.borrow_mut() = selected;
+        position.set(0);
+        selected.is_some()
+    });
+    options.hunk_callback(|_| {
+        let index = position.get();
+        position.set(index + 1);
+        current.borrow().is_some_and(|selected| selected.contains(&index))
+    });
+
+    repo.apply(&diff, git2::ApplyLocation::Index, Some(&mut options))
+        .context("Failed to apply hunks to the index")?;
+    tracing::debug!(hunks = hunks.len(), "staged hunks");
+    Ok(())
 }
 
 // ============================================================================
@@ -395,14 +430,14 @@ fn parse_single_file_into_chunks(
             end_line: total_lines,
             content: content.to_string(),
             chunk_type: ChunkType::FullFile,
-            description: format!("Add {}", file_path.split('/').last().unwrap_or(file_path)),
+            description: format!("Add {}", file_path.split('/').next_back().unwrap_or(file_path)),
             line_count: total_lines,
             dependencies: extract_dependencies(content, file_path),
         }];
     }
 
     // Detect language and parse accordingly
-    let ext = file_path.split('.').last().unwrap_or("");
+    let ext = file_path.split('.').next_back().unwrap_or("");
 
     match ext {
         "py" => parse_python_file(file_path, &lines, content, chunk_id),
@@ -430,7 +465,7 @@ fn parse_python_file(
     let mut class_indent = 0;
     let mut current_class_name = String::new();
 
-    let file_name = file_path.split('/').last().unwrap_or(file_path);
+    let file_name = file_path.split('/').next_back().unwrap_or(file_path);
 
     for (i, line) in lines.iter().enumerate() {
         let trimmed = line.trim();
@@ -565,6 +600,7 @@ fn parse_python_file(
 }
 
 /// Parse Rust file into logical chunks
+#[allow(clippy::collapsible_if)]
 fn parse_rust_file(
     file_path: &str,
     lines: &[&str],
@@ -576,7 +612,7 @@ fn parse_rust_file(
     let mut current_section_type = ChunkType::Imports;
     let mut brace_depth = 0;
 
-    let file_name = file_path.split('/').last().unwrap_or(file_path);
+    let file_name = file_path.split('/').next_back().unwrap_or(file_path);
 
     for (i, line) in lines.iter().enumerate() {
         let trimmed = line.trim();
@@ -677,7 +713,7 @@ fn parse_js_file(
     let mut current_section_type = ChunkType::Imports;
     let mut brace_depth = 0;
 
-    let file_name = file_path.split('/').last().unwrap_or(file_path);
+    let file_name = file_path.split('/').next_back().unwrap_or(file_path);
 
     for (i, line) in lines.iter().enumerate() {
         let trimmed = line.trim();
@@ -764,7 +800,7 @@ fn parse_go_file(
     let mut current_section_type = ChunkType::Imports;
     let mut brace_depth = 0;
 
-    let file_name = file_path.split('/').last().unwrap_or(file_path);
+    let file_name = file_path.split('/').next_back().unwrap_or(file_path);
 
     for (i, line) in lines.iter().enumerate() {
         let trimmed = line.trim();
@@ -847,7 +883,7 @@ fn parse_generic_file(
 ) -> Vec<FileChunk> {
     let mut chunks = Vec::new();
     let chunk_size = 50;
-    let file_name = file_path.split('/').last().unwrap_or(file_path);
+    let file_name = file_path.split('/').next_back().unwrap_or(file_path);
 
     let mut start = 0;
     while start < lines.len() {
@@ -881,6 +917,7 @@ fn parse_generic_file(
 }
 
 /// Create a chunk from line range
+#[allow(clippy::too_many_arguments)]
 fn create_chunk(
     file_path: &str,
     lines: &[&str],
@@ -966,8 +1003,8 @@ fn extract_function_name(line: &str) -> String {
     }
 
     // JS: function func_name( or const func_name =
-    if trimmed.starts_with("function ") {
-        return trimmed["function ".len()..]
+    if let Some(after_function) = trimmed.strip_prefix("function ") {
+        return after_function
             .split('(')
             .next()
             .unwrap_or("")
@@ -976,8 +1013,7 @@ fn extract_function_name(line: &str) -> String {
     }
 
     // Go: func funcName( or func (r *Receiver) funcName(
-    if trimmed.starts_with("func ") {
-        let after_func = &trimmed["func ".len()..];
+    if let Some(after_func) = trimmed.strip_prefix("func ") {
         if after_func.starts_with('(') {
             // Method with receiver
             return after_func
@@ -1004,9 +1040,10 @@ fn extract_function_name(line: &str) -> String {
 }
 
 /// Extract dependencies (imports) from file content
+#[allow(clippy::collapsible_match)]
 fn extract_dependencies(content: &str, file_path: &str) -> Vec<String> {
     let mut deps = Vec::new();
-    let ext = file_path.split('.').last().unwrap_or("");
+    let ext = file_path.split('.').next_back().unwrap_or("");
 
     for line in content.lines() {
         let trimmed = line.trim();
@@ -1098,7 +1135,7 @@ fn determine_file_order(chunks: &[FileChunk]) -> Vec<String> {
 
 /// Get priority for file ordering (lower = earlier)
 fn file_priority(path: &str) -> u32 {
-    let name = path.split('/').last().unwrap_or(path).to_lowercase();
+    let name = path.split('/').next_back().unwrap_or(path).to_lowercase();
     let dir = path.split('/').rev().nth(1).unwrap_or("").to_lowercase();
 
     // Config and setup files first
@@ -1183,7 +1220,7 @@ pub fn stage_file(repo_path: &Path, file_path: &str) -> Result<()> {
 }
 
 /// Information about staged changes
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct StagedChanges {
     /// Files that were added
     pub added: Vec<String>,
@@ -1193,15 +1230,31 @@ pub struct StagedChanges {
     pub deleted: Vec<String>,
     /// Files that were renamed (old_path, new_path)
     pub renamed: Vec<(String, String)>,
-    /// Full diff as a string
+    /// Diff as a string, cut at a line boundary once it reaches [`MAX_DIFF_BYTES`]
     pub diff: String,
+    /// Whether `diff` was cut short; use [`for_each_staged_patch`] to see every file
+    pub diff_truncated: bool,
     /// Summary statistics
     pub stats: DiffStats,
 }
 
+/// Ceiling on [`StagedChanges::diff`], so huge imports don't load the whole patch into memory
+pub const MAX_DIFF_BYTES: usize = 4 * 1024 * 1024;
+
+/// One file in a staged diff
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    /// Path after the change (the old path for deletions)
+    pub path: String,
+    /// Previous path, for renames
+    pub old_path: Option<String>,
+    /// Kind of change
+    #[allow(dead_code)]
+    pub status: git2::Delta,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct DiffStats {
-    #[allow(dead_code)] // Available for detailed stats display
     pub files_changed: usize,
     pub insertions: usize,
     pub deletions: usize,
@@ -1216,7 +1269,7 @@ impl StagedChanges {
             && self.renamed.is_empty()
     }
 
-    /// Get all files that changed
+    /// Get all files that changed, by their current path (the new path of renames)
     pub fn all_files(&self) -> Vec<&str> {
         let mut files = Vec::new();
         files.extend(self.added.iter().map(|s| s.as_str()));
@@ -1226,6 +1279,14 @@ impl StagedChanges {
         files
     }
 
+    /// Every path to stage to reproduce the change: [`Self::all_files`] plus
+    /// the old side of renames, which staging records as removed
+    pub fn paths_to_stage(&self) -> Vec<&str> {
+        let mut files = self.all_files();
+        files.extend(self.renamed.iter().map(|(old, _)| old.as_str()));
+        files
+    }
+
     /// Get a summary of changes
     pub fn summary(&self) -> String {
         let mut parts = Vec::new();
@@ -1259,39 +1320,63 @@ pub fn open_repo(path: Option<&Path>) -> Result<Repository> {
         .with_context(|| format!("Not a git repository: {}", path.display()))
 }
 
+/// The gitdir shared by all worktrees of a repository.
+///
+/// A linked worktree's gitdir holds a `commondir` file pointing at it
+/// (usually relative); otherwise the gitdir itself is the common one.
+pub fn common_dir(repo: &Repository) -> PathBuf {
+    let gitdir = repo.path();
+    match std::fs::read_to_string(gitdir.join("commondir")) {
+        Ok(contents) => gitdir.join(contents.trim_end()),
+        Err(_) => gitdir.to_path_buf(),
+    }
+}
+
 /// Get staged changes from the repository
 pub fn get_staged_changes(repo: &Repository) -> Result<StagedChanges> {
+    let changes = changes_from_diff(&staged_diff(repo)?)?;
+
+    tracing::debug!(
+        files = changes.stats.files_changed,
+        insertions = changes.stats.insertions,
+        deletions = changes.stats.deletions,
+        diff_bytes = changes.diff.len(),
+        diff_truncated = changes.diff_truncated,
+        "read staged changes"
+    );
+    Ok(changes)
+}
+
+/// Changes an amended HEAD would contain: HEAD's own changes plus anything staged
+pub fn get_amend_changes(repo: &Repository) -> Result<StagedChanges> {
+    let head = repo.head()?.peel_to_commit()
+        .context("Nothing to amend: the branch has no commits yet")?;
+    let parent_tree = match head.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None, // Root commit
+    };
+    let index = repo.index()?;
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.include_untracked(false);
+    let mut diff = repo.diff_tree_to_index(parent_tree.as_ref(), Some(&index), Some(&mut diff_opts))?;
+    detect_renames(&mut diff)?;
+
+    changes_from_diff(&diff)
+}
+
+/// File lists, stats and size-capped patch text for a diff
+fn changes_from_diff(diff: &git2::Diff) -> Result<StagedChanges> {
     let mut changes = StagedChanges {
         added: Vec::new(),
         modified: Vec::new(),
         deleted: Vec::new(),
         renamed: Vec::new(),
         diff: String::new(),
+        diff_truncated: false,
         stats: DiffStats::default(),
     };
 
-    // Get the HEAD tree (or empty tree for initial commit)
-    let head_tree = match repo.head() {
-        Ok(head) => {
-            let commit = head.peel_to_commit()?;
-            Some(commit.tree()?)
-        }
-        Err(_) => None, // No commits yet
-    };
-
-    // Get the index (staging area)
-    let index = repo.index()?;
-
-    // Create diff between HEAD and index
-    let mut diff_opts = DiffOptions::new();
-    diff_opts.include_untracked(false);
-
-    let diff = repo.diff_tree_to_index(
-        head_tree.as_ref(),
-        Some(&index),
-        Some(&mut diff_opts),
-    )?;
-
     // Collect file changes
     diff.foreach(
         &mut |delta, _| {
@@ -1337,9 +1422,136 @@ pub fn get_staged_changes(repo: &Repository) -> Result<StagedChanges> {
         deletions: stats.deletions(),
     };
 
-    // Get full diff text
+    // Build the diff text one file at a time, stopping at the ceiling
+    let mut diff_text = String::new();
+    for idx in 0..diff.deltas().len() {
+        let remaining = MAX_DIFF_BYTES - diff_text.len();
+        let Some((_, patch)) = delta_patch(diff, idx, Some(remaining + 1))? else {
+            continue;
+        };
+        if patch.len() > remaining {
+            let cut = patch.as_bytes()[..remaining].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            diff_text.push_str(&patch[..cut]);
+            changes.diff_truncated = true;
+            break;
+        }
+        diff_text.push_str(&patch);
+    }
+
+    changes.diff = diff_text;
+    Ok(changes)
+}
+
+/// Call `f` with each staged file and its patch, one file at a time, so memory
+/// is bounded by the largest file rather than the whole change. Patches longer
+/// than `max_file_len` are cut short. Return `Ok(false)` from `f` to stop early.
+pub fn for_each_staged_patch<F>(repo: &Repository, max_file_len: Option<usize>, mut f: F) -> Result<()>
+where
+    F: FnMut(&FileChange, &str) -> Result<bool>,
+{
+    let diff = staged_diff(repo)?;
+    for idx in 0..diff.deltas().len() {
+        if let Some((change, patch)) = delta_patch(&diff, idx, max_file_len)? {
+            if !f(&change, &patch)? {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Diff between HEAD (or the empty tree) and the index
+fn staged_diff(repo: &Repository) -> Result<git2::Diff<'_>> {
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?.tree()?),
+        Err(_) => None, // No commits yet
+    };
+    let index = repo.index()?;
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.include_untracked(false);
+
+    let mut diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut diff_opts))?;
+    detect_renames(&mut diff)?;
+    Ok(diff)
+}
+
+/// Pair deletions with similar additions, so a moved file shows up as one
+/// rename (with `rename from`/`rename to` headers) instead of a delete and an add
+fn detect_renames(diff: &mut git2::Diff) -> Result<()> {
+    let mut find_opts = git2::DiffFindOptions::new();
+    find_opts.renames(true);
+    diff.find_similar(Some(&mut find_opts))?;
+    Ok(())
+}
+
+/// Patch text for one delta, stopping once `max_len` bytes have been collected.
+/// Returns `None` for unchanged entries.
+fn delta_patch(diff: &git2::Diff, idx: usize, max_len: Option<usize>) -> Result<Option<(FileChange, String)>> {
+    let Some(delta) = diff.get_delta(idx) else {
+        return Ok(None);
+    };
+    let old_path = delta.old_file().path().map(|p| p.to_string_lossy().to_string());
+    let new_path = delta.new_file().path().map(|p| p.to_string_lossy().to_string());
+    let change = FileChange {
+        path: new_path.clone().or_else(|| old_path.clone()).unwrap_or_default(),
+        old_path: old_path.filter(|old| Some(old) != new_path.as_ref()),
+        status: delta.status(),
+    };
+
+    let Some(mut patch) = git2::Patch::from_diff(diff, idx)? else {
+        // libgit2 yields no patch for binary files; keep the header scanners rely on
+        if delta.flags().is_binary() {
+            let old = change.old_path.as_deref().unwrap_or(&change.path);
+            let text = format!(
+                "diff --git a/{old} b/{new}\nBinary files a/{old} and b/{new} differ\n",
+                old = old,
+                new = change.path
+            );
+            return Ok(Some((change, text)));
+        }
+        return Ok(None);
+    };
+
+    let mut text = String::new();
+    let result = patch.print(&mut |_delta, _hunk, line| {
+        let prefix = match line.origin() {
+            '+' => "+",
+            '-' => "-",
+            ' ' => " ",
+            _ => "",
+        };
+        text.push_str(prefix);
+        if let Ok(content) = std::str::from_utf8(line.content()) {
+            text.push_str(content);
+        }
+        !matches!(max_len, Some(max) if text.len() >= max)
+    });
+
+    // Stopping early surfaces as a user-abort error from libgit2
+    if let Err(e) = result {
+        if e.code() != git2::ErrorCode::User {
+            return Err(e.into());
+        }
+    }
+
+    Ok(Some((change, text)))
+}
+
+/// Get the patch a commit introduced relative to its first parent.
+/// Output stops once `max_len` bytes have been collected.
+pub fn get_commit_diff_text(repo: &Repository, commit: &git2::Commit, max_len: Option<usize>) -> Result<String> {
+    let tree = commit.tree()?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
     let mut diff_text = String::new();
-    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+    let result = diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
         let prefix = match line.origin() {
             '+' => "+",
             '-' => "-",
@@ -1352,12 +1564,28 @@ pub fn get_staged_changes(repo: &Repository) -> Result<StagedChanges> {
         if let Ok(content) = std::str::from_utf8(line.content()) {
             diff_text.push_str(content);
         }
-        true
-    })?;
+        !matches!(max_len, Some(max) if diff_text.len() >= max)
+    });
 
-    changes.diff = diff_text;
+    // Stopping early surfaces as a user-abort error from libgit2
+    if let Err(e) = result {
+        if e.code() != git2::ErrorCode::User {
+            return Err(e.into());
+        }
+    }
 
-    Ok(changes)
+    Ok(diff_text)
+}
+
+/// Committer date of HEAD, or `None` on an unborn branch
+pub fn head_commit_time(repo: &Repository) -> Result<Option<DateTime<Local>>> {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(e) if matches!(e.code(), git2::ErrorCode::UnbornBranch | git2::ErrorCode::NotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let when = head.peel_to_commit()?.committer().when();
+    Ok(Local.timestamp_opt(when.seconds(), 0).single())
 }
 
 /// Create a commit with the staged changes
@@ -1403,7 +1631,7 @@ pub fn create_commit_at(
 
     let commit_id = if sign {
         // Create signed commit using GPG
-        create_signed_commit(repo, &signature, message, &tree, &parent_refs)?
+        create_signed_commit(repo, &signature, &signature, message, &tree, &parent_refs)?
     } else {
         repo.commit(
             Some("HEAD"),
@@ -1415,27 +1643,85 @@ pub fn create_commit_at(
         )?
     };
 
+    tracing::info!(
+        sha = %commit_id,
+        signed = sign,
+        backdated = timestamp.is_some(),
+        subject = message.lines().next().unwrap_or(""),
+        "created commit"
+    );
+    activity::record(repo, Event::Commit { sha: commit_id.to_string(), session: activity::session() });
     Ok(commit_id)
 }
 
-/// Create a GPG-signed commit
+/// Signature formats git supports through `gpg.format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+/// How to sign commits, read from git config the way `git commit -S` does
+#[derive(Debug)]
+struct SigningConfig {
+    format: SigningFormat,
+    /// Signing program (`gpg`, `gpgsm`, `ssh-keygen` or a configured override)
+    program: String,
+    /// Key ID for gpg, or a key file / `key::` literal for SSH
+    key: String,
+}
+
+/// Read `gpg.format`, the matching program setting and `user.signingkey`
+fn signing_config(config: &git2::Config) -> Result<SigningConfig> {
+    let format = match config.get_string("gpg.format").ok().as_deref() {
+        None | Some("openpgp") => SigningFormat::OpenPgp,
+        Some("x509") => SigningFormat::X509,
+        Some("ssh") => SigningFormat::Ssh,
+        Some(other) => anyhow::bail!("Unsupported gpg.format '{}'; expected openpgp, x509 or ssh", other),
+    };
+
+    let (section, default_program) = match format {
+        SigningFormat::OpenPgp => ("openpgp", "gpg"),
+        SigningFormat::X509 => ("x509", "gpgsm"),
+        SigningFormat::Ssh => ("ssh", "ssh-keygen"),
+    };
+    let program = config.get_string(&format!("gpg.{}.program", section)).ok()
+        .or_else(|| match format {
+            SigningFormat::OpenPgp => config.get_string("gpg.program").ok(),
+            _ => None,
+        })
+        .unwrap_or_else(|| default_program.to_string());
+
+    let key = match config.get_string("user.signingkey") {
+        Ok(key) if !key.trim().is_empty() => key.trim().to_string(),
+        // Like git, let gpg pick the key that matches the committer email
+        _ if format != SigningFormat::Ssh => config.get_string("user.email")
+            .context("No signing key configured. Set user.signingkey in git config.")?,
+        _ => anyhow::bail!(
+            "No SSH signing key configured. Set user.signingkey to a key file or \"key::ssh-ed25519 ...\"."
+        ),
+    };
+
+    Ok(SigningConfig { format, program, key })
+}
+
+/// Create a signed commit on HEAD, using gpg, gpgsm or ssh-keygen per `gpg.format`
 fn create_signed_commit(
     repo: &Repository,
-    signature: &Signature,
+    author: &Signature,
+    committer: &Signature,
     message: &str,
     tree: &git2::Tree,
     parents: &[&git2::Commit],
 ) -> Result<git2::Oid> {
-    // Get the signing key from git config
-    let config = repo.config()?;
-    let signing_key = config.get_string("user.signingkey")
-        .or_else(|_| config.get_string("user.email"))
-        .context("No signing key configured. Set user.signingkey in git config.")?;
+    // Fail on missing settings before anything is written
+    let signing = signing_config(&repo.config()?)?;
 
     // Create the commit buffer (unsigned commit content)
     let commit_buf = repo.commit_create_buffer(
-        signature,  // author
-        signature,  // committer
+        author,
+        committer,
         message,
         tree,
         parents,
@@ -1444,19 +1730,24 @@ fn create_signed_commit(
     let commit_content = std::str::from_utf8(&commit_buf)
         .context("Invalid UTF-8 in commit content")?;
 
-    // Sign the commit content with GPG
-    let gpg_signature = sign_with_gpg(commit_content, &signing_key)?;
+    let commit_signature = match signing.format {
+        SigningFormat::OpenPgp | SigningFormat::X509 => sign_with_gpg(&signing, commit_content)?,
+        SigningFormat::Ssh => sign_with_ssh(&signing, commit_content)?,
+    };
 
     // Create the signed commit
     let commit_id = repo.commit_signed(
         commit_content,
-        &gpg_signature,
+        &commit_signature,
         Some("gpgsig"),
     )?;
 
-    // Update HEAD to point to the new commit
+    // Advance the branch HEAD points at (creating it on an unborn branch);
+    // writing "HEAD" itself would detach it
+    let head = repo.find_reference("HEAD")?;
+    let target = head.symbolic_target().unwrap_or("HEAD").to_string();
     repo.reference(
-        "HEAD",
+        &target,
         commit_id,
         true,
         &format!("commit: {}", message.lines().next().unwrap_or("")),
@@ -1465,28 +1756,28 @@ fn create_signed_commit(
     Ok(commit_id)
 }
 
-/// Sign content using GPG
-fn sign_with_gpg(content: &str, key: &str) -> Result<String> {
-    let mut child = Command::new("gpg")
-        .args(["--status-fd", "2", "-bsau", key, "--armor"])
+/// Sign content with gpg or gpgsm, using the arguments `git commit -S` passes
+fn sign_with_gpg(signing: &SigningConfig, content: &str) -> Result<String> {
+    let mut child = Command::new(&signing.program)
+        .args(["--status-fd=2", "-bsau", &signing.key])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
-        .context("Failed to spawn gpg process. Is GPG installed?")?;
+        .with_context(|| format!("Failed to spawn {}. Is it installed?", signing.program))?;
 
     // Write content to gpg stdin
     if let Some(mut stdin) = child.stdin.take() {
         stdin.write_all(content.as_bytes())
-            .context("Failed to write to gpg stdin")?;
+            .with_context(|| format!("Failed to write to {} stdin", signing.program))?;
     }
 
     let output = child.wait_with_output()
-        .context("Failed to wait for gpg process")?;
+        .with_context(|| format!("Failed to wait for {}", signing.program))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("GPG signing failed: {}", stderr);
+        anyhow::bail!("Signing with key '{}' failed: {}", signing.key, stderr.trim());
     }
 
     let signature = String::from_utf8(output.stdout)
@@ -1495,15 +1786,132 @@ fn sign_with_gpg(content: &str, key: &str) -> Result<String> {
     Ok(signature)
 }
 
+/// Sign content with `ssh-keygen -Y sign`, which only works on files
+fn sign_with_ssh(signing: &SigningConfig, content: &str) -> Result<String> {
+    let dir = std::env::temp_dir().join(format!("bahn-sign-{}-{}", std::process::id(), rand::random::<u32>()));
+    std::fs::create_dir_all(&dir).context("Failed to create a directory for signing")?;
+
+    let result = (|| -> Result<String> {
+        let buffer = dir.join("commit");
+        std::fs::write(&buffer, content)?;
+
+        // A literal public key is written out and signed with via ssh-agent
+        let literal = signing.key.strip_prefix("key::")
+            .or_else(|| signing.key.starts_with("ssh-").then_some(signing.key.as_str()));
+        let key_file = match literal {
+            Some(public_key) => {
+                let path = dir.join("key.pub");
+                std::fs::write(&path, format!("{}\n", public_key))?;
+                path
+            }
+            None => match signing.key.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().context("Cannot find the home directory")?.join(rest),
+                None => PathBuf::from(&signing.key),
+            },
+        };
+
+        let mut command = Command::new(&signing.program);
+        command.args(["-Y", "sign", "-n", "git", "-f"]).arg(&key_file);
+        if literal.is_some() {
+            command.arg("-U");
+        }
+        let output = command.arg(&buffer)
+            .output()
+            .with_context(|| format!("Failed to spawn {}. Is OpenSSH installed?", signing.program))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("SSH signing with '{}' failed: {}", signing.key, stderr.trim());
+        }
+
+        std::fs::read_to_string(dir.join("commit.sig")).context("ssh-keygen did not write a signature")
+    })();
+
+    std::fs::remove_dir_all(&dir).ok();
+    result
+}
+
+/// Whether path comparisons should ignore case on this platform's usual filesystem
+pub const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// A relative path in git's `/`-separated form.
+///
+/// `.` segments are dropped and `..` resolved; returns `None` for absolute
+/// paths and paths that climb above their base.
+pub fn to_git_path(path: &Path) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Convert a path from the filesystem or user input to the repo-relative,
+/// `/`-separated form git and the index expect.
+///
+/// Relative paths are taken as relative to `root`. Returns `None` for paths
+/// outside the repository.
+pub fn to_repo_relative(root: &Path, path: &Path) -> Option<String> {
+    if path.has_root() {
+        to_git_path(&strip_root(root, path, CASE_INSENSITIVE_FS)?)
+    } else {
+        to_git_path(path)
+    }
+}
+
+/// `path` with the `root` prefix removed, comparing components without case if asked
+pub fn strip_root(root: &Path, path: &Path, ignore_case: bool) -> Option<PathBuf> {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return Some(relative.to_path_buf());
+    }
+    if !ignore_case {
+        return None;
+    }
+
+    let mut rest = path.components();
+    for expected in root.components() {
+        let actual = rest.next()?;
+        let same = actual.as_os_str().to_string_lossy().to_lowercase()
+            == expected.as_os_str().to_string_lossy().to_lowercase();
+        if !same {
+            return None;
+        }
+    }
+    Some(rest.as_path().to_path_buf())
+}
+
+/// `path` for display: repo-relative with `/` separators when inside the current repository
+pub fn display_path(path: &Path) -> String {
+    let absolute = match std::env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => path.to_path_buf(),
+    };
+
+    open_repo(None).ok()
+        .and_then(|repo| repo.workdir().and_then(|root| to_repo_relative(root, &absolute)))
+        .filter(|relative| !relative.is_empty())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
 /// Stage specific files (add to index)
 pub fn stage_files(repo: &Repository, files: &[&str]) -> Result<()> {
     let mut index = repo.index()?;
+    let workdir = repo.workdir().context("Not a working directory")?;
 
     for file in files {
-        let path = Path::new(file);
+        let relative = to_repo_relative(workdir, Path::new(file))
+            .filter(|relative| !relative.is_empty())
+            .with_context(|| format!("Path is outside the repository: {}", file))?;
+        let path = Path::new(&relative);
 
         // Check if file exists (for adds/modifications) or was deleted
-        let workdir = repo.workdir().context("Not a working directory")?;
         let full_path = workdir.join(path);
 
         if full_path.exists() {
@@ -1542,11 +1950,30 @@ pub fn stage_all(repo: &Repository) -> Result<()> {
     let mut index = repo.index()?;
     index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
     index.write()?;
+    tracing::debug!("staged all changes");
+    Ok(())
+}
+
+/// Stage all changes (like git add -A), skipping paths for which `skip` returns true
+pub fn stage_all_except(repo: &Repository, skip: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let mut index = repo.index()?;
+    let mut callback = |path: &Path, _: &[u8]| -> i32 { if skip(path) { 1 } else { 0 } };
+    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, Some(&mut callback))?;
+    index.update_all(["*"].iter(), Some(&mut callback))?;
+    index.write()?;
     Ok(())
 }
 
 /// Get recent commit messages for context
 pub fn get_recent_commits(repo: &Repository, count: usize) -> Result<Vec<String>> {
+    Ok(get_recent_commit_messages(repo, count)?
+        .iter()
+        .map(|msg| msg.lines().next().unwrap_or("").to_string())
+        .collect())
+}
+
+/// Get the full messages (subject and body) of recent commits
+pub fn get_recent_commit_messages(repo: &Repository, count: usize) -> Result<Vec<String>> {
     let mut messages = Vec::new();
 
     // Handle unborn branch (no commits yet)
@@ -1568,13 +1995,71 @@ pub fn get_recent_commits(repo: &Repository, count: usize) -> Result<Vec<String>
         let oid = oid?;
         let commit = repo.find_commit(oid)?;
         if let Some(msg) = commit.message() {
-            messages.push(msg.lines().next().unwrap_or("").to_string());
+            messages.push(msg.trim_end().to_string());
         }
     }
 
     Ok(messages)
 }
 
+/// A commit listed by [`commits_since`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedCommit {
+    /// Abbreviated hash
+    pub id: String,
+    pub message: String,
+}
+
+/// Non-merge commits reachable from HEAD but not from `since`, newest first.
+/// Without `since`, the whole history.
+pub fn commits_since(repo: &Repository, since: Option<&str>) -> Result<Vec<LoggedCommit>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head().context("No commits yet")?;
+    if let Some(since) = since {
+        let base = repo.revparse_single(since)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Unknown revision: {}", since))?;
+        revwalk.hide(base.id())?;
+    }
+
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        commits.push(LoggedCommit {
+            id: commit.id().to_string()[..7].to_string(),
+            message: commit.message().unwrap_or("").trim_end().to_string(),
+        });
+    }
+    Ok(commits)
+}
+
+/// Name of the most recent tag reachable from `commit`
+pub fn latest_tag(commit: &git2::Commit) -> Result<Option<String>> {
+    let mut options = git2::DescribeOptions::new();
+    options.describe_tags();
+    let describe = match commit.as_object().describe(&options) {
+        Ok(describe) => describe,
+        // libgit2 reports "no reference found" as a generic Describe error
+        Err(e) if e.code() == git2::ErrorCode::NotFound || e.class() == git2::ErrorClass::Describe => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut format = git2::DescribeFormatOptions::new();
+    format.abbreviated_size(0);
+    Ok(Some(describe.format(Some(&format))?))
+}
+
+/// Name of a tag pointing exactly at `commit`, if any
+pub fn tag_at(repo: &Repository, commit: &git2::Commit) -> Result<Option<String>> {
+    let Some(tag) = latest_tag(commit)? else {
+        return Ok(None);
+    };
+    let tagged = repo.revparse_single(&tag)?.peel_to_commit()?;
+    Ok((tagged.id() == commit.id()).then_some(tag))
+}
+
 /// Check if there are uncommitted changes
 pub fn has_uncommitted_changes(repo: &Repository) -> Result<bool> {
     let mut opts = StatusOptions::new();
@@ -1616,6 +2101,11 @@ pub fn current_branch(repo: &Repository) -> Result<String> {
     }
 }
 
+/// Whether the current branch of `repo` matches `git.protected_branches`
+pub fn is_protected(repo: &Repository, config: &Config) -> Result<bool> {
+    Ok(config.is_protected(&current_branch(repo)?))
+}
+
 /// Get repository root path
 pub fn repo_root(repo: &Repository) -> Result<&Path> {
     repo.workdir()
@@ -1655,21 +2145,8 @@ pub fn count_unpushed_commits(repo: &Repository) -> Result<usize> {
         return Ok(0);
     }
 
-    // Try to find upstream branch
-    if let Ok(branch) = repo.find_branch(
-        head.shorthand().unwrap_or("HEAD"),
-        git2::BranchType::Local,
-    ) {
-        if let Ok(upstream) = branch.upstream() {
-            let upstream_oid = upstream.get().target().context("Could not get upstream target")?;
-            let head_oid = head.target().context("Could not get HEAD target")?;
-
-            let mut revwalk = repo.revwalk()?;
-            revwalk.push(head_oid)?;
-            revwalk.hide(upstream_oid)?;
-
-            return Ok(revwalk.count());
-        }
+    if let Some(upstream) = upstream_status(repo)? {
+        return Ok(upstream.ahead);
     }
 
     // No upstream, count all commits
@@ -1680,6 +2157,88 @@ pub fn count_unpushed_commits(repo: &Repository) -> Result<usize> {
     Ok(revwalk.count())
 }
 
+/// How the current branch relates to its upstream
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamStatus {
+    /// Upstream branch, e.g. `origin/main`
+    pub name: String,
+    /// Commits on the branch that the upstream doesn't have
+    pub ahead: usize,
+    /// Commits on the upstream that the branch doesn't have
+    pub behind: usize,
+}
+
+/// Ahead/behind counts against the current branch's upstream, or `None`
+/// when HEAD is unborn, detached or has no upstream
+pub fn upstream_status(repo: &Repository) -> Result<Option<UpstreamStatus>> {
+    let head = match repo.head() {
+        Ok(h) => h,
+        Err(_) => return Ok(None),
+    };
+    let (Some(head_oid), true) = (head.target(), head.is_branch()) else {
+        return Ok(None);
+    };
+
+    let Ok(branch) = repo.find_branch(head.shorthand().unwrap_or("HEAD"), git2::BranchType::Local) else {
+        return Ok(None);
+    };
+    let Ok(upstream) = branch.upstream() else {
+        return Ok(None);
+    };
+
+    let upstream_oid = upstream.get().target().context("Could not get upstream target")?;
+    let (ahead, behind) = repo.graph_ahead_behind(head_oid, upstream_oid)
+        .context("Could not compare the branch with its upstream")?;
+    let name = upstream.name()?.unwrap_or("upstream").to_string();
+
+    Ok(Some(UpstreamStatus { name, ahead, behind }))
+}
+
+/// Working tree files that aren't staged
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkingTreeChanges {
+    /// Tracked files modified, deleted or retyped since they were staged
+    pub unstaged: Vec<String>,
+    /// Files git doesn't track and doesn't ignore
+    pub untracked: Vec<String>,
+}
+
+impl WorkingTreeChanges {
+    pub fn is_empty(&self) -> bool {
+        self.unstaged.is_empty() && self.untracked.is_empty()
+    }
+}
+
+/// List unstaged and untracked files
+pub fn get_working_tree_changes(repo: &Repository) -> Result<WorkingTreeChanges> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+
+    let statuses = repo.statuses(Some(&mut opts))?;
+    let mut changes = WorkingTreeChanges::default();
+    let unstaged = git2::Status::WT_MODIFIED
+        | git2::Status::WT_DELETED
+        | git2::Status::WT_RENAMED
+        | git2::Status::WT_TYPECHANGE;
+
+    for entry in statuses.iter() {
+        let Some(path) = entry.path() else { continue };
+        let status = entry.status();
+        if status.contains(git2::Status::WT_NEW) {
+            changes.untracked.push(path.to_string());
+        } else if status.intersects(unstaged) {
+            changes.unstaged.push(path.to_string());
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Number of stash entries
+pub fn stash_count(repo: &Repository) -> usize {
+    repo.reflog("refs/stash").map(|log| log.len()).unwrap_or(0)
+}
+
 /// Squash the last N commits into one with a new message
 pub fn squash_commits(repo: &Repository, count: usize, message: &str) -> Result<git2::Oid> {
     if count < 2 {
@@ -1690,12 +2249,8 @@ pub fn squash_commits(repo: &Repository, count: usize, message: &str) -> Result<
     let head = repo.head()?;
     let head_commit = head.peel_to_commit()?;
 
-    // Get the parent commit that will be the new parent after squash
-    let mut current = head_commit.clone();
-    for _ in 0..(count - 1) {
-        current = current.parent(0).context("Not enough commits to squash")?;
-    }
-    let base_parent = current.parent(0).context("Cannot squash initial commits")?;
+    // The parent that the squashed commit will sit on
+    let base_parent = squash_base(repo, count)?;
 
     // Get the tree from HEAD (final state after all commits)
     let tree = head_commit.tree()?;
@@ -1710,9 +2265,10 @@ pub fn squash_commits(repo: &Repository, count: usize, message: &str) -> Result<
         &[&base_parent],
     )?;
 
-    // Update HEAD to point to the new commit
+    // Move the branch HEAD points at; writing "HEAD" itself would detach it
+    let head_name = head.name().context("HEAD has a non-UTF-8 name")?;
     repo.reference(
-        "HEAD",
+        head_name,
         commit_id,
         true,
         &format!("squash: {} commits", count),
@@ -1722,24 +2278,66 @@ pub fn squash_commits(repo: &Repository, count: usize, message: &str) -> Result<
     let new_commit = repo.find_commit(commit_id)?;
     repo.reset(new_commit.as_object(), git2::ResetType::Soft, None)?;
 
+    activity::record(repo, Event::Squash { sha: commit_id.to_string(), count });
     Ok(commit_id)
 }
 
-/// Amend the last commit with a new message
-#[allow(dead_code)]
-pub fn amend_last_commit(repo: &Repository, new_message: &str) -> Result<git2::Oid> {
+/// Parent of the oldest of the last `count` commits, which a squash builds on.
+/// Fails when history is too short or one of the commits is a merge, since
+/// squashing would silently drop the merged side.
+pub fn squash_base(repo: &Repository, count: usize) -> Result<git2::Commit<'_>> {
+    let mut current = repo.head()?.peel_to_commit().context("No commits to squash")?;
+    for i in 0..count {
+        if current.parent_count() > 1 {
+            anyhow::bail!(
+                "Cannot squash across merge commit {} ({})",
+                &current.id().to_string()[..7],
+                current.summary().unwrap_or("")
+            );
+        }
+        let Ok(parent) = current.parent(0) else {
+            if i + 1 == count {
+                anyhow::bail!("Cannot squash the initial commit; squash at most {} commits", count - 1);
+            }
+            anyhow::bail!("Only {} commits in history, cannot squash {}", i + 1, count);
+        };
+        current = parent;
+    }
+    Ok(current)
+}
+
+/// Amend the last commit with a new message, folding in anything staged.
+/// The author is kept; the committer becomes the current user.
+pub fn amend_last_commit(repo: &Repository, new_message: &str, sign: bool) -> Result<git2::Oid> {
     let head = repo.head()?;
     let head_commit = head.peel_to_commit()?;
+    let committer = repo.signature()?;
 
-    let commit_id = head_commit.amend(
-        Some("HEAD"),
-        None, // Keep author
-        None, // Keep committer
-        None, // Keep encoding
-        Some(new_message),
-        None, // Keep tree
-    )?;
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let commit_id = if sign {
+        let parents: Vec<git2::Commit> = head_commit.parents().collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        create_signed_commit(repo, &head_commit.author(), &committer, new_message, &tree, &parent_refs)?
+    } else {
+        head_commit.amend(
+            Some("HEAD"),
+            None, // Keep author
+            Some(&committer),
+            None, // Keep encoding
+            Some(new_message),
+            Some(&tree),
+        )?
+    };
 
+    tracing::info!(
+        sha = %commit_id,
+        replaced = %head_commit.id(),
+        signed = sign,
+        subject = new_message.lines().next().unwrap_or(""),
+        "amended commit"
+    );
     Ok(commit_id)
 }
 
@@ -0,0 +1,244 @@
+//! GitHub REST API client for pull requests and reviews.
+//!
+//! The repository is read from the `origin` remote, so commands work in any
+//! clone of a GitHub repository given a token.
+
+use anyhow::{Context, Result};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Default API root
+const API_URL: &str = "https://api.github.com";
+
+/// A non-success response from the API
+#[derive(Debug, thiserror::Error)]
+#[error("GitHub API error ({status}): {message}")]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    /// GitHub rejected the request's content, e.g. a comment on a line outside the diff
+    pub fn is_unprocessable(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<ApiError>().is_some_and(|e| e.status == StatusCode::UNPROCESSABLE_ENTITY)
+    }
+}
+
+/// Pull request creation request
+#[derive(Debug, Serialize)]
+pub struct NewPullRequest {
+    pub title: String,
+    pub body: String,
+    pub head: String,
+    pub base: String,
+    pub draft: bool,
+}
+
+/// Branch end of a pull request
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequestRef {
+    #[serde(rename = "ref")]
+    pub name: String,
+    pub sha: String,
+}
+
+/// Pull request, as returned by the API
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+    #[serde(default)]
+    pub state: String,
+    pub head: PullRequestRef,
+    pub base: PullRequestRef,
+}
+
+/// Pull request review creation request
+#[derive(Debug, Serialize)]
+pub struct NewReview {
+    /// Commit the comments' lines refer to
+    pub commit_id: String,
+    pub body: String,
+    /// `APPROVE`, `REQUEST_CHANGES` or `COMMENT`
+    pub event: &'static str,
+    pub comments: Vec<ReviewComment>,
+}
+
+/// Inline comment on a line of the new version of a file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewComment {
+    pub path: String,
+    pub line: usize,
+    pub side: &'static str,
+    pub body: String,
+}
+
+/// Posted review, as returned by the API
+#[derive(Debug, Deserialize)]
+pub struct PostedReview {
+    pub html_url: String,
+}
+
+/// Client for one repository
+pub struct GitHub {
+    client: reqwest::Client,
+    token: String,
+    api_url: String,
+    pub owner: String,
+    pub repo: String,
+}
+
+impl GitHub {
+    pub fn new(token: &str, owner: String, repo: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            token: token.to_string(),
+            api_url: API_URL.to_string(),
+            owner,
+            repo,
+        }
+    }
+
+    /// Client for the repository behind `origin`
+    pub fn for_repo(token: &str, repo: &git2::Repository) -> Result<Self> {
+        let (owner, name) = repo_info(repo)?;
+        Ok(Self::new(token, owner, name))
+    }
+
+    /// `owner/repo`, for messages
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}/repos/{}/{}{}", self.api_url, self.owner, self.repo, path))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "gitBahn")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+    }
+
+    async fn send<T: DeserializeOwned>(request: reqwest::RequestBuilder, what: &str) -> Result<T> {
+        let response = request.send().await
+            .with_context(|| format!("Failed to send {} request", what))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(ApiError { status, message }.into());
+        }
+
+        response.json().await
+            .with_context(|| format!("Failed to parse {} response", what))
+    }
+
+    /// Open a pull request
+    pub async fn create_pull_request(&self, request: &NewPullRequest) -> Result<PullRequest> {
+        Self::send(self.request(Method::POST, "/pulls").json(request), "PR").await
+    }
+
+    /// The open pull request whose head is `branch` in this repository
+    pub async fn open_pull_request_for_branch(&self, branch: &str) -> Result<Option<PullRequest>> {
+        let head = format!("{}:{}", self.owner, branch);
+        let request = self.request(Method::GET, "/pulls").query(&[("head", head.as_str()), ("state", "open")]);
+        let pulls: Vec<PullRequest> = Self::send(request, "pull request lookup").await?;
+        Ok(pulls.into_iter().next())
+    }
+
+    /// The first open pull request that contains commit `sha`
+    pub async fn open_pull_request_for_commit(&self, sha: &str) -> Result<Option<PullRequest>> {
+        let request = self.request(Method::GET, &format!("/commits/{}/pulls", sha));
+        let pulls: Vec<PullRequest> = match Self::send(request, "pull request lookup").await {
+            Ok(pulls) => pulls,
+            // GitHub doesn't know the commit, e.g. it was never pushed
+            Err(e) if ApiError::is_unprocessable(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(pulls.into_iter().find(|pr| pr.state == "open"))
+    }
+
+    /// Post a review on pull request `number`
+    pub async fn create_review(&self, number: u64, review: &NewReview) -> Result<PostedReview> {
+        let request = self.request(Method::POST, &format!("/pulls/{}/reviews", number)).json(review);
+        Self::send(request, "review").await
+    }
+}
+
+/// Get owner and repo name from the `origin` remote
+pub fn repo_info(repo: &git2::Repository) -> Result<(String, String)> {
+    let remote = repo.find_remote("origin")
+        .context("No 'origin' remote found")?;
+
+    let url = remote.url()
+        .context("Could not get remote URL")?;
+
+    parse_github_url(url)
+}
+
+/// Parse GitHub URL to extract owner and repo
+pub fn parse_github_url(url: &str) -> Result<(String, String)> {
+    // Handle SSH format: git@github.com:owner/repo.git
+    if url.starts_with("git@github.com:") {
+        let path = url.trim_start_matches("git@github.com:");
+        let path = path.trim_end_matches(".git");
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.len() >= 2 {
+            return Ok((parts[0].to_string(), parts[1].to_string()));
+        }
+    }
+
+    // Handle HTTPS format: https://github.com/owner/repo.git
+    if url.contains("github.com") {
+        let path = url
+            .trim_start_matches("https://github.com/")
+            .trim_start_matches("http://github.com/")
+            .trim_end_matches(".git");
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.len() >= 2 {
+            return Ok((parts[0].to_string(), parts[1].to_string()));
+        }
+    }
+
+    anyhow::bail!("Could not parse GitHub repository from URL: {}", url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_url_ssh() {
+        let (owner, repo) = parse_github_url("git@github.com:user/project.git").unwrap();
+        assert_eq!(owner, "user");
+        assert_eq!(repo, "project");
+    }
+
+    #[test]
+    fn test_parse_github_url_https() {
+        let (owner, repo) = parse_github_url("https://github.com/user/project.git").unwrap();
+        assert_eq!(owner, "user");
+        assert_eq!(repo, "project");
+    }
+
+    #[test]
+    fn test_unprocessable_errors() {
+        let error = |status| anyhow::Error::from(ApiError { status, message: "Validation Failed".to_string() });
+        assert!(ApiError::is_unprocessable(&error(StatusCode::UNPROCESSABLE_ENTITY)));
+        assert!(!ApiError::is_unprocessable(&error(StatusCode::NOT_FOUND)));
+        assert!(!ApiError::is_unprocessable(&anyhow::anyhow!("offline")));
+        assert_eq!(error(StatusCode::NOT_FOUND).to_string(), "GitHub API error (404 Not Found): Validation Failed");
+    }
+
+    #[test]
+    fn test_pull_request_parsing() {
+        let json = r#"{"number": 42, "html_url": "https://github.com/user/project/pull/42", "state": "open",
+            "head": {"ref": "feat/x", "sha": "abc"}, "base": {"ref": "main", "sha": "def"}, "title": "X"}"#;
+        let pr: PullRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(pr.number, 42);
+        assert_eq!(pr.head.name, "feat/x");
+        assert_eq!(pr.base.sha, "def");
+    }
+}
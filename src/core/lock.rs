@@ -1,55 +1,189 @@
 //! Lock file management to prevent concurrent bahn instances.
 
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use fs4::FileExt;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
 
-const LOCK_FILE: &str = ".bahn.lock";
+use crate::core::git;
 
-/// A guard that removes the lock file when dropped
+/// Lock file name inside the common gitdir
+const LOCK_FILE: &str = "gitbahn.lock";
+
+/// Lock file older versions wrote to the worktree root
+const LEGACY_LOCK_FILE: &str = ".bahn.lock";
+
+/// A lock file younger than this with no metadata yet is still being acquired
+const ACQUIRE_GRACE: Duration = Duration::from_secs(2);
+
+/// How often to retry while waiting for the lock
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Who holds the lock, written to the lock file as TOML
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub hostname: String,
+    pub user: String,
+    /// The bahn subcommand holding the lock (e.g. "auto")
+    pub command: String,
+    /// When the lock was taken (RFC 3339)
+    pub started_at: String,
+    /// Process start time in clock ticks since boot, to detect PID reuse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_start: Option<u64>,
+}
+
+impl LockInfo {
+    /// Metadata for the current process
+    pub fn current(command: &str) -> Self {
+        let pid = process::id();
+        Self {
+            pid,
+            hostname: hostname(),
+            user: username(),
+            command: command.to_string(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            process_start: process_start_time(pid),
+        }
+    }
+
+    /// Parse lock file content; bare PIDs from older versions are accepted
+    pub fn parse(content: &str) -> Option<Self> {
+        if let Ok(info) = toml::from_str::<Self>(content) {
+            return Some(info);
+        }
+
+        let pid = content.lines().next()?.trim().parse().ok()?;
+        Some(Self {
+            pid,
+            hostname: String::new(),
+            user: String::new(),
+            command: String::new(),
+            started_at: String::new(),
+            process_start: None,
+        })
+    }
+
+    /// Whether the recorded process still appears to be alive
+    pub fn is_alive(&self) -> bool {
+        // PIDs from another machine can't be checked, so assume they're live
+        if !self.hostname.is_empty() && self.hostname != hostname() {
+            return true;
+        }
+        if !is_process_running(self.pid) {
+            return false;
+        }
+        // A running PID with a different start time is a recycled PID
+        match (self.process_start, process_start_time(self.pid)) {
+            (Some(recorded), Some(current)) => recorded == current,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for LockInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  PID:     {}", self.pid)?;
+        if !self.hostname.is_empty() {
+            writeln!(f, "  Host:    {}", self.hostname)?;
+        }
+        if !self.user.is_empty() {
+            writeln!(f, "  User:    {}", self.user)?;
+        }
+        if !self.command.is_empty() {
+            writeln!(f, "  Command: bahn {}", self.command)?;
+        }
+        if !self.started_at.is_empty() {
+            writeln!(f, "  Started: {}", self.started_at)?;
+        }
+        Ok(())
+    }
+}
+
+/// Current state of a repository's lock
+#[derive(Debug)]
+pub enum LockState {
+    /// No lock file
+    Free,
+    /// Held by a live process (or one that can't be checked)
+    Held(Option<LockInfo>),
+    /// Left behind by a process that is gone
+    Stale(Option<LockInfo>),
+}
+
+/// A guard that holds an OS lock on the lock file and removes it when dropped
 pub struct LockGuard {
     path: PathBuf,
+    /// Open handle carrying the advisory lock for the guard's lifetime
+    file: File,
 }
 
 impl LockGuard {
-    /// Acquire a lock for the given repository path
-    pub fn acquire(repo_path: &std::path::Path) -> Result<Self> {
-        let lock_path = repo_path.join(LOCK_FILE);
-
-        // Check if lock file exists
-        if lock_path.exists() {
-            let content = fs::read_to_string(&lock_path)
-                .unwrap_or_default();
-
-            // Try to parse PID
-            if let Some(pid_str) = content.lines().next() {
-                if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                    // Check if process is still running
-                    if is_process_running(pid) {
-                        anyhow::bail!(
-                            "Another bahn instance is already running (PID: {}). \
-                            If this is incorrect, remove {}",
-                            pid,
-                            lock_path.display()
-                        );
+    /// Acquire the repository lock on behalf of `command`.
+    ///
+    /// The lock lives in the common gitdir, so all worktrees of a
+    /// repository contend on the same file.
+    pub fn acquire(repo: &Repository, command: &str) -> Result<Self> {
+        clear_legacy_lock(repo)?;
+        let lock_path = lock_path(repo);
+
+        // Atomic create; on conflict check for a stale lock and retry once
+        for attempt in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+                Ok(mut file) => {
+                    // Other instances only hold it briefly while probing for staleness
+                    file.lock_exclusive()
+                        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+                    let info = toml::to_string(&LockInfo::current(command))?;
+                    file.write_all(info.as_bytes())?;
+                    file.flush()?;
+                    tracing::debug!(path = %lock_path.display(), command, "acquired repository lock");
+                    return Ok(Self { path: lock_path, file });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && attempt == 0 => {
+                    match lock_state(&lock_path) {
+                        LockState::Held(info) => return Err(held_error(&lock_path, info.as_ref())),
+                        LockState::Stale(_) | LockState::Free => {
+                            // Whoever loses a race over the stale lock finds it held on retry
+                            if remove_stale_lock(&lock_path) {
+                                tracing::info!(path = %lock_path.display(), "removed stale repository lock");
+                            }
+                        }
                     }
                 }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => break,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create lock file: {}", lock_path.display()));
+                }
             }
-
-            // Stale lock file, remove it
-            let _ = fs::remove_file(&lock_path);
         }
 
-        // Create lock file with our PID
-        let mut file = File::create(&lock_path)
-            .with_context(|| format!("Failed to create lock file: {}", lock_path.display()))?;
-
-        writeln!(file, "{}", process::id())?;
+        let info = fs::read_to_string(&lock_path).ok().and_then(|c| LockInfo::parse(&c));
+        Err(held_error(&lock_path, info.as_ref()))
+    }
 
-        Ok(Self { path: lock_path })
+    /// Acquire the repository lock, waiting up to `timeout` for its holder to exit.
+    ///
+    /// Polls with the async runtime's timer so waiting doesn't block a worker thread.
+    pub async fn acquire_with_timeout(repo: &Repository, command: &str, timeout: Duration) -> Result<Self> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match Self::acquire(repo, command) {
+                Err(e) if e.is::<LockHeld>() && Instant::now() < deadline => {
+                    tracing::trace!(command, "waiting for repository lock");
+                    tokio::time::sleep(WAIT_POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now()))).await;
+                }
+                result => return result,
+            }
+        }
     }
 
     /// Get the lock file path
@@ -61,11 +195,210 @@ impl LockGuard {
 
 impl Drop for LockGuard {
     fn drop(&mut self) {
-        // Remove lock file on drop
+        // Remove lock file while still holding the OS lock
         let _ = fs::remove_file(&self.path);
+        let _ = FileExt::unlock(&self.file);
+        tracing::debug!(path = %self.path.display(), "released repository lock");
+    }
+}
+
+/// Path of the lock file for a repository
+pub fn lock_path(repo: &Repository) -> PathBuf {
+    git::common_dir(repo).join(LOCK_FILE)
+}
+
+/// Remove a lock left in the worktree root by an older version.
+///
+/// A legacy lock whose holder is still running is respected.
+fn clear_legacy_lock(repo: &Repository) -> Result<()> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(());
+    };
+    let legacy = workdir.join(LEGACY_LOCK_FILE);
+
+    match lock_state(&legacy) {
+        LockState::Free => Ok(()),
+        LockState::Held(info) => Err(held_error(&legacy, info.as_ref())),
+        LockState::Stale(_) => {
+            fs::remove_file(&legacy)
+                .with_context(|| format!("Failed to remove legacy lock file: {}", legacy.display()))
+        }
     }
 }
 
+/// Inspect a lock file without taking it
+pub fn lock_state(lock_path: &Path) -> LockState {
+    let content = match fs::read_to_string(lock_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return LockState::Free,
+        Err(_) => String::new(),
+    };
+    let info = LockInfo::parse(&content);
+
+    // A live holder keeps the OS lock, even if its PID was reused
+    if let Ok(file) = OpenOptions::new().read(true).write(true).open(lock_path) {
+        if file.try_lock_exclusive().is_err() {
+            return LockState::Held(info);
+        }
+        let _ = FileExt::unlock(&file);
+    }
+
+    state_from_content(info, fs::metadata(lock_path).ok())
+}
+
+/// Judge a lock file nobody holds the OS lock on from its content and age
+fn state_from_content(info: Option<LockInfo>, metadata: Option<fs::Metadata>) -> LockState {
+    match info {
+        Some(info) if info.is_alive() => LockState::Held(Some(info)),
+        Some(info) => LockState::Stale(Some(info)),
+        None => {
+            // Freshly created by another process that hasn't written its metadata yet
+            let young = metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age < ACQUIRE_GRACE);
+            if young {
+                LockState::Held(None)
+            } else {
+                LockState::Stale(None)
+            }
+        }
+    }
+}
+
+/// Remove a stale lock file; `false` if it turned out to be taken.
+///
+/// Another instance may replace the stale lock with its own between our
+/// check and the removal, so the file is locked and judged again through
+/// an open handle, and only unlinked if the path still refers to it.
+fn remove_stale_lock(lock_path: &Path) -> bool {
+    let mut file = match OpenOptions::new().read(true).write(true).open(lock_path) {
+        Ok(file) => file,
+        Err(e) => return e.kind() == ErrorKind::NotFound,
+    };
+    if file.try_lock_exclusive().is_err() {
+        return false;
+    }
+
+    let mut content = String::new();
+    let _ = file.read_to_string(&mut content);
+    let stale = !matches!(
+        state_from_content(LockInfo::parse(&content), file.metadata().ok()),
+        LockState::Held(_)
+    );
+    let removed = stale && is_same_file(&file, lock_path) && fs::remove_file(lock_path).is_ok();
+    let _ = FileExt::unlock(&file);
+    removed
+}
+
+/// Whether `path` still names the open `file`
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(named)) => open.dev() == named.dev() && open.ino() == named.ino(),
+        _ => false,
+    }
+}
+
+/// Whether `path` still names the open `file`
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    // Files can't be replaced while open here, so the path still names it
+    path.exists()
+}
+
+/// Remove a repository's lock file.
+///
+/// Stale locks are always removed; a lock that still looks live is only
+/// removed with `force`. Returns the state that was found.
+pub fn release(repo: &Repository, force: bool) -> Result<LockState> {
+    let path = lock_path(repo);
+    let state = lock_state(&path);
+
+    match &state {
+        LockState::Free => {}
+        LockState::Held(info) if !force => {
+            return Err(held_error(&path, info.as_ref()));
+        }
+        LockState::Held(_) | LockState::Stale(_) => {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove lock file: {}", path.display()))?;
+        }
+    }
+
+    Ok(state)
+}
+
+/// The lock is held by another live instance
+#[derive(Debug)]
+pub struct LockHeld {
+    path: PathBuf,
+    info: Option<LockInfo>,
+}
+
+impl fmt::Display for LockHeld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.info {
+            Some(info) => write!(f, "Another bahn instance is already running:\n{}", info)?,
+            None => writeln!(f, "Another bahn instance is acquiring the lock.")?,
+        }
+        write!(
+            f,
+            "If this is incorrect, run `bahn lock release --force` or remove {}",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for LockHeld {}
+
+/// Error describing who holds the lock
+fn held_error(lock_path: &Path, info: Option<&LockInfo>) -> anyhow::Error {
+    anyhow::Error::new(LockHeld {
+        path: lock_path.to_path_buf(),
+        info: info.cloned(),
+    })
+}
+
+/// Name of this machine
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            process::Command::new("hostname")
+                .output()
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        })
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Name of the current user
+fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+/// Process start time in clock ticks since boot (Linux only)
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, so count fields after its closing paren;
+    // starttime is field 22 overall, the 20th after the paren
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    after_comm.split_whitespace().nth(19)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
 /// Check if a process with the given PID is running
 #[cfg(unix)]
 fn is_process_running(pid: u32) -> bool {
@@ -93,3 +426,203 @@ fn is_process_running(pid: u32) -> bool {
         })
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bahn-lock-{}-{}", std::process::id(), rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn temp_repo() -> Repository {
+        Repository::init(temp_dir()).unwrap()
+    }
+
+    #[test]
+    fn test_concurrent_acquire_has_one_winner() {
+        let dir = temp_repo().workdir().unwrap().to_path_buf();
+        let barrier = Arc::new(Barrier::new(2));
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let dir = dir.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let repo = Repository::open(&dir).unwrap();
+                    barrier.wait();
+                    let guard = LockGuard::acquire(&repo, "test");
+                    // Hold the lock until both threads have tried
+                    std::thread::sleep(Duration::from_millis(200));
+                    guard.is_ok()
+                })
+            })
+            .collect();
+
+        let wins = handles.into_iter().map(|h| h.join().unwrap()).filter(|ok| *ok).count();
+        assert_eq!(wins, 1);
+    }
+
+    #[test]
+    fn test_concurrent_takeover_of_stale_lock_has_one_winner() {
+        let dir = temp_repo().workdir().unwrap().to_path_buf();
+        let path = lock_path(&Repository::open(&dir).unwrap());
+
+        for _ in 0..10 {
+            fs::write(&path, "4294967290\n").unwrap();
+            let barrier = Arc::new(Barrier::new(4));
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let dir = dir.clone();
+                    let barrier = barrier.clone();
+                    std::thread::spawn(move || {
+                        let repo = Repository::open(&dir).unwrap();
+                        barrier.wait();
+                        let guard = LockGuard::acquire(&repo, "test");
+                        // Hold the lock until every thread has tried
+                        std::thread::sleep(Duration::from_millis(200));
+                        guard.is_ok()
+                    })
+                })
+                .collect();
+
+            let wins = handles.into_iter().map(|h| h.join().unwrap()).filter(|ok| *ok).count();
+            assert_eq!(wins, 1);
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn test_release_and_stale_cleanup() {
+        let repo = temp_repo();
+
+        let guard = LockGuard::acquire(&repo, "auto").unwrap();
+        let err = LockGuard::acquire(&repo, "commit").err().unwrap().to_string();
+        assert!(err.contains("Command: bahn auto"));
+        assert!(err.contains(&format!("PID:     {}", process::id())));
+        drop(guard);
+        assert!(!lock_path(&repo).exists());
+
+        // A lock left by a dead process (legacy bare-PID format) is taken over
+        fs::write(lock_path(&repo), "4294967290\n").unwrap();
+        let guard = LockGuard::acquire(&repo, "auto").unwrap();
+        let info = LockInfo::parse(&fs::read_to_string(guard.path()).unwrap()).unwrap();
+        assert_eq!(info.pid, process::id());
+        assert_eq!(info.command, "auto");
+    }
+
+    #[test]
+    fn test_acquire_waits_for_release() {
+        let repo = temp_repo();
+        let dir = repo.workdir().unwrap().to_path_buf();
+        let guard = LockGuard::acquire(&repo, "auto").unwrap();
+
+        let waiter = std::thread::spawn(move || {
+            let repo = Repository::open(&dir).unwrap();
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+            runtime.block_on(LockGuard::acquire_with_timeout(&repo, "commit", Duration::from_secs(10))).map(|_| ())
+        });
+
+        std::thread::sleep(Duration::from_millis(300));
+        drop(guard);
+        assert!(waiter.join().unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_wait_timeout_reports_holder() {
+        let repo = temp_repo();
+        let _guard = LockGuard::acquire(&repo, "auto").unwrap();
+
+        let start = Instant::now();
+        let err = LockGuard::acquire_with_timeout(&repo, "commit", Duration::from_millis(300)).await.err().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert!(err.is::<LockHeld>());
+        assert!(err.to_string().contains("Command: bahn auto"));
+    }
+
+    #[test]
+    fn test_lock_lives_in_gitdir() {
+        let repo = temp_repo();
+        assert_eq!(lock_path(&repo), repo.path().join("gitbahn.lock"));
+
+        let _guard = LockGuard::acquire(&repo, "auto").unwrap();
+        assert!(repo.path().join("gitbahn.lock").exists());
+        assert!(!repo.workdir().unwrap().join(LEGACY_LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn test_stale_legacy_lock_is_removed() {
+        let repo = temp_repo();
+        let legacy = repo.workdir().unwrap().join(LEGACY_LOCK_FILE);
+        fs::write(&legacy, "4294967290\n").unwrap();
+
+        let _guard = LockGuard::acquire(&repo, "auto").unwrap();
+        assert!(!legacy.exists());
+    }
+
+    #[test]
+    fn test_worktrees_share_one_lock() {
+        let repo = temp_repo();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[]).unwrap();
+
+        let worktree_dir = temp_dir().join("feature");
+        let worktree = repo.worktree("feature", &worktree_dir, None).unwrap();
+        let linked = Repository::open_from_worktree(&worktree).unwrap();
+        assert_eq!(lock_path(&linked), lock_path(&repo));
+
+        let _guard = LockGuard::acquire(&repo, "auto").unwrap();
+        let err = LockGuard::acquire(&linked, "commit").err().unwrap().to_string();
+        assert!(err.contains("Another bahn instance is already running"));
+    }
+
+    #[test]
+    fn test_lock_info_roundtrip() {
+        let info = LockInfo {
+            pid: 4242,
+            hostname: "build-01".to_string(),
+            user: "ci".to_string(),
+            command: "auto".to_string(),
+            started_at: "2026-01-02T03:04:05+00:00".to_string(),
+            process_start: Some(123456),
+        };
+        let serialized = toml::to_string(&info).unwrap();
+        assert!(serialized.contains("hostname = \"build-01\""));
+        assert_eq!(LockInfo::parse(&serialized), Some(info));
+
+        assert_eq!(LockInfo::parse("31337\n").unwrap().pid, 31337);
+        assert!(LockInfo::parse("garbage").is_none());
+    }
+
+    #[test]
+    fn test_force_release() {
+        let repo = temp_repo();
+
+        // Looks live (our own PID) but no OS lock is held on it
+        let info = LockInfo::current("auto");
+        fs::write(lock_path(&repo), toml::to_string(&info).unwrap()).unwrap();
+
+        assert!(matches!(lock_state(&lock_path(&repo)), LockState::Held(Some(_))));
+        assert!(release(&repo, false).is_err());
+        assert!(lock_path(&repo).exists());
+
+        assert!(matches!(release(&repo, true).unwrap(), LockState::Held(Some(_))));
+        assert!(!lock_path(&repo).exists());
+        assert!(matches!(release(&repo, false).unwrap(), LockState::Free));
+    }
+
+    #[test]
+    fn test_recycled_pid_is_stale() {
+        let mut info = LockInfo::current("auto");
+        if info.process_start.is_some() {
+            info.process_start = Some(info.process_start.unwrap() + 1);
+            assert!(!info.is_alive());
+        }
+        info.hostname = "some-other-host".to_string();
+        assert!(info.is_alive());
+    }
+}
@@ -0,0 +1,213 @@
+//! Structured logging via `tracing`.
+//!
+//! Events go to stderr in a compact human format and, with `--log-file`,
+//! to a file as JSON lines. `BAHN_LOG` takes an env-filter directive
+//! (e.g. `BAHN_LOG=debug`) and overrides the `-v` level.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::{self, MakeWriter};
+use tracing_subscriber::layer::Layer;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Env var holding the log filter directive
+pub const LOG_ENV: &str = "BAHN_LOG";
+
+/// Filter directive for a `-v` count: warnings by default, then info, then debug
+pub fn default_directive(verbosity: u8) -> String {
+    let level = match verbosity {
+        0 => "warn",
+        1 => "info",
+        _ => "debug",
+    };
+    format!("warn,{}={}", env!("CARGO_CRATE_NAME"), level)
+}
+
+/// Build the event filter; a `BAHN_LOG` directive wins over the verbosity
+pub fn filter(verbosity: u8, env: Option<&str>) -> Result<EnvFilter> {
+    match env.filter(|d| !d.trim().is_empty()) {
+        Some(directive) => EnvFilter::try_new(directive)
+            .with_context(|| format!("Invalid {} filter: {}", LOG_ENV, directive)),
+        None => Ok(EnvFilter::new(default_directive(verbosity))),
+    }
+}
+
+/// JSON-lines layer used for `--log-file`
+pub fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(writer)
+}
+
+/// Install the global subscriber for this run
+pub fn init(verbosity: u8, log_file: Option<&Path>) -> Result<()> {
+    let filter = filter(verbosity, std::env::var(LOG_ENV).ok().as_deref())?;
+
+    let file = log_file
+        .map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file: {}", path.display()))
+        })
+        .transpose()?;
+
+    let stderr = fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .without_time()
+        .compact();
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr)
+        .with(file.map(|f| json_layer(Mutex::new(f))))
+        .try_init()
+        .context("Failed to initialize logging")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ai::AiClient;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    const API_KEY: &str = "sk-ant-REDACTED";
+
+    /// Cloneable in-memory writer for capturing subscriber output
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Capture {
+        fn lines(&self) -> Vec<serde_json::Value> {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    /// Read one HTTP request, including its body, from `stream`
+    fn read_request(stream: &mut std::net::TcpStream) {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                return;
+            }
+            data.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&data);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end].lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if data.len() >= end + 4 + length {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Answer successive connections with `responses`, then stop
+    fn mock_api(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let Ok((mut stream, _)) = listener.accept() else { return };
+                read_request(&mut stream);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        format!("http://{}/v1/messages", addr)
+    }
+
+    #[test]
+    fn test_bahn_log_overrides_verbosity() {
+        let crate_name = env!("CARGO_CRATE_NAME");
+        assert_eq!(default_directive(0), format!("warn,{}=warn", crate_name));
+        assert_eq!(default_directive(1), format!("warn,{}=info", crate_name));
+        assert_eq!(default_directive(3), format!("warn,{}=debug", crate_name));
+
+        assert_eq!(filter(1, Some("trace")).unwrap().to_string(), "trace");
+        assert_eq!(filter(2, Some("  ")).unwrap().to_string(), EnvFilter::new(default_directive(2)).to_string());
+        assert!(filter(0, Some("bahn=notalevel")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ai_retry_is_logged_without_api_key() {
+        let url = mock_api(vec![
+            ("503 Service Unavailable", r#"{"error":"overloaded"}"#),
+            ("200 OK", r#"{"content":[{"type":"text","text":"Fix retry logging"}],"usage":{"input_tokens":120,"output_tokens":8}}"#),
+        ]);
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(EnvFilter::new("debug"))
+            .with(json_layer(move || writer.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let ai = AiClient::new(API_KEY.to_string(), Some("claude-test".to_string())).with_api_url(url);
+        let message = ai.generate_commit_message("+fn main() {}\n", None, None, None).await.unwrap();
+        assert!(message.contains("Fix retry logging"));
+
+        let events = capture.lines();
+        let find = |msg: &str| events.iter()
+            .find(|e| e["fields"]["message"] == msg)
+            .unwrap_or_else(|| panic!("no '{}' event in {:?}", msg, events));
+
+        let retry = find("retrying API request");
+        assert_eq!(retry["level"], "WARN");
+        assert_eq!(retry["fields"]["attempt"], 2);
+        assert!(retry["fields"]["error"].as_str().unwrap().contains("503"));
+
+        let done = find("API request completed");
+        assert_eq!(done["fields"]["status"], 200);
+        assert_eq!(done["fields"]["input_tokens"], 120);
+        assert_eq!(done["fields"]["output_tokens"], 8);
+        assert!(done["fields"]["latency_ms"].is_u64());
+        assert_eq!(done["span"]["name"], "ai_request");
+        assert_eq!(done["span"]["model"], "claude-test");
+        assert_eq!(done["span"]["api_key"], "sk-ant-…6789");
+
+        let raw = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(!raw.contains(API_KEY));
+        assert!(!raw.contains("very-secret"));
+    }
+}
@@ -0,0 +1,371 @@
+//! Commit message checks for the commit-msg hook, Conventional Commits parsing,
+//! and `commit.template` rendering.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Longest subject line we accept
+const MAX_SUBJECT_LEN: usize = 72;
+
+/// Column body lines of AI-written messages are wrapped at
+const BODY_WIDTH: usize = 100;
+
+/// Conventional Commits types
+const TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// Subjects git or tooling generate, which don't follow the conventions
+const GENERATED_PREFIXES: &[&str] = &["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+/// Verbs whose `-s`/`-es` form gives away a non-imperative description ("adds", "fixes")
+const COMMON_VERBS: &[&str] = &[
+    "add", "allow", "bump", "change", "clean", "create", "delete", "drop", "fix", "handle",
+    "implement", "improve", "introduce", "make", "move", "refactor", "remove", "rename",
+    "replace", "support", "update", "use",
+];
+
+/// Imperative verbs that happen to end in "ed" or "ing"
+const IMPERATIVE_EXCEPTIONS: &[&str] = &[
+    "bleed", "breed", "embed", "exceed", "feed", "need", "proceed", "seed", "shed", "speed",
+    "succeed", "string",
+];
+
+/// `{{name}}` in a `commit.template`
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap());
+
+/// A ticket key like `ABC-123`, as found in branch names
+static TICKET: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|[^A-Za-z0-9])([A-Z][A-Z0-9]+-[0-9]+)(?:$|[^0-9])").unwrap());
+
+static CONVENTIONAL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()\s][^()]*)\))?(?P<breaking>!)?: (?P<description>\S.*)").unwrap()
+});
+
+/// The parts of a Conventional Commits message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conventional<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    /// `!` after the type, or a `BREAKING CHANGE:` footer
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+/// Parse `message` as a Conventional Commit with one of the known types
+pub fn parse_conventional(message: &str) -> Option<Conventional<'_>> {
+    let subject = message.lines().next()?;
+    let caps = CONVENTIONAL.captures(subject)?;
+    let kind = caps.name("type")?.as_str();
+    if !TYPES.contains(&kind) {
+        return None;
+    }
+    let footer_breaking = message.lines().skip(1)
+        .any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+    Some(Conventional {
+        kind,
+        scope: caps.name("scope").map(|m| m.as_str()),
+        breaking: caps.name("breaking").is_some() || footer_breaking,
+        description: caps.name("description")?.as_str().trim_end(),
+    })
+}
+
+/// Drop git's `#` comment lines and everything below the scissors line
+pub fn strip_comments(message: &str) -> String {
+    message
+        .lines()
+        .take_while(|line| !line.starts_with("# ------------------------ >8 ------------------------"))
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Problems with a commit message (already stripped of comments); empty when it passes
+pub fn validate(message: &str, conventional: bool) -> Vec<String> {
+    let mut lines = message.lines();
+    let Some(subject) = lines.next().filter(|s| !s.trim().is_empty()) else {
+        return vec!["Commit message is empty".to_string()];
+    };
+
+    let mut problems = Vec::new();
+
+    let length = subject.chars().count();
+    if length > MAX_SUBJECT_LEN {
+        problems.push(format!("Subject is {} characters; keep it to {}", length, MAX_SUBJECT_LEN));
+    }
+
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        problems.push("Separate the subject from the body with a blank line".to_string());
+    }
+
+    if conventional && !GENERATED_PREFIXES.iter().any(|p| subject.starts_with(p)) {
+        match CONVENTIONAL.captures(subject) {
+            Some(caps) if TYPES.contains(&&caps["type"]) => {
+                if let Some(word) = non_imperative(&caps["description"]) {
+                    problems.push(format!("Start the description with an imperative verb instead of \"{}\"", word));
+                }
+                if caps["description"].ends_with('.') {
+                    problems.push("Don't end the subject with a period".to_string());
+                }
+            }
+            Some(caps) => problems.push(format!(
+                "Unknown type '{}'; use one of {}",
+                &caps["type"],
+                TYPES.join(", ")
+            )),
+            None => problems.push("Subject should follow Conventional Commits: type(scope): description".to_string()),
+        }
+    }
+
+    problems
+}
+
+/// The description's first word, if it reads as past tense, a gerund or
+/// third person ("added", "adding", "adds") rather than imperative
+fn non_imperative(description: &str) -> Option<&str> {
+    let word = description.split_whitespace().next()?;
+    let lower = word.to_lowercase();
+    if IMPERATIVE_EXCEPTIONS.contains(&lower.as_str()) {
+        return None;
+    }
+    let past = lower.len() > 4 && lower.ends_with("ed");
+    let gerund = lower.len() > 5 && lower.ends_with("ing");
+    let third_person = [lower.strip_suffix("es"), lower.strip_suffix('s')]
+        .into_iter()
+        .flatten()
+        .any(|stem| COMMON_VERBS.contains(&stem));
+    (past || gerund || third_person).then_some(word)
+}
+
+/// Mechanical fixes for an AI-written Conventional Commit: a lowercase type,
+/// no trailing period on the subject, a blank line before the body and body
+/// lines wrapped at [`BODY_WIDTH`]. Anything else is left for [`validate`].
+pub fn fix_conventional(message: &str) -> String {
+    let mut lines = message.trim().lines();
+    let Some(subject) = lines.next() else {
+        return String::new();
+    };
+
+    let mut subject = subject.trim_end().trim_end_matches('.').to_string();
+    let kind = CONVENTIONAL.captures(&subject)
+        .and_then(|caps| caps.name("type").map(|m| (m.range(), m.as_str().to_lowercase())));
+    if let Some((range, lower)) = kind.filter(|(_, lower)| TYPES.contains(&lower.as_str())) {
+        subject.replace_range(range, &lower);
+    }
+
+    let body: Vec<&str> = lines.skip_while(|line| line.trim().is_empty()).collect();
+    if body.is_empty() {
+        return subject;
+    }
+    let body: Vec<String> = body.iter().flat_map(|line| wrap_line(line, BODY_WIDTH)).collect();
+    format!("{}\n\n{}", subject, body.join("\n"))
+}
+
+/// `line` broken at spaces to fit `width` columns. Continuation lines keep the
+/// indentation, lined up under the text of `-`/`*` bullets; a single word
+/// longer than `width` (a URL, say) stays whole.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let line = line.trim_end();
+    if line.chars().count() <= width {
+        return vec![line.to_string()];
+    }
+
+    let text = line.trim_start();
+    let indent = &line[..line.len() - text.len()];
+    let hanging = if text.starts_with("- ") || text.starts_with("* ") {
+        format!("{}  ", indent)
+    } else {
+        indent.to_string()
+    };
+
+    let mut wrapped = Vec::new();
+    let mut current = indent.to_string();
+    for word in text.split_whitespace() {
+        let fresh = current.trim().is_empty();
+        if !fresh && current.chars().count() + 1 + word.chars().count() > width {
+            wrapped.push(std::mem::replace(&mut current, hanging.clone()));
+        }
+        if !current.trim().is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    wrapped.push(current);
+    wrapped
+}
+
+/// The ticket key in a branch name, e.g. `ABC-123` in `feature/ABC-123-login`
+pub fn ticket_from_branch(branch: &str) -> Option<String> {
+    TICKET.captures(branch).map(|caps| caps[1].to_string())
+}
+
+/// Fill a `commit.template` from `values`. Unknown or empty placeholders
+/// render empty, taking the whitespace around them (and a `()` left around an
+/// empty scope) along; a line whose placeholders were all empty and that is
+/// left blank or as a bare `Label:` is dropped, and blank lines don't pile up.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    let value = |name: &str| {
+        values.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.trim())
+            .unwrap_or("")
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in template.lines() {
+        let mut out = String::new();
+        let mut rest = line;
+        let (mut filled, mut placeholders) = (false, false);
+        while let Some(caps) = PLACEHOLDER.captures(rest) {
+            let found = caps.get(0).unwrap();
+            placeholders = true;
+            out.push_str(&rest[..found.start()]);
+            rest = &rest[found.end()..];
+
+            let text = value(&caps[1]);
+            if !text.is_empty() {
+                filled = true;
+                out.push_str(text);
+                continue;
+            }
+            if out.ends_with('(') && rest.starts_with(')') {
+                out.pop();
+                rest = &rest[1..];
+            }
+            let spaced = out.ends_with([' ', '\t']) && rest.starts_with([' ', '\t']);
+            out.truncate(out.trim_end_matches([' ', '\t']).len());
+            rest = rest.trim_start_matches([' ', '\t']);
+            if spaced && !out.is_empty() && !rest.is_empty() {
+                out.push(' ');
+            }
+        }
+        out.push_str(rest);
+
+        let out = out.trim_end();
+        if placeholders && !filled && (out.trim().is_empty() || out.ends_with(':')) {
+            continue;
+        }
+        if out.is_empty() && lines.last().is_some_and(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(out.to_string());
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let template = "{{type}}({{scope}}): {{subject}}\n\n{{body}}\n\nRefs: {{ticket}}";
+        let all = [("type", "feat"), ("scope", "auth"), ("subject", "add login"), ("body", "Uses sessions.\nNo JWT."), ("ticket", "ABC-123")];
+        assert_eq!(render_template(template, &all), "feat(auth): add login\n\nUses sessions.\nNo JWT.\n\nRefs: ABC-123");
+
+        let sparse = [("type", "fix"), ("subject", "handle empty input")];
+        assert_eq!(render_template(template, &sparse), "fix: handle empty input");
+        assert_eq!(render_template("{{ type }}: {{unknown}} {{subject}} [{{ticket}}]", &sparse), "fix: handle empty input []");
+        assert_eq!(render_template("[{{ticket}}] {{subject}}", &sparse), "[] handle empty input");
+    }
+
+    #[test]
+    fn test_ticket_from_branch() {
+        assert_eq!(ticket_from_branch("feature/ABC-123-login").as_deref(), Some("ABC-123"));
+        assert_eq!(ticket_from_branch("PROJ2-7").as_deref(), Some("PROJ2-7"));
+        assert_eq!(ticket_from_branch("fix/utf-8-names"), None);
+        assert_eq!(ticket_from_branch("main"), None);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let message = "feat: add x\n\nBody line  \n# Please enter the commit message\n# ------------------------ >8 ------------------------\ndiff --git a/x b/x\n";
+        assert_eq!(strip_comments(message), "feat: add x\n\nBody line");
+        assert_eq!(strip_comments("# only comments\n\n"), "");
+    }
+
+    #[test]
+    fn test_valid_messages() {
+        for message in [
+            "feat: add parser",
+            "fix(core): handle empty diff",
+            "refactor(api)!: drop v1 endpoints\n\nBREAKING CHANGE: v1 is gone",
+            "Merge branch 'main' into feature",
+            "fixup! feat: add parser",
+        ] {
+            assert!(validate(message, true).is_empty(), "{}", message);
+        }
+        assert!(validate("Add parser", false).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        assert_eq!(validate("", true), vec!["Commit message is empty"]);
+        assert_eq!(
+            validate("Add parser", true),
+            vec!["Subject should follow Conventional Commits: type(scope): description"]
+        );
+        assert!(validate("feature: add parser", true)[0].starts_with("Unknown type 'feature'"));
+        assert!(!validate("feat:add parser", true).is_empty());
+        assert_eq!(
+            validate("feat: add parser\nbody right away", true),
+            vec!["Separate the subject from the body with a blank line"]
+        );
+
+        let long = format!("feat: {}", "x".repeat(80));
+        assert_eq!(validate(&long, true), vec!["Subject is 86 characters; keep it to 72"]);
+    }
+
+    #[test]
+    fn test_conventional_table() {
+        let table: &[(&str, &[&str])] = &[
+            ("feat(parser): accept trailing commas", &[]),
+            ("fix: embed the font files", &[]),
+            ("perf!: drop the string cache", &[]),
+            ("feat: added parser", &["Start the description with an imperative verb instead of \"added\""]),
+            ("fix: fixes the empty diff crash", &["Start the description with an imperative verb instead of \"fixes\""]),
+            ("refactor: moving helpers to util", &["Start the description with an imperative verb instead of \"moving\""]),
+            ("docs: add usage notes.", &["Don't end the subject with a period"]),
+            ("Feat: add parser", &["Unknown type 'Feat'; use one of feat, fix, docs, style, refactor, perf, test, build, ci, chore, revert"]),
+            ("add parser", &["Subject should follow Conventional Commits: type(scope): description"]),
+        ];
+        for (message, expected) in table {
+            assert_eq!(validate(message, true), *expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_fix_conventional() {
+        assert_eq!(fix_conventional("Feat(cli): add --json output.\n"), "feat(cli): add --json output");
+        assert_eq!(fix_conventional("FIX: handle tabs\nBody right away"), "fix: handle tabs\n\nBody right away");
+        // Unknown types aren't guessed at
+        assert_eq!(fix_conventional("Feature: add parser"), "Feature: add parser");
+
+        let body = format!("- {}\nhttps://example.com/{}", "word ".repeat(30).trim_end(), "x".repeat(110));
+        let fixed = fix_conventional(&format!("feat: add parser\n\n\n{}", body));
+        let lines: Vec<&str> = fixed.lines().collect();
+        assert_eq!(lines[1], "");
+        assert!(lines[2].starts_with("- word") && lines[2].len() <= BODY_WIDTH);
+        assert!(lines[3].starts_with("  word"));
+        assert_eq!(lines[4].len(), "https://example.com/".len() + 110);
+        assert!(validate(&fixed, true).is_empty());
+        assert!(validate(&fix_conventional("Docs: update readme."), true).is_empty());
+    }
+
+    #[test]
+    fn test_parse_conventional() {
+        let parsed = parse_conventional("fix(core): handle empty diff").unwrap();
+        assert_eq!((parsed.kind, parsed.scope, parsed.breaking, parsed.description), ("fix", Some("core"), false, "handle empty diff"));
+
+        assert!(parse_conventional("refactor(api)!: drop v1 endpoints").unwrap().breaking);
+        let footer = parse_conventional("feat: new config format\n\nBREAKING CHANGE: old keys are ignored").unwrap();
+        assert!(footer.breaking && footer.scope.is_none());
+
+        for message in ["Add parser", "feature: add parser", "feat:add parser", "Merge branch 'main'", ""] {
+            assert!(parse_conventional(message).is_none(), "{}", message);
+        }
+    }
+}
@@ -1,8 +1,23 @@
+pub mod activity;
 pub mod git;
 pub mod ai;
+pub mod agents;
+pub mod annotations;
+pub mod batch;
+pub mod cache;
+pub mod credentials;
+pub mod diff;
+pub mod embeddings;
+pub mod github;
 pub mod watcher;
 pub mod lock;
+pub mod logging;
+pub mod message;
+pub mod prompt;
+pub mod report;
+pub mod review_report;
 pub mod secrets;
+pub mod secrets_report;
 
 #[cfg(test)]
 mod tests;
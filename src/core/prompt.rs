@@ -0,0 +1,115 @@
+//! Guards for interactive prompts when there is no terminal.
+//!
+//! dialoguer fails or hangs without a TTY (CI, git hooks, pipes), so
+//! interactive flows decide up front whether to prompt, take the `--yes`
+//! path, or stop with a clear error.
+
+use std::io::IsTerminal;
+
+use anyhow::Result;
+
+/// Env var that makes non-interactive runs behave as if `--yes` was passed
+pub const ASSUME_YES_ENV: &str = "BAHN_ASSUME_YES";
+
+/// How an interactive flow should handle its prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    /// A terminal is attached; ask as usual
+    Prompt,
+    /// Skip prompts and take the `--yes` path
+    AssumeYes,
+}
+
+/// Whether stdin and stdout are both attached to a terminal
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// Whether `BAHN_ASSUME_YES` is set to a true value
+pub fn assume_yes_env() -> bool {
+    is_truthy(std::env::var(ASSUME_YES_ENV).ok().as_deref())
+}
+
+fn is_truthy(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("1" | "true" | "yes" | "on")
+    )
+}
+
+/// Decide how to handle prompts.
+///
+/// `--yes` always wins; with a terminal we prompt; without one we assume yes
+/// only if configured to, and otherwise refuse rather than hang.
+pub fn decide(interactive: bool, yes: bool, assume_yes: bool) -> Result<Interaction> {
+    if yes {
+        Ok(Interaction::AssumeYes)
+    } else if interactive {
+        Ok(Interaction::Prompt)
+    } else if assume_yes {
+        Ok(Interaction::AssumeYes)
+    } else {
+        Err(non_interactive_error("Pass -y"))
+    }
+}
+
+/// Fail unless a terminal is attached, for prompts with no `--yes` path.
+/// `alternative` tells the user what to do instead, e.g. "Set ANTHROPIC_API_KEY"
+pub fn require_terminal(interactive: bool, alternative: &str) -> Result<()> {
+    if interactive {
+        Ok(())
+    } else {
+        Err(non_interactive_error(alternative))
+    }
+}
+
+fn non_interactive_error(alternative: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Interactive input required, but no terminal is attached. {} or run in a terminal.",
+        alternative
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yes_flag_skips_prompts_everywhere() {
+        assert_eq!(decide(true, true, false).unwrap(), Interaction::AssumeYes);
+        assert_eq!(decide(false, true, false).unwrap(), Interaction::AssumeYes);
+    }
+
+    #[test]
+    fn test_terminal_prompts_even_with_assume_yes() {
+        assert_eq!(decide(true, false, false).unwrap(), Interaction::Prompt);
+        assert_eq!(decide(true, false, true).unwrap(), Interaction::Prompt);
+    }
+
+    #[test]
+    fn test_non_tty_uses_assume_yes_or_fails() {
+        assert_eq!(decide(false, false, true).unwrap(), Interaction::AssumeYes);
+
+        let err = decide(false, false, false).unwrap_err().to_string();
+        assert!(err.contains("Interactive input required"));
+        assert!(err.contains("Pass -y"));
+    }
+
+    #[test]
+    fn test_require_terminal() {
+        assert!(require_terminal(true, "Set ANTHROPIC_API_KEY").is_ok());
+        let err = require_terminal(false, "Set ANTHROPIC_API_KEY").unwrap_err().to_string();
+        assert!(err.ends_with("Set ANTHROPIC_API_KEY or run in a terminal."));
+    }
+
+    #[test]
+    fn test_assume_yes_values() {
+        for value in ["1", "true", "YES", " on "] {
+            assert!(is_truthy(Some(value)), "{}", value);
+        }
+        for value in ["0", "false", "", "maybe"] {
+            assert!(!is_truthy(Some(value)), "{}", value);
+        }
+        assert!(!is_truthy(None));
+    }
+}
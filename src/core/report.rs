@@ -0,0 +1,598 @@
+//! Machine-readable command results for `--json`.
+//!
+//! In JSON mode each command prints exactly one JSON document on stdout and
+//! everything meant for humans goes to stderr via [`say!`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use colored::Colorize;
+use git2::{Oid, Repository, Sort};
+use serde::Serialize;
+
+use crate::core::ai::UsageStats;
+use crate::core::git::{StagedChanges, UpstreamStatus};
+use crate::core::secrets::SecretMatch;
+
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Switch JSON output on or off for this run
+pub fn set_json(enabled: bool) {
+    JSON_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--json` was passed
+pub fn is_json() -> bool {
+    JSON_MODE.load(Ordering::Relaxed)
+}
+
+/// `println!` for human-facing output: stdout normally, stderr in JSON mode
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::core::report::is_json() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use say;
+
+/// Print a command result as the JSON document on stdout
+pub fn emit<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Print a failed command as `{"error": "..."}` on stdout
+pub fn emit_error(err: &anyhow::Error) {
+    let report = ErrorReport {
+        error: err.to_string(),
+        causes: err.chain().skip(1).map(|cause| cause.to_string()).collect(),
+    };
+    if let Ok(json) = serde_json::to_string_pretty(&report) {
+        println!("{}", json);
+    }
+}
+
+/// Print the AI usage line that ends a command, if it made any requests
+pub fn say_usage(usage: &UsageStats) {
+    if usage.requests > 0 {
+        say!("{}", format!("AI usage: {}", usage.summary()).dimmed());
+    }
+}
+
+/// Error for a prompt that can't be answered in JSON mode
+pub fn confirmation_required(action: &str, flag: &str) -> anyhow::Error {
+    anyhow::anyhow!("{} needs confirmation, which --json can't prompt for. Pass {} to proceed.", action, flag)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub causes: Vec<String>,
+}
+
+/// `bahn status`
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub repository: String,
+    pub branch: String,
+    /// `null` when the branch has no upstream
+    pub upstream: Option<UpstreamReport>,
+    pub staged: StagedReport,
+    pub unstaged: Vec<String>,
+    pub untracked: Vec<String>,
+    pub uncommitted_changes: bool,
+    pub stashes: usize,
+    pub recent_commits: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamReport {
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl From<&UpstreamStatus> for UpstreamReport {
+    fn from(upstream: &UpstreamStatus) -> Self {
+        Self {
+            name: upstream.name.clone(),
+            ahead: upstream.ahead,
+            behind: upstream.behind,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StagedReport {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    pub renamed: Vec<RenamedFile>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+}
+
+impl From<&StagedChanges> for StagedReport {
+    fn from(changes: &StagedChanges) -> Self {
+        Self {
+            added: changes.added.clone(),
+            modified: changes.modified.clone(),
+            deleted: changes.deleted.clone(),
+            renamed: changes.renamed.iter()
+                .map(|(from, to)| RenamedFile { from: from.clone(), to: to.clone() })
+                .collect(),
+            insertions: changes.stats.insertions,
+            deletions: changes.stats.deletions,
+        }
+    }
+}
+
+/// `bahn commit`
+#[derive(Debug, Serialize)]
+pub struct CommitReport {
+    pub branch: String,
+    pub commits: Vec<CommitEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitEntry {
+    pub sha: String,
+    pub message: String,
+    pub files: Vec<String>,
+}
+
+impl CommitReport {
+    /// Commits reachable from HEAD but not from `before`, oldest first
+    pub fn since(repo: &Repository, branch: &str, before: Option<Oid>) -> Result<Self> {
+        let mut walk = repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        if repo.head().is_ok() {
+            walk.push_head()?;
+        }
+        if let Some(before) = before {
+            walk.hide(before)?;
+        }
+
+        let mut commits = Vec::new();
+        for oid in walk {
+            let commit = repo.find_commit(oid?)?;
+            commits.push(CommitEntry {
+                sha: commit.id().to_string(),
+                message: commit.message().unwrap_or("").trim_end().to_string(),
+                files: changed_files(repo, &commit)?,
+            });
+        }
+
+        Ok(Self { branch: branch.to_string(), commits })
+    }
+}
+
+/// Paths touched by a commit relative to its first parent
+fn changed_files(repo: &Repository, commit: &git2::Commit) -> Result<Vec<String>> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+
+    Ok(diff.deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// `bahn undo`
+#[derive(Debug, Serialize)]
+pub struct UndoReport {
+    pub undone: Vec<String>,
+    pub hard: bool,
+    pub head: Option<String>,
+}
+
+/// `bahn undo --preview`
+#[derive(Debug, Serialize)]
+pub struct UndoPreviewReport {
+    pub commits: Vec<String>,
+    pub unpushed: usize,
+}
+
+/// `bahn squash`
+#[derive(Debug, Serialize)]
+pub struct SquashReport {
+    /// Messages of the commits that were combined, newest first
+    pub squashed: Vec<String>,
+    pub message: String,
+    pub head: String,
+}
+
+/// `bahn changelog`
+#[derive(Debug, Serialize)]
+pub struct ChangelogReport {
+    pub heading: String,
+    /// Tag or ref the commits were listed from; `None` for the whole history
+    pub since: Option<String>,
+    pub commits: usize,
+    /// `None` when there were no commits to describe
+    pub section: Option<String>,
+    /// Path of the changelog file, when `--write` was given
+    pub written: Option<String>,
+}
+
+/// `bahn push`
+#[derive(Debug, Serialize)]
+pub struct PushReport {
+    pub remote: String,
+    pub branch: String,
+    pub pushed_refs: Vec<String>,
+    pub forced: bool,
+    pub pr_url: Option<String>,
+}
+
+/// `bahn secrets baseline`
+#[derive(Debug, Serialize)]
+pub struct BaselineReport {
+    pub file: String,
+    pub added: usize,
+    pub total: usize,
+}
+
+/// Outcome of one `bahn doctor` check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    /// What to do about a warning or failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    pub fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Pass, message: message.into(), hint: None }
+    }
+
+    pub fn warn(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Warn, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    pub fn fail(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Fail, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    pub fn skip(name: &'static str, message: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Skip, message: message.into(), hint: None }
+    }
+}
+
+/// `bahn doctor`
+#[derive(Debug, Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+    pub warnings: usize,
+    pub failures: usize,
+}
+
+impl DoctorReport {
+    pub fn new(checks: Vec<DoctorCheck>) -> Self {
+        let count = |status| checks.iter().filter(|c| c.status == status).count();
+        let (warnings, failures) = (count(CheckStatus::Warn), count(CheckStatus::Fail));
+        Self { checks, warnings, failures }
+    }
+}
+
+/// `bahn hook run`
+#[derive(Debug, Serialize)]
+pub struct HookReport {
+    pub stage: String,
+    pub passed: bool,
+    pub problems: Vec<String>,
+    /// Reported secret findings (pre-commit, pre-push)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretMatch>,
+}
+
+/// `bahn stats`
+#[derive(Debug, Serialize)]
+pub struct StatsReport {
+    /// Start of the window (RFC 3339), or `None` for all history
+    pub since: Option<String>,
+    pub commits: CommitStats,
+    pub sessions: Vec<SessionStats>,
+    pub squashes: usize,
+    pub squashed_commits: usize,
+    pub secrets_blocked: usize,
+    /// Whether bahn has logged any activity here; without it every commit counts as manual
+    pub activity_recorded: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CommitStats {
+    pub total: usize,
+    pub by_bahn: usize,
+    pub manual: usize,
+    pub avg_files: f64,
+    pub avg_lines: f64,
+}
+
+/// One `bahn auto` run
+#[derive(Debug, Serialize)]
+pub struct SessionStats {
+    pub id: String,
+    pub started: String,
+    pub ended: String,
+    pub commits: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ai::{CodeReview, ReviewIssue};
+    use crate::core::git::DiffStats;
+    use serde_json::json;
+
+    fn to_json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_status_report_shape() {
+        let changes = StagedChanges {
+            added: vec!["src/new.rs".to_string()],
+            modified: vec!["src/lib.rs".to_string()],
+            deleted: vec![],
+            renamed: vec![("old.rs".to_string(), "new.rs".to_string())],
+            diff: String::new(),
+            diff_truncated: false,
+            stats: DiffStats { files_changed: 3, insertions: 12, deletions: 4 },
+        };
+        let report = StatusReport {
+            repository: "/work/repo".to_string(),
+            branch: "feature/x".to_string(),
+            upstream: Some(UpstreamReport::from(&UpstreamStatus {
+                name: "origin/feature/x".to_string(),
+                ahead: 3,
+                behind: 1,
+            })),
+            staged: StagedReport::from(&changes),
+            unstaged: vec!["src/main.rs".to_string()],
+            untracked: vec!["notes.txt".to_string()],
+            uncommitted_changes: true,
+            stashes: 2,
+            recent_commits: vec!["Add parser".to_string()],
+        };
+
+        assert_eq!(to_json(&report), json!({
+            "repository": "/work/repo",
+            "branch": "feature/x",
+            "upstream": { "name": "origin/feature/x", "ahead": 3, "behind": 1 },
+            "staged": {
+                "added": ["src/new.rs"],
+                "modified": ["src/lib.rs"],
+                "deleted": [],
+                "renamed": [{ "from": "old.rs", "to": "new.rs" }],
+                "insertions": 12,
+                "deletions": 4
+            },
+            "unstaged": ["src/main.rs"],
+            "untracked": ["notes.txt"],
+            "uncommitted_changes": true,
+            "stashes": 2,
+            "recent_commits": ["Add parser"]
+        }));
+    }
+
+    #[test]
+    fn test_commit_report_shape() {
+        let report = CommitReport {
+            branch: "main".to_string(),
+            commits: vec![CommitEntry {
+                sha: "abc123".to_string(),
+                message: "Fix typo".to_string(),
+                files: vec!["README.md".to_string()],
+            }],
+        };
+
+        assert_eq!(to_json(&report), json!({
+            "branch": "main",
+            "commits": [{ "sha": "abc123", "message": "Fix typo", "files": ["README.md"] }]
+        }));
+    }
+
+    #[test]
+    fn test_commit_report_since_lists_new_commits() {
+        let dir = std::env::temp_dir().join(format!("bahn-report-{}-{}", std::process::id(), rand::random::<u32>()));
+        let repo = Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+
+        let commit = |path: &str, message: &str| {
+            std::fs::write(dir.join(path), message).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(std::path::Path::new(path)).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents: Vec<git2::Commit> = repo.head().ok()
+                .and_then(|h| h.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap()
+        };
+
+        let before = commit("a.txt", "First");
+        commit("b.txt", "Second");
+        commit("c.txt", "Third\n\nWith a body\n");
+
+        let report = CommitReport::since(&repo, "main", Some(before)).unwrap();
+        let messages: Vec<&str> = report.commits.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(messages, vec!["Second", "Third\n\nWith a body"]);
+        assert_eq!(report.commits[0].files, vec!["b.txt"]);
+        assert_eq!(report.commits[1].sha.len(), 40);
+
+        let all = CommitReport::since(&repo, "main", None).unwrap();
+        assert_eq!(all.commits.len(), 3);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_review_report_shape() {
+        let review = CodeReview {
+            verdict: "request_changes".to_string(),
+            summary: "One bug".to_string(),
+            issues: vec![ReviewIssue {
+                severity: "critical".to_string(),
+                file: "src/main.rs".to_string(),
+                line: Some(42),
+                message: "Unchecked unwrap".to_string(),
+                suggestion: None,
+            }],
+            positives: vec!["Good tests".to_string()],
+            overall_score: 6,
+        };
+
+        assert_eq!(to_json(&review), json!({
+            "verdict": "request_changes",
+            "summary": "One bug",
+            "issues": [{
+                "severity": "critical",
+                "file": "src/main.rs",
+                "line": 42,
+                "message": "Unchecked unwrap",
+                "suggestion": null
+            }],
+            "positives": ["Good tests"],
+            "overall_score": 6
+        }));
+    }
+
+    #[test]
+    fn test_undo_report_shape() {
+        let report = UndoReport {
+            undone: vec!["Add feature".to_string()],
+            hard: false,
+            head: Some("def456".to_string()),
+        };
+        assert_eq!(to_json(&report), json!({
+            "undone": ["Add feature"],
+            "hard": false,
+            "head": "def456"
+        }));
+
+        let preview = UndoPreviewReport { commits: vec!["Add feature".to_string()], unpushed: 1 };
+        assert_eq!(to_json(&preview), json!({ "commits": ["Add feature"], "unpushed": 1 }));
+    }
+
+    #[test]
+    fn test_push_report_shape() {
+        let report = PushReport {
+            remote: "origin".to_string(),
+            branch: "feature/x".to_string(),
+            pushed_refs: vec!["refs/heads/feature/x".to_string()],
+            forced: false,
+            pr_url: Some("https://github.com/o/r/pull/1".to_string()),
+        };
+
+        assert_eq!(to_json(&report), json!({
+            "remote": "origin",
+            "branch": "feature/x",
+            "pushed_refs": ["refs/heads/feature/x"],
+            "forced": false,
+            "pr_url": "https://github.com/o/r/pull/1"
+        }));
+    }
+
+    #[test]
+    fn test_baseline_and_error_report_shapes() {
+        let baseline = BaselineReport { file: ".bahn-secrets-baseline.toml".to_string(), added: 2, total: 5 };
+        assert_eq!(to_json(&baseline), json!({
+            "file": ".bahn-secrets-baseline.toml",
+            "added": 2,
+            "total": 5
+        }));
+
+        let err = anyhow::anyhow!("disk full").context("Failed to write commit");
+        let report = ErrorReport {
+            error: err.to_string(),
+            causes: err.chain().skip(1).map(|c| c.to_string()).collect(),
+        };
+        assert_eq!(to_json(&report), json!({
+            "error": "Failed to write commit",
+            "causes": ["disk full"]
+        }));
+
+        let plain = ErrorReport { error: "nope".to_string(), causes: vec![] };
+        assert_eq!(to_json(&plain), json!({ "error": "nope" }));
+    }
+
+    #[test]
+    fn test_stats_report_shape() {
+        let report = StatsReport {
+            since: Some("2026-09-16T00:00:00+00:00".to_string()),
+            commits: CommitStats { total: 4, by_bahn: 3, manual: 1, avg_files: 1.5, avg_lines: 12.0 },
+            sessions: vec![SessionStats {
+                id: "s1".to_string(),
+                started: "2026-10-01T09:00:00+00:00".to_string(),
+                ended: "2026-10-01T10:00:00+00:00".to_string(),
+                commits: 2,
+            }],
+            squashes: 1,
+            squashed_commits: 3,
+            secrets_blocked: 0,
+            activity_recorded: true,
+        };
+
+        assert_eq!(to_json(&report), json!({
+            "since": "2026-09-16T00:00:00+00:00",
+            "commits": { "total": 4, "by_bahn": 3, "manual": 1, "avg_files": 1.5, "avg_lines": 12.0 },
+            "sessions": [{
+                "id": "s1",
+                "started": "2026-10-01T09:00:00+00:00",
+                "ended": "2026-10-01T10:00:00+00:00",
+                "commits": 2
+            }],
+            "squashes": 1,
+            "squashed_commits": 3,
+            "secrets_blocked": 0,
+            "activity_recorded": true
+        }));
+    }
+
+    #[test]
+    fn test_doctor_report_shape() {
+        let report = DoctorReport::new(vec![
+            DoctorCheck::pass("git", "git version 2.43.0"),
+            DoctorCheck::warn("upstream", "No upstream", "git push -u origin main"),
+            DoctorCheck::skip("api", "Skipped (--offline)"),
+        ]);
+
+        assert_eq!(to_json(&report), json!({
+            "checks": [
+                { "name": "git", "status": "pass", "message": "git version 2.43.0" },
+                { "name": "upstream", "status": "warn", "message": "No upstream", "hint": "git push -u origin main" },
+                { "name": "api", "status": "skip", "message": "Skipped (--offline)" }
+            ],
+            "warnings": 1,
+            "failures": 0
+        }));
+    }
+}
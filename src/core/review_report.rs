@@ -0,0 +1,296 @@
+//! Machine-readable reports (JSON, SARIF) and exit gating for code reviews.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use super::ai::{CodeReview, ReviewIssue};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/0xinit/gitBahn";
+
+/// Lowest issue severity that makes `bahn review` exit non-zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailOn {
+    Critical,
+    Warning,
+    Never,
+}
+
+impl FromStr for FailOn {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "critical" => Ok(Self::Critical),
+            "warning" => Ok(Self::Warning),
+            "never" => Ok(Self::Never),
+            _ => anyhow::bail!("Unknown --fail-on level '{}'. Use critical, warning or never", s),
+        }
+    }
+}
+
+impl FailOn {
+    /// Issues at or above the level
+    pub fn failing<'a>(&self, review: &'a CodeReview) -> Vec<&'a ReviewIssue> {
+        let threshold = match self {
+            FailOn::Critical => 2,
+            FailOn::Warning => 1,
+            FailOn::Never => return Vec::new(),
+        };
+        review.issues.iter()
+            .filter(|issue| severity_rank(&issue.severity) >= threshold)
+            .collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FailOn::Critical => "critical",
+            FailOn::Warning => "warning",
+            FailOn::Never => "never",
+        }
+    }
+}
+
+/// critical > warning > anything else (suggestions)
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "critical" => 2,
+        "warning" => 1,
+        _ => 0,
+    }
+}
+
+fn sarif_level(severity: &str) -> &'static str {
+    match severity {
+        "critical" => "error",
+        "warning" => "warning",
+        _ => "note",
+    }
+}
+
+/// Render a review as the `CodeReview` JSON document
+pub fn render_json(review: &CodeReview) -> Result<String> {
+    Ok(serde_json::to_string_pretty(review)?)
+}
+
+/// Render review issues as a SARIF 2.1.0 log with one rule per severity
+pub fn render_sarif(review: &CodeReview) -> Result<String> {
+    let mut rule_ids: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+
+    for issue in &review.issues {
+        let index = match rule_ids.iter().position(|r| *r == issue.severity) {
+            Some(index) => index,
+            None => {
+                rule_ids.push(&issue.severity);
+                rule_ids.len() - 1
+            }
+        };
+
+        let text = match &issue.suggestion {
+            Some(suggestion) => format!("{}\nSuggestion: {}", issue.message, suggestion),
+            None => issue.message.clone(),
+        };
+        let mut location = json!({ "artifactLocation": { "uri": issue.file } });
+        if let Some(line) = issue.line.filter(|l| *l > 0) {
+            location["region"] = json!({ "startLine": line });
+        }
+
+        results.push(json!({
+            "ruleId": issue.severity,
+            "ruleIndex": index,
+            "level": sarif_level(&issue.severity),
+            "message": { "text": text },
+            "locations": [{ "physicalLocation": location }],
+        }));
+    }
+
+    let rules: Vec<Value> = rule_ids.iter()
+        .map(|id| json!({
+            "id": id,
+            "shortDescription": { "text": format!("bahn review {} issue", id) },
+            "defaultConfiguration": { "level": sarif_level(id) },
+        }))
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "gitBahn",
+                    "informationUri": INFORMATION_URI,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+            "properties": {
+                "verdict": review.verdict,
+                "score": review.overall_score,
+                "summary": review.summary,
+            },
+        }],
+    });
+
+    Ok(serde_json::to_string_pretty(&log)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review() -> CodeReview {
+        CodeReview {
+            verdict: "request_changes".to_string(),
+            summary: "One real bug.".to_string(),
+            issues: vec![
+                ReviewIssue {
+                    severity: "critical".to_string(),
+                    file: "src/db.rs".to_string(),
+                    line: Some(42),
+                    message: "SQL built from user input".to_string(),
+                    suggestion: Some("Use a bound parameter".to_string()),
+                },
+                ReviewIssue {
+                    severity: "suggestion".to_string(),
+                    file: "README.md".to_string(),
+                    line: None,
+                    message: "Mention the new flag".to_string(),
+                    suggestion: None,
+                },
+                ReviewIssue {
+                    severity: "critical".to_string(),
+                    file: "src/api.rs".to_string(),
+                    line: Some(7),
+                    message: "Token logged".to_string(),
+                    suggestion: None,
+                },
+            ],
+            positives: vec!["Good tests".to_string()],
+            overall_score: 4,
+        }
+    }
+
+    #[test]
+    fn test_fail_on() {
+        let review = review();
+        assert_eq!("Critical".parse::<FailOn>().unwrap(), FailOn::Critical);
+        assert!("error".parse::<FailOn>().is_err());
+        assert_eq!(FailOn::Critical.failing(&review).len(), 2);
+        assert_eq!(FailOn::Warning.failing(&review).len(), 2);
+        assert!(FailOn::Never.failing(&review).is_empty());
+
+        let mut review = review;
+        review.issues[0].severity = "warning".to_string();
+        assert_eq!(FailOn::Critical.failing(&review).len(), 1);
+        assert_eq!(FailOn::Warning.failing(&review).len(), 2);
+    }
+
+    #[test]
+    fn test_json_snapshot() {
+        let value: Value = serde_json::from_str(&render_json(&review()).unwrap()).unwrap();
+        assert_eq!(value, json!({
+            "verdict": "request_changes",
+            "summary": "One real bug.",
+            "issues": [
+                {
+                    "severity": "critical",
+                    "file": "src/db.rs",
+                    "line": 42,
+                    "message": "SQL built from user input",
+                    "suggestion": "Use a bound parameter",
+                },
+                {
+                    "severity": "suggestion",
+                    "file": "README.md",
+                    "line": null,
+                    "message": "Mention the new flag",
+                    "suggestion": null,
+                },
+                {
+                    "severity": "critical",
+                    "file": "src/api.rs",
+                    "line": 7,
+                    "message": "Token logged",
+                    "suggestion": null,
+                },
+            ],
+            "positives": ["Good tests"],
+            "overall_score": 4,
+        }));
+    }
+
+    #[test]
+    fn test_sarif_snapshot() {
+        let value: Value = serde_json::from_str(&render_sarif(&review()).unwrap()).unwrap();
+        assert_eq!(value, json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "gitBahn",
+                        "informationUri": INFORMATION_URI,
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": [
+                            {
+                                "id": "critical",
+                                "shortDescription": { "text": "bahn review critical issue" },
+                                "defaultConfiguration": { "level": "error" },
+                            },
+                            {
+                                "id": "suggestion",
+                                "shortDescription": { "text": "bahn review suggestion issue" },
+                                "defaultConfiguration": { "level": "note" },
+                            },
+                        ],
+                    },
+                },
+                "results": [
+                    {
+                        "ruleId": "critical",
+                        "ruleIndex": 0,
+                        "level": "error",
+                        "message": { "text": "SQL built from user input\nSuggestion: Use a bound parameter" },
+                        "locations": [{
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": "src/db.rs" },
+                                "region": { "startLine": 42 },
+                            },
+                        }],
+                    },
+                    {
+                        "ruleId": "suggestion",
+                        "ruleIndex": 1,
+                        "level": "note",
+                        "message": { "text": "Mention the new flag" },
+                        "locations": [{
+                            "physicalLocation": { "artifactLocation": { "uri": "README.md" } },
+                        }],
+                    },
+                    {
+                        "ruleId": "critical",
+                        "ruleIndex": 0,
+                        "level": "error",
+                        "message": { "text": "Token logged" },
+                        "locations": [{
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": "src/api.rs" },
+                                "region": { "startLine": 7 },
+                            },
+                        }],
+                    },
+                ],
+                "properties": {
+                    "verdict": "request_changes",
+                    "score": 4,
+                    "summary": "One real bug.",
+                },
+            }],
+        }));
+    }
+}
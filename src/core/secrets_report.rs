@@ -0,0 +1,325 @@
+//! Machine-readable reports (JSON, SARIF, GitHub annotations) for secret scan results.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::annotations;
+use super::secrets::{SecretAction, SecretMatch, SecretPolicy};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/0xinit/gitBahn";
+
+/// Output format for secret scan results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Sarif,
+    /// GitHub Actions workflow commands
+    Github,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            "github" => Ok(Self::Github),
+            _ => anyhow::bail!("Unknown format '{}'. Use text, json, sarif or github", s),
+        }
+    }
+}
+
+impl ReportFormat {
+    /// Pick a file format from the extension (`.sarif` for SARIF, JSON otherwise)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("sarif") => Self::Sarif,
+            _ => Self::Json,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonFinding<'a> {
+    #[serde(flatten)]
+    secret: &'a SecretMatch,
+    action: &'static str,
+}
+
+fn action_name(action: SecretAction) -> &'static str {
+    match action {
+        SecretAction::Block => "block",
+        SecretAction::Warn => "warn",
+        SecretAction::Ignore => "ignore",
+    }
+}
+
+fn sarif_level(action: SecretAction) -> &'static str {
+    match action {
+        SecretAction::Block => "error",
+        SecretAction::Warn => "warning",
+        SecretAction::Ignore => "note",
+    }
+}
+
+/// Stable SARIF rule id for a secret type ("GitHub OAuth Token" -> "github-oauth-token")
+pub fn rule_id(secret_type: &str) -> String {
+    secret_type
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Render findings as JSON, with the action the policy takes for each
+pub fn render_json(secrets: &[SecretMatch], policy: &SecretPolicy) -> Result<String> {
+    let findings: Vec<JsonFinding> = secrets
+        .iter()
+        .map(|secret| JsonFinding {
+            secret,
+            action: action_name(policy.action_for(secret)),
+        })
+        .collect();
+
+    let report = json!({
+        "tool": { "name": "gitBahn", "version": env!("CARGO_PKG_VERSION") },
+        "findings": findings,
+    });
+
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+/// Render findings as a SARIF 2.1.0 log with one rule per secret type
+pub fn render_sarif(secrets: &[SecretMatch], policy: &SecretPolicy) -> Result<String> {
+    let mut rules: Vec<Value> = Vec::new();
+    let mut rule_ids: Vec<String> = Vec::new();
+    let mut results = Vec::new();
+
+    for secret in secrets {
+        let id = rule_id(&secret.secret_type);
+        let index = match rule_ids.iter().position(|r| *r == id) {
+            Some(index) => index,
+            None => {
+                rules.push(json!({
+                    "id": id,
+                    "name": secret.secret_type,
+                    "shortDescription": { "text": format!("{} detected", secret.secret_type) },
+                }));
+                rule_ids.push(id.clone());
+                rule_ids.len() - 1
+            }
+        };
+
+        results.push(json!({
+            "ruleId": id,
+            "ruleIndex": index,
+            "level": sarif_level(policy.action_for(secret)),
+            "message": {
+                "text": format!(
+                    "Potential {} ({}), confidence {:.0}%",
+                    secret.secret_type, secret.masked_value, secret.confidence * 100.0
+                ),
+            },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": secret.file_path },
+                    "region": { "startLine": secret.line },
+                },
+            }],
+            "partialFingerprints": { "secretFingerprint/v1": secret.fingerprint },
+            "properties": { "confidence": secret.confidence },
+        }));
+    }
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "gitBahn",
+                    "informationUri": INFORMATION_URI,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    });
+
+    Ok(serde_json::to_string_pretty(&log)?)
+}
+
+/// Render findings in a machine-readable format
+pub fn render(secrets: &[SecretMatch], policy: &SecretPolicy, format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Json => render_json(secrets, policy),
+        ReportFormat::Sarif => render_sarif(secrets, policy),
+        ReportFormat::Github => Ok(annotations::secret_annotations(secrets, policy).join("\n")),
+        ReportFormat::Text => Ok(super::secrets::format_secret_warnings(secrets, policy)),
+    }
+}
+
+/// Write a report to a file, choosing the format from its extension
+pub fn write_report(path: &Path, secrets: &[SecretMatch], policy: &SecretPolicy) -> Result<()> {
+    let report = render(secrets, policy, ReportFormat::from_path(path))?;
+    std::fs::write(path, report)
+        .with_context(|| format!("Failed to write secrets report: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::secrets::fingerprint;
+
+    fn findings() -> Vec<SecretMatch> {
+        vec![
+            SecretMatch {
+                secret_type: "GitHub Personal Access Token".to_string(),
+                line: 12,
+                masked_value: "ghp_********cD5".to_string(),
+                confidence: 0.95,
+                file_path: "src/config.rs".to_string(),
+                fingerprint: fingerprint("ghp_token"),
+            },
+            SecretMatch {
+                secret_type: "High Entropy String".to_string(),
+                line: 3,
+                masked_value: "********Qz".to_string(),
+                confidence: 0.75,
+                file_path: ".env".to_string(),
+                fingerprint: fingerprint("entropy"),
+            },
+            SecretMatch {
+                secret_type: "GitHub Personal Access Token".to_string(),
+                line: 40,
+                masked_value: "ghp_********9aB".to_string(),
+                confidence: 0.95,
+                file_path: "deploy.sh".to_string(),
+                fingerprint: fingerprint("ghp_other"),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_rule_id() {
+        assert_eq!(rule_id("GitHub Personal Access Token"), "github-personal-access-token");
+        assert_eq!(rule_id("AWS Secret Access Key"), "aws-secret-access-key");
+        assert_eq!(rule_id("npm Token"), "npm-token");
+    }
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!("SARIF".parse::<ReportFormat>().unwrap(), ReportFormat::Sarif);
+        assert_eq!("github".parse::<ReportFormat>().unwrap(), ReportFormat::Github);
+        assert!("xml".parse::<ReportFormat>().is_err());
+        assert_eq!(ReportFormat::from_path(Path::new("out/secrets.sarif")), ReportFormat::Sarif);
+        assert_eq!(ReportFormat::from_path(Path::new("secrets.json")), ReportFormat::Json);
+    }
+
+    #[test]
+    fn test_json_snapshot() {
+        let output = render_json(&findings()[..2], &SecretPolicy::default()).unwrap();
+        let value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value, json!({
+            "tool": { "name": "gitBahn", "version": env!("CARGO_PKG_VERSION") },
+            "findings": [
+                {
+                    "secret_type": "GitHub Personal Access Token",
+                    "line": 12,
+                    "masked_value": "ghp_********cD5",
+                    "confidence": 0.95,
+                    "file_path": "src/config.rs",
+                    "fingerprint": fingerprint("ghp_token"),
+                    "action": "block",
+                },
+                {
+                    "secret_type": "High Entropy String",
+                    "line": 3,
+                    "masked_value": "********Qz",
+                    "confidence": 0.75,
+                    "file_path": ".env",
+                    "fingerprint": fingerprint("entropy"),
+                    "action": "warn",
+                },
+            ],
+        }));
+    }
+
+    #[test]
+    fn test_sarif_snapshot() {
+        let output = render_sarif(&findings(), &SecretPolicy::default()).unwrap();
+        let value: Value = serde_json::from_str(&output).unwrap();
+
+        let location = |uri: &str, line: usize| json!([{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": { "startLine": line },
+            },
+        }]);
+
+        assert_eq!(value, json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "gitBahn",
+                        "informationUri": INFORMATION_URI,
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": [
+                            {
+                                "id": "github-personal-access-token",
+                                "name": "GitHub Personal Access Token",
+                                "shortDescription": { "text": "GitHub Personal Access Token detected" },
+                            },
+                            {
+                                "id": "high-entropy-string",
+                                "name": "High Entropy String",
+                                "shortDescription": { "text": "High Entropy String detected" },
+                            },
+                        ],
+                    },
+                },
+                "results": [
+                    {
+                        "ruleId": "github-personal-access-token",
+                        "ruleIndex": 0,
+                        "level": "error",
+                        "message": { "text": "Potential GitHub Personal Access Token (ghp_********cD5), confidence 95%" },
+                        "locations": location("src/config.rs", 12),
+                        "partialFingerprints": { "secretFingerprint/v1": fingerprint("ghp_token") },
+                        "properties": { "confidence": 0.95 },
+                    },
+                    {
+                        "ruleId": "high-entropy-string",
+                        "ruleIndex": 1,
+                        "level": "warning",
+                        "message": { "text": "Potential High Entropy String (********Qz), confidence 75%" },
+                        "locations": location(".env", 3),
+                        "partialFingerprints": { "secretFingerprint/v1": fingerprint("entropy") },
+                        "properties": { "confidence": 0.75 },
+                    },
+                    {
+                        "ruleId": "github-personal-access-token",
+                        "ruleIndex": 0,
+                        "level": "error",
+                        "message": { "text": "Potential GitHub Personal Access Token (ghp_********9aB), confidence 95%" },
+                        "locations": location("deploy.sh", 40),
+                        "partialFingerprints": { "secretFingerprint/v1": fingerprint("ghp_other") },
+                        "properties": { "confidence": 0.95 },
+                    },
+                ],
+            }],
+        }));
+    }
+}
@@ -1,18 +1,308 @@
 //! File system watcher for auto-commit mode.
 
-use std::path::PathBuf;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
-use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+
+use crate::core::git;
+
+/// Paths that are always ignored, on top of the repository's ignore rules.
+/// `.bahn.lock` is where older versions kept the repository lock.
+const ALWAYS_IGNORED: &[&str] = &[".git/", ".bahn.toml", ".bahn.pause", ".bahn.lock"];
+
+/// Editor swap, backup and temp files (vim, emacs, JetBrains)
+pub const EDITOR_ARTIFACTS: &[&str] = &[
+    "*.swp",
+    "*.swo",
+    "*.swx",
+    "*~",
+    ".#*",
+    "#*#",
+    "4913",
+    "*___jb_tmp___",
+    "*___jb_old___",
+];
+
+/// Matcher for editor artifacts plus extra globs, used to keep them out of commits
+pub fn artifact_matcher(root: &Path, extra_patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in EDITOR_ARTIFACTS.iter().copied().chain(extra_patterns.iter().map(String::as_str)) {
+        builder.add_line(None, pattern)
+            .with_context(|| format!("Invalid ignore pattern: {}", pattern))?;
+    }
+    builder.build().context("Failed to build ignore rules")
+}
+
+/// Batches buffered between the watcher thread and the async consumer
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Extra time on top of the debounce window during which bahn's own writes are ignored
+const INHIBIT_MARGIN: Duration = Duration::from_millis(500);
+
+/// Decides which paths under a watch root should not wake the watcher
+pub struct WatchFilter {
+    root: PathBuf,
+    /// Built-ins and extra globs, which nothing can re-include
+    always: Gitignore,
+    /// `.gitignore` files below the root, deepest first
+    nested: Vec<(PathBuf, Gitignore)>,
+    /// The root `.gitignore` and `.git/info/exclude`
+    local: Gitignore,
+    /// The user's global excludes file (core.excludesFile)
+    global: Gitignore,
+    /// Absolute directories ignored wholesale (the resolved gitdir)
+    excluded: Vec<PathBuf>,
+}
+
+impl WatchFilter {
+    /// Build a filter from the repository's ignore files plus extra globs
+    pub fn new(root: &Path, extra_patterns: &[String]) -> Result<Self> {
+        let mut always = ignore_builder(root)?;
+        let builtin = ALWAYS_IGNORED.iter().chain(EDITOR_ARTIFACTS).copied();
+        for pattern in builtin.chain(extra_patterns.iter().map(String::as_str)) {
+            always.add_line(None, &native_pattern(pattern))
+                .with_context(|| format!("Invalid ignore pattern: {}", pattern))?;
+        }
+
+        let local = ignore_files(root, &[root.join(".gitignore"), root.join(".git").join("info").join("exclude")])?;
+
+        // Walking with the ignore rules applied never descends into ignored trees
+        let mut nested = Vec::new();
+        let walker = WalkBuilder::new(root)
+            .hidden(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walker.flatten() {
+            let path = entry.path();
+            if entry.file_name() == ".gitignore" && path.parent().is_some_and(|dir| dir != root) {
+                let dir = path.parent().unwrap_or(root).to_path_buf();
+                let rules = ignore_files(&dir, &[path.to_path_buf()])?;
+                nested.push((dir, rules));
+            }
+        }
+        nested.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            always: always.build().context("Failed to build ignore rules")?,
+            nested,
+            local,
+            global: Gitignore::global().0,
+            excluded: Vec::new(),
+        })
+    }
+
+    /// Ignore everything under a directory, wherever it lives
+    pub fn exclude_dir(mut self, dir: PathBuf) -> Self {
+        self.excluded.push(dir);
+        self
+    }
+
+    /// Whether a path should be ignored; paths outside the root never are
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let under = |dir: &Path| git::strip_root(dir, path, git::CASE_INSENSITIVE_FS);
+        if self.excluded.iter().any(|dir| under(dir).is_some()) {
+            return true;
+        }
+
+        let relative = match under(&self.root) {
+            Some(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => return false,
+        };
+        let is_dir = path.is_dir();
+        if self.always.matched_path_or_any_parents(&relative, is_dir).is_ignore() {
+            return true;
+        }
+
+        // As in git, the closest .gitignore decides, and it may re-include with `!`
+        for (dir, rules) in &self.nested {
+            let Some(below) = under(dir).filter(|p| !p.as_os_str().is_empty()) else { continue };
+            match rules.matched_path_or_any_parents(&below, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        self.local.matched_path_or_any_parents(&relative, is_dir).is_ignore()
+            || self.global.matched_path_or_any_parents(&relative, is_dir).is_ignore()
+    }
+}
+
+fn ignore_builder(root: &Path) -> Result<GitignoreBuilder> {
+    let mut builder = GitignoreBuilder::new(root);
+    builder.case_insensitive(git::CASE_INSENSITIVE_FS)
+        .context("Failed to configure ignore rules")?;
+    Ok(builder)
+}
+
+/// Rules from ignore files that apply below `root`; missing files are skipped
+fn ignore_files(root: &Path, files: &[PathBuf]) -> Result<Gitignore> {
+    let mut builder = ignore_builder(root)?;
+    for file in files.iter().filter(|f| f.exists()) {
+        if let Some(e) = builder.add(file) {
+            anyhow::bail!("Failed to read {}: {}", file.display(), e);
+        }
+    }
+    builder.build().context("Failed to build ignore rules")
+}
+
+/// Ignore patterns are `/`-separated; accept `\` from Windows users too.
+/// Elsewhere a backslash is gitignore's escape character and is kept.
+fn native_pattern(pattern: &str) -> String {
+    if cfg!(windows) {
+        pattern.replace('\\', "/")
+    } else {
+        pattern.to_string()
+    }
+}
+
+/// Shared handle used to drop events caused by bahn's own writes
+#[derive(Debug, Clone, Default)]
+pub struct WriteInhibitor {
+    until: Arc<Mutex<Option<Instant>>>,
+    window: Duration,
+}
+
+impl WriteInhibitor {
+    /// Create an inhibitor that drops events for `window` after each write
+    pub fn new(window: Duration) -> Self {
+        Self {
+            until: Arc::new(Mutex::new(None)),
+            window,
+        }
+    }
+
+    /// Record that bahn just wrote to the repository (commit, squash, stash)
+    pub fn mark_write(&self) {
+        if let Ok(mut until) = self.until.lock() {
+            *until = Some(Instant::now() + self.window);
+        }
+    }
+
+    /// Whether events should currently be dropped
+    pub fn is_active(&self) -> bool {
+        self.until.lock()
+            .map(|until| until.is_some_and(|t| Instant::now() < t))
+            .unwrap_or(false)
+    }
+}
+
+/// Apply the ignore rules and self-write window to a batch of changed paths
+pub fn filter_batch<T: AsRef<Path>>(paths: Vec<T>, filter: &WatchFilter, inhibitor: &WriteInhibitor) -> Vec<T> {
+    if inhibitor.is_active() {
+        return Vec::new();
+    }
+    paths.into_iter().filter(|p| !filter.is_ignored(p.as_ref())).collect()
+}
+
+/// How a path changed within a debounced batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+impl ChangeKind {
+    /// Map a raw notify event kind; access events are not changes
+    fn from_event(kind: &EventKind) -> Option<Self> {
+        match kind {
+            EventKind::Create(_) => Some(Self::Created),
+            EventKind::Modify(ModifyKind::Name(_)) => Some(Self::Renamed),
+            EventKind::Modify(_) | EventKind::Any | EventKind::Other => Some(Self::Modified),
+            EventKind::Remove(_) => Some(Self::Removed),
+            EventKind::Access(_) => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+            Self::Renamed => "renamed",
+        }
+    }
+}
+
+/// A changed path and how it changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedPath {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+impl AsRef<Path> for ChangedPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Collapse raw events into one entry per path, in first-seen order.
+///
+/// A path created and removed within the same batch (editor temp files)
+/// is dropped entirely.
+pub fn classify_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> Vec<ChangedPath> {
+    let mut changes: Vec<ChangedPath> = Vec::new();
+
+    for event in events {
+        let Some(kind) = ChangeKind::from_event(&event.kind) else {
+            continue;
+        };
+
+        for path in &event.paths {
+            let existing = changes.iter().position(|c| &c.path == path);
+            match existing {
+                None => changes.push(ChangedPath { path: path.clone(), kind }),
+                Some(i) => match (changes[i].kind, kind) {
+                    (ChangeKind::Created, ChangeKind::Removed) => {
+                        changes.remove(i);
+                    }
+                    (ChangeKind::Created, _) => {}
+                    (ChangeKind::Removed, ChangeKind::Created) => changes[i].kind = ChangeKind::Modified,
+                    (_, ChangeKind::Removed) => changes[i].kind = ChangeKind::Removed,
+                    (ChangeKind::Renamed, _) => {}
+                    (_, new) => changes[i].kind = new,
+                },
+            }
+        }
+    }
+
+    changes
+}
+
+/// Just the paths of a batch, for callers that don't care about kinds
+#[allow(dead_code)]
+pub fn changed_paths(changes: &[ChangedPath]) -> Vec<&Path> {
+    changes.iter().map(|c| c.path.as_path()).collect()
+}
+
+/// Summarize a batch as counts per kind, e.g. "2 created, 1 modified"
+pub fn summarize_changes(changes: &[ChangedPath]) -> String {
+    [ChangeKind::Created, ChangeKind::Modified, ChangeKind::Removed, ChangeKind::Renamed]
+        .iter()
+        .filter_map(|kind| {
+            let count = changes.iter().filter(|c| c.kind == *kind).count();
+            (count > 0).then(|| format!("{} {}", count, kind.label()))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
 /// Events emitted by the file watcher
 #[derive(Debug, Clone)]
 pub enum WatchEvent {
     /// Files were changed (debounced batch)
-    FilesChanged(Vec<PathBuf>),
+    FilesChanged(Vec<ChangedPath>),
     /// Watcher error occurred
     Error(String),
 }
@@ -21,8 +311,16 @@ pub enum WatchEvent {
 pub struct FileWatcher {
     /// Debounce duration for batching events
     debounce_duration: Duration,
-    /// Patterns to ignore (e.g., .git, node_modules)
+    /// Extra gitignore-style globs on top of the repository's ignore files
     ignore_patterns: Vec<String>,
+    /// Directories excluded by absolute path (gitdir, worktree metadata)
+    excluded_dirs: Vec<PathBuf>,
+    /// Subdirectories of the watch root to watch instead of the whole root
+    watch_paths: Vec<PathBuf>,
+    /// Drops events that follow bahn's own writes
+    inhibitor: WriteInhibitor,
+    /// Active watches; dropping them closes their channels
+    debouncers: Vec<Debouncer<RecommendedWatcher, FileIdMap>>,
 }
 
 impl FileWatcher {
@@ -30,84 +328,125 @@ impl FileWatcher {
     pub fn new(debounce_ms: u64) -> Self {
         Self {
             debounce_duration: Duration::from_millis(debounce_ms),
-            ignore_patterns: vec![
-                ".git".to_string(),
-                "node_modules".to_string(),
-                "target".to_string(),
-                ".bahn.lock".to_string(),
-                ".bahn.toml".to_string(),
-            ],
+            ignore_patterns: Vec::new(),
+            excluded_dirs: Vec::new(),
+            watch_paths: Vec::new(),
+            inhibitor: WriteInhibitor::new(Duration::from_millis(debounce_ms) + INHIBIT_MARGIN),
+            debouncers: Vec::new(),
         }
     }
 
-    /// Add patterns to ignore
-    #[allow(dead_code)]
+    /// Watch a repository, excluding its actual gitdir (which may live outside the worktree)
+    pub fn for_repo(debounce_ms: u64, repo: &git2::Repository) -> Self {
+        let mut watcher = Self::new(debounce_ms);
+        watcher.excluded_dirs.push(repo.path().to_path_buf());
+        watcher.excluded_dirs.push(git::common_dir(repo));
+        watcher
+    }
+
+    /// Handle for marking bahn's own writes so they don't wake the watcher
+    pub fn inhibitor(&self) -> WriteInhibitor {
+        self.inhibitor.clone()
+    }
+
+    /// Add gitignore-style globs to ignore, on top of the defaults
     pub fn with_ignore_patterns(mut self, patterns: Vec<String>) -> Self {
         self.ignore_patterns.extend(patterns);
         self
     }
 
-    /// Watch a directory and return a receiver for events
-    pub fn watch(&self, path: PathBuf) -> Result<mpsc::Receiver<WatchEvent>> {
-        let (tx, rx) = mpsc::channel();
-        let ignore_patterns = self.ignore_patterns.clone();
+    /// Only watch these subdirectories of the root passed to `watch`
+    pub fn with_watch_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.watch_paths.extend(paths);
+        self
+    }
+
+    /// Debounce window for batching events
+    #[allow(dead_code)]
+    pub fn debounce_duration(&self) -> Duration {
+        self.debounce_duration
+    }
+
+    /// Extra ignore globs on top of the defaults
+    #[allow(dead_code)]
+    pub fn ignore_patterns(&self) -> &[String] {
+        &self.ignore_patterns
+    }
+
+    /// Subdirectories watched instead of the whole root
+    #[allow(dead_code)]
+    pub fn watch_paths(&self) -> &[PathBuf] {
+        &self.watch_paths
+    }
 
-        let (debounce_tx, debounce_rx) = mpsc::channel();
+    /// Watch a directory and return an async receiver for events.
+    ///
+    /// With watch paths configured, only those subdirectories of `root` are
+    /// watched, all feeding the same channel. The watch stays active until
+    /// the `FileWatcher` is dropped, which closes the channel.
+    pub fn watch(&mut self, root: PathBuf) -> Result<tokio::sync::mpsc::Receiver<WatchEvent>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(EVENT_CHANNEL_CAPACITY);
 
-        // Create debounced watcher
+        let targets = if self.watch_paths.is_empty() {
+            vec![root.clone()]
+        } else {
+            self.watch_paths.iter().map(|p| root.join(p)).collect()
+        };
+        for target in targets {
+            self.watch_target(&root, target, tx.clone())?;
+        }
+
+        Ok(rx)
+    }
+
+    /// Start one debounced watch on `path`, filtering relative to `root`
+    fn watch_target(
+        &mut self,
+        root: &Path,
+        path: PathBuf,
+        tx: tokio::sync::mpsc::Sender<WatchEvent>,
+    ) -> Result<()> {
+        // The root's `.git` is dropped by path, whatever the ignore rules say
+        let filter = self.excluded_dirs.iter().cloned()
+            .chain(std::iter::once(root.join(".git")))
+            .fold(WatchFilter::new(root, &self.ignore_patterns)?, WatchFilter::exclude_dir);
+        let inhibitor = self.inhibitor.clone();
+
+        // The callback runs on the debouncer's own thread, so blocking_send is safe
         let mut debouncer = new_debouncer(
             self.debounce_duration,
-            move |res: Result<Vec<notify_debouncer_mini::DebouncedEvent>, notify::Error>| {
-                match res {
+            None,
+            move |res: DebounceEventResult| {
+                let event = match res {
                     Ok(events) => {
-                        let _ = debounce_tx.send(Ok(events));
+                        let changes = classify_events(events.iter().map(|e| &e.event));
+                        let raw = changes.len();
+                        let changes = filter_batch(changes, &filter, &inhibitor);
+                        tracing::debug!(events = events.len(), paths = raw, kept = changes.len(), "watcher batch");
+                        if changes.is_empty() {
+                            return;
+                        }
+                        WatchEvent::FilesChanged(changes)
                     }
-                    Err(e) => {
-                        let _ = debounce_tx.send(Err(e));
+                    Err(errors) => {
+                        tracing::debug!(errors = errors.len(), "watcher reported errors");
+                        WatchEvent::Error(
+                            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "),
+                        )
                     }
-                }
+                };
+                let _ = tx.blocking_send(event);
             },
         ).context("Failed to create file watcher")?;
 
-        // Start watching
+        // Start watching; the cache tracks file ids so renames pair up
         debouncer.watcher().watch(&path, RecursiveMode::Recursive)
-            .context("Failed to watch directory")?;
+            .with_context(|| format!("Failed to watch directory: {}", path.display()))?;
+        debouncer.cache().add_root(&path, RecursiveMode::Recursive);
+        tracing::debug!(path = %path.display(), debounce_ms = self.debounce_duration.as_millis() as u64, "watching");
 
-        // Spawn thread to process debounced events
-        let tx_clone = tx.clone();
-        std::thread::spawn(move || {
-            // Keep debouncer alive
-            let _debouncer = debouncer;
-
-            loop {
-                match debounce_rx.recv() {
-                    Ok(Ok(events)) => {
-                        let paths: Vec<PathBuf> = events
-                            .into_iter()
-                            .filter(|e| e.kind == DebouncedEventKind::Any)
-                            .map(|e| e.path)
-                            .filter(|p| {
-                                let path_str = p.to_string_lossy();
-                                !ignore_patterns.iter().any(|pattern| path_str.contains(pattern))
-                            })
-                            .collect();
-
-                        if !paths.is_empty() {
-                            let _ = tx_clone.send(WatchEvent::FilesChanged(paths));
-                        }
-                    }
-                    Ok(Err(e)) => {
-                        let _ = tx_clone.send(WatchEvent::Error(e.to_string()));
-                    }
-                    Err(_) => {
-                        // Channel closed, exit
-                        break;
-                    }
-                }
-            }
-        });
-
-        Ok(rx)
+        self.debouncers.push(debouncer);
+        Ok(())
     }
 }
 
@@ -122,27 +461,19 @@ impl SimpleWatcher {
     /// Create a new simple watcher
     pub fn new() -> Self {
         Self {
-            ignore_patterns: vec![
-                ".git".to_string(),
-                "node_modules".to_string(),
-                "target".to_string(),
-                ".bahn.lock".to_string(),
-            ],
+            ignore_patterns: Vec::new(),
         }
     }
 
     /// Watch and return receiver
     pub fn watch(&self, path: PathBuf) -> Result<(mpsc::Receiver<Event>, RecommendedWatcher)> {
         let (tx, rx) = mpsc::channel();
-        let ignore_patterns = self.ignore_patterns.clone();
+        let filter = WatchFilter::new(&path, &self.ignore_patterns)?;
 
         let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
             if let Ok(event) = res {
                 // Filter out ignored paths
-                let dominated_by_ignored = event.paths.iter().all(|p| {
-                    let path_str = p.to_string_lossy();
-                    ignore_patterns.iter().any(|pattern| path_str.contains(pattern))
-                });
+                let dominated_by_ignored = event.paths.iter().all(|p| filter.is_ignored(p));
 
                 if !dominated_by_ignored {
                     let _ = tx.send(event);
@@ -162,3 +493,258 @@ impl Default for SimpleWatcher {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bahn-watch-{}-{}", std::process::id(), rand::random::<u32>()));
+        std::fs::create_dir_all(dir.join(".git").join("info")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_substrings_are_not_ignored() {
+        let root = temp_repo();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        let filter = WatchFilter::new(&root, &[]).unwrap();
+
+        assert!(!filter.is_ignored(&root.join("src/retargeting.rs")));
+        assert!(!filter.is_ignored(&root.join("targets/main.rs")));
+        assert!(!filter.is_ignored(&root.join("docs/.github-notes.md")));
+        assert!(!filter.is_ignored(&root.join(".github/workflows/ci.yml")));
+        assert!(!filter.is_ignored(&root.join(".gitignore")));
+        assert!(!filter.is_ignored(&root.join("vendor/lib.git/config")));
+        assert!(filter.is_ignored(&root.join("target/debug/bahn")));
+    }
+
+    #[test]
+    fn test_gitignored_directory_is_filtered() {
+        let root = temp_repo();
+        std::fs::write(root.join(".gitignore"), "dist/\n*.log\n").unwrap();
+        std::fs::write(root.join(".git/info/exclude"), ".venv/\n").unwrap();
+        std::fs::create_dir_all(root.join("dist")).unwrap();
+        let filter = WatchFilter::new(&root, &["*.tmp".to_string()]).unwrap();
+
+        assert!(filter.is_ignored(&root.join("dist")));
+        assert!(filter.is_ignored(&root.join("dist/app.js")));
+        assert!(filter.is_ignored(&root.join("logs/server.log")));
+        assert!(filter.is_ignored(&root.join(".venv/lib/site.py")));
+        assert!(filter.is_ignored(&root.join("scratch.tmp")));
+        assert!(filter.is_ignored(&root.join(".git/index")));
+        assert!(filter.is_ignored(&root.join(".bahn.pause")));
+        assert!(filter.is_ignored(&root.join(".bahn.lock")));
+        assert!(!filter.is_ignored(&root.join("src/dist.rs")));
+        assert!(!filter.is_ignored(Path::new("/elsewhere/dist/app.js")));
+    }
+
+    #[test]
+    fn test_nested_gitignore_files_apply_to_their_directory() {
+        let root = temp_repo();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::create_dir_all(root.join("app/build")).unwrap();
+        std::fs::write(root.join("app/.gitignore"), "__pycache__/\n/build/\n!keep.log\n").unwrap();
+        std::fs::create_dir_all(root.join("app/__pycache__")).unwrap();
+        let filter = WatchFilter::new(&root, &["keep.log".to_string()]).unwrap();
+
+        assert!(filter.is_ignored(&root.join("app/__pycache__/mod.cpython-312.pyc")));
+        assert!(filter.is_ignored(&root.join("app/build/out.o")));
+        assert!(filter.is_ignored(&root.join("app/server.log")));
+        // Anchored to app/, so a top-level build/ is not ignored
+        assert!(!filter.is_ignored(&root.join("build/out.o")));
+        // The nested file re-includes it, but extra globs always win
+        assert!(filter.is_ignored(&root.join("app/keep.log")));
+
+        let filter = WatchFilter::new(&root, &[]).unwrap();
+        assert!(!filter.is_ignored(&root.join("app/keep.log")));
+        assert!(filter.is_ignored(&root.join("other.log")));
+    }
+
+    #[test]
+    fn test_gitdir_outside_worktree_is_excluded() {
+        let root = temp_repo();
+        let gitdir = std::env::temp_dir().join(format!("bahn-gitdir-{}-{}", std::process::id(), rand::random::<u32>()));
+        let filter = WatchFilter::new(&root, &[]).unwrap().exclude_dir(gitdir.clone());
+
+        // A worktree's gitdir burst: index, HEAD, logs
+        let burst = vec![
+            gitdir.join("index"),
+            gitdir.join("HEAD"),
+            gitdir.join("logs/HEAD"),
+            root.join(".git/index.lock"),
+        ];
+        assert!(filter_batch(burst, &filter, &WriteInhibitor::default()).is_empty());
+    }
+
+    #[test]
+    fn test_self_write_window_drops_events() {
+        let root = temp_repo();
+        let filter = WatchFilter::new(&root, &[]).unwrap();
+        let inhibitor = WriteInhibitor::new(Duration::from_millis(200));
+        let changed = vec![root.join("src/main.rs")];
+
+        assert_eq!(filter_batch(changed.clone(), &filter, &inhibitor).len(), 1);
+
+        inhibitor.mark_write();
+        assert!(filter_batch(changed.clone(), &filter, &inhibitor).is_empty());
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(filter_batch(changed, &filter, &inhibitor).len(), 1);
+    }
+
+    #[test]
+    fn test_editor_artifacts_are_ignored() {
+        let root = temp_repo();
+        let filter = WatchFilter::new(&root, &["*.bak".to_string()]).unwrap();
+
+        for artifact in ["foo.swp", "src/.main.rs.swo", "notes.txt~", "src/.#lib.rs", "4913", "src/app.ts___jb_tmp___", "old.bak"] {
+            assert!(filter.is_ignored(&root.join(artifact)), "{} should be ignored", artifact);
+        }
+        assert!(!filter.is_ignored(&root.join("src/swap.rs")));
+
+        let burst = vec![root.join("foo.swp"), root.join(".foo.rs.swx")];
+        assert!(filter_batch(burst, &filter, &WriteInhibitor::default()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_arrive_on_async_receiver() {
+        let root = temp_repo();
+        let mut watcher = FileWatcher::new(50);
+        let mut rx = watcher.watch(root.clone()).unwrap();
+
+        // Write from another thread, as an editor would
+        let file = root.join("main.rs");
+        let writer = file.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            std::fs::write(writer, "fn main() {}\n").unwrap();
+        });
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await
+            .expect("no event within timeout");
+        match event {
+            Some(WatchEvent::FilesChanged(changes)) => {
+                assert!(changed_paths(&changes).iter().any(|p| p.file_name() == file.file_name()));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Dropping the watcher stops the debouncer and closes the channel
+        drop(watcher);
+        let closed = tokio::time::timeout(Duration::from_secs(5), async {
+            while rx.recv().await.is_some() {}
+        }).await;
+        assert!(closed.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_watch_paths_limit_events() {
+        let root = temp_repo();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        let mut watcher = FileWatcher::new(50).with_watch_paths(vec![PathBuf::from("src")]);
+        let mut rx = watcher.watch(root.clone()).unwrap();
+
+        let unwatched = root.join("notes/todo.md");
+        let watched = root.join("src/lib.rs");
+        let (a, b) = (unwatched.clone(), watched.clone());
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            std::fs::write(a, "todo\n").unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            std::fs::write(b, "pub fn f() {}\n").unwrap();
+        });
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await
+            .expect("no event within timeout");
+        match event {
+            Some(WatchEvent::FilesChanged(changes)) => {
+                let paths = changed_paths(&changes);
+                assert!(paths.iter().all(|p| p.file_name() == watched.file_name()));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_classify_events() {
+        use notify::event::{CreateKind, DataChange, RemoveKind, RenameMode};
+
+        let events = vec![
+            event(EventKind::Create(CreateKind::File), "/repo/new.rs"),
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), "/repo/new.rs"),
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), "/repo/lib.rs"),
+            event(EventKind::Remove(RemoveKind::File), "/repo/old.rs"),
+            event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), "/repo/moved.rs"),
+            event(EventKind::Access(notify::event::AccessKind::Any), "/repo/read.rs"),
+            // Created and removed in the same batch: vim's 4913 probe
+            event(EventKind::Create(CreateKind::File), "/repo/4913"),
+            event(EventKind::Remove(RemoveKind::File), "/repo/4913"),
+        ];
+
+        let changes = classify_events(&events);
+        let kinds: Vec<(&str, ChangeKind)> = changes.iter()
+            .map(|c| (c.path.to_str().unwrap(), c.kind))
+            .collect();
+        assert_eq!(kinds, vec![
+            ("/repo/new.rs", ChangeKind::Created),
+            ("/repo/lib.rs", ChangeKind::Modified),
+            ("/repo/old.rs", ChangeKind::Removed),
+            ("/repo/moved.rs", ChangeKind::Renamed),
+        ]);
+        assert_eq!(summarize_changes(&changes), "1 created, 1 modified, 1 removed, 1 renamed");
+        assert_eq!(changed_paths(&changes).len(), 4);
+    }
+
+    #[test]
+    fn test_commit_churn_is_suppressed() {
+        use notify::event::{CreateKind, DataChange, RemoveKind};
+
+        let root = temp_repo();
+        let filter = WatchFilter::new(&root, &[]).unwrap().exclude_dir(root.join(".git"));
+        let inhibitor = WriteInhibitor::new(Duration::from_millis(200));
+        let path = |relative: &str| root.join(relative).to_string_lossy().to_string();
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+
+        // What `git commit` does to the gitdir, without any window in effect
+        let commit = vec![
+            event(EventKind::Create(CreateKind::File), &path(".git/index.lock")),
+            event(modify, &path(".git/index")),
+            event(EventKind::Create(CreateKind::File), &path(".git/objects/ab/cdef0123")),
+            event(modify, &path(".git/refs/heads/main")),
+            event(modify, &path(".git/logs/HEAD")),
+            event(EventKind::Remove(RemoveKind::File), &path(".git/index.lock")),
+        ];
+        assert!(filter_batch(classify_events(&commit), &filter, &WriteInhibitor::default()).is_empty());
+
+        // Line-ending normalization rewrites the committed file right after the commit
+        inhibitor.mark_write();
+        let normalized = vec![event(modify, &path("src/lib.rs")), event(modify, &path(".git/index"))];
+        assert!(filter_batch(classify_events(&normalized), &filter, &inhibitor).is_empty());
+        // Batches already queued are dropped by the watch loop on the same check
+        assert!(inhibitor.is_active());
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(!inhibitor.is_active());
+        let edit = vec![event(modify, &path("src/lib.rs")), event(modify, &path(".git/index"))];
+        let kept = filter_batch(classify_events(&edit), &filter, &inhibitor);
+        assert_eq!(changed_paths(&kept), [root.join("src/lib.rs").as_path()]);
+    }
+
+    #[test]
+    fn test_classify_modify_then_delete() {
+        use notify::event::{DataChange, RemoveKind};
+
+        let events = vec![
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Any)), "/repo/a.rs"),
+            event(EventKind::Remove(RemoveKind::File), "/repo/a.rs"),
+        ];
+        let changes = classify_events(&events);
+        assert_eq!(changes, vec![ChangedPath { path: PathBuf::from("/repo/a.rs"), kind: ChangeKind::Removed }]);
+    }
+}
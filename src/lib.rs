@@ -0,0 +1,8 @@
+//! gitBahn - Autonomous Git operations with AI-powered commits.
+//!
+//! The `bahn` binary is a thin CLI over these modules; gitbahn-mcp uses
+//! them too, so both apply the same config, secret scan and branch rules.
+
+pub mod commands;
+pub mod config;
+pub mod core;
@@ -1,11 +1,12 @@
 //! gitBahn - Autonomous Git operations with AI-powered commits.
 
+use std::path::PathBuf;
+use std::time::Duration;
+
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
-mod commands;
-mod config;
-mod core;
+use gitbahn::{commands, config, core};
 
 use config::Config;
 
@@ -15,9 +16,49 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Enable verbose output
+    /// Verbose output; -v logs info events, -vv debug (BAHN_LOG overrides)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Also write log events to this file as JSON lines
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// AI model to use for this run (overrides ai.model)
+    #[arg(long, global = true, value_name = "NAME")]
+    model: Option<String>,
+
+    /// AI provider to use for this run (overrides ai.provider)
+    #[arg(long, global = true, value_name = "NAME")]
+    provider: Option<String>,
+
+    /// Config file to use instead of ./.bahn.toml
+    #[arg(long, global = true, value_name = "PATH", env = "BAHN_CONFIG")]
+    config: Option<String>,
+
+    /// Config profile to apply from [profiles.<name>]
+    #[arg(long, global = true, value_name = "NAME", env = "BAHN_PROFILE")]
+    profile: Option<String>,
+
+    /// Don't read or store API keys in the OS keyring
+    #[arg(long, global = true)]
+    no_keyring: bool,
+
+    /// Seconds to wait for another bahn instance to release the repository lock (commit, auto, squash, undo)
+    #[arg(long, global = true, value_name = "SECS", env = "BAHN_LOCK_WAIT", default_value = "0")]
+    wait_lock: u64,
+
+    /// Print results as a single JSON document on stdout; other output goes to stderr
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Don't print the AI token usage summary (overrides ai.show_usage)
     #[arg(short, long, global = true)]
-    verbose: bool,
+    quiet: bool,
+
+    /// Don't reuse or store cached AI responses
+    #[arg(long, global = true)]
+    no_cache: bool,
 }
 
 #[derive(Subcommand)]
@@ -40,7 +81,7 @@ enum Commands {
         #[arg(short, long)]
         realistic: bool,
 
-        /// Use conventional commit format
+        /// Check and fix AI messages against Conventional Commits (also `commit.conventional`)
         #[arg(long)]
         conventional: bool,
 
@@ -59,6 +100,62 @@ enum Commands {
         /// Start time for atomic commits (e.g., "2025-12-25 09:00")
         #[arg(long)]
         start: Option<String>,
+
+        /// Keep spread timestamps inside working hours (e.g., "9-18" or "09:00-17:30")
+        #[arg(long, value_name = "RANGE")]
+        work_hours: Option<String>,
+
+        /// Move spread timestamps off Saturdays and Sundays
+        #[arg(long)]
+        skip_weekends: bool,
+
+        /// Start one minute after the last commit when --start is earlier, instead of failing
+        #[arg(long)]
+        clamp_start: bool,
+
+        /// Number of commit message candidates to choose from
+        #[arg(long)]
+        candidates: Option<usize>,
+
+        /// How to group files for atomic commits
+        #[arg(long, value_enum, default_value_t = commands::commit::Grouping::Ai)]
+        grouping: commands::commit::Grouping,
+
+        /// Write secret scan results to a file (JSON, or SARIF for .sarif)
+        #[arg(long)]
+        secrets_report: Option<PathBuf>,
+
+        /// Commit to a protected branch without warning
+        #[arg(long)]
+        allow_protected: bool,
+
+        /// Commit even if the secret scan finds high-confidence secrets
+        #[arg(long)]
+        allow_secrets: bool,
+
+        /// Reword the last commit with an AI message, folding in any staged changes
+        #[arg(long, conflicts_with_all = ["atomic", "split", "granular", "realistic", "spread", "start", "candidates"])]
+        amend: bool,
+
+        /// Amend even if the last commit has been pushed
+        #[arg(long, requires = "amend")]
+        force: bool,
+
+        /// Print the message rendered from commit.template without committing
+        #[arg(long, conflicts_with_all = ["atomic", "split", "granular", "realistic", "amend", "candidates"])]
+        show_template: bool,
+
+        /// Ask the AI even for lockfile updates and version bumps
+        #[arg(long)]
+        no_fast_path: bool,
+
+        /// Rewrite each atomic commit's message from the diff it stages (default)
+        #[arg(long, overrides_with = "fast")]
+        refine_messages: bool,
+
+        /// Keep the atomic grouping's suggested messages, saving one AI request per group
+        #[arg(long, overrides_with = "refine_messages")]
+        fast: bool,
     },
 
     /// Autonomous mode - watch and auto-commit
@@ -71,7 +168,7 @@ enum Commands {
         #[arg(short, long, default_value = "30")]
         interval: u64,
 
-        /// Auto-merge to target branch
+        /// Merge commits into the target branch (after each one, or on exit with auto.merge_on = "exit")
         #[arg(short, long)]
         merge: bool,
 
@@ -102,20 +199,41 @@ enum Commands {
         /// Start time for spread commits (e.g., "2025-01-05 09:00")
         #[arg(long)]
         start: Option<String>,
+
+        /// Extra glob to ignore when watching and staging (repeatable)
+        #[arg(long = "ignore", value_name = "GLOB")]
+        ignore: Vec<String>,
+
+        /// Allow auto-committing on a protected branch
+        #[arg(long)]
+        allow_protected: bool,
+
+        /// Ask the AI even for lockfile updates and version bumps
+        #[arg(long)]
+        no_fast_path: bool,
     },
 
     /// AI-powered code rewrite
     Rewrite {
         /// Path to rewrite
-        path: String,
+        #[arg(required_unless_present = "undo")]
+        path: Option<String>,
 
         /// Rewrite instructions
         #[arg(short, long)]
         instructions: Option<String>,
 
-        /// Dry run - show changes without applying
+        /// Dry run - show a diff without applying
         #[arg(long)]
         dry_run: bool,
+
+        /// Also rewrite files with uncommitted changes
+        #[arg(long)]
+        allow_dirty: bool,
+
+        /// Restore the files changed by the last rewrite
+        #[arg(long, conflicts_with_all = ["path", "instructions", "dry_run", "allow_dirty"])]
+        undo: bool,
     },
 
     /// AI-assisted merge with conflict resolution
@@ -136,6 +254,14 @@ enum Commands {
         /// Documentation format (rust, markdown, jsdoc)
         #[arg(short, long, default_value = "rust")]
         format: String,
+
+        /// Insert doc comments above Rust items and docstrings into Python functions and classes
+        #[arg(long)]
+        write: bool,
+
+        /// Show what --write would change as a diff, without writing
+        #[arg(long, requires = "write")]
+        dry_run: bool,
     },
 
     /// AI-powered code review
@@ -148,9 +274,34 @@ enum Commands {
         #[arg(long)]
         commit: Option<String>,
 
+        /// Review a commit range, e.g. main..feature (or A...B from the merge-base)
+        #[arg(long, conflicts_with_all = ["staged", "commit", "branch", "post"])]
+        range: Option<String>,
+
+        /// Review the current branch's changes since it diverged from this branch
+        #[arg(long, conflicts_with_all = ["staged", "commit", "post"])]
+        branch: Option<String>,
+
         /// Strictness level (relaxed, normal, strict)
         #[arg(long, default_value = "normal")]
         strictness: String,
+
+        /// AI personality/agent to use
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Output format (text, json, sarif, github); defaults to github under GitHub Actions
+        #[arg(long)]
+        format: Option<String>,
+
+        /// Exit non-zero when issues at or above this severity exist (critical, warning, never)
+        #[arg(long, default_value = "never")]
+        fail_on: String,
+
+        /// Post the review to the commit's open pull request, or without
+        /// --commit, review and post to the current branch's pull request
+        #[arg(long)]
+        post: bool,
     },
 
     /// Initialize gitBahn in a repository
@@ -162,6 +313,13 @@ enum Commands {
     /// Show repository status
     Status,
 
+    /// Summarize bahn's activity in this repository
+    Stats {
+        /// Only count the last window, e.g. 12h, 30d or 2w
+        #[arg(long)]
+        since: Option<String>,
+    },
+
     /// Push to remote with optional PR creation
     Push {
         /// Create a pull request after pushing
@@ -187,14 +345,59 @@ enum Commands {
         /// Force push (with lease)
         #[arg(short, long)]
         force: bool,
+
+        /// Don't set the upstream branch (skips `-u origin`)
+        #[arg(long)]
+        no_upstream: bool,
+    },
+
+    /// Manage secret detection
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+    },
+
+    /// Inspect configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Check the environment and repository for common problems
+    Doctor {
+        /// Skip checks that need the network (API key ping)
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Run bahn's checks from git hooks or the pre-commit framework
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+
+    /// Inspect or release the repository lock
+    Lock {
+        #[command(subcommand)]
+        action: LockAction,
+    },
+
+    /// Manage cached AI responses
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
     },
 
     /// Undo the last commit(s)
     Undo {
         /// Number of commits to undo
-        #[arg(default_value = "1")]
+        #[arg(short = 'n', long, value_name = "N", default_value = "1")]
         count: usize,
 
+        /// Number of commits as a positional argument (`bahn undo 2`)
+        #[arg(value_name = "N", hide = true, conflicts_with = "count")]
+        count_arg: Option<usize>,
+
         /// Hard reset - discard all changes (DANGEROUS)
         #[arg(long)]
         hard: bool,
@@ -211,31 +414,258 @@ enum Commands {
         #[arg(long)]
         preview: bool,
     },
+
+    /// Undo commits with new revert commits, safe for pushed history
+    Revert {
+        /// Commit or range to revert (e.g. abc123 or abc123..def456)
+        #[arg(value_name = "COMMIT")]
+        target: String,
+
+        /// Why the commits are being reverted, for the message
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Use git's standard message instead of an AI-written one
+        #[arg(long)]
+        no_ai: bool,
+
+        /// Resolve conflicts with AI instead of stopping
+        #[arg(long, conflicts_with = "no_ai")]
+        auto_resolve: bool,
+
+        /// Skip confirmation prompt
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Squash the last N commits into one
+    Squash {
+        /// Number of commits to squash (at least 2)
+        #[arg(value_name = "N")]
+        count: usize,
+
+        /// Message for the squashed commit (default: AI summary of the commits)
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Skip confirmation prompt
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Squash even if some of the commits are pushed
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Write a Keep a Changelog section from the commits since the last tag
+    Changelog {
+        /// Tag or ref to list commits from (default: the latest tag)
+        #[arg(long, value_name = "REF")]
+        since: Option<String>,
+
+        /// Title the section "Unreleased" even when HEAD is tagged
+        #[arg(long)]
+        unreleased: bool,
+
+        /// Prepend the section to CHANGELOG.md, replacing one with the same version
+        #[arg(long)]
+        write: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookAction {
+    /// Run the checks for one hook stage; exits 1 if they fail
+    #[command(after_long_help = commands::hook::HOOK_HELP)]
+    Run {
+        /// Hook stage
+        #[arg(value_enum)]
+        stage: commands::hook::HookStage,
+
+        /// Arguments git passes to the hook (commit-msg: the message file)
+        args: Vec<String>,
+
+        /// Output format (text, json, github); defaults to github under GitHub Actions
+        #[arg(long)]
+        format: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsAction {
+    /// Accept current findings so later scans only report new ones
+    Baseline {
+        /// Scan all tracked files instead of the staged diff
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Scan the full repository history for secrets
+    Audit {
+        /// Only scan commits after this revision
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Branch to scan (default: current HEAD)
+        #[arg(long)]
+        branch: Option<String>,
+
+        /// Output format (text, json, sarif, github); defaults to github under GitHub Actions
+        #[arg(long)]
+        format: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Show the merged configuration and where each value comes from
+    Show,
+
+    /// Print every effective value with its source, one per line
+    List,
+
+    /// Print the effective value of a dotted key (e.g. ai.model)
+    Get {
+        key: String,
+    },
+
+    /// Set a dotted key in .bahn.toml, checking the value first
+    Set {
+        /// Dotted key, e.g. commit.atomic or ai.model
+        key: String,
+
+        /// Value, read as TOML where the setting isn't a string (true, 30, ["a"])
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+
+        /// Write to the global config instead of the project's
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Open .bahn.toml in $EDITOR and check it before saving
+    Edit {
+        /// Edit the global config instead of the project's
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Check config files for unknown keys and invalid values (exits non-zero on problems)
+    Validate,
+
+    /// Store an API key in the OS keyring (anthropic, openai, github)
+    SetKey {
+        /// Provider the key belongs to
+        provider: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum LockAction {
+    /// Show who holds the lock
+    Status,
+
+    /// Remove a stale lock
+    Release {
+        /// Remove the lock even if its holder appears to be running
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Delete every cached AI response
+    Clear,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let config = Config::load(None)?;
+    core::report::set_json(cli.json);
+
+    let result = run(cli).await;
+    if let Err(e) = &result {
+        if core::report::is_json() {
+            core::report::emit_error(e);
+            std::process::exit(1);
+        }
+    }
+    result
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    core::logging::init(cli.verbose, cli.log_file.as_deref())?;
+
+    let store = core::credentials::store(cli.no_keyring);
+
+    // Doctor reports a broken config instead of failing on it
+    if let Commands::Doctor { offline } = cli.command {
+        let config = Config::load(cli.config.as_deref(), cli.profile.as_deref(), store.as_ref())
+            .and_then(|mut config| {
+                config.apply_cli_overrides(cli.model.clone(), cli.provider.clone())?;
+                Ok(config)
+            });
+        return commands::doctor::run(config, cli.config.as_deref(), offline).await;
+    }
+
+    // Fixing a broken config mustn't require loading it
+    match &cli.command {
+        Commands::Config { action: ConfigAction::Set { key, value, global } } => {
+            return config::set_config_value(cli.config.as_deref(), *global, key, value);
+        }
+        Commands::Config { action: ConfigAction::Edit { global } } => {
+            return config::edit_config(cli.config.as_deref(), *global);
+        }
+        _ => {}
+    }
+
+    let mut config = Config::load(cli.config.as_deref(), cli.profile.as_deref(), store.as_ref())?;
+    config.apply_cli_overrides(cli.model.clone(), cli.provider.clone())?;
+    if cli.quiet {
+        config.ai.show_usage = false;
+    }
+    if cli.no_cache {
+        config.ai.cache_ttl_hours = 0;
+    }
+
+    tracing::info!(model = %config.ai.model, provider = %config.ai.provider, "loaded configuration");
+    let lock_wait = Duration::from_secs(cli.wait_lock);
 
     match cli.command {
-        Commands::Commit { atomic, split, granular, realistic, conventional, agent, yes, spread, start } => {
+        Commands::Commit { atomic, split, granular, realistic, conventional, agent, yes, spread, start, work_hours, skip_weekends, clamp_start, candidates, grouping, secrets_report, allow_protected, allow_secrets, amend, force, show_template, no_fast_path, refine_messages: _, fast } => {
             let options = commands::commit::CommitOptions {
-                atomic: atomic || split.is_some() || granular || realistic,
+                atomic: atomic || split.is_some() || granular || realistic || grouping == commands::commit::Grouping::Embeddings,
                 split,
                 granular,
                 realistic,
                 conventional,
                 agent,
                 auto_confirm: yes,
-                verbose: cli.verbose,
+                verbose: cli.verbose > 0,
                 spread,
                 start,
+                candidates: candidates.unwrap_or(config.commit.candidates),
+                grouping,
+                secrets_report,
+                allow_protected,
+                allow_secrets,
+                sign: config.commit.sign,
+                work_hours,
+                skip_weekends,
+                clamp_start,
+                amend,
+                force,
+                template: config.commit.template.clone(),
+                show_template,
+                fast_path: (!no_fast_path).then(|| config.commit.fast_path.clone()),
+                refine_messages: !fast,
+                lock_wait,
             };
             commands::commit::run(options, &config).await
         }
 
-        Commands::Auto { watch, interval, merge, target, max_commits, dry_run, prompt, defer, spread, start } => {
+        Commands::Auto { watch, interval, merge, target, max_commits, dry_run, prompt, defer, spread, start, ignore, allow_protected, no_fast_path } => {
             let auto_options = commands::auto::AutoModeOptions {
                 watch,
                 interval,
@@ -247,24 +677,52 @@ async fn main() -> Result<()> {
                 defer,
                 spread,
                 start,
+                ignore,
+                lock_wait,
+                allow_protected,
+                no_fast_path,
             };
             commands::auto::run(&config, auto_options).await
         }
 
-        Commands::Rewrite { path, instructions, dry_run } => {
-            commands::rewrite::run(&config, &path, instructions.as_deref(), dry_run).await
-        }
+        Commands::Rewrite { path, instructions, dry_run, allow_dirty, undo } => match path {
+            Some(path) if !undo => {
+                let options = commands::rewrite::RewriteOptions { instructions, dry_run, allow_dirty };
+                commands::rewrite::run(&config, &path, options).await
+            }
+            _ => commands::rewrite::undo(),
+        },
 
         Commands::Merge { branch, auto_resolve } => {
             commands::merge::run(&config, &branch, auto_resolve).await
         }
 
-        Commands::Docs { path, format } => {
-            commands::docs::run(&config, &path, &format).await
+        Commands::Docs { path, format, write, dry_run } => {
+            commands::docs::run(&config, &path, commands::docs::DocsOptions { format, write, dry_run }).await
         }
 
-        Commands::Review { staged, commit, strictness } => {
-            commands::review::run(&config, staged, commit.as_deref(), &strictness).await
+        Commands::Review { staged, commit, range, branch, strictness, agent, format, fail_on, post } => {
+            let format: core::secrets_report::ReportFormat = match format {
+                Some(format) if !(cli.json && format == "text") => format,
+                _ if cli.json => "json".to_string(),
+                _ if core::annotations::in_github_actions() => "github".to_string(),
+                _ => "text".to_string(),
+            }.parse()?;
+            // Keep stdout for the report alone
+            if matches!(format, core::secrets_report::ReportFormat::Json | core::secrets_report::ReportFormat::Sarif) {
+                core::report::set_json(true);
+            }
+            commands::review::run(&config, commands::review::ReviewOptions {
+                staged,
+                commit,
+                range,
+                branch,
+                strictness,
+                agent,
+                format,
+                fail_on: fail_on.parse()?,
+                post,
+            }).await
         }
 
         Commands::Init { path } => {
@@ -275,20 +733,65 @@ async fn main() -> Result<()> {
             commands::status::run()
         }
 
-        Commands::Push { pr, title, body, base, draft, force } => {
-            let options = commands::push::PushOptions {
-                create_pr: pr,
-                title,
-                body,
-                base,
-                draft,
-                force,
-                set_upstream: true,
-            };
+        Commands::Stats { since } => {
+            commands::stats::run(since.as_deref())
+        }
+
+        Commands::Push { pr, title, body, base, draft, force, no_upstream } => {
+            let options = push_options(pr, title, body, base, draft, force, no_upstream);
             commands::push::run(&config, options).await
         }
 
-        Commands::Undo { count, hard, yes, force, preview } => {
+        Commands::Secrets { action } => match action {
+            SecretsAction::Baseline { all } => commands::secrets::baseline(&config, all),
+            SecretsAction::Audit { since, branch, format } => {
+                let format = match format {
+                    Some(format) if !(cli.json && format == "text") => format,
+                    _ if cli.json => "json".to_string(),
+                    _ if core::annotations::in_github_actions() => "github".to_string(),
+                    _ => "text".to_string(),
+                };
+                commands::secrets::audit(&config, since.as_deref(), branch.as_deref(), &format)
+            }
+        },
+
+        Commands::Config { action } => match action {
+            ConfigAction::Show => {
+                let (config, sources) = Config::load_with_sources(cli.config.as_deref(), cli.profile.as_deref(), store.as_ref())?;
+                config::show_config(&config, &sources, cli.config.as_deref())
+            }
+            ConfigAction::List => {
+                let (config, sources) = Config::load_with_sources(cli.config.as_deref(), cli.profile.as_deref(), store.as_ref())?;
+                config::list_config(&config, &sources)
+            }
+            ConfigAction::Get { key } => config::get_config_value(&config, &key),
+            ConfigAction::Set { .. } | ConfigAction::Edit { .. } => unreachable!("handled before loading config"),
+            ConfigAction::Validate => config::validate_config(cli.config.as_deref()),
+            ConfigAction::SetKey { provider } => config::set_key(&provider, store.as_ref(), cli.config.as_deref()),
+        },
+
+        Commands::Doctor { .. } => unreachable!("handled before loading config"),
+
+        Commands::Hook { action: HookAction::Run { stage, args, format } } => {
+            if format.as_deref() == Some("json") {
+                core::report::set_json(true);
+            }
+            let format = format.filter(|f| f != "json");
+            let format = core::annotations::Format::resolve(format.as_deref(), core::annotations::in_github_actions())?;
+            commands::hook::run(&config, stage, &args, format)
+        }
+
+        Commands::Lock { action } => match action {
+            LockAction::Status => commands::lock::status(),
+            LockAction::Release { force } => commands::lock::release(force),
+        },
+
+        Commands::Cache { action } => match action {
+            CacheAction::Clear => commands::cache::clear(),
+        },
+
+        Commands::Undo { count, count_arg, hard, yes, force, preview } => {
+            let count = count_arg.unwrap_or(count);
             if preview {
                 commands::undo::preview(count)
             } else {
@@ -297,9 +800,344 @@ async fn main() -> Result<()> {
                     hard,
                     yes,
                     force,
+                    lock_wait,
                 };
-                commands::undo::run(options)
+                commands::undo::run(options).await
             }
         }
+
+        Commands::Revert { target, reason, no_ai, auto_resolve, yes } => {
+            let options = commands::revert::RevertOptions {
+                target,
+                reason,
+                no_ai,
+                auto_resolve,
+                yes,
+                sign: config.commit.sign,
+            };
+            commands::revert::run(&config, options).await
+        }
+
+        Commands::Squash { count, message, yes, force } => {
+            let options = commands::squash::SquashOptions { count, message, yes, force, lock_wait };
+            commands::squash::run(&config, options).await
+        }
+
+        Commands::Changelog { since, unreleased, write } => {
+            let options = commands::changelog::ChangelogOptions { since, unreleased, write };
+            commands::changelog::run(&config, options).await
+        }
+    }
+}
+
+/// Map `bahn push` flags onto the command's options
+fn push_options(
+    pr: bool,
+    title: Option<String>,
+    body: Option<String>,
+    base: String,
+    draft: bool,
+    force: bool,
+    no_upstream: bool,
+) -> commands::push::PushOptions {
+    commands::push::PushOptions {
+        create_pr: pr,
+        title,
+        body,
+        base,
+        draft,
+        force,
+        set_upstream: !no_upstream,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_flag_overrides_config() {
+        let cli = Cli::try_parse_from(["bahn", "status", "--model", "claude-opus-4-20250514"]).unwrap();
+        let mut config = Config::default();
+        config.apply_cli_overrides(cli.model, cli.provider).unwrap();
+        assert_eq!(config.ai.model, "claude-opus-4-20250514");
+        assert_eq!(config.ai.provider, "anthropic");
+    }
+
+    #[test]
+    fn test_config_get_set_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "config", "set", "ai.temperature", "-0.5", "--global"]).unwrap();
+        let Commands::Config { action: ConfigAction::Set { key, value, global } } = cli.command else {
+            panic!("expected config set");
+        };
+        assert_eq!((key.as_str(), value.as_str(), global), ("ai.temperature", "-0.5", true));
+
+        let cli = Cli::try_parse_from(["bahn", "config", "get", "ai.model"]).unwrap();
+        assert!(matches!(cli.command, Commands::Config { action: ConfigAction::Get { .. } }));
+        assert!(Cli::try_parse_from(["bahn", "config", "set", "ai.model"]).is_err());
+        assert!(Cli::try_parse_from(["bahn", "config", "edit", "--global"]).is_ok());
+    }
+
+    #[test]
+    fn test_config_path_from_flag_or_env() {
+        let cli = Cli::try_parse_from(["bahn", "--config", "ci/bahn.toml", "status"]).unwrap();
+        assert_eq!(cli.config.as_deref(), Some("ci/bahn.toml"));
+
+        std::env::set_var("BAHN_CONFIG", "env/bahn.toml");
+        let from_env = Cli::try_parse_from(["bahn", "status"]).unwrap();
+        let flag_wins = Cli::try_parse_from(["bahn", "status", "--config", "ci/bahn.toml"]).unwrap();
+        std::env::remove_var("BAHN_CONFIG");
+
+        assert_eq!(from_env.config.as_deref(), Some("env/bahn.toml"));
+        assert_eq!(flag_wins.config.as_deref(), Some("ci/bahn.toml"));
+    }
+
+    #[test]
+    fn test_missing_config_file_is_an_error() {
+        let store = core::credentials::NoKeyring;
+        let err = Config::load(Some("/nonexistent/bahn.toml"), None, &store).unwrap_err();
+        assert_eq!(err.to_string(), "Config file not found: /nonexistent/bahn.toml");
+    }
+
+    #[test]
+    fn test_unknown_provider_is_rejected() {
+        let cli = Cli::try_parse_from(["bahn", "--provider", "nope", "status"]).unwrap();
+        let mut config = Config::default();
+        let err = config.apply_cli_overrides(cli.model, cli.provider).unwrap_err();
+        assert!(err.to_string().contains("Unknown provider 'nope'"));
+
+        let cli = Cli::try_parse_from(["bahn", "status"]).unwrap();
+        config.apply_cli_overrides(cli.model, cli.provider).unwrap();
+        assert_eq!(config.ai.model, Config::default().ai.model);
+    }
+
+    #[test]
+    fn test_json_flag_is_global() {
+        let before = Cli::try_parse_from(["bahn", "--json", "status"]).unwrap();
+        let after = Cli::try_parse_from(["bahn", "undo", "--yes", "--json"]).unwrap();
+        let plain = Cli::try_parse_from(["bahn", "status"]).unwrap();
+        assert!(before.json);
+        assert!(after.json);
+        assert!(!plain.json);
+
+        assert!(Cli::try_parse_from(["bahn", "commit", "-q"]).unwrap().quiet);
+        assert!(!plain.quiet);
+    }
+
+    #[test]
+    fn test_hook_run_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "hook", "run", "commit-msg", ".git/COMMIT_EDITMSG"]).unwrap();
+        let Commands::Hook { action: HookAction::Run { stage, args, format } } = cli.command else {
+            panic!("expected hook run");
+        };
+        assert_eq!(stage, commands::hook::HookStage::CommitMsg);
+        assert_eq!(args, vec![".git/COMMIT_EDITMSG"]);
+        assert_eq!(format, None);
+
+        let cli = Cli::try_parse_from(["bahn", "hook", "run", "pre-push", "origin", "git@host:repo", "--format", "github"]);
+        assert!(cli.is_ok());
+        assert!(Cli::try_parse_from(["bahn", "hook", "run", "post-merge"]).is_err());
+    }
+
+    #[test]
+    fn test_undo_arguments() {
+        let parse = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            let Commands::Undo { count, count_arg, hard, yes, force, preview } = cli.command else {
+                panic!("expected undo");
+            };
+            (count_arg.unwrap_or(count), hard, yes, force, preview)
+        };
+
+        assert_eq!(parse(&["bahn", "undo"]), (1, false, false, false, false));
+        assert_eq!(parse(&["bahn", "undo", "--count", "3", "--hard", "--yes", "--force"]), (3, true, true, true, false));
+        assert_eq!(parse(&["bahn", "undo", "-n", "2", "--preview"]), (2, false, false, false, true));
+        assert_eq!(parse(&["bahn", "undo", "2"]), (2, false, false, false, false));
+        assert!(Cli::try_parse_from(["bahn", "undo", "2", "--count", "3"]).is_err());
+    }
+
+    #[test]
+    fn test_cache_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "cache", "clear"]).unwrap();
+        assert!(matches!(cli.command, Commands::Cache { action: CacheAction::Clear }));
+        assert!(!cli.no_cache);
+
+        assert!(Cli::try_parse_from(["bahn", "commit", "--no-cache"]).unwrap().no_cache);
+        assert!(Cli::try_parse_from(["bahn", "cache"]).is_err());
+    }
+
+    #[test]
+    fn test_revert_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "revert", "abc123..def456", "--reason", "Broke login", "-y"]).unwrap();
+        let Commands::Revert { target, reason, no_ai, auto_resolve, yes } = cli.command else {
+            panic!("expected revert");
+        };
+        assert_eq!(target, "abc123..def456");
+        assert_eq!(reason.as_deref(), Some("Broke login"));
+        assert!(!no_ai && !auto_resolve && yes);
+
+        assert!(Cli::try_parse_from(["bahn", "revert"]).is_err());
+        assert!(Cli::try_parse_from(["bahn", "revert", "abc123", "--no-ai", "--auto-resolve"]).is_err());
+    }
+
+    #[test]
+    fn test_squash_arguments() {
+        let parse = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            let Commands::Squash { count, message, yes, force } = cli.command else {
+                panic!("expected squash");
+            };
+            (count, message, yes, force)
+        };
+
+        assert_eq!(parse(&["bahn", "squash", "3"]), (3, None, false, false));
+        assert_eq!(
+            parse(&["bahn", "squash", "2", "-m", "Add parser", "-y", "--force"]),
+            (2, Some("Add parser".to_string()), true, true)
+        );
+        assert!(Cli::try_parse_from(["bahn", "squash"]).is_err());
+    }
+
+    #[test]
+    fn test_work_hours_arguments() {
+        let parse = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            let Commands::Commit { work_hours, skip_weekends, .. } = cli.command else {
+                panic!("expected commit");
+            };
+            (work_hours, skip_weekends)
+        };
+
+        assert_eq!(parse(&["bahn", "commit", "--atomic"]), (None, false));
+        assert_eq!(
+            parse(&["bahn", "commit", "--atomic", "--spread", "2d", "--work-hours", "9-18", "--skip-weekends"]),
+            (Some("9-18".to_string()), true)
+        );
+    }
+
+    #[test]
+    fn test_changelog_arguments() {
+        let parse = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            let Commands::Changelog { since, unreleased, write } = cli.command else {
+                panic!("expected changelog");
+            };
+            (since, unreleased, write)
+        };
+
+        assert_eq!(parse(&["bahn", "changelog"]), (None, false, false));
+        assert_eq!(
+            parse(&["bahn", "changelog", "--since", "v1.0.0", "--unreleased", "--write"]),
+            (Some("v1.0.0".to_string()), true, true)
+        );
+    }
+
+    #[test]
+    fn test_rewrite_undo_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "rewrite", "--undo"]).unwrap();
+        assert!(matches!(cli.command, Commands::Rewrite { path: None, undo: true, .. }));
+        assert!(Cli::try_parse_from(["bahn", "rewrite"]).is_err());
+        assert!(Cli::try_parse_from(["bahn", "rewrite", "src", "--undo"]).is_err());
+    }
+
+    #[test]
+    fn test_commit_amend_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "commit", "--amend", "--force"]).unwrap();
+        assert!(matches!(cli.command, Commands::Commit { amend: true, force: true, .. }));
+        assert!(Cli::try_parse_from(["bahn", "commit", "--force"]).is_err());
+        assert!(Cli::try_parse_from(["bahn", "commit", "--amend", "--atomic"]).is_err());
+    }
+
+    #[test]
+    fn test_commit_grouping_argument() {
+        use commands::commit::Grouping;
+        let cli = Cli::try_parse_from(["bahn", "commit", "--grouping", "embeddings"]).unwrap();
+        assert!(matches!(cli.command, Commands::Commit { grouping: Grouping::Embeddings, .. }));
+        let cli = Cli::try_parse_from(["bahn", "commit"]).unwrap();
+        assert!(matches!(cli.command, Commands::Commit { grouping: Grouping::Ai, .. }));
+        assert!(Cli::try_parse_from(["bahn", "commit", "--grouping", "embedings"]).is_err());
+    }
+
+    #[test]
+    fn test_commit_refine_arguments() {
+        let fast = |args: &[&str]| {
+            let Commands::Commit { fast, .. } = Cli::try_parse_from(args).unwrap().command else {
+                panic!("expected commit");
+            };
+            fast
+        };
+        assert!(!fast(&["bahn", "commit", "--atomic"]));
+        assert!(fast(&["bahn", "commit", "--atomic", "--fast"]));
+        // The last of the two flags wins
+        assert!(!fast(&["bahn", "commit", "--fast", "--refine-messages"]));
+        assert!(fast(&["bahn", "commit", "--refine-messages", "--fast"]));
+    }
+
+    #[test]
+    fn test_docs_dry_run_requires_write() {
+        let cli = Cli::try_parse_from(["bahn", "docs", "src", "--write", "--dry-run"]).unwrap();
+        let Commands::Docs { write, dry_run, .. } = cli.command else {
+            panic!("expected docs");
+        };
+        assert!(write && dry_run);
+        assert!(Cli::try_parse_from(["bahn", "docs", "src", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn test_review_range_arguments() {
+        let cli = Cli::try_parse_from(["bahn", "review", "--range", "main..feature"]).unwrap();
+        let Commands::Review { range, branch, .. } = cli.command else {
+            panic!("expected review");
+        };
+        assert_eq!(range.as_deref(), Some("main..feature"));
+        assert!(branch.is_none());
+        assert!(Cli::try_parse_from(["bahn", "review", "--branch", "main"]).is_ok());
+        assert!(Cli::try_parse_from(["bahn", "review", "--range", "a..b", "--branch", "main"]).is_err());
+        assert!(Cli::try_parse_from(["bahn", "review", "--branch", "main", "--post"]).is_err());
+        assert!(Cli::try_parse_from(["bahn", "review", "--range", "a..b", "--staged"]).is_err());
+
+        let cli = Cli::try_parse_from(["bahn", "review", "--staged", "--format", "sarif", "--fail-on", "warning"]).unwrap();
+        let Commands::Review { format, fail_on, .. } = cli.command else {
+            panic!("expected review");
+        };
+        assert_eq!(format.as_deref(), Some("sarif"));
+        assert_eq!(fail_on, "warning");
+    }
+
+    #[test]
+    fn test_push_arguments() {
+        let parse = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            let Commands::Push { pr, title, body, base, draft, force, no_upstream } = cli.command else {
+                panic!("expected push");
+            };
+            push_options(pr, title, body, base, draft, force, no_upstream)
+        };
+
+        let defaults = parse(&["bahn", "push"]);
+        assert!(!defaults.create_pr && !defaults.draft && !defaults.force);
+        assert!(defaults.set_upstream);
+        assert_eq!(defaults.base, "main");
+        assert_eq!((defaults.title, defaults.body), (None, None));
+
+        let options = parse(&[
+            "bahn", "push", "--pr", "--title", "Add parser", "--body", "Details",
+            "--base", "develop", "--draft", "--force", "--no-upstream",
+        ]);
+        assert!(options.create_pr && options.draft && options.force);
+        assert!(!options.set_upstream);
+        assert_eq!(options.base, "develop");
+        assert_eq!(options.title.as_deref(), Some("Add parser"));
+        assert_eq!(options.body.as_deref(), Some("Details"));
+    }
+
+    #[test]
+    fn test_verbose_counts_and_log_file() {
+        let cli = Cli::try_parse_from(["bahn", "-vv", "status", "--log-file", "bahn.jsonl"]).unwrap();
+        assert_eq!(cli.verbose, 2);
+        assert_eq!(cli.log_file, Some(PathBuf::from("bahn.jsonl")));
+
+        let cli = Cli::try_parse_from(["bahn", "auto", "--verbose"]).unwrap();
+        assert_eq!(cli.verbose, 1);
     }
 }